@@ -0,0 +1,3 @@
+-- Signature of Offer id made by issuing identity. NULL for Offers
+-- received from nodes using previous discovery protocol version.
+ALTER TABLE market_offer ADD COLUMN signature TEXT;
@@ -0,0 +1,69 @@
+-- This file should undo anything in `up.sql`
+
+DROP TABLE market_agreement_amendment;
+
+CREATE TABLE market_agreement_event_tmp(
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    agreement_id INTEGER NOT NULL,
+    event_type VARCHAR(10) NOT NULL,
+    timestamp DATETIME NOT NULL DEFAULT(STRFTIME('%Y-%m-%d %H:%M:%f', 'NOW')),
+    issuer VARCHAR(1) NOT NULL,
+    reason TEXT,
+    signature TEXT,
+
+    FOREIGN KEY(agreement_id) REFERENCES market_agreement (id),
+    UNIQUE(agreement_id, event_type)
+    CHECK (event_type in ('Terminated', 'Approved', 'Cancelled', 'Rejected'))
+    CHECK (issuer in ('P', 'R'))
+);
+
+INSERT INTO market_agreement_event_tmp(id, agreement_id, event_type, timestamp, issuer, reason, signature)
+SELECT id, agreement_id, event_type, timestamp, issuer, reason, signature FROM market_agreement_event
+WHERE event_type in ('Terminated', 'Approved', 'Cancelled', 'Rejected');
+
+DROP TABLE market_agreement_event;
+ALTER TABLE market_agreement_event_tmp RENAME TO market_agreement_event;
+
+-- SQLite doesn't support dropping columns.
+CREATE TABLE market_agreement_tmp(
+    id VARCHAR(100) NOT NULL PRIMARY KEY,
+
+    demand_properties TEXT NOT NULL,
+    demand_constraints TEXT NOT NULL,
+
+    offer_properties TEXT NOT NULL,
+    offer_constraints TEXT NOT NULL,
+
+    offer_id VARCHAR(97) NOT NULL,
+    demand_id VARCHAR(97) NOT NULL,
+
+    offer_proposal_id VARCHAR(100) NOT NULL,
+    demand_proposal_id VARCHAR(100) NOT NULL,
+
+    provider_id VARCHAR(20) NOT NULL,
+    requestor_id VARCHAR(20) NOT NULL,
+
+    session_id VARCHAR(100),
+
+    creation_ts DATETIME NOT NULL,
+    valid_to DATETIME NOT NULL,
+    state VARCHAR(20) NOT NULL,
+    approved_ts DATETIME,
+
+    proposed_signature TEXT,
+    approved_signature TEXT,
+    committed_signature TEXT,
+
+    CHECK (state in ('Proposal','Pending','Cancelled','Rejected','Approved','Expired','Terminated', 'Approving'))
+);
+
+INSERT INTO market_agreement_tmp(id, demand_properties, demand_constraints, offer_properties, offer_constraints,
+    offer_id, demand_id, offer_proposal_id, demand_proposal_id, provider_id, requestor_id, session_id,
+    creation_ts, valid_to, state, approved_ts, proposed_signature, approved_signature, committed_signature)
+SELECT id, demand_properties, demand_constraints, offer_properties, offer_constraints,
+    offer_id, demand_id, offer_proposal_id, demand_proposal_id, provider_id, requestor_id, session_id,
+    creation_ts, valid_to, state, approved_ts, proposed_signature, approved_signature, committed_signature
+FROM market_agreement;
+
+DROP TABLE market_agreement;
+ALTER TABLE market_agreement_tmp RENAME TO market_agreement;
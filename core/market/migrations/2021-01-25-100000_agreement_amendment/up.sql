@@ -0,0 +1,50 @@
+-- Version of Agreement terms. Incremented with every approved amendment.
+ALTER TABLE market_agreement ADD COLUMN version INTEGER NOT NULL DEFAULT 0;
+
+CREATE TABLE market_agreement_amendment(
+    agreement_id VARCHAR(100) NOT NULL,
+    version INTEGER NOT NULL,
+    issuer VARCHAR(1) NOT NULL,
+    state VARCHAR(10) NOT NULL,
+
+    offer_properties TEXT NOT NULL,
+    demand_properties TEXT NOT NULL,
+
+    creation_ts DATETIME NOT NULL,
+    resolved_ts DATETIME,
+    reason TEXT,
+
+    proposed_signature TEXT NOT NULL,
+    approved_signature TEXT,
+
+    PRIMARY KEY(agreement_id, version),
+    FOREIGN KEY(agreement_id) REFERENCES market_agreement (id),
+    CHECK (state in ('Proposed', 'Approved', 'Rejected'))
+    CHECK (issuer in ('P', 'R'))
+);
+
+-- Amendment events can appear many times for the same Agreement,
+-- so uniqueness is kept only for Agreement lifecycle events.
+CREATE TABLE market_agreement_event_tmp(
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    agreement_id VARCHAR(100) NOT NULL,
+    event_type VARCHAR(20) NOT NULL,
+    timestamp DATETIME NOT NULL DEFAULT(STRFTIME('%Y-%m-%d %H:%M:%f', 'NOW')),
+    issuer VARCHAR(1) NOT NULL,
+    reason TEXT,
+    signature TEXT,
+
+    FOREIGN KEY(agreement_id) REFERENCES market_agreement (id),
+    CHECK (event_type in ('Terminated', 'Approved', 'Cancelled', 'Rejected', 'AmendmentProposed', 'AmendmentApproved', 'AmendmentRejected'))
+    CHECK (issuer in ('P', 'R'))
+);
+
+INSERT INTO market_agreement_event_tmp(id, agreement_id, event_type, timestamp, issuer, reason, signature)
+SELECT id, agreement_id, event_type, timestamp, issuer, reason, signature FROM market_agreement_event;
+
+DROP TABLE market_agreement_event;
+ALTER TABLE market_agreement_event_tmp RENAME TO market_agreement_event;
+
+CREATE UNIQUE INDEX market_agreement_event_lifecycle
+    ON market_agreement_event(agreement_id, event_type)
+    WHERE event_type in ('Terminated', 'Approved', 'Cancelled', 'Rejected');
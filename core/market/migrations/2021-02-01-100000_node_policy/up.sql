@@ -0,0 +1,12 @@
+-- Nodes allowed or blocked by local identities. Identity with any
+-- 'Allow' entry matches and negotiates only with allowed nodes.
+CREATE TABLE market_node_policy(
+    owner_id VARCHAR(20) NOT NULL,
+    node_id VARCHAR(20) NOT NULL,
+    policy VARCHAR(5) NOT NULL,
+    description TEXT,
+    insertion_ts DATETIME NOT NULL DEFAULT(STRFTIME('%Y-%m-%d %H:%M:%f', 'NOW')),
+
+    PRIMARY KEY(owner_id, node_id),
+    CHECK (policy in ('Allow', 'Block'))
+);
@@ -0,0 +1,20 @@
+-- This file should undo anything in `up.sql`
+
+-- SQLite doesn't support dropping columns.
+CREATE TABLE market_offer_tmp (
+    id VARCHAR(97) NOT NULL PRIMARY KEY,
+    properties TEXT NOT NULL,
+    constraints TEXT NOT NULL,
+    node_id VARCHAR(20) NOT NULL,
+
+    creation_ts DATETIME NOT NULL,
+    insertion_ts DATETIME NOT NULL DEFAULT(STRFTIME('%Y-%m-%d %H:%M:%f', 'NOW')),
+    expiration_ts DATETIME NOT NULL,
+    signature TEXT
+);
+
+INSERT INTO market_offer_tmp(id, properties, constraints, node_id, creation_ts, insertion_ts, expiration_ts, signature)
+SELECT id, properties, constraints, node_id, creation_ts, insertion_ts, expiration_ts, signature FROM market_offer;
+
+DROP TABLE market_offer;
+ALTER TABLE market_offer_tmp RENAME TO market_offer;
@@ -0,0 +1,3 @@
+-- Expiration set on Offer creation, which is covered by subscription id hash.
+-- Set only for Offers refreshed by issuer; `expiration_ts` holds extended expiration then.
+ALTER TABLE market_offer ADD COLUMN initial_expiration_ts DATETIME;
@@ -0,0 +1,5 @@
+-- Number of Proposals delivered for Demand limiting them (`golem.market.rank.limit`).
+CREATE TABLE market_demand_delivered(
+    demand_id VARCHAR(97) NOT NULL PRIMARY KEY,
+    delivered INTEGER NOT NULL
+);
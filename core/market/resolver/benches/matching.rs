@@ -0,0 +1,136 @@
+//! Compares matching one Demand against many Offers:
+//! - parsing both sides for every pair (`match_demand_offer`),
+//! - matching subscriptions prepared once (`match_prepared`),
+//! - matching prepared subscriptions after rejecting pairs by `Prefilter`.
+//!
+//! Run with `cargo bench -p ya-market-resolver [-- <offers count>]`.
+
+use std::time::Instant;
+
+use ya_market_resolver::{
+    match_demand_offer, match_prepared, prepare_subscription, Match, Prefilter,
+    PreparedSubscription, PropertyResolvers, DEFAULT_INDEXED_PROPERTIES,
+};
+
+const DEMAND_PROPERTIES: &str = r#"{
+  "golem.node.id.name": "requestor",
+  "golem.node.debug.subnet": "bench",
+  "golem.srv.comp.expiration": 1590765503361
+}"#;
+
+const DEMAND_CONSTRAINTS: &str = r#"(&
+  (golem.runtime.name=vm)
+  (golem.node.debug.subnet=bench)
+  (golem.inf.mem.gib>=4)
+  (golem.inf.cpu.threads>=2)
+  (golem.com.pricing.model=linear)
+)"#;
+
+const OFFER_CONSTRAINTS: &str = "(&(golem.node.debug.subnet=bench)(golem.srv.comp.expiration>0))";
+
+const RUNTIMES: &[&str] = &["vm", "wasmtime", "emscripten"];
+
+fn offer_properties(idx: usize) -> String {
+    format!(
+        r#"{{
+  "golem.com.pricing.model": "linear",
+  "golem.com.pricing.model.linear.coeffs": [0.1, 0.2, 1.0],
+  "golem.inf.mem.gib": {},
+  "golem.inf.storage.gib": 10.0,
+  "golem.inf.cpu.cores": {},
+  "golem.inf.cpu.threads": {},
+  "golem.node.debug.subnet": "bench",
+  "golem.node.id.name": "provider-{}",
+  "golem.runtime.name": "{}"
+}}"#,
+        1 + idx % 8,
+        1 + idx % 4,
+        1 + idx % 8,
+        idx,
+        RUNTIMES[idx % RUNTIMES.len()]
+    )
+}
+
+fn measure(name: &str, offers: usize, mut f: impl FnMut() -> usize) {
+    // warm up
+    let matched = f();
+
+    let iterations = 10;
+    let started = Instant::now();
+    for _ in 0..iterations {
+        assert_eq!(f(), matched);
+    }
+    let elapsed = started.elapsed() / iterations;
+
+    println!(
+        "{:<24} {:>8} offers {:>6} matched {:>12?} per scan {:>10?} per offer",
+        name,
+        offers,
+        matched,
+        elapsed,
+        elapsed / offers as u32
+    );
+}
+
+fn main() {
+    let count = std::env::args()
+        .skip(1)
+        .find_map(|arg| arg.parse::<usize>().ok())
+        .unwrap_or(5000);
+
+    let offers: Vec<String> = (0..count).map(offer_properties).collect();
+    let resolvers = PropertyResolvers::builtin();
+
+    measure("match_demand_offer", count, || {
+        offers
+            .iter()
+            .filter(|properties| {
+                match_demand_offer(
+                    DEMAND_PROPERTIES,
+                    DEMAND_CONSTRAINTS,
+                    properties,
+                    OFFER_CONSTRAINTS,
+                )
+                .unwrap()
+                    == Match::Yes
+            })
+            .count()
+    });
+
+    let started = Instant::now();
+    let demand = prepare_subscription(DEMAND_PROPERTIES, DEMAND_CONSTRAINTS)
+        .unwrap()
+        .with_resolvers(resolvers.clone());
+    let demand_prefilter = Prefilter::from(&demand, DEFAULT_INDEXED_PROPERTIES);
+    let prepared: Vec<(PreparedSubscription, Prefilter)> = offers
+        .iter()
+        .map(|properties| {
+            let offer = prepare_subscription(properties, OFFER_CONSTRAINTS)
+                .unwrap()
+                .with_resolvers(resolvers.clone());
+            let prefilter = Prefilter::from(&offer, DEFAULT_INDEXED_PROPERTIES);
+            (offer, prefilter)
+        })
+        .collect();
+    println!(
+        "{:<24} {:>8} offers {:>12?} once",
+        "prepare + index",
+        count,
+        started.elapsed()
+    );
+
+    measure("match_prepared", count, || {
+        prepared
+            .iter()
+            .filter(|(offer, _)| match_prepared(&demand, offer).unwrap() == Match::Yes)
+            .count()
+    });
+
+    measure("prefilter + prepared", count, || {
+        prepared
+            .iter()
+            .filter(|(_, prefilter)| demand_prefilter.may_match(prefilter))
+            .filter(|(offer, _)| match_prepared(&demand, offer).unwrap() == Match::Yes)
+            .count()
+    });
+}
@@ -8,9 +8,16 @@ use resolver::error::MatchError as InternalMatchErorr;
 
 use crate::resolver::properties::PropertyRef;
 use flatten::{flatten_properties, FlattenError};
+pub use resolver::dynamic::{PropertyResolver, PropertyResolvers, TimeResolver};
 use resolver::error::PrepareError;
-pub use resolver::matching::{match_weak, MatchResult};
-pub use resolver::prepare::{PreparedDemand, PreparedOffer};
+pub use resolver::explain::{
+    explain_prepared, explain_weak, ExplainNode, ExplainOperator, ExplainResult, MatchExplanation,
+};
+pub use resolver::lint::{lint_constraints, LintKind, LintWarning, PropertySchema, PropertyType};
+pub use resolver::matching::{match_weak, match_weak_prepared, MatchResult};
+pub use resolver::prefilter::{Prefilter, DEFAULT_INDEXED_PROPERTIES};
+pub use resolver::prepare::{PreparedDemand, PreparedOffer, PreparedSubscription};
+pub use resolver::scoring::{Ranking, ScoreExpression, LIMIT_PROPERTY, SCORE_PROPERTY};
 
 #[derive(Debug, PartialEq)]
 pub enum Match {
@@ -40,13 +47,62 @@ pub fn match_demand_offer(
     demand_constraints: &str,
     offer_properties: &str,
     offer_constraints: &str,
+) -> Result<Match, MatchError> {
+    match_demand_offer_with_resolvers(
+        demand_properties,
+        demand_constraints,
+        offer_properties,
+        offer_constraints,
+        &PropertyResolvers::builtin(),
+    )
+}
+
+/// Matches Demand and Offer, resolving absent and implicit properties
+/// of both sides with given dynamic property resolvers.
+pub fn match_demand_offer_with_resolvers(
+    demand_properties: &str,
+    demand_constraints: &str,
+    offer_properties: &str,
+    offer_constraints: &str,
+    resolvers: &PropertyResolvers,
 ) -> Result<Match, MatchError> {
     let demand = Demand::from(demand_properties, demand_constraints)?;
-    let prep_demand_result = PreparedDemand::from(&demand)?;
+    let mut prep_demand_result = PreparedDemand::from(&demand)?;
+    prep_demand_result
+        .properties
+        .set_resolvers(resolvers.clone());
     let offer = Offer::from(offer_properties, offer_constraints)?;
-    let prep_offer_result = PreparedOffer::from(&offer)?;
+    let mut prep_offer_result = PreparedOffer::from(&offer)?;
+    prep_offer_result
+        .properties
+        .set_resolvers(resolvers.clone());
+
+    into_match(match_weak(&prep_demand_result, &prep_offer_result)?)
+}
+
+/// Flattens properties and parses constraints of Offer or Demand once,
+/// so that it can be matched many times with `match_prepared`.
+pub fn prepare_subscription(
+    properties: &str,
+    constraints: &str,
+) -> Result<PreparedSubscription, MatchError> {
+    Ok(PreparedSubscription::from(
+        flatten_properties(properties)?,
+        constraints,
+    )?)
+}
+
+/// Matches already prepared Demand and Offer. Dynamic property resolvers
+/// are the ones set in their property sets.
+pub fn match_prepared(
+    demand: &PreparedSubscription,
+    offer: &PreparedSubscription,
+) -> Result<Match, MatchError> {
+    into_match(match_weak_prepared(demand, offer)?)
+}
 
-    match match_weak(&prep_demand_result, &prep_offer_result)? {
+fn into_match(result: MatchResult) -> Result<Match, MatchError> {
+    match result {
         MatchResult::True => Ok(Match::Yes),
         MatchResult::False(from_offer, from_demand) => Ok(Match::No {
             offer_mismatch: extract_names(&from_offer),
@@ -60,6 +116,59 @@ pub fn match_demand_offer(
     }
 }
 
+/// Explains the match relation between Demand and Offer.
+/// Returns the evaluation trace of both constraint expressions, where every clause
+/// carries its result, the property value it saw and the literal it was compared against.
+pub fn explain_match(
+    demand_properties: &str,
+    demand_constraints: &str,
+    offer_properties: &str,
+    offer_constraints: &str,
+) -> Result<MatchExplanation, MatchError> {
+    explain_match_with_resolvers(
+        demand_properties,
+        demand_constraints,
+        offer_properties,
+        offer_constraints,
+        &PropertyResolvers::builtin(),
+    )
+}
+
+/// Explains the match relation between Demand and Offer, resolving absent and implicit
+/// properties of both sides with given dynamic property resolvers.
+pub fn explain_match_with_resolvers(
+    demand_properties: &str,
+    demand_constraints: &str,
+    offer_properties: &str,
+    offer_constraints: &str,
+    resolvers: &PropertyResolvers,
+) -> Result<MatchExplanation, MatchError> {
+    let demand = Demand::from(demand_properties, demand_constraints)?;
+    let mut prep_demand_result = PreparedDemand::from(&demand)?;
+    prep_demand_result
+        .properties
+        .set_resolvers(resolvers.clone());
+    let offer = Offer::from(offer_properties, offer_constraints)?;
+    let mut prep_offer_result = PreparedOffer::from(&offer)?;
+    prep_offer_result
+        .properties
+        .set_resolvers(resolvers.clone());
+
+    Ok(explain_weak(&prep_demand_result, &prep_offer_result))
+}
+
+/// Parses constraints and prints them back in normal form (see `Expression::normalize`).
+/// Logically equivalent constraints, which differ only in ordering, nesting or duplicates
+/// of clauses, result in the same text.
+pub fn canonical_constraints(constraints: &str) -> Result<String, PrepareError> {
+    let tags = resolver::ldap_parser::parse(constraints)
+        .map_err(|error| PrepareError::new(&format!("Error parsing constraints: {}", error)))?;
+    let expression = resolver::expression::build_expression(&tags).map_err(|error| {
+        PrepareError::new(&format!("Error building constraints expression: {}", error))
+    })?;
+    Ok(expression.to_canonical_string())
+}
+
 fn extract_names(props_vec: &Vec<&PropertyRef>) -> Vec<String> {
     props_vec
         .iter()
@@ -1,12 +1,23 @@
+pub mod canonical;
+pub mod dynamic;
 pub mod error;
+pub mod explain;
 pub mod expression;
 pub mod ldap_parser;
+pub mod lint;
 pub mod matching;
+pub mod prefilter;
 pub mod prepare;
 pub mod prop_parser;
 pub mod properties;
+pub mod scoring;
 
+pub use self::dynamic::{PropertyResolver, PropertyResolvers};
+pub use self::explain::{explain_prepared, explain_weak, MatchExplanation};
 pub use self::expression::Expression;
-pub use self::matching::match_weak;
-pub use self::prepare::{PreparedDemand, PreparedOffer};
+pub use self::lint::{lint, lint_constraints, LintWarning, PropertySchema};
+pub use self::matching::{match_weak, match_weak_prepared};
+pub use self::prefilter::Prefilter;
+pub use self::prepare::{PreparedDemand, PreparedOffer, PreparedSubscription};
 pub use self::properties::PropertySet;
+pub use self::scoring::{Ranking, ScoreExpression};
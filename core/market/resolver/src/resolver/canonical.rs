@@ -0,0 +1,108 @@
+use std::fmt;
+
+use super::expression::Expression;
+use super::ldap_parser::escape_value;
+
+// #region Printer
+
+// Expression in LDAP filter syntax accepted by ldap_parser, without any whitespace.
+// Literals are printed in the form kept by expression: typed literals compared with property
+// values keep their type (eg. `(a>=v"1.0.0")`), other ones are bare (eg. `(a=TRUE)` is printed
+// as `(a=true)`) and types of literals compared with aspects are carried by property references.
+// Literals are escaped, so that they are parsed back to the same value
+// (wildcards and escaped asterisks are kept apart, see `ldap_parser::literal_value`).
+// Empty(false) has no LDAP representation, so it is printed as negation of empty filter.
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Expression::Equals(prop_ref, val) => write!(f, "({}={})", prop_ref, escape_value(val)),
+            Expression::Greater(prop_ref, val) => write!(f, "({}>{})", prop_ref, escape_value(val)),
+            Expression::GreaterEqual(prop_ref, val) => {
+                write!(f, "({}>={})", prop_ref, escape_value(val))
+            }
+            Expression::Less(prop_ref, val) => write!(f, "({}<{})", prop_ref, escape_value(val)),
+            Expression::LessEqual(prop_ref, val) => {
+                write!(f, "({}<={})", prop_ref, escape_value(val))
+            }
+            Expression::Present(prop_ref) => write!(f, "({}=*)", prop_ref),
+            Expression::And(operands) => write_operands(f, "&", operands),
+            Expression::Or(operands) => write_operands(f, "|", operands),
+            Expression::Not(operand) => write!(f, "(!{})", operand),
+            Expression::Empty(true) => write!(f, "()"),
+            Expression::Empty(false) => write!(f, "(!())"),
+        }
+    }
+}
+
+fn write_operands(f: &mut fmt::Formatter, oper: &str, operands: &[Box<Expression>]) -> fmt::Result {
+    write!(f, "({}", oper)?;
+    for operand in operands {
+        write!(f, "{}", operand)?;
+    }
+    write!(f, ")")
+}
+
+// #endregion
+
+// #region Normalizer
+
+impl Expression {
+    // Logically equivalent expression in normal form:
+    // - nested And/Or expressions are flattened,
+    // - Empty operands are removed (or absorb the whole expression, eg. false in And),
+    // - double negations and negations of Empty are removed,
+    // - duplicate operands are removed and remaining ones are ordered by their canonical text,
+    // - And/Or with single operand is replaced by the operand.
+    // Normalized expressions are equal if they differ only in the above aspects.
+    pub fn normalize(&self) -> Expression {
+        match self {
+            Expression::And(operands) => normalize_operands(operands, true),
+            Expression::Or(operands) => normalize_operands(operands, false),
+            Expression::Not(operand) => match operand.normalize() {
+                Expression::Empty(val) => Expression::Empty(!val),
+                Expression::Not(inner) => *inner,
+                inner => Expression::Not(Box::new(inner)),
+            },
+            _ => self.clone(),
+        }
+    }
+
+    // Expression text in normal form, suitable for comparing constraints
+    pub fn to_canonical_string(&self) -> String {
+        self.normalize().to_string()
+    }
+}
+
+// Normalize operands of And (conjunction == true) or Or expression
+fn normalize_operands(operands: &[Box<Expression>], conjunction: bool) -> Expression {
+    let mut normalized: Vec<Box<Expression>> = vec![];
+
+    for operand in operands {
+        match operand.normalize() {
+            // neutral element is skipped, absorbing element absorbs the whole expression
+            Expression::Empty(val) if val == conjunction => {}
+            Expression::Empty(val) => return Expression::Empty(val),
+            Expression::And(inner) if conjunction => normalized.extend(inner),
+            Expression::Or(inner) if !conjunction => normalized.extend(inner),
+            other => normalized.push(Box::new(other)),
+        }
+    }
+
+    let mut keyed: Vec<(String, Box<Expression>)> = normalized
+        .into_iter()
+        .map(|operand| (operand.to_string(), operand))
+        .collect();
+    keyed.sort_by(|(key1, _), (key2, _)| key1.cmp(key2));
+    keyed.dedup_by(|(key1, _), (key2, _)| key1 == key2);
+
+    let mut operands: Vec<Box<Expression>> =
+        keyed.into_iter().map(|(_, operand)| operand).collect();
+    match operands.len() {
+        0 => Expression::Empty(conjunction),
+        1 => *operands.pop().unwrap(),
+        _ if conjunction => Expression::And(operands),
+        _ => Expression::Or(operands),
+    }
+}
+
+// #endregion
@@ -0,0 +1,106 @@
+use std::fmt;
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+
+// Names of built-in time-based dynamic properties
+pub const PROP_NOW: &str = "now"; // current time (DateTime)
+pub const PROP_NOW_TIMESTAMP: &str = "now.timestamp"; // current time as UNIX timestamp in seconds (Number)
+
+// Dynamic property resolver.
+// Consulted by PropertySet when a referenced property is absent or declared without value (implicit),
+// so that properties can be computed at match time (eg. current free slots, current price, current time).
+pub trait PropertyResolver: Send + Sync {
+    // Returns property value literal (in the same syntax as flat property values,
+    // eg. `t"2020-01-01T00:00:00Z"`, `5`, `"text"`), or None if property isn't handled by this resolver.
+    fn resolve(&self, name: &str) -> Option<String>;
+}
+
+// #region PropertyResolvers
+
+// Ordered collection of dynamic property resolvers. First resolver which answers wins.
+#[derive(Clone, Default)]
+pub struct PropertyResolvers {
+    resolvers: Vec<Arc<dyn PropertyResolver>>,
+}
+
+impl PropertyResolvers {
+    // Resolvers shipped with the market resolver (time-based properties)
+    pub fn builtin() -> PropertyResolvers {
+        let mut resolvers = PropertyResolvers::default();
+        resolvers.add(Arc::new(TimeResolver::new()));
+        resolvers
+    }
+
+    pub fn add(&mut self, resolver: Arc<dyn PropertyResolver>) {
+        self.resolvers.push(resolver);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.resolvers.is_empty()
+    }
+
+    pub fn resolve(&self, name: &str) -> Option<String> {
+        self.resolvers
+            .iter()
+            .find_map(|resolver| resolver.resolve(name))
+    }
+}
+
+impl fmt::Debug for PropertyResolvers {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "PropertyResolvers({})", self.resolvers.len())
+    }
+}
+
+// Resolver collections are equal if they consist of the same resolver instances.
+impl PartialEq for PropertyResolvers {
+    fn eq(&self, other: &Self) -> bool {
+        self.resolvers.len() == other.resolvers.len()
+            && self
+                .resolvers
+                .iter()
+                .zip(other.resolvers.iter())
+                .all(|(r1, r2)| Arc::ptr_eq(r1, r2))
+    }
+}
+
+// #endregion
+
+// #region TimeResolver
+
+// Built-in resolver of time-based properties (`now`, `now.timestamp`)
+pub struct TimeResolver {
+    clock: Box<dyn Fn() -> DateTime<Utc> + Send + Sync>,
+}
+
+impl TimeResolver {
+    pub fn new() -> TimeResolver {
+        TimeResolver::with_clock(Utc::now)
+    }
+
+    // Resolver with custom time source (eg. fixed time for testing)
+    pub fn with_clock(clock: impl Fn() -> DateTime<Utc> + Send + Sync + 'static) -> TimeResolver {
+        TimeResolver {
+            clock: Box::new(clock),
+        }
+    }
+}
+
+impl Default for TimeResolver {
+    fn default() -> Self {
+        TimeResolver::new()
+    }
+}
+
+impl PropertyResolver for TimeResolver {
+    fn resolve(&self, name: &str) -> Option<String> {
+        match name {
+            PROP_NOW => Some(format!("t\"{}\"", (self.clock)().to_rfc3339())),
+            PROP_NOW_TIMESTAMP => Some((self.clock)().timestamp().to_string()),
+            _ => None,
+        }
+    }
+}
+
+// #endregion
@@ -0,0 +1,299 @@
+use serde::{Deserialize, Serialize};
+
+use super::expression::{Expression, ResolveResult};
+use super::prepare::{PreparedDemand, PreparedOffer, PreparedSubscription};
+use super::properties::{Property, PropertyRef, PropertySet, PropertyValue};
+
+// Result of a single explained clause
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ExplainResult {
+    True,
+    False,
+    Undefined,
+    Error(String),
+}
+
+impl<'a> From<&ResolveResult<'a>> for ExplainResult {
+    fn from(result: &ResolveResult<'a>) -> Self {
+        match result {
+            ResolveResult::True => ExplainResult::True,
+            ResolveResult::False(_, _) => ExplainResult::False,
+            ResolveResult::Undefined(_, _) => ExplainResult::Undefined,
+            ResolveResult::Err(err) => ExplainResult::Error(err.msg.clone()),
+        }
+    }
+}
+
+// Operator of the explained clause (mirrors Expression variants)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ExplainOperator {
+    Equals,
+    Greater,
+    GreaterEqual,
+    Less,
+    LessEqual,
+    Present,
+    Or,
+    And,
+    Not,
+    Empty,
+}
+
+// Evaluation trace of a filter expression.
+// The tree mirrors the structure of the LDAP expression, each node carries its own result,
+// the property value seen in the PropertySet and the literal it was compared against.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExplainNode {
+    pub operator: ExplainOperator,
+    pub result: ExplainResult,
+    // Referenced property (name[aspect]), for comparison and presence clauses only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub property: Option<String>,
+    // Property (or aspect) value found in the PropertySet or computed by dynamic property resolvers
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    // Literal from the filter expression
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub literal: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub operands: Vec<ExplainNode>,
+}
+
+// Explanation of the weak match relation between Demand and Offer
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MatchExplanation {
+    pub result: ExplainResult,
+    // Demand constraints resolved against Offer properties
+    pub demand_constraints: ExplainNode,
+    // Offer constraints resolved against Demand properties
+    pub offer_constraints: ExplainNode,
+}
+
+impl Expression {
+    // Resolve the expression with a given PropertySet, evaluating every clause (no eager resolution)
+    // and returning the full evaluation trace.
+    // Results of the trace are consistent with Expression::resolve().
+    pub fn explain<'a>(&'a self, property_set: &'a PropertySet) -> ExplainNode {
+        match self {
+            Expression::Equals(attr, val) => {
+                self.explain_leaf(ExplainOperator::Equals, attr, Some(&val[..]), property_set)
+            }
+            Expression::Greater(attr, val) => {
+                self.explain_leaf(ExplainOperator::Greater, attr, Some(&val[..]), property_set)
+            }
+            Expression::GreaterEqual(attr, val) => self.explain_leaf(
+                ExplainOperator::GreaterEqual,
+                attr,
+                Some(&val[..]),
+                property_set,
+            ),
+            Expression::Less(attr, val) => {
+                self.explain_leaf(ExplainOperator::Less, attr, Some(&val[..]), property_set)
+            }
+            Expression::LessEqual(attr, val) => self.explain_leaf(
+                ExplainOperator::LessEqual,
+                attr,
+                Some(&val[..]),
+                property_set,
+            ),
+            Expression::Present(attr) => {
+                self.explain_leaf(ExplainOperator::Present, attr, None, property_set)
+            }
+            Expression::And(inner_expressions) => {
+                let operands = explain_all(inner_expressions, property_set);
+                ExplainNode::composite(ExplainOperator::And, and_result(&operands), operands)
+            }
+            Expression::Or(inner_expressions) => {
+                let operands = explain_all(inner_expressions, property_set);
+                ExplainNode::composite(ExplainOperator::Or, or_result(&operands), operands)
+            }
+            Expression::Not(inner_expression) => {
+                let operand = inner_expression.explain(property_set);
+                let result = match &operand.result {
+                    ExplainResult::True => ExplainResult::False,
+                    ExplainResult::False => ExplainResult::True,
+                    other => other.clone(),
+                };
+                ExplainNode::composite(ExplainOperator::Not, result, vec![operand])
+            }
+            Expression::Empty(val) => ExplainNode::composite(
+                ExplainOperator::Empty,
+                if *val {
+                    ExplainResult::True
+                } else {
+                    ExplainResult::False
+                },
+                vec![],
+            ),
+        }
+    }
+
+    fn explain_leaf<'a>(
+        &'a self,
+        operator: ExplainOperator,
+        prop_ref: &'a PropertyRef,
+        literal: Option<&str>,
+        property_set: &'a PropertySet,
+    ) -> ExplainNode {
+        ExplainNode {
+            operator,
+            result: ExplainResult::from(&self.resolve(property_set)),
+            property: Some(prop_ref_name(prop_ref)),
+            value: seen_value(prop_ref, property_set),
+            literal: literal.map(|val| val.to_string()),
+            operands: vec![],
+        }
+    }
+}
+
+impl ExplainNode {
+    fn composite(
+        operator: ExplainOperator,
+        result: ExplainResult,
+        operands: Vec<ExplainNode>,
+    ) -> ExplainNode {
+        ExplainNode {
+            operator,
+            result,
+            property: None,
+            value: None,
+            literal: None,
+            operands,
+        }
+    }
+}
+
+// Explain match relation, ie. resolve constraints of each side against properties of the other side
+// and combine the results the same way as match_weak() does.
+pub fn explain_weak<'a>(demand: &'a PreparedDemand, offer: &'a PreparedOffer) -> MatchExplanation {
+    explain_sides(
+        (&demand.constraints, &demand.properties),
+        (&offer.constraints, &offer.properties),
+    )
+}
+
+// Explain match relation of already prepared Offer and Demand
+pub fn explain_prepared(
+    demand: &PreparedSubscription,
+    offer: &PreparedSubscription,
+) -> MatchExplanation {
+    explain_sides(
+        (&demand.constraints, demand.property_set()),
+        (&offer.constraints, offer.property_set()),
+    )
+}
+
+fn explain_sides(
+    (demand_constraints, demand_properties): (&Expression, &PropertySet),
+    (offer_constraints, offer_properties): (&Expression, &PropertySet),
+) -> MatchExplanation {
+    let demand_constraints = demand_constraints.explain(offer_properties);
+    let offer_constraints = offer_constraints.explain(demand_properties);
+
+    let result = match (&demand_constraints.result, &offer_constraints.result) {
+        (ExplainResult::Error(err), _) | (_, ExplainResult::Error(err)) => {
+            ExplainResult::Error(err.clone())
+        }
+        (ExplainResult::Undefined, _) | (_, ExplainResult::Undefined) => ExplainResult::Undefined,
+        (ExplainResult::True, ExplainResult::True) => ExplainResult::True,
+        _ => ExplainResult::False,
+    };
+
+    MatchExplanation {
+        result,
+        demand_constraints,
+        offer_constraints,
+    }
+}
+
+fn explain_all<'a>(
+    seq: &'a Vec<Box<Expression>>,
+    property_set: &'a PropertySet,
+) -> Vec<ExplainNode> {
+    seq.iter().map(|exp| exp.explain(property_set)).collect()
+}
+
+// AND is evaluated operand by operand, the same way as Expression::resolve() does,
+// so the first operand resolving to false or error decides the result.
+// Otherwise it is undefined if any operand is undefined, or true.
+fn and_result(operands: &Vec<ExplainNode>) -> ExplainResult {
+    sequence_result(operands, ExplainResult::False, ExplainResult::True)
+}
+
+// OR is evaluated operand by operand, the same way as Expression::resolve() does,
+// so the first operand resolving to true or error decides the result.
+// Otherwise it is undefined if any operand is undefined, or false.
+fn or_result(operands: &Vec<ExplainNode>) -> ExplainResult {
+    sequence_result(operands, ExplainResult::True, ExplainResult::False)
+}
+
+fn sequence_result(
+    operands: &Vec<ExplainNode>,
+    decisive: ExplainResult,
+    otherwise: ExplainResult,
+) -> ExplainResult {
+    let mut undefined_found = false;
+    for op in operands {
+        match &op.result {
+            ExplainResult::Error(_) => return op.result.clone(),
+            ExplainResult::Undefined => undefined_found = true,
+            result if *result == decisive => return decisive,
+            _ => {}
+        }
+    }
+    if undefined_found {
+        ExplainResult::Undefined
+    } else {
+        otherwise
+    }
+}
+
+fn prop_ref_name(prop_ref: &PropertyRef) -> String {
+    match prop_ref {
+        PropertyRef::Value(name, _) => name.to_string(),
+        PropertyRef::Aspect(name, aspect, _) => format!("{}[{}]", name, aspect),
+    }
+}
+
+// Value of referenced property (or aspect), converted to the type implied by the reference
+fn seen_value(prop_ref: &PropertyRef, property_set: &PropertySet) -> Option<String> {
+    let name = match prop_ref {
+        PropertyRef::Value(n, _) => n,
+        PropertyRef::Aspect(n, _a, _) => n,
+    };
+
+    let property = match property_set.properties.get(&name[..]) {
+        Some(property) => property,
+        None => return dynamic_value(prop_ref, property_set),
+    };
+
+    match property {
+        Property::Explicit(_name, value, aspects) => match prop_ref {
+            PropertyRef::Value(_n, impl_type) => match value.to_prop_ref_type(impl_type) {
+                Ok(Some(conv_value)) => Some(conv_value.to_string()),
+                _ => Some(value.to_string()),
+            },
+            PropertyRef::Aspect(_n, aspect, _impl_type) => {
+                aspects.get(&aspect[..]).map(|val| val.to_string())
+            }
+        },
+        Property::Implicit(_name) => dynamic_value(prop_ref, property_set),
+    }
+}
+
+// Value computed at match time by dynamic property resolvers (aspects are not supported)
+fn dynamic_value(prop_ref: &PropertyRef, property_set: &PropertySet) -> Option<String> {
+    let literal = match prop_ref {
+        PropertyRef::Value(name, _) => property_set.resolvers.resolve(name)?,
+        PropertyRef::Aspect(..) => return None,
+    };
+    let value = match PropertyValue::from_value(&literal) {
+        Ok(value) => value.to_string(),
+        Err(_) => literal.clone(),
+    };
+    Some(value)
+}
@@ -4,7 +4,11 @@ use asnom::structures::{ExplicitTag, OctetString, Tag};
 
 use super::error::{ExpressionError, ResolveError};
 use super::ldap_parser;
-use super::properties::{parse_prop_ref, Property, PropertyRef, PropertySet, PropertyValue};
+use super::prop_parser;
+use super::prop_parser::Literal;
+use super::properties::{
+    parse_prop_ref, Property, PropertyRef, PropertyRefType, PropertySet, PropertyValue,
+};
 
 // Expression resolution result enum
 #[derive(Debug, Clone, PartialEq)]
@@ -31,15 +35,15 @@ pub enum Expression {
 }
 
 impl Expression {
-    // Resolve the expression with a give PropertySet and return the reduced result or error message.
+    // Resolve the expression with a give PropertySet and return the reduced (and normalized) result or error message.
     pub fn resolve_reduce<'a>(
         &'a self,
         property_set: &'a PropertySet,
     ) -> Result<Expression, String> {
         match self.resolve(property_set) {
             ResolveResult::True => Ok(Expression::Empty(true)),
-            ResolveResult::False(_, expr) => Ok(expr),
-            ResolveResult::Undefined(_, expr) => Ok(expr),
+            ResolveResult::False(_, expr) => Ok(expr.normalize()),
+            ResolveResult::Undefined(_, expr) => Ok(expr.normalize()),
             ResolveResult::Err(err) => Err(err.msg),
         }
     }
@@ -80,7 +84,7 @@ impl Expression {
         }
     }
 
-    // TODO: Implement ultimate reduction of AND and OR expressions where only one factor remains
+    // (DONE) Implement ultimate reduction of AND and OR expressions where only one factor remains (see normalize())
 
     // (DONE) Rework for adjusted property definition syntax (property types derived form literals)
     // (DONE) Implement strong resolution and expression 'reduce' (ie. undefined results are propagated rather than ignored)
@@ -93,12 +97,12 @@ impl Expression {
     // (DONE) Handling of List property type
     // (DONE) equals operator for List property type with single operand (ignore other comparison operators)
     // (DONE) equals operator for List property type with List operand (list equivalence operator)
-    // TODO: Handling of types in constraint filter expressions
+    // (DONE) Handling of types in constraint filter expressions
     // TODO: Implement allowed characters in property names
     // (DONE) Rework resolve so that ResolveResult is based on strs and not Strings
     // (DONE) wildcard matching of property values
-    // TODO: wildcard matching of value-less properties
-    //       - Implement dynamic property "handler" - via trait?
+    // (DONE) wildcard matching of value-less properties
+    //       - (DONE) Implement dynamic property "handler" - via trait (PropertyResolver)
     // (DONE) aspects
     // TODO: finalize and review the matching relation implementations
     pub fn resolve<'a>(&'a self, property_set: &'a PropertySet) -> ResolveResult {
@@ -162,8 +166,8 @@ impl Expression {
         property_set: &'a PropertySet,
         oper_function: impl Fn(&PropertyValue, &str) -> bool,
     ) -> ResolveResult {
-        // TODO this requires rewrite to cater for implicit properties...
-        // test if property exists and then if the value matches
+        // test if property exists and then if the value matches,
+        // absent and implicit properties are resolved dynamically
 
         // extract referred property name
         let name = match prop_ref {
@@ -178,26 +182,7 @@ impl Expression {
                         // now decide if we are referring to value or aspect
                         match prop_ref {
                             PropertyRef::Value(_n, impl_type) => {
-                                match value.to_prop_ref_type(impl_type) {
-                                    Ok(conv_result) => {
-                                        let resolve_result = match conv_result {
-                                            Some(val) => oper_function(&val, val_string),
-                                            None => oper_function(value, val_string),
-                                        };
-
-                                        // resolve against prop value
-                                        if resolve_result {
-                                            ResolveResult::True
-                                        } else {
-                                            ResolveResult::False(vec![], Expression::Empty(false))
-                                            // if resolved to false - return Empty as reduced expression
-                                        }
-                                    }
-                                    Err(_) => {
-                                        ResolveResult::Undefined(vec![], self.clone())
-                                        // if resolved to undefined - return self copy as reduced expression (cannot reduce self)
-                                    }
-                                }
+                                self.resolve_with_value(value, impl_type, val_string, oper_function)
                             }
                             PropertyRef::Aspect(_n, aspect, _impl_type) => {
                                 // resolve against prop aspect
@@ -219,10 +204,91 @@ impl Expression {
                         }
                     }
                     Property::Implicit(_name) => {
-                        ResolveResult::Undefined(vec![prop_ref], self.clone()) // if resolved to undefined - return self copy as reduced expression (cannot reduce self)
+                        self.resolve_dynamic(prop_ref, val_string, property_set, oper_function)
                     }
                 }
             }
+            None => self.resolve_dynamic(prop_ref, val_string, property_set, oper_function),
+        }
+    }
+
+    // Resolve against property value (converted to type implied by property reference)
+    fn resolve_with_value<'a>(
+        &'a self,
+        value: &PropertyValue,
+        impl_type: &PropertyRefType,
+        val_string: &str,
+        oper_function: impl Fn(&PropertyValue, &str) -> bool,
+    ) -> ResolveResult<'a> {
+        // Typed literal is compared only with property of the same declared type
+        // (or string in format of that type). Property of other type is an error,
+        // so that it can't be mistaken for a non-matching value, while property
+        // reference type code would leave it undefined.
+        if let Some((literal_type, literal)) = typed_literal(val_string) {
+            let resolve_result = match value.to_prop_ref_type(&literal_type) {
+                Ok(Some(val)) => oper_function(&val, literal),
+                Ok(None) => oper_function(value, literal),
+                Err(error) => {
+                    return ResolveResult::Err(ResolveError::new(&format!(
+                        "Type of literal {} doesn't match property value: {}",
+                        val_string, error
+                    )))
+                }
+            };
+            return if resolve_result {
+                ResolveResult::True
+            } else {
+                ResolveResult::False(vec![], Expression::Empty(false))
+            };
+        }
+
+        match value.to_prop_ref_type(impl_type) {
+            Ok(conv_result) => {
+                let resolve_result = match conv_result {
+                    Some(val) => oper_function(&val, val_string),
+                    None => oper_function(value, val_string),
+                };
+
+                // resolve against prop value
+                if resolve_result {
+                    ResolveResult::True
+                } else {
+                    ResolveResult::False(vec![], Expression::Empty(false))
+                    // if resolved to false - return Empty as reduced expression
+                }
+            }
+            Err(_) => {
+                ResolveResult::Undefined(vec![], self.clone())
+                // if resolved to undefined - return self copy as reduced expression (cannot reduce self)
+            }
+        }
+    }
+
+    // Resolve against value of property computed at match time by dynamic property resolvers.
+    // Used for properties which are absent or value-less (implicit).
+    fn resolve_dynamic<'a>(
+        &'a self,
+        prop_ref: &'a PropertyRef,
+        val_string: &str,
+        property_set: &'a PropertySet,
+        oper_function: impl Fn(&PropertyValue, &str) -> bool,
+    ) -> ResolveResult<'a> {
+        // aspects of dynamic properties are not supported
+        let (literal, impl_type) = match prop_ref {
+            PropertyRef::Value(name, impl_type) => {
+                (property_set.resolvers.resolve(name), impl_type)
+            }
+            PropertyRef::Aspect(_n, _a, _) => (None, &PropertyRefType::Any),
+        };
+
+        match literal {
+            Some(literal) => match PropertyValue::from_value(&literal) {
+                Ok(value) => self.resolve_with_value(&value, impl_type, val_string, oper_function),
+                Err(error) => {
+                    log::debug!("Dynamic property resolution error: {}", error);
+                    ResolveResult::Undefined(vec![prop_ref], self.clone())
+                }
+            },
             None => {
                 ResolveResult::Undefined(vec![prop_ref], self.clone()) // if resolved to undefined - return self copy as reduced expression (cannot reduce self)
             }
@@ -353,7 +419,11 @@ impl Expression {
             // for value reference - only check if property exists in PropertySet
             PropertyRef::Value(name, _) => match property_set.properties.get(&name[..]) {
                 Some(_value) => ResolveResult::True,
-                None => ResolveResult::False(vec![attr], Expression::Empty(false)),
+                // dynamic properties are present if any resolver is able to compute them
+                None => match property_set.resolvers.resolve(name) {
+                    Some(_value) => ResolveResult::True,
+                    None => ResolveResult::False(vec![attr], Expression::Empty(false)),
+                },
             },
             // for aspect reference - first check if property exists, then check for aspect
             PropertyRef::Aspect(name, aspect, _) => {
@@ -483,18 +553,15 @@ fn build_simple_expression(
                     )))
                 }
             };
+            let literal = ldap_parser::literal_value(result.1)
+                .map_err(|error| ExpressionError::new(&error))?;
+            let (prop_ref, value) = typecheck_literal(prop_ref, expr_type, &literal)?;
             match expr_type {
-                ldap_parser::TAG_EQUAL => Ok(Expression::Equals(prop_ref, String::from(result.1))),
-                ldap_parser::TAG_GREATER => {
-                    Ok(Expression::Greater(prop_ref, String::from(result.1)))
-                }
-                ldap_parser::TAG_GREATER_EQUAL => {
-                    Ok(Expression::GreaterEqual(prop_ref, String::from(result.1)))
-                }
-                ldap_parser::TAG_LESS => Ok(Expression::Less(prop_ref, String::from(result.1))),
-                ldap_parser::TAG_LESS_EQUAL => {
-                    Ok(Expression::LessEqual(prop_ref, String::from(result.1)))
-                }
+                ldap_parser::TAG_EQUAL => Ok(Expression::Equals(prop_ref, value)),
+                ldap_parser::TAG_GREATER => Ok(Expression::Greater(prop_ref, value)),
+                ldap_parser::TAG_GREATER_EQUAL => Ok(Expression::GreaterEqual(prop_ref, value)),
+                ldap_parser::TAG_LESS => Ok(Expression::Less(prop_ref, value)),
+                ldap_parser::TAG_LESS_EQUAL => Ok(Expression::LessEqual(prop_ref, value)),
                 // add other binary operators handling here
                 _ => Err(ExpressionError::new(&format!(
                     "Unknown expression type {}",
@@ -506,6 +573,98 @@ fn build_simple_expression(
     }
 }
 
+// Typecheck comparison literal against the property reference.
+// Explicitly typed literals are accepted in the same syntax as in property definitions:
+// - d"<decimal>", v"<version>", t"<datetime>" - conflict with a different type code
+//   ($d, $v, $t) given in the reference. With untyped property value reference the literal
+//   is kept in its typed form, so it is checked against declared type of the property
+//   when resolving (see `typed_literal`). Otherwise it is reduced to its bare form
+//   (and aspect references get the type implied by the literal),
+// - true/false and [item1,item2,...] - allowed only with equality operator and untyped reference.
+// Untyped literals are validated against the type code of the reference (if any).
+fn typecheck_literal(
+    prop_ref: PropertyRef,
+    expr_type: u64,
+    literal: &str,
+) -> Result<(PropertyRef, String), ExpressionError> {
+    let literal_type = match prop_parser::parse_prop_value_literal(literal) {
+        Ok(Literal::Decimal(val)) => Some((PropertyRefType::Decimal, val)),
+        Ok(Literal::Version(val)) => Some((PropertyRefType::Version, val)),
+        Ok(Literal::DateTime(val)) => Some((PropertyRefType::DateTime, val)),
+        Ok(Literal::Bool(_)) | Ok(Literal::List(_)) => {
+            if expr_type != ldap_parser::TAG_EQUAL {
+                return Err(ExpressionError::new(&format!(
+                    "Only equality operator allowed for literal {}",
+                    literal
+                )));
+            }
+            if *prop_ref.impl_type() != PropertyRefType::Any {
+                return Err(ExpressionError::new(&format!(
+                    "Literal {} doesn't match property reference type {:?}",
+                    literal,
+                    prop_ref.impl_type()
+                )));
+            }
+            let value = PropertyValue::from_value(literal).map_err(|error| {
+                ExpressionError::new(&format!("Invalid literal {}: {}", literal, error))
+            })?;
+            return Ok((prop_ref, value.to_string()));
+        }
+        // strings, numbers and anything else isn't explicitly typed
+        _ => None,
+    };
+
+    match literal_type {
+        Some((lit_type, val)) => {
+            if let Err(error) = PropertyValue::from_value(literal) {
+                return Err(ExpressionError::new(&format!(
+                    "Invalid literal {}: {}",
+                    literal, error
+                )));
+            }
+            match (&prop_ref, prop_ref.impl_type()) {
+                (PropertyRef::Value(..), PropertyRefType::Any) => {
+                    Ok((prop_ref, literal.to_string()))
+                }
+                (_, PropertyRefType::Any) => {
+                    Ok((prop_ref.with_impl_type(lit_type), val.to_string()))
+                }
+                (_, impl_type) if *impl_type == lit_type => Ok((prop_ref, val.to_string())),
+                (_, impl_type) => Err(ExpressionError::new(&format!(
+                    "Literal {} of type {:?} doesn't match property reference type {:?}",
+                    literal, lit_type, impl_type
+                ))),
+            }
+        }
+        None => {
+            if let Err(error) = PropertyValue::Str(literal).to_prop_ref_type(prop_ref.impl_type()) {
+                return Err(ExpressionError::new(&format!(
+                    "Literal {} doesn't match property reference type {:?}: {}",
+                    literal,
+                    prop_ref.impl_type(),
+                    error
+                )));
+            }
+            Ok((prop_ref, literal.to_string()))
+        }
+    }
+}
+
+// Type and bare value of explicitly typed literal (d"<decimal>", v"<version>", t"<datetime>").
+// None for other literals.
+pub(crate) fn typed_literal(literal: &str) -> Option<(PropertyRefType, &str)> {
+    // cheap check first, since it is called for each resolved comparison
+    if !literal.ends_with('"') {
+        return None;
+    }
+    match prop_parser::parse_prop_value_literal(literal) {
+        Ok(Literal::Decimal(val)) => Some((PropertyRefType::Decimal, val)),
+        Ok(Literal::Version(val)) => Some((PropertyRefType::Version, val)),
+        Ok(Literal::DateTime(val)) => Some((PropertyRefType::DateTime, val)),
+        _ => None,
+    }
+}
+
 fn extract_str_from_octet_string<'a>(tag: &'a Tag) -> Result<&'a str, ExpressionError> {
     match tag {
         Tag::OctetString(oct) => match str::from_utf8(&oct.inner) {
@@ -144,3 +144,71 @@ named!(
 pub fn is_delimiter(chr: u8) -> bool {
     chr == '=' as u8 || chr == '<' as u8 || chr == '>' as u8 || chr == '~' as u8
 }
+
+// Decode escape sequences of filter value (RFC 4515), ie. backslash followed by two hex digits.
+// Backslash not followed by two hex digits is kept as is.
+pub fn unescape_value(value: &str) -> Result<String, String> {
+    decode_value(value, false)
+}
+
+// Literal of filter value as kept by expression: unescaped, except for escaped `*` and `\`,
+// which are kept in escaped form (`\2a` and `\5c`), so that they can't be confused
+// with wildcards. Backslash not starting escape sequence is escaped as well.
+// Wildcard segments of such literal are unescaped separately (see `wildcard_segments`).
+pub fn literal_value(value: &str) -> Result<String, String> {
+    decode_value(value, true)
+}
+
+// Fully unescaped segments of literal split at wildcards. Single segment means no wildcard.
+pub fn wildcard_segments(literal: &str) -> Result<Vec<String>, String> {
+    literal.split('*').map(unescape_value).collect()
+}
+
+fn decode_value(value: &str, keep_wildcard_escapes: bool) -> Result<String, String> {
+    if !value.contains('\\') {
+        return Ok(value.to_string());
+    }
+    let bytes = value.as_bytes();
+    let mut unescaped = Vec::with_capacity(bytes.len());
+    let mut pos = 0;
+    while pos < bytes.len() {
+        let escaped = match (bytes[pos], bytes.get(pos + 1..pos + 3)) {
+            (b'\\', Some(hex)) if hex.iter().all(u8::is_ascii_hexdigit) => std::str::from_utf8(hex)
+                .ok()
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok()),
+            _ => None,
+        };
+        match escaped {
+            Some(byte @ b'*') | Some(byte @ b'\\') if keep_wildcard_escapes => {
+                unescaped.extend_from_slice(format!("\\{:02x}", byte).as_bytes());
+                pos += 3;
+            }
+            Some(byte) => {
+                unescaped.push(byte);
+                pos += 3;
+            }
+            None if keep_wildcard_escapes && bytes[pos] == b'\\' => {
+                unescaped.extend_from_slice(b"\\5c");
+                pos += 1;
+            }
+            None => {
+                unescaped.push(bytes[pos]);
+                pos += 1;
+            }
+        }
+    }
+    String::from_utf8(unescaped).map_err(|e| format!("Invalid escaped value {}: {}", value, e))
+}
+
+// Escape characters with special meaning in literal kept by expression (see `literal_value`):
+// `(`, `)` and NUL. Wildcards and escape sequences of `*` and `\` are kept as they are.
+pub fn escape_value(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for chr in value.chars() {
+        match chr {
+            '(' | ')' | '\0' => escaped.push_str(&format!("\\{:02x}", chr as u8)),
+            _ => escaped.push(chr),
+        }
+    }
+    escaped
+}
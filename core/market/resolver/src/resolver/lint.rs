@@ -0,0 +1,696 @@
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+use semver::Version;
+use serde::{Deserialize, Serialize};
+
+use super::expression::{build_expression, typed_literal, Expression};
+use super::ldap_parser;
+use super::properties::{PropertyRef, PropertyRefType};
+
+// #region PropertySchema
+
+// Type of property declared in property schema
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PropertyType {
+    Str,
+    Bool,
+    Number,
+    Decimal,
+    Version,
+    DateTime,
+    List,
+}
+
+// Set of known property names (and their types) used to validate constraint expressions.
+// Names in one of the schema namespaces, which aren't declared in the schema, are reported as unknown.
+// Names outside of schema namespaces are never reported.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct PropertySchema {
+    #[serde(default)]
+    pub properties: HashMap<String, PropertyType>,
+    #[serde(default)]
+    pub namespaces: Vec<String>,
+}
+
+impl PropertySchema {
+    // Schema of standard Golem properties.
+    // golem.inf namespace lists all properties of the infrastructure standard,
+    // so any other name within it is reported as unknown.
+    pub fn builtin() -> PropertySchema {
+        PropertySchema::default()
+            .with_namespace("golem.inf")
+            .with_property("golem.inf.mem.gib", PropertyType::Number)
+            .with_property("golem.inf.storage.gib", PropertyType::Number)
+            .with_property("golem.inf.cpu.architecture", PropertyType::Str)
+            .with_property("golem.inf.cpu.bit", PropertyType::List)
+            .with_property("golem.inf.cpu.brand", PropertyType::Str)
+            .with_property("golem.inf.cpu.capabilities", PropertyType::List)
+            .with_property("golem.inf.cpu.cores", PropertyType::Number)
+            .with_property("golem.inf.cpu.model", PropertyType::Str)
+            .with_property("golem.inf.cpu.threads", PropertyType::Number)
+            .with_property("golem.inf.cpu.vendor", PropertyType::Str)
+            .with_property("golem.activity.caps.transfer.protocol", PropertyType::List)
+            .with_property("golem.com.pricing.model", PropertyType::Str)
+            .with_property("golem.com.pricing.model.linear.coeffs", PropertyType::List)
+            .with_property("golem.com.scheme", PropertyType::Str)
+            .with_property("golem.com.usage.vector", PropertyType::List)
+            .with_property("golem.node.id.name", PropertyType::Str)
+            .with_property("golem.node.debug.subnet", PropertyType::Str)
+            .with_property("golem.runtime.name", PropertyType::Str)
+            .with_property("golem.srv.comp.expiration", PropertyType::Number)
+    }
+
+    pub fn with_property(mut self, name: &str, prop_type: PropertyType) -> PropertySchema {
+        self.properties.insert(name.to_string(), prop_type);
+        self
+    }
+
+    pub fn with_namespace(mut self, namespace: &str) -> PropertySchema {
+        self.namespaces.push(namespace.to_string());
+        self
+    }
+
+    pub fn property_type(&self, name: &str) -> Option<&PropertyType> {
+        self.properties.get(name)
+    }
+
+    pub fn is_unknown(&self, name: &str) -> bool {
+        !self.properties.contains_key(name)
+            && self
+                .namespaces
+                .iter()
+                .any(|ns| name.starts_with(&format!("{}.", ns)))
+    }
+
+    // Known property name closest to the given one (if close enough to be a typo)
+    fn suggest(&self, name: &str) -> Option<&str> {
+        self.properties
+            .keys()
+            .map(|known| (edit_distance(name, known), known))
+            .filter(|(distance, _)| *distance <= 2)
+            .min()
+            .map(|(_, known)| &known[..])
+    }
+}
+
+// #endregion
+
+// #region LintWarning
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LintKind {
+    Malformed,       // constraint expression can't be parsed
+    Contradiction,   // (sub)expression can never be satisfied
+    Tautology,       // (sub)expression is always satisfied
+    InvalidOperator, // operator makes no sense for the type of property
+    TypeMismatch,    // literal can't be compared with the type of property
+    UnknownProperty, // property name not declared in property schema
+}
+
+impl fmt::Display for LintKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            LintKind::Malformed => "malformed",
+            LintKind::Contradiction => "contradiction",
+            LintKind::Tautology => "tautology",
+            LintKind::InvalidOperator => "invalid operator",
+            LintKind::TypeMismatch => "type mismatch",
+            LintKind::UnknownProperty => "unknown property",
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LintWarning {
+    pub kind: LintKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub property: Option<String>,
+    pub message: String,
+}
+
+impl LintWarning {
+    fn new(kind: LintKind, property: Option<&str>, message: String) -> LintWarning {
+        LintWarning {
+            kind,
+            property: property.map(|name| name.to_string()),
+            message,
+        }
+    }
+}
+
+impl fmt::Display for LintWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.kind, self.message)
+    }
+}
+
+// #endregion
+
+// #region Linting
+
+// Parse constraints and lint the resulting expression.
+// Parsing errors are reported as Malformed warning.
+pub fn lint_constraints(constraints: &str, schema: &PropertySchema) -> Vec<LintWarning> {
+    let tags = match ldap_parser::parse(constraints) {
+        Ok(tags) => tags,
+        Err(error) => {
+            return vec![LintWarning::new(
+                LintKind::Malformed,
+                None,
+                format!("Error parsing constraints: {}", error),
+            )]
+        }
+    };
+    match build_expression(&tags) {
+        Ok(expression) => lint(&expression, schema),
+        Err(error) => vec![LintWarning::new(
+            LintKind::Malformed,
+            None,
+            format!("Error building constraints expression: {}", error),
+        )],
+    }
+}
+
+// Static analysis of constraint expression. Reports:
+// - contradictions and tautologies among operands of the same And/Or expression,
+// - comparison operators which make no sense for the type of referenced property,
+// - literals which can't be compared with the type of referenced property,
+// - references to names unknown to the property schema.
+// Subexpressions under odd number of negations have opposite effect on the whole
+// expression, eg. contradiction under negation is reported as tautology.
+pub fn lint(expression: &Expression, schema: &PropertySchema) -> Vec<LintWarning> {
+    let mut linter = Linter {
+        schema,
+        warnings: vec![],
+        reported_names: HashSet::new(),
+        negated: false,
+    };
+    linter.lint(expression);
+    linter.warnings
+}
+
+struct Linter<'s> {
+    schema: &'s PropertySchema,
+    warnings: Vec<LintWarning>,
+    reported_names: HashSet<String>,
+    // Whether currently linted subexpression is under odd number of negations
+    negated: bool,
+}
+
+impl<'s> Linter<'s> {
+    fn lint(&mut self, expression: &Expression) {
+        match expression {
+            Expression::Equals(prop_ref, val) => self.lint_comparison(prop_ref, "=", val, false),
+            Expression::Greater(prop_ref, val) => self.lint_comparison(prop_ref, ">", val, true),
+            Expression::GreaterEqual(prop_ref, val) => {
+                self.lint_comparison(prop_ref, ">=", val, true)
+            }
+            Expression::Less(prop_ref, val) => self.lint_comparison(prop_ref, "<", val, true),
+            Expression::LessEqual(prop_ref, val) => self.lint_comparison(prop_ref, "<=", val, true),
+            Expression::Present(prop_ref) => self.lint_name(prop_ref),
+            Expression::And(_) => {
+                let operands = flatten_operands(expression);
+                operands.iter().for_each(|operand| self.lint(operand));
+                self.lint_and(&operands);
+            }
+            Expression::Or(_) => {
+                let operands = flatten_operands(expression);
+                operands.iter().for_each(|operand| self.lint(operand));
+                self.lint_or(&operands);
+            }
+            Expression::Not(inner) => {
+                self.negated = !self.negated;
+                self.lint(inner);
+                self.negated = !self.negated;
+            }
+            Expression::Empty(_) => {}
+        }
+    }
+
+    // Report subexpression, which always has given value.
+    // Under negation it makes the whole expression behave the opposite way.
+    fn push_constant(&mut self, value: bool, property: Option<&str>, message: String) {
+        let kind = if value != self.negated {
+            LintKind::Tautology
+        } else {
+            LintKind::Contradiction
+        };
+        let message = message + &self.negation_note(value);
+        self.warnings
+            .push(LintWarning::new(kind, property, message));
+    }
+
+    fn negation_note(&self, value: bool) -> String {
+        if self.negated {
+            format!(", so its negation is always {}", !value)
+        } else {
+            String::new()
+        }
+    }
+
+    fn lint_name(&mut self, prop_ref: &PropertyRef) {
+        let name = prop_ref_name(prop_ref);
+        if !self.schema.is_unknown(name) || !self.reported_names.insert(name.to_string()) {
+            return;
+        }
+
+        let message = match self.schema.suggest(name) {
+            Some(known) => format!("Unknown property {}, did you mean {}?", name, known),
+            None => format!("Unknown property {}", name),
+        };
+        self.warnings.push(LintWarning::new(
+            LintKind::UnknownProperty,
+            Some(name),
+            message,
+        ));
+    }
+
+    fn lint_comparison(&mut self, prop_ref: &PropertyRef, oper: &str, val: &str, ordering: bool) {
+        self.lint_name(prop_ref);
+
+        // aspects are always compared as strings
+        if let PropertyRef::Aspect(..) = prop_ref {
+            return;
+        }
+
+        let name = prop_ref_name(prop_ref);
+        if let Some(warning) = self.declared_type_mismatch(prop_ref, val) {
+            self.warnings.push(warning);
+            return;
+        }
+        let (prop_type, val) = match self.compared_type(prop_ref, val) {
+            Some(compared) => compared,
+            None => return,
+        };
+
+        let warning = match prop_type {
+            PropertyType::List | PropertyType::Bool if ordering => Some(LintWarning::new(
+                LintKind::InvalidOperator,
+                Some(name),
+                format!(
+                    "Operator {} is always false for {:?} property {}{}",
+                    oper,
+                    prop_type,
+                    name,
+                    self.negation_note(false)
+                ),
+            )),
+            PropertyType::Bool if val.parse::<bool>().is_err() => Some(LintWarning::new(
+                LintKind::TypeMismatch,
+                Some(name),
+                format!(
+                    "Value {} can't be compared with Bool property {}",
+                    val, name
+                ),
+            )),
+            PropertyType::Number
+            | PropertyType::Decimal
+            | PropertyType::Version
+            | PropertyType::DateTime
+                if !val.contains('*') && OrderedValue::parse(&prop_type, val).is_none() =>
+            {
+                Some(LintWarning::new(
+                    LintKind::TypeMismatch,
+                    Some(name),
+                    format!(
+                        "Value {} can't be compared with {:?} property {}",
+                        val, prop_type, name
+                    ),
+                ))
+            }
+            _ => None,
+        };
+        self.warnings.extend(warning);
+    }
+
+    // Contradictions between operands of And expression (tautologies under negation)
+    fn lint_and(&mut self, operands: &[&Expression]) {
+        // expression together with its negation
+        for operand in operands {
+            if let Expression::Not(inner) = operand {
+                if operands.iter().any(|other| *other == inner.as_ref()) {
+                    self.push_constant(
+                        false,
+                        None,
+                        format!(
+                            "Expression {:?} is required together with its negation",
+                            inner
+                        ),
+                    );
+                }
+            }
+        }
+
+        // comparisons of property required to be absent
+        for operand in operands {
+            if let Expression::Not(inner) = operand {
+                if let Expression::Present(prop_ref) = inner.as_ref() {
+                    let name = prop_ref_name(prop_ref);
+                    let compared = operands
+                        .iter()
+                        .filter_map(|other| compared_ref(other))
+                        .any(|other_ref| other_ref == prop_ref);
+                    if compared {
+                        self.push_constant(
+                            false,
+                            Some(name),
+                            format!("Property {} is compared, but required to be absent", name),
+                        );
+                    }
+                }
+            }
+        }
+
+        // disjoint ranges of values required for the same property
+        let mut ranges: Vec<(&PropertyRef, Range)> = vec![];
+        for operand in operands {
+            if let Some((prop_ref, range)) = self.range(operand, true) {
+                match ranges.iter_mut().find(|(other, _)| *other == prop_ref) {
+                    Some((_, other_range)) => other_range.intersect(range),
+                    None => ranges.push((prop_ref, range)),
+                }
+            }
+        }
+        for (prop_ref, range) in ranges {
+            if range.is_empty() {
+                let name = prop_ref_name(prop_ref);
+                self.push_constant(
+                    false,
+                    Some(name),
+                    format!(
+                        "Conditions on property {} can never be satisfied together",
+                        name
+                    ),
+                );
+            }
+        }
+    }
+
+    // Tautologies between operands of Or expression (contradictions under negation)
+    fn lint_or(&mut self, operands: &[&Expression]) {
+        // expression together with its negation
+        for operand in operands {
+            if let Expression::Not(inner) = operand {
+                if operands.iter().any(|other| *other == inner.as_ref()) {
+                    self.push_constant(
+                        true,
+                        None,
+                        format!("Expression {:?} is alternative to its negation", inner),
+                    );
+                }
+            }
+        }
+
+        // open ranges of values covering all values of the same property
+        let ranges: Vec<(&PropertyRef, Range)> = operands
+            .iter()
+            .filter_map(|operand| self.range(operand, false))
+            .collect();
+        let mut reported: Vec<&PropertyRef> = vec![];
+        for (prop_ref, lower_range) in ranges.iter() {
+            for (other_ref, upper_range) in ranges.iter() {
+                if prop_ref == other_ref
+                    && lower_range.covers_all_with(upper_range)
+                    && !reported.contains(prop_ref)
+                {
+                    reported.push(*prop_ref);
+                    let name = prop_ref_name(prop_ref);
+                    self.push_constant(
+                        true,
+                        Some(name),
+                        format!(
+                            "Alternative conditions on property {} are always true",
+                            name
+                        ),
+                    );
+                }
+            }
+        }
+    }
+
+    fn referenced_type(&self, prop_ref: &PropertyRef) -> Option<PropertyType> {
+        match prop_ref.impl_type() {
+            PropertyRefType::Any => self
+                .schema
+                .property_type(prop_ref_name(prop_ref))
+                .map(|prop_type| prop_type.clone()),
+            impl_type => implied_type(impl_type),
+        }
+    }
+
+    // Type, in which property is compared with literal, and the literal in its bare form.
+    // Explicitly typed literal determines the type by itself.
+    fn compared_type<'v>(
+        &self,
+        prop_ref: &PropertyRef,
+        val: &'v str,
+    ) -> Option<(PropertyType, &'v str)> {
+        match typed_literal(val) {
+            Some((literal_type, bare)) => Some((implied_type(&literal_type)?, bare)),
+            None => Some((self.referenced_type(prop_ref)?, val)),
+        }
+    }
+
+    // Typed literal never satisfies comparison with property declared in schema
+    // with other type (strings are interpreted in the literal's format).
+    fn declared_type_mismatch(&self, prop_ref: &PropertyRef, val: &str) -> Option<LintWarning> {
+        let (literal_type, _) = typed_literal(val)?;
+        let literal_type = implied_type(&literal_type)?;
+        let name = prop_ref_name(prop_ref);
+        match self.schema.property_type(name)? {
+            PropertyType::Str => None,
+            declared if *declared == literal_type => None,
+            declared => Some(LintWarning::new(
+                LintKind::TypeMismatch,
+                Some(name),
+                format!(
+                    "Literal {} of type {:?} can't be compared with {:?} property {}",
+                    val, literal_type, declared, name
+                ),
+            )),
+        }
+    }
+
+    // Range of property values satisfying comparison.
+    // Equality defines a range only for property of known ordered type
+    // (otherwise it may be a List property, where equality means "contains").
+    fn range<'e>(
+        &self,
+        expression: &'e Expression,
+        with_equality: bool,
+    ) -> Option<(&'e PropertyRef, Range)> {
+        let (prop_ref, val) = match expression {
+            Expression::Equals(prop_ref, val)
+            | Expression::Greater(prop_ref, val)
+            | Expression::GreaterEqual(prop_ref, val)
+            | Expression::Less(prop_ref, val)
+            | Expression::LessEqual(prop_ref, val) => (prop_ref, val),
+            _ => return None,
+        };
+        if let PropertyRef::Aspect(..) = prop_ref {
+            return None;
+        }
+
+        let compared = self.compared_type(prop_ref, val);
+        let (prop_type, val) = compared.clone().unwrap_or((PropertyType::Number, &val[..]));
+        let value = OrderedValue::parse(&prop_type, val)?;
+        let range = match expression {
+            Expression::Equals(..) if with_equality && compared.is_some() => {
+                Range::between(Bound::new(value.clone(), true), Bound::new(value, true))
+            }
+            Expression::Greater(..) => Range::above(Bound::new(value, false)),
+            Expression::GreaterEqual(..) => Range::above(Bound::new(value, true)),
+            Expression::Less(..) => Range::below(Bound::new(value, false)),
+            Expression::LessEqual(..) => Range::below(Bound::new(value, true)),
+            _ => return None,
+        };
+        Some((prop_ref, range))
+    }
+}
+
+// Operands of nested expressions of the same kind, eg. (&(a=1)(&(b=2)(c=3))) -> [(a=1), (b=2), (c=3)]
+fn flatten_operands(expression: &Expression) -> Vec<&Expression> {
+    match expression {
+        Expression::And(operands) => operands
+            .iter()
+            .flat_map(|operand| match operand.as_ref() {
+                Expression::And(_) => flatten_operands(operand),
+                _ => vec![operand.as_ref()],
+            })
+            .collect(),
+        Expression::Or(operands) => operands
+            .iter()
+            .flat_map(|operand| match operand.as_ref() {
+                Expression::Or(_) => flatten_operands(operand),
+                _ => vec![operand.as_ref()],
+            })
+            .collect(),
+        _ => vec![expression],
+    }
+}
+
+fn compared_ref(expression: &Expression) -> Option<&PropertyRef> {
+    match expression {
+        Expression::Equals(prop_ref, _)
+        | Expression::Greater(prop_ref, _)
+        | Expression::GreaterEqual(prop_ref, _)
+        | Expression::Less(prop_ref, _)
+        | Expression::LessEqual(prop_ref, _) => Some(prop_ref),
+        _ => None,
+    }
+}
+
+fn prop_ref_name(prop_ref: &PropertyRef) -> &str {
+    match prop_ref {
+        PropertyRef::Value(name, _) => name,
+        PropertyRef::Aspect(name, _, _) => name,
+    }
+}
+
+// Property type implied by type code of property reference or by typed literal
+fn implied_type(impl_type: &PropertyRefType) -> Option<PropertyType> {
+    match impl_type {
+        PropertyRefType::Decimal => Some(PropertyType::Decimal),
+        PropertyRefType::Version => Some(PropertyType::Version),
+        PropertyRefType::DateTime => Some(PropertyType::DateTime),
+        PropertyRefType::Any => None,
+    }
+}
+
+// Levenshtein distance between two strings
+fn edit_distance(s1: &str, s2: &str) -> usize {
+    let s2: Vec<char> = s2.chars().collect();
+    let mut row: Vec<usize> = (0..=s2.len()).collect();
+
+    for (i, c1) in s1.chars().enumerate() {
+        let mut prev_diag = row[0];
+        row[0] = i + 1;
+        for (j, c2) in s2.iter().enumerate() {
+            let substitution = prev_diag + if c1 == *c2 { 0 } else { 1 };
+            prev_diag = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(row[j + 1] + 1);
+        }
+    }
+    row[s2.len()]
+}
+
+// #endregion
+
+// #region Ranges
+
+// Value of property type with defined order
+#[derive(Clone, Debug, PartialEq, PartialOrd)]
+enum OrderedValue {
+    Number(f64),
+    Version(Version),
+    DateTime(DateTime<Utc>),
+}
+
+impl OrderedValue {
+    fn parse(prop_type: &PropertyType, val: &str) -> Option<OrderedValue> {
+        match prop_type {
+            PropertyType::Number | PropertyType::Decimal => {
+                val.parse::<f64>().ok().map(OrderedValue::Number)
+            }
+            PropertyType::Version => Version::parse(val).ok().map(OrderedValue::Version),
+            PropertyType::DateTime => DateTime::parse_from_rfc3339(val)
+                .ok()
+                .map(|dt| OrderedValue::DateTime(dt.with_timezone(&Utc))),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Bound {
+    value: OrderedValue,
+    inclusive: bool,
+}
+
+impl Bound {
+    fn new(value: OrderedValue, inclusive: bool) -> Bound {
+        Bound { value, inclusive }
+    }
+}
+
+// Range of values (unbounded if bound is None)
+#[derive(Clone, Debug)]
+struct Range {
+    lower: Option<Bound>,
+    upper: Option<Bound>,
+}
+
+impl Range {
+    fn between(lower: Bound, upper: Bound) -> Range {
+        Range {
+            lower: Some(lower),
+            upper: Some(upper),
+        }
+    }
+
+    fn above(lower: Bound) -> Range {
+        Range {
+            lower: Some(lower),
+            upper: None,
+        }
+    }
+
+    fn below(upper: Bound) -> Range {
+        Range {
+            lower: None,
+            upper: Some(upper),
+        }
+    }
+
+    fn intersect(&mut self, other: Range) {
+        self.lower = match (self.lower.take(), other.lower) {
+            (Some(b1), Some(b2)) => Some(tighter(b1, b2, Ordering::Greater)),
+            (b1, b2) => b1.or(b2),
+        };
+        self.upper = match (self.upper.take(), other.upper) {
+            (Some(b1), Some(b2)) => Some(tighter(b1, b2, Ordering::Less)),
+            (b1, b2) => b1.or(b2),
+        };
+    }
+
+    fn is_empty(&self) -> bool {
+        match (&self.lower, &self.upper) {
+            (Some(lower), Some(upper)) => match lower.value.partial_cmp(&upper.value) {
+                Some(Ordering::Greater) => true,
+                Some(Ordering::Equal) => !(lower.inclusive && upper.inclusive),
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    // Whether self (open from above) together with other (open from below) covers all values
+    fn covers_all_with(&self, other: &Range) -> bool {
+        match (&self.lower, &self.upper, &other.lower, &other.upper) {
+            (Some(lower), None, None, Some(upper)) => match upper.value.partial_cmp(&lower.value) {
+                Some(Ordering::Greater) => true,
+                Some(Ordering::Equal) => lower.inclusive || upper.inclusive,
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+}
+
+// Pick the bound which is more restrictive (greater for lower bounds, less for upper bounds)
+fn tighter(b1: Bound, b2: Bound, direction: Ordering) -> Bound {
+    match b1.value.partial_cmp(&b2.value) {
+        Some(Ordering::Equal) => Bound::new(b1.value, b1.inclusive && b2.inclusive),
+        Some(ordering) if ordering == direction => b1,
+        Some(_) => b2,
+        None => b1,
+    }
+}
+
+// #endregion
@@ -1,7 +1,7 @@
 use super::error::MatchError;
 use super::expression::{Expression, ResolveResult};
-use super::prepare::{PreparedDemand, PreparedOffer};
-use super::properties::PropertyRef;
+use super::prepare::{PreparedDemand, PreparedOffer, PreparedSubscription};
+use super::properties::{PropertyRef, PropertySet};
 
 // Matching relation result enum
 #[derive(Debug, Clone, PartialEq)]
@@ -24,8 +24,32 @@ pub fn match_weak<'a>(
     log::trace!("Demand: {:?}", demand);
     log::trace!("Offer: {:?}", offer);
 
-    let result1 = demand.constraints.resolve(&offer.properties);
-    let result2 = offer.constraints.resolve(&demand.properties);
+    match_sides(
+        (&demand.constraints, &demand.properties),
+        (&offer.constraints, &offer.properties),
+    )
+}
+
+// Weak match relation of already prepared Offer and Demand
+//
+pub fn match_weak_prepared<'a>(
+    demand: &'a PreparedSubscription,
+    offer: &'a PreparedSubscription,
+) -> Result<MatchResult<'a>, MatchError> {
+    match_sides(
+        (&demand.constraints, demand.property_set()),
+        (&offer.constraints, offer.property_set()),
+    )
+}
+
+// Resolves constraints of each side with properties of the other one
+//
+fn match_sides<'a>(
+    (demand_constraints, demand_properties): (&'a Expression, &'a PropertySet),
+    (offer_constraints, offer_properties): (&'a Expression, &'a PropertySet),
+) -> Result<MatchResult<'a>, MatchError> {
+    let result1 = demand_constraints.resolve(offer_properties);
+    let result2 = offer_constraints.resolve(demand_properties);
 
     log::trace!("Demand constraints with Offer properties: {:?}", result1);
     log::trace!("Offer constraints with Demand properties: {:?}", result2);
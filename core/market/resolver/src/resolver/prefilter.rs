@@ -0,0 +1,164 @@
+use std::collections::HashMap;
+
+use super::expression::{typed_literal, Expression};
+use super::ldap_parser::unescape_value;
+use super::prepare::PreparedSubscription;
+use super::prop_parser;
+use super::properties::{PropertyRef, PropertyRefType, PropertyValue};
+
+// Properties most commonly constrained in Demands and Offers
+pub const DEFAULT_INDEXED_PROPERTIES: &[&str] = &[
+    "golem.runtime.name",
+    "golem.node.debug.subnet",
+    "golem.inf.mem.gib",
+    "golem.inf.storage.gib",
+    "golem.inf.cpu.cores",
+    "golem.inf.cpu.threads",
+];
+
+// Explicit value of indexed property
+#[derive(Clone, Debug, PartialEq)]
+enum IndexedValue {
+    Str(String),
+    Number(f64),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Operator {
+    Equals,
+    Greater,
+    GreaterEqual,
+    Less,
+    LessEqual,
+}
+
+// Simple comparison of indexed property required by constraints
+#[derive(Clone, Debug, PartialEq)]
+struct Requirement {
+    property: String,
+    operator: Operator,
+    literal: String,
+}
+
+impl Requirement {
+    fn from_expression(expression: &Expression, indexed: &[&str]) -> Option<Requirement> {
+        let (prop_ref, operator, literal) = match expression {
+            Expression::Equals(prop_ref, val) => (prop_ref, Operator::Equals, val),
+            Expression::Greater(prop_ref, val) => (prop_ref, Operator::Greater, val),
+            Expression::GreaterEqual(prop_ref, val) => (prop_ref, Operator::GreaterEqual, val),
+            Expression::Less(prop_ref, val) => (prop_ref, Operator::Less, val),
+            Expression::LessEqual(prop_ref, val) => (prop_ref, Operator::LessEqual, val),
+            _ => return None,
+        };
+
+        // only untyped value references and untyped literals without wildcards
+        match prop_ref {
+            PropertyRef::Value(name, PropertyRefType::Any)
+                if indexed.contains(&&name[..])
+                    && !literal.contains('*')
+                    && typed_literal(literal).is_none() =>
+            {
+                Some(Requirement {
+                    property: name.clone(),
+                    operator,
+                    literal: unescape_value(literal).ok()?,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    // Mirrors comparisons of PropertyValue::Str and PropertyValue::Number
+    fn is_satisfied(&self, value: &IndexedValue) -> bool {
+        match value {
+            IndexedValue::Str(value) => {
+                let (value, literal) = (&value[..], &self.literal[..]);
+                match self.operator {
+                    Operator::Equals => value == literal,
+                    Operator::Greater => value > literal,
+                    Operator::GreaterEqual => value >= literal,
+                    Operator::Less => value < literal,
+                    Operator::LessEqual => value <= literal,
+                }
+            }
+            IndexedValue::Number(value) => match self.literal.parse::<f64>() {
+                Ok(literal) => match self.operator {
+                    Operator::Equals => *value == literal,
+                    Operator::Greater => *value > literal,
+                    Operator::GreaterEqual => *value >= literal,
+                    Operator::Less => *value < literal,
+                    Operator::LessEqual => *value <= literal,
+                },
+                Err(_) => false,
+            },
+        }
+    }
+}
+
+// Prefilter
+// Summary of a subscription used to cheaply reject Demand-Offer pairs, which can't match,
+// before full resolution. Consists of explicit values of indexed properties and comparisons
+// of indexed properties required by the top-level conjunction of constraints.
+// Absent properties never reject a pair, because they may be resolved dynamically.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Prefilter {
+    values: HashMap<String, IndexedValue>,
+    requirements: Vec<Requirement>,
+}
+
+impl Prefilter {
+    pub fn from(subscription: &PreparedSubscription, indexed: &[&str]) -> Prefilter {
+        let mut values = HashMap::new();
+
+        // same precedence as in PropertySet::from_flat_props (last valid definition wins)
+        for prop_flat in subscription.properties().iter() {
+            match prop_parser::parse_prop_def(prop_flat) {
+                Ok((name, value)) if indexed.contains(&name) => {
+                    match value.map(PropertyValue::from_value) {
+                        Some(Ok(PropertyValue::Str(val))) => {
+                            values.insert(name.to_string(), IndexedValue::Str(val.to_string()));
+                        }
+                        Some(Ok(PropertyValue::Number(val))) => {
+                            values.insert(name.to_string(), IndexedValue::Number(val));
+                        }
+                        Some(Err(_)) => {}
+                        _ => {
+                            values.remove(name);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let requirements = match subscription.constraints.normalize() {
+            Expression::And(operands) => operands
+                .iter()
+                .filter_map(|operand| Requirement::from_expression(operand, indexed))
+                .collect(),
+            expression => Requirement::from_expression(&expression, indexed)
+                .into_iter()
+                .collect(),
+        };
+
+        Prefilter {
+            values,
+            requirements,
+        }
+    }
+
+    // Returns false if the pair surely doesn't match,
+    // true means that the pair has to go through full resolution.
+    pub fn may_match(&self, other: &Prefilter) -> bool {
+        self.is_satisfied_by(other) && other.is_satisfied_by(self)
+    }
+
+    fn is_satisfied_by(&self, other: &Prefilter) -> bool {
+        self.requirements.iter().all(
+            |requirement| match other.values.get(&requirement.property) {
+                Some(value) => requirement.is_satisfied(value),
+                None => true,
+            },
+        )
+    }
+}
@@ -1,6 +1,9 @@
+use self_cell::self_cell;
+
 use super::super::{Demand, Offer};
+use super::dynamic::PropertyResolvers;
 use super::error::PrepareError;
-use super::expression::{build_expression, Expression};
+use super::expression::{build_expression, Expression, ResolveResult};
 use super::ldap_parser;
 use super::properties::PropertySet;
 
@@ -64,3 +67,86 @@ impl<'a> PreparedDemand<'a> {
         Ok(result)
     }
 }
+
+self_cell!(
+    // Properties (expressed in flat form, ie. as lines of text) together with
+    // PropertySet parsed from them, which borrows their strings.
+    struct ParsedProperties {
+        owner: Vec<String>,
+
+        #[covariant]
+        dependent: PropertySet,
+    }
+
+    impl {Debug}
+);
+
+// PreparedSubscription
+// Offer or Demand with flattened properties and parsed constraints, which owns all its data.
+// It can be prepared once, kept across calls and matched many times without parsing
+// properties or constraints again.
+// Dynamic property resolvers are bound to the prepared properties (see `with_resolvers`).
+#[derive(Debug)]
+pub struct PreparedSubscription {
+    // Filter expression
+    pub constraints: Expression,
+
+    // Properties (values, aspects)
+    properties: ParsedProperties,
+}
+
+impl PreparedSubscription {
+    pub fn from(properties: Vec<String>, constraints: &str) -> Result<Self, PrepareError> {
+        let cons_tags = ldap_parser::parse(constraints)
+            .map_err(|error| PrepareError::new(&format!("Error parsing constraints: {}", error)))?;
+        let constraints = build_expression(&cons_tags).map_err(|error| {
+            PrepareError::new(&format!("Error building constraints expression: {}", error))
+        })?;
+        Ok(PreparedSubscription::new(properties, constraints))
+    }
+
+    fn new(properties: Vec<String>, constraints: Expression) -> Self {
+        PreparedSubscription {
+            constraints,
+            properties: ParsedProperties::new(properties, |props| {
+                PropertySet::from_flat_props(props)
+            }),
+        }
+    }
+
+    // Set dynamic property resolvers consulted for absent and implicit properties
+    pub fn with_resolvers(mut self, resolvers: PropertyResolvers) -> Self {
+        self.properties
+            .with_dependent_mut(|_, property_set| property_set.set_resolvers(resolvers));
+        self
+    }
+
+    // Properties expressed in flat form
+    pub fn properties(&self) -> &[String] {
+        self.properties.borrow_owner()
+    }
+
+    pub fn property_set(&self) -> &PropertySet {
+        self.properties.borrow_dependent()
+    }
+
+    // Checks if properties satisfy given constraints (ignoring own constraints).
+    // Properties, which can't be resolved, don't satisfy constraints.
+    pub fn satisfies(&self, constraints: &Expression) -> bool {
+        constraints.resolve(self.property_set()) == ResolveResult::True
+    }
+}
+
+impl Clone for PreparedSubscription {
+    // Properties are parsed again, since cloned property set would borrow from `self`.
+    fn clone(&self) -> Self {
+        PreparedSubscription::new(self.properties().to_vec(), self.constraints.clone())
+            .with_resolvers(self.property_set().resolvers.clone())
+    }
+}
+
+impl PartialEq for PreparedSubscription {
+    fn eq(&self, other: &Self) -> bool {
+        self.constraints == other.constraints && self.property_set() == other.property_set()
+    }
+}
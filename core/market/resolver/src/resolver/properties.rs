@@ -1,3 +1,4 @@
+use std::fmt;
 use std::str;
 
 use bigdecimal::BigDecimal;
@@ -6,7 +7,9 @@ use regex::Regex;
 use semver::Version;
 use std::collections::HashMap;
 
+use super::dynamic::PropertyResolvers;
 use super::error::ParseError;
+use super::ldap_parser::wildcard_segments;
 use super::prop_parser;
 use super::prop_parser::Literal;
 
@@ -160,17 +163,22 @@ impl<'a> PropertyValue<'a> {
     }
 
     // Implement string equality with * wildcard
-    // Note: Only str1 may contain wildcard
+    // Note: Only str1 may contain wildcard, escaped asterisk (\2a) matches literal asterisk
     // TODO my be sensible to move the Regex building to the point where property is parsed...
     fn str_equal_with_wildcard(str1: &str, str2: &str) -> bool {
-        if str1.contains("*") {
-            let regex_text = format!("^{}$", str1.replace("*", ".*"));
+        let segments = match wildcard_segments(str1) {
+            Ok(segments) => segments,
+            Err(_error) => return false,
+        };
+        if segments.len() > 1 {
+            let segments: Vec<String> = segments.iter().map(|s| regex::escape(s)).collect();
+            let regex_text = format!("^{}$", segments.join(".*"));
             match Regex::new(&regex_text) {
                 Ok(regex) => regex.is_match(str2),
                 Err(_error) => false,
             }
         } else {
-            str1 == str2
+            segments[0] == str2
         }
     }
 
@@ -350,6 +358,28 @@ impl<'a> PropertyValue<'a> {
     }
 }
 
+impl<'a> fmt::Display for PropertyValue<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PropertyValue::Str(value) => write!(f, "{}", value),
+            PropertyValue::Boolean(value) => write!(f, "{}", value),
+            PropertyValue::Number(value) => write!(f, "{}", value),
+            PropertyValue::Decimal(value) => write!(f, "{}", value),
+            PropertyValue::DateTime(value) => write!(f, "{}", value.to_rfc3339()),
+            PropertyValue::Version(value) => write!(f, "{}", value),
+            PropertyValue::List(items) => write!(
+                f,
+                "[{}]",
+                items
+                    .iter()
+                    .map(|item| item.to_string())
+                    .collect::<Vec<String>>()
+                    .join(",")
+            ),
+        }
+    }
+}
+
 // #endregion
 
 // Property - describes the property with its value and aspects.
@@ -364,6 +394,8 @@ pub enum Property<'a> {
 #[derive(Debug, Clone, PartialEq, Default)]
 pub struct PropertySet<'a> {
     pub properties: HashMap<&'a str, Property<'a>>,
+    // Resolvers of properties computed at match time (consulted for absent and implicit properties)
+    pub resolvers: PropertyResolvers,
 }
 
 impl<'a> PropertySet<'a> {
@@ -371,6 +403,7 @@ impl<'a> PropertySet<'a> {
     pub fn from_flat_props(props: &'a Vec<String>) -> PropertySet<'a> {
         let mut result = PropertySet {
             properties: HashMap::new(),
+            resolvers: PropertyResolvers::default(),
         };
 
         // parse and pack props
@@ -406,6 +439,11 @@ impl<'a> PropertySet<'a> {
         }
     }
 
+    // Set dynamic property resolvers
+    pub fn set_resolvers(&mut self, resolvers: PropertyResolvers) {
+        self.resolvers = resolvers;
+    }
+
     // Set property aspect
     pub fn set_property_aspect(
         &mut self,
@@ -440,6 +478,45 @@ pub enum PropertyRef {
     Aspect(String, String, PropertyRefType), // reference to property aspect (prop name, aspect name)
 }
 
+impl PropertyRef {
+    pub fn impl_type(&self) -> &PropertyRefType {
+        match self {
+            PropertyRef::Value(_, impl_type) => impl_type,
+            PropertyRef::Aspect(_, _, impl_type) => impl_type,
+        }
+    }
+
+    // Return the same reference with different implied type
+    pub fn with_impl_type(self, impl_type: PropertyRefType) -> PropertyRef {
+        match self {
+            PropertyRef::Value(name, _) => PropertyRef::Value(name, impl_type),
+            PropertyRef::Aspect(name, aspect, _) => PropertyRef::Aspect(name, aspect, impl_type),
+        }
+    }
+}
+
+// Property reference in filter expression syntax, eg. name[aspect]$v
+impl fmt::Display for PropertyRef {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let impl_type = match self {
+            PropertyRef::Value(name, impl_type) => {
+                write!(f, "{}", name)?;
+                impl_type
+            }
+            PropertyRef::Aspect(name, aspect, impl_type) => {
+                write!(f, "{}[{}]", name, aspect)?;
+                impl_type
+            }
+        };
+        match impl_type {
+            PropertyRefType::Any => Ok(()),
+            PropertyRefType::Decimal => write!(f, "$d"),
+            PropertyRefType::Version => write!(f, "$v"),
+            PropertyRefType::DateTime => write!(f, "$t"),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum PropertyRefType {
     Any,
@@ -0,0 +1,312 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::iter::Peekable;
+use std::str::Chars;
+
+use super::error::ParseError;
+use super::prepare::PreparedSubscription;
+use super::properties::{Property, PropertySet, PropertyValue};
+
+// Demand property with scoring expression used to rank matched Offers
+pub const SCORE_PROPERTY: &str = "golem.market.rank.score";
+// Demand property with maximum number of Offers to be delivered as Proposals
+pub const LIMIT_PROPERTY: &str = "golem.market.rank.limit";
+
+// #region ScoreExpression
+
+// Arithmetic expression over numeric properties of an Offer, eg.
+// `golem.inf.cpu.threads / (golem.com.pricing.model.linear.coeffs[0] + 0.01)`.
+// Supports numeric literals, property names (with optional index of list element),
+// `+`, `-`, `*`, `/`, unary minus and parentheses.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ScoreExpression {
+    Number(f64),
+    Property(String, Option<usize>), // name, list index
+    Neg(Box<ScoreExpression>),
+    Add(Box<ScoreExpression>, Box<ScoreExpression>),
+    Sub(Box<ScoreExpression>, Box<ScoreExpression>),
+    Mul(Box<ScoreExpression>, Box<ScoreExpression>),
+    Div(Box<ScoreExpression>, Box<ScoreExpression>),
+}
+
+impl ScoreExpression {
+    pub fn parse(input: &str) -> Result<ScoreExpression, ParseError> {
+        let mut parser = ScoreParser {
+            input: input.chars().peekable(),
+        };
+        let expression = parser.expression()?;
+        match parser.next_token() {
+            None => Ok(expression),
+            Some(c) => Err(ParseError::new(&format!(
+                "Unexpected '{}' in scoring expression '{}'",
+                c, input
+            ))),
+        }
+    }
+
+    // Score of given property set. Undefined (None) if any of referenced properties
+    // is missing or not numeric, or if the result isn't a finite number.
+    pub fn evaluate(&self, properties: &PropertySet) -> Option<f64> {
+        let score = match self {
+            ScoreExpression::Number(val) => *val,
+            ScoreExpression::Property(name, index) => property_number(properties, name, *index)?,
+            ScoreExpression::Neg(expr) => -expr.evaluate(properties)?,
+            ScoreExpression::Add(left, right) => {
+                left.evaluate(properties)? + right.evaluate(properties)?
+            }
+            ScoreExpression::Sub(left, right) => {
+                left.evaluate(properties)? - right.evaluate(properties)?
+            }
+            ScoreExpression::Mul(left, right) => {
+                left.evaluate(properties)? * right.evaluate(properties)?
+            }
+            ScoreExpression::Div(left, right) => {
+                left.evaluate(properties)? / right.evaluate(properties)?
+            }
+        };
+        if score.is_finite() {
+            Some(score)
+        } else {
+            None
+        }
+    }
+}
+
+impl fmt::Display for ScoreExpression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ScoreExpression::Number(val) => write!(f, "{}", val),
+            ScoreExpression::Property(name, None) => write!(f, "{}", name),
+            ScoreExpression::Property(name, Some(index)) => write!(f, "{}[{}]", name, index),
+            ScoreExpression::Neg(expr) => write!(f, "-{}", expr),
+            ScoreExpression::Add(left, right) => write!(f, "({}+{})", left, right),
+            ScoreExpression::Sub(left, right) => write!(f, "({}-{})", left, right),
+            ScoreExpression::Mul(left, right) => write!(f, "({}*{})", left, right),
+            ScoreExpression::Div(left, right) => write!(f, "({}/{})", left, right),
+        }
+    }
+}
+
+// Explicit property value or the one computed by dynamic property resolvers
+fn property_number(properties: &PropertySet, name: &str, index: Option<usize>) -> Option<f64> {
+    match properties.properties.get(name) {
+        Some(Property::Explicit(_name, value, _aspects)) => value_number(value, index),
+        _ => {
+            let literal = properties.resolvers.resolve(name)?;
+            value_number(&PropertyValue::from_value(&literal).ok()?, index)
+        }
+    }
+}
+
+fn value_number(value: &PropertyValue, index: Option<usize>) -> Option<f64> {
+    match (value, index) {
+        (PropertyValue::Number(val), None) => Some(*val),
+        (PropertyValue::Decimal(val), None) => val.to_string().parse::<f64>().ok(),
+        (PropertyValue::List(items), Some(index)) => {
+            items.get(index).and_then(|item| value_number(item, None))
+        }
+        _ => None,
+    }
+}
+
+// Recursive descent parser of scoring expressions:
+// expression := term (('+' | '-') term)*
+// term := factor (('*' | '/') factor)*
+// factor := '-' factor | number | property ('[' index ']')? | '(' expression ')'
+struct ScoreParser<'a> {
+    input: Peekable<Chars<'a>>,
+}
+
+impl<'a> ScoreParser<'a> {
+    fn expression(&mut self) -> Result<ScoreExpression, ParseError> {
+        let mut expression = self.term()?;
+        loop {
+            match self.peek_token() {
+                Some('+') => {
+                    self.input.next();
+                    expression = ScoreExpression::Add(Box::new(expression), Box::new(self.term()?));
+                }
+                Some('-') => {
+                    self.input.next();
+                    expression = ScoreExpression::Sub(Box::new(expression), Box::new(self.term()?));
+                }
+                _ => return Ok(expression),
+            }
+        }
+    }
+
+    fn term(&mut self) -> Result<ScoreExpression, ParseError> {
+        let mut expression = self.factor()?;
+        loop {
+            match self.peek_token() {
+                Some('*') => {
+                    self.input.next();
+                    expression =
+                        ScoreExpression::Mul(Box::new(expression), Box::new(self.factor()?));
+                }
+                Some('/') => {
+                    self.input.next();
+                    expression =
+                        ScoreExpression::Div(Box::new(expression), Box::new(self.factor()?));
+                }
+                _ => return Ok(expression),
+            }
+        }
+    }
+
+    fn factor(&mut self) -> Result<ScoreExpression, ParseError> {
+        match self.peek_token() {
+            Some('-') => {
+                self.input.next();
+                Ok(ScoreExpression::Neg(Box::new(self.factor()?)))
+            }
+            Some('(') => {
+                self.input.next();
+                let expression = self.expression()?;
+                match self.next_token() {
+                    Some(')') => Ok(expression),
+                    _ => Err(ParseError::new("Missing ')' in scoring expression")),
+                }
+            }
+            Some(c) if c.is_ascii_digit() || c == '.' => {
+                let number = self.take_while(|c| c.is_ascii_digit() || c == '.');
+                number
+                    .parse::<f64>()
+                    .map(ScoreExpression::Number)
+                    .map_err(|_| {
+                        ParseError::new(&format!(
+                            "Invalid number '{}' in scoring expression",
+                            number
+                        ))
+                    })
+            }
+            Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+                let name = self.take_while(|c| c.is_ascii_alphanumeric() || "_.@".contains(c));
+                let index = match self.input.peek() {
+                    Some('[') => {
+                        self.input.next();
+                        let index = self.take_while(|c| c.is_ascii_digit());
+                        match (index.parse::<usize>(), self.input.next()) {
+                            (Ok(index), Some(']')) => Some(index),
+                            _ => {
+                                return Err(ParseError::new(&format!(
+                                    "Invalid list index of '{}' in scoring expression",
+                                    name
+                                )))
+                            }
+                        }
+                    }
+                    _ => None,
+                };
+                Ok(ScoreExpression::Property(name, index))
+            }
+            Some(c) => Err(ParseError::new(&format!(
+                "Unexpected '{}' in scoring expression",
+                c
+            ))),
+            None => Err(ParseError::new("Unexpected end of scoring expression")),
+        }
+    }
+
+    fn take_while(&mut self, predicate: impl Fn(char) -> bool) -> String {
+        let mut taken = String::new();
+        while let Some(c) = self.input.peek() {
+            if !predicate(*c) {
+                break;
+            }
+            taken.push(*c);
+            self.input.next();
+        }
+        taken
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.input.peek() {
+            if !c.is_whitespace() {
+                break;
+            }
+            self.input.next();
+        }
+    }
+
+    fn peek_token(&mut self) -> Option<char> {
+        self.skip_whitespace();
+        self.input.peek().cloned()
+    }
+
+    fn next_token(&mut self) -> Option<char> {
+        self.skip_whitespace();
+        self.input.next()
+    }
+}
+
+// #endregion
+
+// #region Ranking
+
+// Ranking of matched Offers requested by Demand properties.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Ranking {
+    pub score: Option<ScoreExpression>,
+    pub limit: Option<usize>,
+}
+
+impl Ranking {
+    // Returns None, if Demand doesn't request neither scoring nor limit.
+    pub fn from(demand: &PreparedSubscription) -> Result<Option<Ranking>, ParseError> {
+        let properties = demand.property_set();
+
+        let score = match properties.properties.get(SCORE_PROPERTY) {
+            Some(Property::Explicit(_, PropertyValue::Str(score), _)) => {
+                Some(ScoreExpression::parse(score)?)
+            }
+            Some(_) => {
+                return Err(ParseError::new(&format!(
+                    "Property {} should be a string",
+                    SCORE_PROPERTY
+                )))
+            }
+            None => None,
+        };
+        let limit = match properties.properties.get(LIMIT_PROPERTY) {
+            Some(Property::Explicit(_, PropertyValue::Number(limit), _))
+                if *limit >= 0.0 && limit.fract() == 0.0 =>
+            {
+                Some(*limit as usize)
+            }
+            Some(_) => {
+                return Err(ParseError::new(&format!(
+                    "Property {} should be a non-negative integer",
+                    LIMIT_PROPERTY
+                )))
+            }
+            None => None,
+        };
+
+        Ok(match (score, limit) {
+            (None, None) => None,
+            (score, limit) => Some(Ranking { score, limit }),
+        })
+    }
+
+    // Score of prepared Offer, None if it can't be computed.
+    pub fn score(&self, offer: &PreparedSubscription) -> Option<f64> {
+        self.score
+            .as_ref()
+            .and_then(|score| score.evaluate(offer.property_set()))
+    }
+
+    // Orders scored items best-first. Items with undefined score go last.
+    // Items with equal scores keep their original order.
+    pub fn rank<T>(&self, mut scored: Vec<(T, Option<f64>)>) -> Vec<T> {
+        scored.sort_by(|(_, score1), (_, score2)| match (score1, score2) {
+            (Some(score1), Some(score2)) => score2.partial_cmp(score1).unwrap_or(Ordering::Equal),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        });
+        scored.into_iter().map(|(item, _)| item).collect()
+    }
+}
+
+// #endregion
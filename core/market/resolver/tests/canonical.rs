@@ -0,0 +1,150 @@
+use ya_market_resolver::canonical_constraints;
+use ya_market_resolver::resolver::expression::*;
+use ya_market_resolver::resolver::ldap_parser::parse;
+use ya_market_resolver::resolver::properties::*;
+
+mod sample;
+
+use sample::{POC_DEMAND_CONSTRAINTS, POC_OFFER_CONSTRAINTS};
+
+fn build(f: &str) -> Expression {
+    build_expression(&parse(f).unwrap()).unwrap()
+}
+
+fn assert_round_trip(f: &str) {
+    let expression = build(f);
+    assert_eq!(expression.to_string(), f);
+    assert_eq!(build(&expression.to_string()), expression);
+}
+
+#[test]
+fn print_round_trip() {
+    assert_round_trip("()");
+    assert_round_trip("(cn=Babs Jensen)");
+    assert_round_trip("(objectClass=*)");
+    assert_round_trip("(!(cn=Tim Howes))");
+    assert_round_trip("(&(a>1)(b>=2)(c<3)(d<=4))");
+    assert_round_trip("(|(a=b)(&(c=d)(!(e=*))))");
+    assert_round_trip("(cn[lang]=pl)");
+    assert_round_trip("(cn[lang]$d=1)");
+    assert_round_trip("(a$v>=1.0.0)");
+    assert_round_trip("(a$t<1985-04-12T23:20:50.52Z)");
+}
+
+#[test]
+fn print_typed_literals() {
+    assert_eq!(build("(a>=v\"1.0.0\")").to_string(), "(a>=v\"1.0.0\")");
+    assert_eq!(build("(a[b]>=v\"1.0.0\")").to_string(), "(a[b]$v>=1.0.0)");
+    assert_eq!(build("(a=TRUE)").to_string(), "(a=true)");
+    assert_eq!(build("(a=[\"x\",\"y\"])").to_string(), "(a=[x,y])");
+}
+
+#[test]
+fn print_samples() {
+    assert_eq!(
+        build(POC_DEMAND_CONSTRAINTS).to_string(),
+        "(&(golem.inf.mem.gib>0.5)(golem.inf.storage.gib>1)(golem.com.pricing.model=linear)(golem.node.debug.subnet=piotr))"
+    );
+    assert_round_trip(POC_OFFER_CONSTRAINTS);
+}
+
+#[test]
+fn print_empty_false() {
+    let expression = Expression::Empty(false);
+
+    assert_eq!(expression.to_string(), "(!())");
+    assert_eq!(build(&expression.to_string()).normalize(), expression);
+}
+
+#[test]
+fn normalize_flattens_and_orders() {
+    assert_eq!(
+        build("(&(c=3)(&(b=2)(a=1))(|(e=5)(|(d=4))))").to_canonical_string(),
+        "(&(a=1)(b=2)(c=3)(|(d=4)(e=5)))"
+    );
+}
+
+#[test]
+fn normalize_removes_empty_operands() {
+    assert_eq!(build("(&(a=1)())").normalize(), build("(a=1)"));
+    assert_eq!(build("(&(a=1)(!()))").normalize(), Expression::Empty(false));
+    assert_eq!(build("(|(a=1)(!()))").normalize(), build("(a=1)"));
+    assert_eq!(build("(|(a=1)())").normalize(), Expression::Empty(true));
+    assert_eq!(build("(&()())").normalize(), Expression::Empty(true));
+}
+
+#[test]
+fn normalize_dedupes() {
+    assert_eq!(
+        build("(&(a=1)(b=2)(a=1))").to_canonical_string(),
+        "(&(a=1)(b=2))"
+    );
+    assert_eq!(build("(|(a=1)(&(a=1)))").normalize(), build("(a=1)"));
+}
+
+#[test]
+fn normalize_negations() {
+    assert_eq!(build("(!(!(a=1)))").normalize(), build("(a=1)"));
+    assert_eq!(
+        build("(!(&(b=2)(a=1)))").to_canonical_string(),
+        "(!(&(a=1)(b=2)))"
+    );
+}
+
+#[test]
+fn reduce_normalizes() {
+    let expression = build("(&(a=1)(|(b=2)(c=3))(d=4))");
+    let properties = vec!["a=1".to_string(), "c=\"x\"".to_string()];
+    let property_set = PropertySet::from_flat_props(&properties);
+
+    assert_eq!(
+        expression.resolve_reduce(&property_set),
+        Ok(build("(&(b=2)(d=4))"))
+    );
+}
+
+#[test]
+fn reduce_flattens_and_orders_remaining_operands() {
+    let expression = build("(&(d=4)(&(a=1)(c=3))(b=2))");
+    let properties = vec!["a=1".to_string()];
+    let property_set = PropertySet::from_flat_props(&properties);
+
+    assert_eq!(
+        expression.resolve_reduce(&property_set),
+        Ok(build("(&(b=2)(c=3)(d=4))"))
+    );
+}
+
+#[test]
+fn print_escaped_literals() {
+    // escaped asterisk and backslash are kept escaped, so they aren't taken for wildcards
+    assert_eq!(
+        Expression::Equals(
+            PropertyRef::Value("a".to_string(), PropertyRefType::Any),
+            "x\\2a(y)\\5cz\0".to_string()
+        )
+        .to_string(),
+        "(a=x\\2a\\28y\\29\\5cz\\00)"
+    );
+    assert_round_trip("(a=x\\2a\\28y\\29\\5cz\\00)");
+    assert_round_trip("(&(a=\\2a)(b>=\\28\\29))");
+    assert_round_trip("(a=x*\\2a*)");
+    assert_eq!(
+        build("(a=x\\2A\\28y\\29\\5cz\\00)"),
+        Expression::Equals(
+            PropertyRef::Value("a".to_string(), PropertyRefType::Any),
+            "x\\2a(y)\\5cz\0".to_string()
+        )
+    );
+    // backslash not starting escape sequence is taken literally
+    assert_eq!(build("(a=x\\y)").to_string(), "(a=x\\5cy)");
+}
+
+#[test]
+fn canonical_constraints_equal_for_equivalent() {
+    assert_eq!(
+        canonical_constraints("(&(b=2)(a=1))").unwrap(),
+        canonical_constraints("(&\n  (a=1)\n  (&(b=2)(a=1))\n)").unwrap()
+    );
+    assert!(canonical_constraints("(a=1").is_err());
+}
@@ -0,0 +1,168 @@
+use std::sync::Arc;
+
+use chrono::{TimeZone, Utc};
+
+use ya_market_resolver::resolver::dynamic::*;
+use ya_market_resolver::resolver::expression::*;
+use ya_market_resolver::resolver::ldap_parser::parse;
+use ya_market_resolver::resolver::properties::*;
+use ya_market_resolver::{match_demand_offer_with_resolvers, Match};
+
+struct FreeSlotsResolver {
+    free_slots: u32,
+}
+
+impl PropertyResolver for FreeSlotsResolver {
+    fn resolve(&self, name: &str) -> Option<String> {
+        match name {
+            "golem.node.free_slots" => Some(self.free_slots.to_string()),
+            _ => None,
+        }
+    }
+}
+
+fn fixed_time_resolvers() -> PropertyResolvers {
+    let mut resolvers = PropertyResolvers::default();
+    resolvers.add(Arc::new(TimeResolver::with_clock(|| {
+        Utc.ymd(2020, 6, 1).and_hms(12, 0, 0)
+    })));
+    resolvers
+}
+
+fn run_resolve_test(
+    expr: &str,
+    props: &Vec<&str>,
+    resolvers: PropertyResolvers,
+    expect_result: ResolveResult,
+) {
+    let expression = build_expression(&parse(expr).unwrap()).unwrap();
+
+    let mut properties = vec![];
+    for prop in props {
+        properties.push(prop.to_string());
+    }
+
+    let mut property_set = PropertySet::from_flat_props(&properties);
+    property_set.set_resolvers(resolvers);
+
+    assert_eq!(expression.resolve(&property_set), expect_result);
+}
+
+#[test]
+fn resolve_now() {
+    run_resolve_test(
+        "(now<2020-06-01T13:00:00Z)",
+        &vec![],
+        fixed_time_resolvers(),
+        ResolveResult::True,
+    );
+    run_resolve_test(
+        "(now>2020-06-01T13:00:00Z)",
+        &vec![],
+        fixed_time_resolvers(),
+        ResolveResult::False(vec![], Expression::Empty(false)),
+    );
+    run_resolve_test(
+        "(now.timestamp>=1591012800)",
+        &vec![],
+        fixed_time_resolvers(),
+        ResolveResult::True,
+    );
+}
+
+#[test]
+fn resolve_explicit_property_overrides_resolver() {
+    run_resolve_test(
+        "(now=t\"2020-06-01T12:00:00Z\")",
+        &vec!["now=\"overridden\""],
+        fixed_time_resolvers(),
+        ResolveResult::False(vec![], Expression::Empty(false)),
+    );
+}
+
+#[test]
+fn resolve_explicit_typed_property_overrides_resolver() {
+    run_resolve_test(
+        "(now=t\"2020-06-01T12:00:00Z\")",
+        &vec!["now=t\"2020-01-01T00:00:00Z\""],
+        fixed_time_resolvers(),
+        ResolveResult::False(vec![], Expression::Empty(false)),
+    );
+    run_resolve_test(
+        "(now<t\"2020-06-01T12:00:00Z\")",
+        &vec!["now=t\"2020-01-01T00:00:00Z\""],
+        fixed_time_resolvers(),
+        ResolveResult::True,
+    );
+}
+
+#[test]
+fn resolve_implicit_property() {
+    let mut resolvers = PropertyResolvers::default();
+    resolvers.add(Arc::new(FreeSlotsResolver { free_slots: 3 }));
+
+    run_resolve_test(
+        "(golem.node.free_slots>0)",
+        &vec!["golem.node.free_slots"],
+        resolvers.clone(),
+        ResolveResult::True,
+    );
+    run_resolve_test(
+        "(golem.node.free_slots=*)",
+        &vec![],
+        resolvers,
+        ResolveResult::True,
+    );
+}
+
+#[test]
+fn resolve_without_resolver_undefined() {
+    let f = "(golem.node.free_slots>0)";
+
+    run_resolve_test(
+        f,
+        &vec!["golem.node.free_slots"],
+        PropertyResolvers::default(),
+        ResolveResult::Undefined(
+            vec![&PropertyRef::Value(
+                String::from("golem.node.free_slots"),
+                PropertyRefType::Any,
+            )],
+            Expression::Greater(
+                PropertyRef::Value(String::from("golem.node.free_slots"), PropertyRefType::Any),
+                String::from("0"),
+            ),
+        ),
+    );
+}
+
+#[test]
+fn resolvers_first_answer_wins() {
+    let mut resolvers = PropertyResolvers::default();
+    resolvers.add(Arc::new(FreeSlotsResolver { free_slots: 0 }));
+    resolvers.add(Arc::new(FreeSlotsResolver { free_slots: 5 }));
+
+    assert_eq!(
+        resolvers.resolve("golem.node.free_slots"),
+        Some("0".to_string())
+    );
+    assert_eq!(resolvers.resolve("golem.node.other"), None);
+}
+
+#[test]
+fn match_with_resolvers() {
+    let mut resolvers = PropertyResolvers::builtin();
+    resolvers.add(Arc::new(FreeSlotsResolver { free_slots: 2 }));
+
+    assert_eq!(
+        match_demand_offer_with_resolvers(
+            "{}",
+            "(golem.node.free_slots>=1)",
+            "{}",
+            "(now>2020-01-01T00:00:00Z)",
+            &resolvers,
+        )
+        .unwrap(),
+        Match::Yes
+    );
+}
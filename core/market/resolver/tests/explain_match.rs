@@ -0,0 +1,196 @@
+use ya_market_resolver::resolver::expression::*;
+use ya_market_resolver::resolver::ldap_parser::parse;
+use ya_market_resolver::resolver::properties::*;
+use ya_market_resolver::{explain_match, ExplainNode, ExplainOperator, ExplainResult};
+
+mod sample;
+
+use sample::{
+    POC_DEMAND_CONSTRAINTS, POC_DEMAND_PROPERTIES_JSON, POC_OFFER_CONSTRAINTS,
+    POC_OFFER_PROPERTIES_JSON,
+};
+
+fn run_explain_test(expr: &str, props: &Vec<&str>) -> ExplainNode {
+    let expression = build_expression(&parse(expr).unwrap()).unwrap();
+
+    let mut properties = vec![];
+    for prop in props {
+        properties.push(prop.to_string());
+    }
+
+    let property_set = PropertySet::from_flat_props(&properties);
+    let explained = expression.explain(&property_set);
+
+    // explain must always agree with resolve
+    assert_eq!(
+        explained.result,
+        ExplainResult::from(&expression.resolve(&property_set))
+    );
+    explained
+}
+
+fn leaf(
+    operator: ExplainOperator,
+    result: ExplainResult,
+    property: &str,
+    value: Option<&str>,
+    literal: Option<&str>,
+) -> ExplainNode {
+    ExplainNode {
+        operator,
+        result,
+        property: Some(property.to_string()),
+        value: value.map(|v| v.to_string()),
+        literal: literal.map(|l| l.to_string()),
+        operands: vec![],
+    }
+}
+
+#[test]
+fn explain_equals() {
+    assert_eq!(
+        run_explain_test("(cn=Babs Jensen)", &vec!["cn=\"Dblah\""]),
+        leaf(
+            ExplainOperator::Equals,
+            ExplainResult::False,
+            "cn",
+            Some("Dblah"),
+            Some("Babs Jensen"),
+        )
+    );
+
+    assert_eq!(
+        run_explain_test("(cn=Babs Jensen)", &vec!["cnas=\"Dblah\""]),
+        leaf(
+            ExplainOperator::Equals,
+            ExplainResult::Undefined,
+            "cn",
+            None,
+            Some("Babs Jensen"),
+        )
+    );
+}
+
+#[test]
+fn explain_present() {
+    assert_eq!(
+        run_explain_test("(objectClass=*)", &vec!["objectClass=\"Babs Jensen\""]),
+        leaf(
+            ExplainOperator::Present,
+            ExplainResult::True,
+            "objectClass",
+            Some("Babs Jensen"),
+            None,
+        )
+    );
+}
+
+#[test]
+fn explain_nested_evaluates_all_operands() {
+    let explained = run_explain_test(
+        "(&(|(golem.runtime.name=docker)(golem.runtime.name=vm))(golem.inf.mem.gib>=4))",
+        &vec!["golem.runtime.name=\"vm\"", "golem.inf.mem.gib=2"],
+    );
+
+    assert_eq!(explained.operator, ExplainOperator::And);
+    assert_eq!(explained.result, ExplainResult::False);
+    assert_eq!(explained.operands.len(), 2);
+
+    let or_node = &explained.operands[0];
+    assert_eq!(or_node.operator, ExplainOperator::Or);
+    assert_eq!(or_node.result, ExplainResult::True);
+    assert_eq!(
+        or_node.operands[0],
+        leaf(
+            ExplainOperator::Equals,
+            ExplainResult::False,
+            "golem.runtime.name",
+            Some("vm"),
+            Some("docker"),
+        )
+    );
+
+    assert_eq!(
+        explained.operands[1],
+        leaf(
+            ExplainOperator::GreaterEqual,
+            ExplainResult::False,
+            "golem.inf.mem.gib",
+            Some("2"),
+            Some("4"),
+        )
+    );
+}
+
+#[test]
+fn explain_not() {
+    let explained = run_explain_test("(!(a=b))", &vec!["a=\"b\""]);
+
+    assert_eq!(explained.operator, ExplainOperator::Not);
+    assert_eq!(explained.result, ExplainResult::False);
+    assert_eq!(explained.operands[0].result, ExplainResult::True);
+}
+
+#[test]
+fn explain_list_and_version_values() {
+    assert_eq!(
+        run_explain_test("(a=2)", &vec!["a=[1,2,3]"]),
+        leaf(
+            ExplainOperator::Equals,
+            ExplainResult::True,
+            "a",
+            Some("[1,2,3]"),
+            Some("2"),
+        )
+    );
+
+    assert_eq!(
+        run_explain_test("(a$v>1.0.0)", &vec!["a=\"0.9.0\""]),
+        leaf(
+            ExplainOperator::Greater,
+            ExplainResult::False,
+            "a",
+            Some("0.9.0"),
+            Some("1.0.0"),
+        )
+    );
+}
+
+#[test]
+fn explain_match_poc_samples() {
+    let explanation = explain_match(
+        POC_DEMAND_PROPERTIES_JSON,
+        POC_DEMAND_CONSTRAINTS,
+        POC_OFFER_PROPERTIES_JSON,
+        POC_OFFER_CONSTRAINTS,
+    )
+    .unwrap();
+
+    assert_eq!(explanation.result, ExplainResult::True);
+    assert_eq!(explanation.demand_constraints.result, ExplainResult::True);
+    assert_eq!(explanation.offer_constraints.result, ExplainResult::True);
+}
+
+#[test]
+fn explain_match_mismatch() {
+    let explanation = explain_match(
+        "{\"foo\": \"bar1\"}",
+        "(qux=baz)",
+        "{\"qux\": \"baz\"}",
+        "(foo=bar)",
+    )
+    .unwrap();
+
+    assert_eq!(explanation.result, ExplainResult::False);
+    assert_eq!(explanation.demand_constraints.result, ExplainResult::True);
+    assert_eq!(
+        explanation.offer_constraints,
+        leaf(
+            ExplainOperator::Equals,
+            ExplainResult::False,
+            "foo",
+            Some("bar1"),
+            Some("bar"),
+        )
+    );
+}
@@ -153,6 +153,28 @@ fn resolve_equals_with_wildcard() {
     );
 }
 
+#[test]
+fn resolve_equals_with_escaped_asterisk() {
+    let f = "(name=a\\2ab)";
+
+    run_resolve_test(f, &vec!["name=\"a*b\""], ResolveResult::True);
+    run_resolve_test(
+        f,
+        &vec!["name=\"axb\""],
+        ResolveResult::False(vec![], Expression::Empty(false)),
+    );
+
+    // escaped asterisk next to wildcard, other regex characters are taken literally
+    let f = "(name=a.\\2a*)";
+
+    run_resolve_test(f, &vec!["name=\"a.*b\""], ResolveResult::True);
+    run_resolve_test(
+        f,
+        &vec!["name=\"ax*b\""],
+        ResolveResult::False(vec![], Expression::Empty(false)),
+    );
+}
+
 #[test]
 fn resolve_equals_int() {
     let f = "(cn=123)";
@@ -0,0 +1,201 @@
+use ya_market_resolver::resolver::expression::*;
+use ya_market_resolver::resolver::ldap_parser::parse;
+use ya_market_resolver::resolver::properties::*;
+use ya_market_resolver::{match_demand_offer, Match, MatchError};
+
+fn run_resolve_test(expr: &str, props: &Vec<&str>, expect_result: ResolveResult) {
+    let expression = build_expression(&parse(expr).unwrap()).unwrap();
+
+    let mut properties = vec![];
+    for prop in props {
+        properties.push(prop.to_string());
+    }
+
+    let property_set = PropertySet::from_flat_props(&properties);
+
+    assert_eq!(expression.resolve(&property_set), expect_result);
+}
+
+fn assert_build_error(expr: &str) {
+    match build_expression(&parse(expr).unwrap()) {
+        Err(_) => {}
+        Ok(expression) => panic!("Expression error expected, but got: {:?}", expression),
+    }
+}
+
+#[test]
+fn build_expression_typed_literal_keeps_literal_type() {
+    assert_eq!(
+        build_expression(&parse("(a>=v\"1.2.0\")").unwrap()),
+        Ok(Expression::GreaterEqual(
+            PropertyRef::Value(String::from("a"), PropertyRefType::Any),
+            String::from("v\"1.2.0\""),
+        ))
+    );
+    assert_eq!(
+        build_expression(&parse("(a<d\"10.5\")").unwrap()),
+        Ok(Expression::Less(
+            PropertyRef::Value(String::from("a"), PropertyRefType::Any),
+            String::from("d\"10.5\""),
+        ))
+    );
+    assert_eq!(
+        build_expression(&parse("(a$t>t\"1985-04-12T23:20:50.52Z\")").unwrap()),
+        Ok(Expression::Greater(
+            PropertyRef::Value(String::from("a"), PropertyRefType::DateTime),
+            String::from("1985-04-12T23:20:50.52Z"),
+        ))
+    );
+    assert_eq!(
+        build_expression(&parse("(a[aspect]=d\"1\")").unwrap()),
+        Ok(Expression::Equals(
+            PropertyRef::Aspect(
+                String::from("a"),
+                String::from("aspect"),
+                PropertyRefType::Decimal
+            ),
+            String::from("1"),
+        ))
+    );
+}
+
+#[test]
+fn build_expression_bool_and_list_literals() {
+    assert_eq!(
+        build_expression(&parse("(a=True)").unwrap()),
+        Ok(Expression::Equals(
+            PropertyRef::Value(String::from("a"), PropertyRefType::Any),
+            String::from("true"),
+        ))
+    );
+    assert_eq!(
+        build_expression(&parse("(a=[\"x\",\"y\"])").unwrap()),
+        Ok(Expression::Equals(
+            PropertyRef::Value(String::from("a"), PropertyRefType::Any),
+            String::from("[x,y]"),
+        ))
+    );
+}
+
+#[test]
+fn build_expression_invalid_typed_literal() {
+    assert_build_error("(a=v\"not a version\")");
+    assert_build_error("(a=d\"abc\")");
+    assert_build_error("(a>t\"yesterday\")");
+}
+
+#[test]
+fn build_expression_literal_type_mismatch() {
+    assert_build_error("(a$d=v\"1.0.0\")");
+    assert_build_error("(a$v>t\"1985-04-12T23:20:50.52Z\")");
+    assert_build_error("(a$v=true)");
+    assert_build_error("(a$d=[1,2])");
+}
+
+#[test]
+fn build_expression_untyped_literal_validated_against_ref_type() {
+    assert_build_error("(a$v>=latest)");
+    assert_build_error("(a$d<ten)");
+    assert_build_error("(a$t<now)");
+}
+
+#[test]
+fn build_expression_ordering_of_bool_and_list_literals() {
+    assert_build_error("(a>true)");
+    assert_build_error("(a<=[1,2])");
+}
+
+#[test]
+fn resolve_typed_literal_converts_string_property() {
+    run_resolve_test(
+        "(a>=v\"1.5.0\")",
+        &vec!["a=\"1.10.0\""],
+        ResolveResult::True,
+    );
+    run_resolve_test(
+        "(a>d\"10\")",
+        &vec!["a=\"9.99\""],
+        ResolveResult::False(vec![], Expression::Empty(false)),
+    );
+    run_resolve_test(
+        "(a<t\"1985-04-12T23:20:51Z\")",
+        &vec!["a=t\"1985-04-12T23:20:50.52Z\""],
+        ResolveResult::True,
+    );
+}
+
+fn assert_resolve_error(expr: &str, props: &Vec<&str>) {
+    let expression = build_expression(&parse(expr).unwrap()).unwrap();
+    let properties = props.iter().map(|prop| prop.to_string()).collect();
+    let property_set = PropertySet::from_flat_props(&properties);
+
+    match expression.resolve(&property_set) {
+        ResolveResult::Err(_) => {}
+        result => panic!("Resolve error expected, but got: {:?}", result),
+    }
+}
+
+#[test]
+fn resolve_typed_literal_checks_declared_property_type() {
+    assert_resolve_error("(a>=v\"1.0.0\")", &vec!["a=5"]);
+    assert_resolve_error("(a=d\"5\")", &vec!["a=v\"5.0.0\""]);
+    assert_resolve_error("(a<v\"2.0.0\")", &vec!["a=\"not a version\""]);
+    // Mismatch is not hidden by other operands.
+    assert_resolve_error("(|(b=1)(a>=v\"1.0.0\"))", &vec!["a=5", "b=2"]);
+    run_resolve_test(
+        "(a$v<v\"2.0.0\")",
+        &vec!["a=\"not a version\""],
+        ResolveResult::Undefined(
+            vec![],
+            Expression::Less(
+                PropertyRef::Value(String::from("a"), PropertyRefType::Version),
+                String::from("2.0.0"),
+            ),
+        ),
+    );
+}
+
+#[test]
+fn resolve_bool_and_list_literals() {
+    run_resolve_test("(a=TRUE)", &vec!["a=true"], ResolveResult::True);
+    run_resolve_test(
+        "(a=[\"x\",\"y\"])",
+        &vec!["a=[\"y\",\"x\"]"],
+        ResolveResult::True,
+    );
+}
+
+#[test]
+fn match_literal_type_mismatch_prepare_error() {
+    match match_demand_offer("{}", "(a$d>v\"1.0.0\")", "{}", "()").unwrap_err() {
+        MatchError::PrepareError(_) => (),
+        e => panic!("Prepare error expected, but got: {}", e),
+    }
+}
+
+#[test]
+fn match_typed_literal_property_type_mismatch_error() {
+    match match_demand_offer(
+        "{}",
+        "(golem.runtime.version>=v\"0.2.0\")",
+        "{\"golem.runtime.version\": 2}",
+        "()",
+    ) {
+        Err(MatchError::InternalError(_)) => (),
+        result => panic!("Resolve error expected, but got: {:?}", result),
+    }
+}
+
+#[test]
+fn match_typed_literal() {
+    assert_eq!(
+        match_demand_offer(
+            "{}",
+            "(golem.runtime.version>=v\"0.2.0\")",
+            "{\"golem.runtime.version\": \"0.10.1\"}",
+            "()",
+        )
+        .unwrap(),
+        Match::Yes
+    );
+}
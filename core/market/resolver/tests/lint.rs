@@ -0,0 +1,145 @@
+use ya_market_resolver::{lint_constraints, LintKind, PropertySchema, PropertyType};
+
+mod sample;
+
+use sample::{POC_DEMAND_CONSTRAINTS, POC_OFFER_CONSTRAINTS};
+
+fn lint_kinds(constraints: &str) -> Vec<LintKind> {
+    lint_constraints(constraints, &PropertySchema::builtin())
+        .into_iter()
+        .map(|warning| warning.kind)
+        .collect()
+}
+
+#[test]
+fn lint_valid_constraints() {
+    assert!(lint_kinds("()").is_empty());
+    assert!(lint_kinds("(&(x>3)(x<5))").is_empty());
+    assert!(lint_kinds("(|(x<3)(x>5))").is_empty());
+    assert!(lint_kinds("(&(golem.inf.mem.gib>0.5)(golem.inf.storage.gib>1))").is_empty());
+    assert!(lint_kinds("(&(golem.inf.cpu.vendor=GenuineIntel)(golem.inf.cpu.bit=64))").is_empty());
+    assert!(lint_kinds(POC_DEMAND_CONSTRAINTS).is_empty());
+    assert!(lint_kinds(POC_OFFER_CONSTRAINTS).is_empty());
+}
+
+#[test]
+fn lint_malformed() {
+    assert_eq!(lint_kinds("(x>5"), vec![LintKind::Malformed]);
+    assert_eq!(lint_kinds("(x$v>abc)"), vec![LintKind::Malformed]);
+}
+
+#[test]
+fn lint_contradiction_of_ranges() {
+    assert_eq!(lint_kinds("(&(x>5)(x<3))"), vec![LintKind::Contradiction]);
+    assert_eq!(lint_kinds("(&(x>=5)(x<5))"), vec![LintKind::Contradiction]);
+    assert!(lint_kinds("(&(x>=5)(x<=5))").is_empty());
+    assert_eq!(
+        lint_kinds("(&(x>v\"1.2.0\")(&(y=1)(x<v\"1.1.0\")))"),
+        vec![LintKind::Contradiction]
+    );
+    assert_eq!(
+        lint_kinds("(&(golem.inf.mem.gib=2)(golem.inf.mem.gib>4))"),
+        vec![LintKind::Contradiction]
+    );
+}
+
+#[test]
+fn lint_equality_on_untyped_property_isnt_contradiction() {
+    // untyped property may be a List, where equality means "contains"
+    assert!(lint_kinds("(&(x=1)(x=2))").is_empty());
+}
+
+#[test]
+fn lint_contradiction_of_negation() {
+    assert_eq!(
+        lint_kinds("(&(a=b)(!(a=b)))"),
+        vec![LintKind::Contradiction]
+    );
+    assert_eq!(
+        lint_kinds("(&(a=b)(!(a=*)))"),
+        vec![LintKind::Contradiction]
+    );
+}
+
+#[test]
+fn lint_tautology() {
+    assert_eq!(lint_kinds("(|(a=b)(!(a=b)))"), vec![LintKind::Tautology]);
+    assert_eq!(lint_kinds("(|(x>3)(x<=3))"), vec![LintKind::Tautology]);
+    assert_eq!(lint_kinds("(|(x>3)(x<4))"), vec![LintKind::Tautology]);
+    assert!(lint_kinds("(|(x>3)(x<3))").is_empty());
+}
+
+#[test]
+fn lint_under_negation_has_opposite_effect() {
+    assert_eq!(lint_kinds("(!(&(x>5)(x<3)))"), vec![LintKind::Tautology]);
+    assert_eq!(
+        lint_kinds("(!(|(x>3)(x<=3)))"),
+        vec![LintKind::Contradiction]
+    );
+    assert_eq!(
+        lint_kinds("(!(!(&(x>5)(x<3))))"),
+        vec![LintKind::Contradiction]
+    );
+    assert_eq!(
+        lint_kinds("(&(y=1)(!(|(a=b)(!(a=b)))))"),
+        vec![LintKind::Contradiction]
+    );
+
+    let warnings = lint_constraints("(!(golem.com.usage.vector>1))", &PropertySchema::builtin());
+    assert_eq!(warnings[0].kind, LintKind::InvalidOperator);
+    assert!(warnings[0].message.ends_with("its negation is always true"));
+}
+
+#[test]
+fn lint_invalid_operator() {
+    assert_eq!(
+        lint_kinds("(golem.com.usage.vector>1)"),
+        vec![LintKind::InvalidOperator]
+    );
+    assert!(lint_kinds("(golem.com.usage.vector=golem.usage.cpu)").is_empty());
+}
+
+#[test]
+fn lint_type_mismatch() {
+    assert_eq!(
+        lint_kinds("(golem.inf.mem.gib>lots)"),
+        vec![LintKind::TypeMismatch]
+    );
+    assert!(lint_kinds("(golem.inf.mem.gib=*)").is_empty());
+}
+
+#[test]
+fn lint_unknown_property() {
+    let warnings = lint_constraints(
+        "(&(golem.inf.mem.gbi>1)(golem.inf.mem.gbi<8))",
+        &PropertySchema::builtin(),
+    );
+
+    // each unknown name reported once
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0].kind, LintKind::UnknownProperty);
+    assert_eq!(warnings[0].property, Some("golem.inf.mem.gbi".to_string()));
+    assert!(warnings[0]
+        .message
+        .contains("did you mean golem.inf.mem.gib"));
+
+    // names outside of schema namespaces aren't checked
+    assert!(lint_kinds("(myapp.inf.mem.gbi>1)").is_empty());
+}
+
+#[test]
+fn lint_custom_schema() {
+    let schema = PropertySchema::default()
+        .with_namespace("myapp")
+        .with_property("myapp.tags", PropertyType::List);
+
+    let kinds: Vec<LintKind> = lint_constraints("(&(myapp.tags<=3)(myapp.tag=a))", &schema)
+        .into_iter()
+        .map(|warning| warning.kind)
+        .collect();
+
+    assert_eq!(
+        kinds,
+        vec![LintKind::InvalidOperator, LintKind::UnknownProperty]
+    );
+}
@@ -174,6 +174,7 @@ fn match_weak_simple_aspect_match() {
     // Inject aspect here (note this seems very inefficient - worth review)
     prepared_offer
         .properties
+        .to_mut()
         .set_property_aspect("o1", "aspect", "dblah");
 
     assert_eq!(
@@ -0,0 +1,161 @@
+use ya_market_resolver::{
+    match_demand_offer, match_prepared, prepare_subscription, Match, Prefilter, PropertyResolvers,
+    DEFAULT_INDEXED_PROPERTIES,
+};
+
+mod sample;
+
+use sample::{
+    POC_DEMAND_CONSTRAINTS, POC_DEMAND_PROPERTIES_JSON, POC_OFFER_CONSTRAINTS,
+    POC_OFFER_PROPERTIES_JSON,
+};
+
+fn prefilter(properties: &str, constraints: &str) -> Prefilter {
+    Prefilter::from(
+        &prepare_subscription(properties, constraints).unwrap(),
+        DEFAULT_INDEXED_PROPERTIES,
+    )
+}
+
+fn may_match(
+    demand_properties: &str,
+    demand_constraints: &str,
+    offer_properties: &str,
+    offer_constraints: &str,
+) -> bool {
+    prefilter(demand_properties, demand_constraints)
+        .may_match(&prefilter(offer_properties, offer_constraints))
+}
+
+#[test]
+fn match_prepared_same_as_match_demand_offer() {
+    let demand = prepare_subscription(POC_DEMAND_PROPERTIES_JSON, POC_DEMAND_CONSTRAINTS)
+        .unwrap()
+        .with_resolvers(PropertyResolvers::builtin());
+    let offer = prepare_subscription(POC_OFFER_PROPERTIES_JSON, POC_OFFER_CONSTRAINTS)
+        .unwrap()
+        .with_resolvers(PropertyResolvers::builtin());
+
+    assert_eq!(
+        match_prepared(&demand, &offer).unwrap(),
+        match_demand_offer(
+            POC_DEMAND_PROPERTIES_JSON,
+            POC_DEMAND_CONSTRAINTS,
+            POC_OFFER_PROPERTIES_JSON,
+            POC_OFFER_CONSTRAINTS
+        )
+        .unwrap()
+    );
+    assert_eq!(match_prepared(&demand, &offer).unwrap(), Match::Yes);
+}
+
+#[test]
+fn match_prepared_after_clone_and_move() {
+    let demand = prepare_subscription(POC_DEMAND_PROPERTIES_JSON, POC_DEMAND_CONSTRAINTS).unwrap();
+    let offers: Vec<_> = (0..3)
+        .map(|_| prepare_subscription(POC_OFFER_PROPERTIES_JSON, POC_OFFER_CONSTRAINTS).unwrap())
+        .collect();
+
+    let cloned = demand.clone();
+    drop(demand);
+    for offer in offers.into_iter() {
+        assert_eq!(offer.clone(), offer);
+        assert_eq!(match_prepared(&cloned, &offer).unwrap(), Match::Yes);
+    }
+}
+
+#[test]
+fn prefilter_passes_matching_samples() {
+    assert!(may_match(
+        POC_DEMAND_PROPERTIES_JSON,
+        POC_DEMAND_CONSTRAINTS,
+        POC_OFFER_PROPERTIES_JSON,
+        POC_OFFER_CONSTRAINTS
+    ));
+}
+
+#[test]
+fn prefilter_rejects_equality() {
+    assert!(!may_match(
+        "{}",
+        "(golem.runtime.name=vm)",
+        r#"{"golem.runtime.name": "wasmtime"}"#,
+        "()"
+    ));
+    assert!(!may_match(
+        r#"{"golem.node.debug.subnet": "other"}"#,
+        "()",
+        "{}",
+        "(golem.node.debug.subnet=piotr)"
+    ));
+}
+
+#[test]
+fn prefilter_rejects_range() {
+    assert!(!may_match(
+        "{}",
+        "(&(golem.inf.mem.gib>2)(golem.inf.cpu.threads>=1))",
+        r#"{"golem.inf.mem.gib": 2, "golem.inf.cpu.threads": 4}"#,
+        "()"
+    ));
+    assert!(may_match(
+        "{}",
+        "(&(golem.inf.mem.gib>=2)(golem.inf.cpu.threads>=1))",
+        r#"{"golem.inf.mem.gib": 2, "golem.inf.cpu.threads": 4}"#,
+        "()"
+    ));
+}
+
+#[test]
+fn prefilter_passes_undecidable() {
+    // absent properties may be resolved dynamically
+    assert!(may_match("{}", "(golem.inf.mem.gib>2)", "{}", "()"));
+    // only top-level conjunction is considered
+    assert!(may_match(
+        "{}",
+        "(|(golem.inf.mem.gib>2)(golem.inf.cpu.cores>2))",
+        r#"{"golem.inf.mem.gib": 1, "golem.inf.cpu.cores": 1}"#,
+        "()"
+    ));
+    // wildcards and typed references are left to full resolution
+    assert!(may_match(
+        "{}",
+        "(&(golem.runtime.name=wasm*)(golem.inf.mem.gib$d>2))",
+        r#"{"golem.runtime.name": "wasmtime", "golem.inf.mem.gib": 1}"#,
+        "()"
+    ));
+    // not indexed properties
+    assert!(may_match(
+        "{}",
+        "(golem.node.id.name=other)",
+        r#"{"golem.node.id.name": "2rec-prov@dan"}"#,
+        "()"
+    ));
+}
+
+#[test]
+fn prefilter_agrees_with_resolution() {
+    let cases = [
+        ("(golem.inf.mem.gib>0.5)", r#"{"golem.inf.mem.gib": 1.0}"#),
+        ("(golem.inf.mem.gib>0.5)", r#"{"golem.inf.mem.gib": 0.5}"#),
+        ("(golem.inf.mem.gib<=abc)", r#"{"golem.inf.mem.gib": 1}"#),
+        (
+            "(golem.runtime.name>vm)",
+            r#"{"golem.runtime.name": "wasmtime"}"#,
+        ),
+        ("(golem.runtime.name=vm)", r#"{"golem.runtime.name": "vm"}"#),
+        ("(golem.inf.cpu.cores=4)", r#"{"golem.inf.cpu.cores": 4}"#),
+    ];
+
+    for (constraints, properties) in cases.iter() {
+        let matched =
+            match_demand_offer("{}", constraints, properties, "()").unwrap() == Match::Yes;
+        assert_eq!(
+            may_match("{}", constraints, properties, "()"),
+            matched,
+            "{} vs {}",
+            constraints,
+            properties
+        );
+    }
+}
@@ -2,6 +2,7 @@ use std::collections::*;
 
 use chrono::*;
 
+use ya_market_resolver::resolver::dynamic::PropertyResolvers;
 use ya_market_resolver::resolver::error::ParseError;
 use ya_market_resolver::resolver::properties::*;
 
@@ -185,7 +186,8 @@ fn from_flat_props_ok() {
                     ),
                 );
                 x
-            }
+            },
+            resolvers: PropertyResolvers::default(),
         }
     );
 }
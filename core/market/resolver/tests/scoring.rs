@@ -0,0 +1,94 @@
+use ya_market_resolver::resolver::properties::PropertySet;
+use ya_market_resolver::{prepare_subscription, PropertyResolvers, Ranking, ScoreExpression};
+
+mod sample;
+
+use sample::POC_OFFER_PROPERTIES_JSON;
+
+fn evaluate(score: &str, properties: &[&str]) -> Option<f64> {
+    let properties: Vec<String> = properties.iter().map(|prop| prop.to_string()).collect();
+    ScoreExpression::parse(score)
+        .unwrap()
+        .evaluate(&PropertySet::from_flat_props(&properties))
+}
+
+#[test]
+fn parse_precedence() {
+    assert_eq!(
+        ScoreExpression::parse("a + b * -c - (d - e) / 2")
+            .unwrap()
+            .to_string(),
+        "((a+(b*-c))-((d-e)/2))"
+    );
+    assert_eq!(
+        ScoreExpression::parse("golem.com.pricing.model.linear.coeffs[1]")
+            .unwrap()
+            .to_string(),
+        "golem.com.pricing.model.linear.coeffs[1]"
+    );
+}
+
+#[test]
+fn parse_invalid() {
+    assert!(ScoreExpression::parse("").is_err());
+    assert!(ScoreExpression::parse("a +").is_err());
+    assert!(ScoreExpression::parse("(a").is_err());
+    assert!(ScoreExpression::parse("a b").is_err());
+    assert!(ScoreExpression::parse("a[x]").is_err());
+    assert!(ScoreExpression::parse("1.2.3").is_err());
+}
+
+#[test]
+fn evaluate_properties() {
+    assert_eq!(evaluate("2 * (a + 1)", &["a=3"]), Some(8.0));
+    assert_eq!(evaluate("-a / b", &["a=3", "b=2"]), Some(-1.5));
+    assert_eq!(evaluate("a[2]", &["a=[0.1,0.2,1.0]"]), Some(1.0));
+}
+
+#[test]
+fn evaluate_undefined() {
+    assert_eq!(evaluate("a + 1", &[]), None);
+    assert_eq!(evaluate("a", &["a=\"x\""]), None);
+    assert_eq!(evaluate("a", &["a=[1,2]"]), None);
+    assert_eq!(evaluate("a[2]", &["a=[1,2]"]), None);
+    assert_eq!(evaluate("a / 0", &["a=1"]), None);
+}
+
+#[test]
+fn ranking_from_demand_properties() {
+    let demand = prepare_subscription(
+        r#"{"golem.market.rank": {"score": "golem.inf.mem.gib * 2", "limit": 3}}"#,
+        "()",
+    )
+    .unwrap();
+    let ranking = Ranking::from(&demand).unwrap().unwrap();
+    assert_eq!(ranking.limit, Some(3));
+
+    let offer = prepare_subscription(POC_OFFER_PROPERTIES_JSON, "()")
+        .unwrap()
+        .with_resolvers(PropertyResolvers::builtin());
+    assert_eq!(ranking.score(&offer), Some(2.0));
+
+    let no_ranking = prepare_subscription("{}", "()").unwrap();
+    assert_eq!(Ranking::from(&no_ranking).unwrap(), None);
+
+    let invalid = prepare_subscription(r#"{"golem.market.rank.limit": -1}"#, "()").unwrap();
+    assert!(Ranking::from(&invalid).is_err());
+}
+
+#[test]
+fn rank_best_first() {
+    let ranking = Ranking {
+        score: None,
+        limit: None,
+    };
+    assert_eq!(
+        ranking.rank(vec![
+            ("a", Some(1.0)),
+            ("b", None),
+            ("c", Some(3.0)),
+            ("d", Some(1.0)),
+        ]),
+        vec!["c", "a", "d", "b"]
+    );
+}
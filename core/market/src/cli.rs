@@ -0,0 +1,278 @@
+use chrono::{DateTime, Utc};
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+use ya_client::model::NodeId;
+use ya_core_model::market::{
+    local, AgreementState, GetConfig, GetMarketStats, ListAgreements, ListNodePolicies, NodePolicy,
+    RemoveNodePolicy, SetNodePolicy,
+};
+use ya_market_resolver::{lint_constraints, PropertySchema};
+use ya_service_api::{CliCtx, CommandOutput, ResponseTable};
+use ya_service_bus::{typed as bus, RpcEndpoint};
+
+/// Market management.
+#[derive(StructOpt, Debug)]
+pub enum MarketCli {
+    /// Agreements of local identities
+    Agreements(AgreementsCommand),
+    /// Nodes allowed or blocked by local identities
+    Policies(PoliciesCommand),
+    /// Show effective market configuration
+    Config,
+    /// Summarize active Offers visible to the market and local Agreements
+    Stats {
+        /// Identity owning counted Agreements [default: all local identities]
+        #[structopt(long)]
+        id: Option<NodeId>,
+    },
+    /// Check Demand/Offer constraints for contradictions, tautologies, invalid
+    /// operators and unknown properties. Fails, if any warning is reported
+    Lint {
+        /// JSON file extending the built-in schema of known properties,
+        /// eg. {"namespaces": ["myapp"], "properties": {"myapp.threads": "number"}}
+        #[structopt(long)]
+        schema: Option<PathBuf>,
+        /// Constraint expressions
+        #[structopt(required = true)]
+        constraints: Vec<String>,
+    },
+}
+
+#[derive(StructOpt, Debug)]
+pub enum AgreementsCommand {
+    /// List Agreements matching all given criteria, ordered by creation time
+    List {
+        /// Identity owning Agreements [default: all local identities]
+        #[structopt(long)]
+        id: Option<NodeId>,
+        /// Agreement state (Proposal, Pending, Cancelled, Rejected, Approved, Expired, Terminated)
+        #[structopt(long, parse(try_from_str = parse_state))]
+        state: Option<AgreementState>,
+        /// Identity of the other side of Agreement
+        #[structopt(long)]
+        peer_id: Option<NodeId>,
+        #[structopt(long)]
+        app_session_id: Option<String>,
+        /// Created at or after this date (RFC 3339)
+        #[structopt(long)]
+        after_date: Option<DateTime<Utc>>,
+        /// Created before this date (RFC 3339)
+        #[structopt(long)]
+        before_date: Option<DateTime<Utc>>,
+        /// Valid to at or after this date (RFC 3339)
+        #[structopt(long)]
+        valid_after: Option<DateTime<Utc>>,
+        /// Valid to before this date (RFC 3339)
+        #[structopt(long)]
+        valid_before: Option<DateTime<Utc>>,
+        #[structopt(long)]
+        offset: Option<u32>,
+        #[structopt(long)]
+        limit: Option<u32>,
+    },
+}
+
+#[derive(StructOpt, Debug)]
+pub enum PoliciesCommand {
+    /// List node policies
+    List {
+        /// Identity owning policies [default: all local identities]
+        #[structopt(long)]
+        id: Option<NodeId>,
+    },
+    /// Match and negotiate only with allowed nodes
+    Allow {
+        node_id: NodeId,
+        /// Identity owning policy [default: default identity]
+        #[structopt(long)]
+        id: Option<NodeId>,
+        #[structopt(long)]
+        description: Option<String>,
+    },
+    /// Ignore Offers and Proposals of node
+    Block {
+        node_id: NodeId,
+        /// Identity owning policy [default: default identity]
+        #[structopt(long)]
+        id: Option<NodeId>,
+        #[structopt(long)]
+        description: Option<String>,
+    },
+    /// Remove node policy
+    Remove {
+        node_id: NodeId,
+        /// Identity owning policy [default: default identity]
+        #[structopt(long)]
+        id: Option<NodeId>,
+    },
+}
+
+fn parse_state(state: &str) -> Result<AgreementState, serde_json::Error> {
+    serde_json::from_value(serde_json::Value::String(state.to_string()))
+}
+
+impl MarketCli {
+    pub async fn run_command(self, ctx: &CliCtx) -> anyhow::Result<CommandOutput> {
+        match self {
+            MarketCli::Config => {
+                let config = bus::service(local::BUS_ID).send(GetConfig {}).await??;
+                if ctx.json_output {
+                    CommandOutput::object(toml::from_str::<toml::Value>(&config)?)
+                } else {
+                    CommandOutput::object(config)
+                }
+            }
+            MarketCli::Stats { id } => {
+                let stats = bus::service(local::BUS_ID)
+                    .send(GetMarketStats { node_id: id })
+                    .await??;
+                CommandOutput::object(stats)
+            }
+            MarketCli::Lint {
+                schema,
+                constraints,
+            } => lint(schema, constraints),
+            MarketCli::Agreements(AgreementsCommand::List {
+                id,
+                state,
+                peer_id,
+                app_session_id,
+                after_date,
+                before_date,
+                valid_after,
+                valid_before,
+                offset,
+                limit,
+            }) => {
+                let agreements = bus::service(local::BUS_ID)
+                    .send(ListAgreements {
+                        node_id: id,
+                        state,
+                        peer_id,
+                        app_session_id,
+                        after_date,
+                        before_date,
+                        valid_after,
+                        valid_before,
+                        offset,
+                        limit,
+                    })
+                    .await??;
+
+                Ok(ResponseTable {
+                    columns: vec![
+                        "id".into(),
+                        "state".into(),
+                        "provider".into(),
+                        "requestor".into(),
+                        "valid to".into(),
+                        "app session".into(),
+                    ],
+                    values: agreements
+                        .into_iter()
+                        .map(|agreement| {
+                            serde_json::json! {[
+                                agreement.agreement_id,
+                                agreement.state,
+                                agreement.offer.provider_id,
+                                agreement.demand.requestor_id,
+                                agreement.valid_to,
+                                agreement.app_session_id,
+                            ]}
+                        })
+                        .collect(),
+                }
+                .into())
+            }
+            MarketCli::Policies(PoliciesCommand::List { id }) => {
+                let policies = bus::service(local::BUS_ID)
+                    .send(ListNodePolicies { owner_id: id })
+                    .await??;
+
+                Ok(ResponseTable {
+                    columns: vec![
+                        "identity".into(),
+                        "node".into(),
+                        "policy".into(),
+                        "created".into(),
+                        "description".into(),
+                    ],
+                    values: policies
+                        .into_iter()
+                        .map(|entry| {
+                            serde_json::json! {[
+                                entry.owner_id,
+                                entry.node_id,
+                                entry.policy,
+                                entry.insertion_date,
+                                entry.description,
+                            ]}
+                        })
+                        .collect(),
+                }
+                .into())
+            }
+            MarketCli::Policies(PoliciesCommand::Allow {
+                node_id,
+                id,
+                description,
+            }) => set_policy(id, node_id, NodePolicy::Allow, description).await,
+            MarketCli::Policies(PoliciesCommand::Block {
+                node_id,
+                id,
+                description,
+            }) => set_policy(id, node_id, NodePolicy::Block, description).await,
+            MarketCli::Policies(PoliciesCommand::Remove { node_id, id }) => {
+                bus::service(local::BUS_ID)
+                    .send(RemoveNodePolicy {
+                        owner_id: id,
+                        node_id,
+                    })
+                    .await??;
+                CommandOutput::object(format!("Policy of node {} removed.", node_id))
+            }
+        }
+    }
+}
+
+/// Lints constraints locally, with the same schema as market on subscription,
+/// so it doesn't need running service.
+fn lint(schema_path: Option<PathBuf>, constraints: Vec<String>) -> anyhow::Result<CommandOutput> {
+    let mut schema = PropertySchema::builtin();
+    if let Some(path) = schema_path {
+        let custom: PropertySchema = serde_json::from_str(&std::fs::read_to_string(&path)?)?;
+        schema.properties.extend(custom.properties);
+        schema.namespaces.extend(custom.namespaces);
+    }
+
+    let warnings: Vec<String> = constraints
+        .iter()
+        .flat_map(|constraints| {
+            lint_constraints(constraints, &schema)
+                .into_iter()
+                .map(move |warning| format!("{}: {}", constraints, warning))
+        })
+        .collect();
+    if !warnings.is_empty() {
+        anyhow::bail!("Constraints lint warnings:\n{}", warnings.join("\n"));
+    }
+    CommandOutput::object("No lint warnings.")
+}
+
+async fn set_policy(
+    owner_id: Option<NodeId>,
+    node_id: NodeId,
+    policy: NodePolicy,
+    description: Option<String>,
+) -> anyhow::Result<CommandOutput> {
+    let entry = bus::service(local::BUS_ID)
+        .send(SetNodePolicy {
+            owner_id,
+            node_id,
+            policy,
+            description,
+        })
+        .await??;
+    CommandOutput::object(entry)
+}
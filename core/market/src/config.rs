@@ -1,29 +1,108 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
 use std::time::Duration;
 
-/// TODO: Decide where should this config be loaded from.
-///  We could deserialize it from .json file or use structopt and
-///  configure market through env variables.
-#[derive(Default)]
+/// Name of config file in yagna data directory. Market reads `[market]` section of it.
+pub const CONFIG_FILE_NAME: &str = "yagna.toml";
+/// Config file section with market configuration.
+pub const CONFIG_SECTION: &str = "market";
+/// Prefix of environment variables overriding config file values.
+/// Variable name consists of prefix, section and field name, for example:
+/// `YAGNA_MARKET_DISCOVERY_MAX_BCASTED_OFFERS=50` or `YAGNA_MARKET_SUBSCRIPTION_DEFAULT_TTL=1h`.
+pub const ENV_PREFIX: &str = "YAGNA_MARKET";
+
+#[derive(thiserror::Error, Debug)]
+pub enum ConfigError {
+    #[error("Failed to read market config file {0}. Error: {1}")]
+    Read(String, std::io::Error),
+    #[error("Failed to parse market config. Error: {0}")]
+    Parse(String),
+    #[error("Invalid market config value {0}: {1}")]
+    Invalid(&'static str, String),
+}
+
+/// Market configuration. Values not specified in config file
+/// nor in environment variables have defaults suitable for production nodes.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct Config {
     pub discovery: DiscoveryConfig,
     pub subscription: SubscriptionConfig,
+    pub negotiation: NegotiationConfig,
     pub events: EventsConfig,
+    pub cleaner: CleanerConfig,
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct DiscoveryConfig {
+    /// Zero disables cyclic broadcasts of Offers.
     pub max_bcasted_offers: u32,
+    /// Zero disables cyclic broadcasts of unsubscribed Offers.
     pub max_bcasted_unsubscribes: u32,
+    #[serde(with = "duration")]
     pub mean_cyclic_bcast_interval: Duration,
+    #[serde(with = "duration")]
     pub mean_cyclic_unsubscribes_interval: Duration,
+    /// Compatibility with nodes using previous discovery protocol version,
+    /// which don't sign Offers. Enables accepting unsigned Offers and exchanging
+    /// broadcasts using both protocol versions.
+    pub accept_unsigned_offers: bool,
+    /// Period, in which broadcasts received from single node are counted against quotas.
+    /// Quotas are kept for nodes sending us broadcasts, not for Offer issuers.
+    #[serde(with = "duration")]
+    pub quota_period: Duration,
+    /// New Offers and Offer refreshes from single node accepted in quota period.
+    /// Zero disables limit.
+    pub max_received_offers_per_node: u32,
+    /// Unsubscribes of Offers from single node accepted in quota period.
+    /// Zero disables limit.
+    pub max_received_unsubscribes_per_node: u32,
+    /// Active Offers issued by single node, that we store. Zero disables limit.
+    pub max_stored_offers_per_node: u32,
+    /// Node exceeding quotas is ignored for this time.
+    #[serde(with = "duration")]
+    pub offender_ban_period: Duration,
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct SubscriptionConfig {
+    /// TTL of subscriptions, that didn't request their own.
+    #[serde(with = "chrono_duration")]
     pub default_ttl: chrono::Duration,
+    /// Bounds of TTL requested on subscribe or refresh.
+    #[serde(with = "chrono_duration")]
+    pub min_ttl: chrono::Duration,
+    #[serde(with = "chrono_duration")]
+    pub max_ttl: chrono::Duration,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NegotiationConfig {
+    /// Compatibility with nodes using previous negotiation protocol version,
+    /// which send placeholder instead of Agreement signature. Enables accepting
+    /// such Agreements, which can't be verified offline.
+    pub accept_unsigned_agreements: bool,
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct EventsConfig {
     pub max_events_default: i32,
     pub max_events_max: i32,
+    /// Interval of keep-alive comments sent by idle event streams.
+    #[serde(with = "duration")]
+    pub stream_keep_alive_interval: Duration,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CleanerConfig {
+    /// Interval between removals of outdated market database entries.
+    #[serde(with = "duration")]
+    pub interval: Duration,
 }
 
 impl Default for DiscoveryConfig {
@@ -33,6 +112,12 @@ impl Default for DiscoveryConfig {
             max_bcasted_unsubscribes: 200,
             mean_cyclic_bcast_interval: Duration::from_secs(60),
             mean_cyclic_unsubscribes_interval: Duration::from_secs(60),
+            accept_unsigned_offers: false,
+            quota_period: Duration::from_secs(60),
+            max_received_offers_per_node: 2000,
+            max_received_unsubscribes_per_node: 2000,
+            max_stored_offers_per_node: 100,
+            offender_ban_period: Duration::from_secs(600),
         }
     }
 }
@@ -41,6 +126,8 @@ impl Default for SubscriptionConfig {
     fn default() -> Self {
         SubscriptionConfig {
             default_ttl: chrono::Duration::seconds(50),
+            min_ttl: chrono::Duration::seconds(10),
+            max_ttl: chrono::Duration::hours(24),
         }
     }
 }
@@ -50,6 +137,323 @@ impl Default for EventsConfig {
         EventsConfig {
             max_events_default: 20,
             max_events_max: 100,
+            stream_keep_alive_interval: Duration::from_secs(15),
+        }
+    }
+}
+
+impl Default for CleanerConfig {
+    fn default() -> Self {
+        CleanerConfig {
+            interval: Duration::from_secs(3600 * 24),
         }
     }
 }
+
+impl Config {
+    /// Loads `[market]` section of config file from data directory (if the file exists),
+    /// applies environment variables overrides and validates the result.
+    pub fn load(data_dir: &Path) -> Result<Config, ConfigError> {
+        let path = data_dir.join(CONFIG_FILE_NAME);
+        let content = match std::fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(ConfigError::Read(path.display().to_string(), e)),
+        };
+        Config::from_toml(&content, std::env::vars())
+    }
+
+    /// Builds config from `[market]` section of TOML document overridden by
+    /// given environment variables.
+    pub fn from_toml(
+        content: &str,
+        env: impl IntoIterator<Item = (String, String)>,
+    ) -> Result<Config, ConfigError> {
+        let mut document = content
+            .parse::<toml::Value>()
+            .map_err(|e| ConfigError::Parse(e.to_string()))?;
+        let mut market = match document
+            .as_table_mut()
+            .and_then(|t| t.remove(CONFIG_SECTION))
+        {
+            Some(toml::Value::Table(market)) => market,
+            Some(_) => {
+                return Err(ConfigError::Parse(format!(
+                    "[{}] should be a table",
+                    CONFIG_SECTION
+                )))
+            }
+            None => toml::value::Table::new(),
+        };
+
+        let sections = match toml::Value::try_from(Config::default()) {
+            Ok(toml::Value::Table(sections)) => sections,
+            _ => unreachable!("Config serializes to TOML table"),
+        };
+        let env = env.into_iter().collect::<std::collections::HashMap<_, _>>();
+
+        for (section, fields) in sections {
+            let fields = match fields {
+                toml::Value::Table(fields) => fields,
+                _ => continue,
+            };
+            for field in fields.keys() {
+                let name = format!("{}_{}_{}", ENV_PREFIX, section, field).to_uppercase();
+                if let Some(value) = env.get(&name) {
+                    let overrides = market
+                        .entry(section.clone())
+                        .or_insert_with(|| toml::Value::Table(toml::value::Table::new()));
+                    if let toml::Value::Table(overrides) = overrides {
+                        overrides.insert(field.clone(), env_value(value));
+                    }
+                }
+            }
+        }
+
+        let config: Config = toml::Value::Table(market)
+            .try_into()
+            .map_err(|e| ConfigError::Parse(e.to_string()))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.discovery.mean_cyclic_bcast_interval == Duration::from_secs(0) {
+            return Err(ConfigError::Invalid(
+                "discovery.mean_cyclic_bcast_interval",
+                "should be positive".to_string(),
+            ));
+        }
+        if self.discovery.mean_cyclic_unsubscribes_interval == Duration::from_secs(0) {
+            return Err(ConfigError::Invalid(
+                "discovery.mean_cyclic_unsubscribes_interval",
+                "should be positive".to_string(),
+            ));
+        }
+        if self.discovery.quota_period == Duration::from_secs(0) {
+            return Err(ConfigError::Invalid(
+                "discovery.quota_period",
+                "should be positive".to_string(),
+            ));
+        }
+        if self.subscription.min_ttl <= chrono::Duration::zero() {
+            return Err(ConfigError::Invalid(
+                "subscription.min_ttl",
+                "should be positive".to_string(),
+            ));
+        }
+        if self.subscription.max_ttl < self.subscription.min_ttl {
+            return Err(ConfigError::Invalid(
+                "subscription.max_ttl",
+                "should not be less than subscription.min_ttl".to_string(),
+            ));
+        }
+        if self.subscription.default_ttl < self.subscription.min_ttl
+            || self.subscription.default_ttl > self.subscription.max_ttl
+        {
+            return Err(ConfigError::Invalid(
+                "subscription.default_ttl",
+                "should be between subscription.min_ttl and subscription.max_ttl".to_string(),
+            ));
+        }
+        if self.events.max_events_max <= 0 {
+            return Err(ConfigError::Invalid(
+                "events.max_events_max",
+                "should be positive".to_string(),
+            ));
+        }
+        if self.events.max_events_default <= 0
+            || self.events.max_events_default > self.events.max_events_max
+        {
+            return Err(ConfigError::Invalid(
+                "events.max_events_default",
+                format!(
+                    "should be positive and not greater than events.max_events_max ({})",
+                    self.events.max_events_max
+                ),
+            ));
+        }
+        if self.events.stream_keep_alive_interval == Duration::from_secs(0) {
+            return Err(ConfigError::Invalid(
+                "events.stream_keep_alive_interval",
+                "should be positive".to_string(),
+            ));
+        }
+        if self.cleaner.interval == Duration::from_secs(0) {
+            return Err(ConfigError::Invalid(
+                "cleaner.interval",
+                "should be positive".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Effective configuration as `[market]` section of config file.
+    pub fn to_toml(&self) -> String {
+        let mut document = toml::value::Table::new();
+        document.insert(
+            CONFIG_SECTION.to_string(),
+            toml::Value::try_from(self).expect("Config serializes to TOML"),
+        );
+        toml::to_string_pretty(&document).expect("Config serializes to TOML")
+    }
+}
+
+/// Environment variables are untyped, so we guess type of value.
+/// Numbers are kept as numbers, everything else is treated as string.
+fn env_value(value: &str) -> toml::Value {
+    if let Ok(number) = value.parse::<i64>() {
+        toml::Value::Integer(number)
+    } else if let Ok(boolean) = value.parse::<bool>() {
+        toml::Value::Boolean(boolean)
+    } else {
+        toml::Value::String(value.to_string())
+    }
+}
+
+/// Durations are written in human readable form, like `100ms`, `1m 30s` or `24h`.
+/// Plain integer is interpreted as number of seconds.
+mod duration {
+    use serde::{de, Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum HumanDuration {
+        Seconds(u64),
+        Text(String),
+    }
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&humantime::format_duration(*duration).to_string())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        match HumanDuration::deserialize(deserializer)? {
+            HumanDuration::Seconds(secs) => Ok(Duration::from_secs(secs)),
+            HumanDuration::Text(text) => {
+                humantime::parse_duration(&text).map_err(de::Error::custom)
+            }
+        }
+    }
+}
+
+mod chrono_duration {
+    use serde::{de, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        duration: &chrono::Duration,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let duration = duration.to_std().map_err(serde::ser::Error::custom)?;
+        super::duration::serialize(&duration, serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<chrono::Duration, D::Error> {
+        let duration = super::duration::deserialize(deserializer)?;
+        chrono::Duration::from_std(duration).map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env(vars: &[(&str, &str)]) -> Vec<(String, String)> {
+        vars.iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn defaults_without_config_file() {
+        let config = Config::from_toml("", env(&[])).unwrap();
+        assert_eq!(config.discovery.max_bcasted_offers, 200);
+        assert_eq!(
+            config.subscription.default_ttl,
+            chrono::Duration::seconds(50)
+        );
+        assert_eq!(config.cleaner.interval, Duration::from_secs(3600 * 24));
+        assert!(!config.negotiation.accept_unsigned_agreements);
+    }
+
+    #[test]
+    fn env_overrides_config_file() {
+        let content = r#"
+            [net]
+            other = "ignored"
+
+            [market.discovery]
+            max_bcasted_offers = 50
+            mean_cyclic_bcast_interval = "100ms"
+
+            [market.cleaner]
+            interval = 3600
+        "#;
+        let config = Config::from_toml(
+            content,
+            env(&[
+                ("YAGNA_MARKET_DISCOVERY_MAX_BCASTED_OFFERS", "10"),
+                ("YAGNA_MARKET_SUBSCRIPTION_DEFAULT_TTL", "1h 30m"),
+                (
+                    "YAGNA_MARKET_NEGOTIATION_ACCEPT_UNSIGNED_AGREEMENTS",
+                    "true",
+                ),
+            ]),
+        )
+        .unwrap();
+
+        assert_eq!(config.discovery.max_bcasted_offers, 10);
+        assert_eq!(
+            config.discovery.mean_cyclic_bcast_interval,
+            Duration::from_millis(100)
+        );
+        assert_eq!(
+            config.subscription.default_ttl,
+            chrono::Duration::minutes(90)
+        );
+        assert_eq!(config.cleaner.interval, Duration::from_secs(3600));
+        assert_eq!(config.events.max_events_max, 100);
+        assert!(config.negotiation.accept_unsigned_agreements);
+    }
+
+    #[test]
+    fn printed_config_loads_back() {
+        let mut config = Config::default();
+        config.discovery.mean_cyclic_unsubscribes_interval = Duration::from_millis(1500);
+        config.events.max_events_default = 5;
+
+        let loaded = Config::from_toml(&config.to_toml(), env(&[])).unwrap();
+        assert_eq!(loaded.to_toml(), config.to_toml());
+    }
+
+    #[test]
+    fn reject_invalid_config() {
+        assert!(Config::from_toml("[market]\nunknown = 1", env(&[])).is_err());
+        assert!(
+            Config::from_toml("[market.discovery]\nmax_bcasted_offers = -1", env(&[])).is_err()
+        );
+        assert!(Config::from_toml("", env(&[("YAGNA_MARKET_CLEANER_INTERVAL", "0")])).is_err());
+        assert!(Config::from_toml(
+            "",
+            env(&[("YAGNA_MARKET_SUBSCRIPTION_DEFAULT_TTL", "soon")])
+        )
+        .is_err());
+        assert!(Config::from_toml("[market.events]\nmax_events_default = 101", env(&[])).is_err());
+        assert!(Config::from_toml(
+            "",
+            env(&[("YAGNA_MARKET_EVENTS_STREAM_KEEP_ALIVE_INTERVAL", "0")])
+        )
+        .is_err());
+        assert!(
+            Config::from_toml("", env(&[("YAGNA_MARKET_DISCOVERY_QUOTA_PERIOD", "0")])).is_err()
+        );
+        assert!(Config::from_toml(
+            "[market.subscription]\nmin_ttl = \"1m\"\nmax_ttl = \"10s\"",
+            env(&[])
+        )
+        .is_err());
+    }
+}
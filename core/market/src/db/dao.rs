@@ -1,8 +1,10 @@
 mod agreement;
 mod agreement_events;
+mod amendment;
 pub mod cleaner;
 mod demand;
 mod negotiation_events;
+mod node_policy;
 pub mod sql_functions {
     use diesel::sql_types;
     diesel::sql_function!(fn datetime(timestring:sql_types::Text, modifier:sql_types::Text) -> sql_types::Timestamp);
@@ -14,9 +16,11 @@ pub mod sql_functions {
 mod offer;
 mod proposal;
 
-pub use agreement::{AgreementDao, AgreementDaoError, SaveAgreementError};
+pub use agreement::{AgreementDao, AgreementDaoError, AgreementFilter, SaveAgreementError};
 pub use agreement_events::AgreementEventsDao;
+pub use amendment::{AmendmentDao, AmendmentDaoError};
 pub use demand::{DemandDao, DemandState};
 pub use negotiation_events::{NegotiationEventsDao, TakeEventsError};
+pub use node_policy::NodePolicyDao;
 pub use offer::{OfferDao, OfferState};
 pub use proposal::{ChangeProposalStateError, ProposalDao, SaveProposalError};
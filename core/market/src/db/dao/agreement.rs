@@ -1,9 +1,13 @@
-use chrono::{NaiveDateTime, Utc};
+use chrono::NaiveDateTime;
 use diesel::prelude::*;
+use diesel::sql_types::Bool;
+use diesel::sqlite::Sqlite;
 
 use ya_client::model::market::Reason;
 use ya_client::model::NodeId;
-use ya_persistence::executor::{do_with_transaction, AsDao, ConnType, PoolType};
+use ya_persistence::executor::{
+    do_with_connection, do_with_transaction, AsDao, ConnType, PoolType,
+};
 
 use crate::db::dao::agreement_events::create_event;
 use crate::db::dao::proposal::{has_counter_proposal, update_proposal_state};
@@ -14,6 +18,8 @@ use crate::db::model::{
 };
 use crate::db::schema::market_agreement::dsl as agreement;
 use crate::db::schema::market_agreement::dsl::market_agreement;
+use crate::db::schema::market_agreement_amendment::dsl as amendment;
+use crate::db::schema::market_agreement_amendment::dsl::market_agreement_amendment;
 use crate::db::schema::market_agreement_event::dsl as event;
 use crate::db::schema::market_agreement_event::dsl::market_agreement_event;
 use crate::db::{DbError, DbResult};
@@ -35,6 +41,23 @@ pub enum SaveAgreementError {
     Internal(DbError),
 }
 
+/// Criteria of listing Agreements. Criteria, which aren't set, don't filter anything.
+#[derive(Clone, Debug, Default)]
+pub struct AgreementFilter {
+    /// Identity owning Agreements.
+    pub node_id: Option<NodeId>,
+    pub states: Option<Vec<AgreementState>>,
+    /// Identity of the other side of Agreement.
+    pub peer_id: Option<NodeId>,
+    pub app_session_id: AppSessionId,
+    pub created_after: Option<NaiveDateTime>,
+    pub created_before: Option<NaiveDateTime>,
+    pub valid_after: Option<NaiveDateTime>,
+    pub valid_before: Option<NaiveDateTime>,
+    pub offset: Option<i64>,
+    pub limit: Option<i64>,
+}
+
 pub struct AgreementDao<'c> {
     pool: &'c PoolType,
 }
@@ -134,6 +157,89 @@ impl<'c> AgreementDao<'c> {
         .await
     }
 
+    /// Returns Agreements matching filter ordered by creation time.
+    /// Agreements, which weren't finalized within validity period, are listed
+    /// as expired, but their state in database isn't changed.
+    pub async fn list(
+        &self,
+        filter: AgreementFilter,
+        validation_ts: NaiveDateTime,
+    ) -> Result<Vec<Agreement>, AgreementDaoError> {
+        do_with_connection(self.pool, move |conn| {
+            let mut query = market_agreement.into_boxed();
+
+            if let Some(node_id) = filter.node_id {
+                query = query.filter(owned_by(node_id));
+            }
+
+            if let Some(states) = filter.states {
+                query = query.filter(in_states(states, validation_ts));
+            }
+
+            if let Some(peer_id) = filter.peer_id {
+                query = query.filter(with_peer(peer_id));
+            }
+
+            if let Some(session_id) = filter.app_session_id {
+                query = query.filter(agreement::session_id.eq(session_id));
+            }
+
+            if let Some(ts) = filter.created_after {
+                query = query.filter(agreement::creation_ts.ge(ts));
+            }
+
+            if let Some(ts) = filter.created_before {
+                query = query.filter(agreement::creation_ts.lt(ts));
+            }
+
+            if let Some(ts) = filter.valid_after {
+                query = query.filter(agreement::valid_to.ge(ts));
+            }
+
+            if let Some(ts) = filter.valid_before {
+                query = query.filter(agreement::valid_to.lt(ts));
+            }
+
+            // SQLite doesn't accept OFFSET without LIMIT.
+            Ok(query
+                .order_by(agreement::creation_ts.asc())
+                .offset(filter.offset.unwrap_or(0))
+                .limit(filter.limit.unwrap_or(i64::MAX))
+                .load::<Agreement>(conn)?
+                .into_iter()
+                .map(|agreement| with_expiration(agreement, validation_ts))
+                .collect())
+        })
+        .await
+    }
+
+    /// Returns number of Agreements in each state, owned by `node_id` or by all
+    /// local identities. Agreements, which weren't finalized within validity period,
+    /// are counted as expired, but their state in database isn't changed.
+    pub async fn count_by_state(
+        &self,
+        node_id: Option<NodeId>,
+        validation_ts: NaiveDateTime,
+    ) -> Result<Vec<(AgreementState, i64)>, AgreementDaoError> {
+        do_with_connection(self.pool, move |conn| {
+            // Boxed queries can't be grouped.
+            let counts = market_agreement
+                .filter(owned_by_any(node_id))
+                .group_by(agreement::state)
+                .select((agreement::state, diesel::dsl::count_star()))
+                .load::<(AgreementState, i64)>(conn)?;
+            let expired = market_agreement
+                .filter(owned_by_any(node_id))
+                .filter(agreement::state.eq_any(EXPIRING_STATES.to_vec()))
+                .filter(agreement::valid_to.lt(validation_ts))
+                .group_by(agreement::state)
+                .select((agreement::state, diesel::dsl::count_star()))
+                .load::<(AgreementState, i64)>(conn)?;
+            Ok(with_expired_counts(counts, expired))
+        })
+        .await
+    }
+
     pub async fn save(&self, agreement: Agreement) -> Result<Agreement, SaveAgreementError> {
         // Agreement is always created for last Provider Proposal.
         let proposal_id = agreement.offer_proposal_id.clone();
@@ -222,9 +328,11 @@ impl<'c> AgreementDao<'c> {
         &self,
         id: &AgreementId,
         signature: &String,
+        timestamp: &NaiveDateTime,
     ) -> Result<Agreement, AgreementDaoError> {
         let id = id.clone();
         let signature = signature.clone();
+        let timestamp = timestamp.clone();
 
         do_with_transaction(self.pool, move |conn| {
             let mut agreement: Agreement =
@@ -234,13 +342,7 @@ impl<'c> AgreementDao<'c> {
             update_committed_signature(conn, &mut agreement, signature)?;
 
             // Always Provider approves.
-            create_event(
-                conn,
-                &agreement,
-                None,
-                Owner::Provider,
-                agreement.approved_ts.unwrap_or(Utc::now().naive_utc()),
-            )?;
+            create_event(conn, &agreement, None, Owner::Provider, timestamp)?;
 
             Ok(agreement)
         })
@@ -347,8 +449,13 @@ impl<'c> AgreementDao<'c> {
                 event::agreement_id.eq_any(agreements_to_clean.clone().select(agreement::id)),
             );
 
-            let num_agreements = diesel::delete(agreements_to_clean).execute(conn)?;
+            let related_amendments = market_agreement_amendment.filter(
+                amendment::agreement_id.eq_any(agreements_to_clean.clone().select(agreement::id)),
+            );
+
+            diesel::delete(related_amendments).execute(conn)?;
             let num_events = diesel::delete(related_events).execute(conn)?;
+            let num_agreements = diesel::delete(agreements_to_clean).execute(conn)?;
             Result::<(usize, usize), DbError>::Ok((num_agreements, num_events))
         })
         .await?;
@@ -362,6 +469,120 @@ impl<'c> AgreementDao<'c> {
     }
 }
 
+/// Agreements of identity. Both sides of Agreement are stored,
+/// if Provider and Requestor are on the same node.
+fn owned_by(
+    node_id: NodeId,
+) -> Box<dyn BoxableExpression<market_agreement, Sqlite, SqlType = Bool>> {
+    Box::new(
+        agreement::provider_id
+            .eq(node_id)
+            .and(agreement::id.like(format!("{}-%", Owner::Provider)))
+            .or(agreement::requestor_id
+                .eq(node_id)
+                .and(agreement::id.like(format!("{}-%", Owner::Requestor)))),
+    )
+}
+
+/// Agreements of identity or of all local identities, if `node_id` isn't set.
+fn owned_by_any(
+    node_id: Option<NodeId>,
+) -> Box<dyn BoxableExpression<market_agreement, Sqlite, SqlType = Bool>> {
+    match node_id {
+        Some(node_id) => owned_by(node_id),
+        None => Box::new(diesel::dsl::sql::<Bool>("1")),
+    }
+}
+
+/// Agreements, in which `peer_id` is the other side than the owner, ie. Provider
+/// of Agreements owned by Requestor and Requestor of Agreements owned by Provider.
+fn with_peer(
+    peer_id: NodeId,
+) -> Box<dyn BoxableExpression<market_agreement, Sqlite, SqlType = Bool>> {
+    Box::new(
+        agreement::provider_id
+            .eq(peer_id)
+            .and(agreement::id.like(format!("{}-%", Owner::Requestor)))
+            .or(agreement::requestor_id
+                .eq(peer_id)
+                .and(agreement::id.like(format!("{}-%", Owner::Provider)))),
+    )
+}
+
+/// States of Agreements, which expire, if they aren't finalized within validity period.
+const EXPIRING_STATES: [AgreementState; 3] = [
+    AgreementState::Proposal,
+    AgreementState::Pending,
+    AgreementState::Approving,
+];
+
+fn is_expired(
+    state: &AgreementState,
+    valid_to: NaiveDateTime,
+    validation_ts: NaiveDateTime,
+) -> bool {
+    EXPIRING_STATES.contains(state) && valid_to < validation_ts
+}
+
+/// Agreements, which are in one of `states` at `validation_ts`. Expiring
+/// Agreements are in their stored state only until end of validity period
+/// and are expired afterwards.
+fn in_states(
+    states: Vec<AgreementState>,
+    validation_ts: NaiveDateTime,
+) -> Box<dyn BoxableExpression<market_agreement, Sqlite, SqlType = Bool>> {
+    let (expiring, other): (Vec<_>, Vec<_>) = states
+        .iter()
+        .cloned()
+        .partition(|state| EXPIRING_STATES.contains(state));
+    let selected = agreement::state.eq_any(other).or(agreement::state
+        .eq_any(expiring)
+        .and(agreement::valid_to.ge(validation_ts)));
+
+    if states.contains(&AgreementState::Expired) {
+        Box::new(
+            selected.or(agreement::state
+                .eq_any(EXPIRING_STATES.to_vec())
+                .and(agreement::valid_to.lt(validation_ts))),
+        )
+    } else {
+        Box::new(selected)
+    }
+}
+
+/// Agreement in state it is at `validation_ts`.
+fn with_expiration(mut agreement: Agreement, validation_ts: NaiveDateTime) -> Agreement {
+    if is_expired(&agreement.state, agreement.valid_to, validation_ts) {
+        agreement.state = AgreementState::Expired;
+    }
+    agreement
+}
+
+/// Moves number of expired Agreements from counts of their stored states to Expired.
+fn with_expired_counts(
+    mut counts: Vec<(AgreementState, i64)>,
+    expired: Vec<(AgreementState, i64)>,
+) -> Vec<(AgreementState, i64)> {
+    let total = expired.iter().map(|(_, count)| count).sum::<i64>();
+    if total == 0 {
+        return counts;
+    }
+    for (state, num_expired) in expired {
+        if let Some((_, count)) = counts.iter_mut().find(|(counted, _)| *counted == state) {
+            *count -= num_expired;
+        }
+    }
+    match counts
+        .iter_mut()
+        .find(|(state, _)| *state == AgreementState::Expired)
+    {
+        Some((_, count)) => *count += total,
+        None => counts.push((AgreementState::Expired, total)),
+    }
+    counts.retain(|(_, count)| *count > 0);
+    counts
+}
+
 fn find_agreement_for_proposal(
     conn: &ConnType,
     proposal_id: &ProposalId,
@@ -7,7 +7,9 @@ use ya_persistence::executor::{readonly_transaction, ConnType};
 use ya_persistence::executor::{AsDao, PoolType};
 
 use crate::db::dao::AgreementDaoError;
-use crate::db::model::{Agreement, AgreementEvent, AgreementId, NewAgreementEvent};
+use crate::db::model::{
+    Agreement, AgreementEvent, AgreementEventType, AgreementId, NewAgreementEvent,
+};
 use crate::db::model::{AppSessionId, Owner};
 use crate::db::schema::market_agreement::dsl as agreement;
 use crate::db::schema::market_agreement::dsl::market_agreement;
@@ -56,6 +58,7 @@ impl<'c> AgreementEventsDao<'c> {
 
             Ok(market_agreement_event
                 .filter(event::agreement_id.eq_any(select_corresponding_agreement))
+                .filter(event::event_type.ne_all(&AgreementEventType::AMENDMENT_EVENTS[..]))
                 .filter(event::timestamp.gt(after_timestamp))
                 .order_by(event::timestamp.asc())
                 .limit(max_events as i64)
@@ -64,6 +67,43 @@ impl<'c> AgreementEventsDao<'c> {
         .await
     }
 
+    /// Like `select`, but uses event id as a cursor instead of timestamp,
+    /// so events with equal timestamps are never skipped nor duplicated.
+    pub async fn select_after_id(
+        &self,
+        node_id: &NodeId,
+        session_id: &AppSessionId,
+        max_events: i32,
+        after_id: Option<i32>,
+    ) -> DbResult<Vec<AgreementEvent>> {
+        let session_id = session_id.clone();
+        let node_id = node_id.clone();
+        readonly_transaction(self.pool, move |conn| {
+            let filter_my_agreements = agreement::provider_id
+                .eq(node_id)
+                .or(agreement::requestor_id.eq(node_id));
+
+            let mut select_corresponding_agreement = market_agreement
+                .select(agreement::id)
+                .filter(filter_my_agreements)
+                .into_boxed();
+
+            if let Some(session_id) = session_id {
+                select_corresponding_agreement =
+                    select_corresponding_agreement.filter(agreement::session_id.eq(session_id));
+            };
+
+            Ok(market_agreement_event
+                .filter(event::agreement_id.eq_any(select_corresponding_agreement))
+                .filter(event::event_type.ne_all(&AgreementEventType::AMENDMENT_EVENTS[..]))
+                .filter(event::id.gt(after_id.unwrap_or(0)))
+                .order_by(event::id.asc())
+                .limit(max_events as i64)
+                .load::<AgreementEvent>(conn)?)
+        })
+        .await
+    }
+
     pub async fn select_for_agreement(
         &self,
         agreement_id: &AgreementId,
@@ -72,11 +112,28 @@ impl<'c> AgreementEventsDao<'c> {
         readonly_transaction(self.pool, move |conn| {
             Ok(market_agreement_event
                 .filter(event::agreement_id.eq(agreement_id))
+                .filter(event::event_type.ne_all(&AgreementEventType::AMENDMENT_EVENTS[..]))
                 .order_by(event::timestamp.asc())
                 .load::<AgreementEvent>(conn)?)
         })
         .await
     }
+
+    /// Events of Agreement amendment flow in order of recording them.
+    pub async fn select_amendments_for_agreement(
+        &self,
+        agreement_id: &AgreementId,
+    ) -> DbResult<Vec<AgreementEvent>> {
+        let agreement_id = agreement_id.clone();
+        readonly_transaction(self.pool, move |conn| {
+            Ok(market_agreement_event
+                .filter(event::agreement_id.eq(agreement_id))
+                .filter(event::event_type.eq_any(&AgreementEventType::AMENDMENT_EVENTS[..]))
+                .order_by(event::id.asc())
+                .load::<AgreementEvent>(conn)?)
+        })
+        .await
+    }
 }
 
 pub(crate) fn create_event(
@@ -0,0 +1,350 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+
+use ya_client::model::market::Reason;
+use ya_persistence::executor::{do_with_transaction, AsDao, ConnType, PoolType};
+
+use crate::db::model::{
+    Agreement, AgreementEventType, AgreementId, AgreementState, Amendment, AmendmentState,
+    DbReason, NewAgreementEvent, Owner,
+};
+use crate::db::schema::market_agreement::dsl as agreement;
+use crate::db::schema::market_agreement::dsl::market_agreement;
+use crate::db::schema::market_agreement_amendment::dsl as amendment;
+use crate::db::schema::market_agreement_amendment::dsl::market_agreement_amendment;
+use crate::db::schema::market_agreement_event::dsl as event;
+use crate::db::schema::market_agreement_event::dsl::market_agreement_event;
+use crate::db::DbError;
+
+#[derive(thiserror::Error, Debug)]
+pub enum AmendmentDaoError {
+    #[error("Agreement [{0}] not found.")]
+    AgreementNotFound(AgreementId),
+    #[error("Can't amend Agreement [{0}] in state {1}.")]
+    InvalidState(AgreementId, AgreementState),
+    #[error("Agreement [{0}] has pending amendment of version {1}.")]
+    Pending(AgreementId, i32),
+    #[error("Amendment of Agreement [{0}] has version {1}, but {2} was expected.")]
+    InvalidVersion(AgreementId, i32, i32),
+    #[error("Amendment {1} of Agreement [{0}] not found.")]
+    NotFound(AgreementId, i32),
+    #[error("Amendment {1} of Agreement [{0}] was already {2}.")]
+    AlreadyResolved(AgreementId, i32, AmendmentState),
+    #[error("Amendment database error: {0}")]
+    DbError(DbError),
+}
+
+pub struct AmendmentDao<'c> {
+    pool: &'c PoolType,
+}
+
+impl<'a> AsDao<'a> for AmendmentDao<'a> {
+    fn as_dao(pool: &'a PoolType) -> Self {
+        Self { pool }
+    }
+}
+
+impl<'c> AmendmentDao<'c> {
+    /// Stores new amendment. Agreement must be Approved, can't have other pending
+    /// amendment and amendment must describe next Agreement version.
+    pub async fn propose(&self, amendment: Amendment) -> Result<Amendment, AmendmentDaoError> {
+        do_with_transaction(self.pool, move |conn| {
+            let agreement = select_agreement(conn, &amendment.agreement_id)?;
+            if agreement.state != AgreementState::Approved {
+                return Err(AmendmentDaoError::InvalidState(
+                    agreement.id,
+                    agreement.state,
+                ));
+            }
+
+            if let Some(pending) = market_agreement_amendment
+                .filter(amendment::agreement_id.eq(&agreement.id))
+                .filter(amendment::state.eq(AmendmentState::Proposed))
+                .first::<Amendment>(conn)
+                .optional()?
+            {
+                return Err(AmendmentDaoError::Pending(agreement.id, pending.version));
+            }
+
+            if amendment.version != agreement.version + 1 {
+                return Err(AmendmentDaoError::InvalidVersion(
+                    agreement.id,
+                    amendment.version,
+                    agreement.version + 1,
+                ));
+            }
+
+            diesel::insert_into(market_agreement_amendment)
+                .values(&amendment)
+                .execute(conn)?;
+            create_event(
+                conn,
+                &amendment.agreement_id,
+                AgreementEventType::AmendmentProposed,
+                amendment.issuer,
+                None,
+                amendment.creation_ts,
+            )?;
+            Ok(amendment)
+        })
+        .await
+    }
+
+    pub async fn select(
+        &self,
+        agreement_id: &AgreementId,
+        version: i32,
+    ) -> Result<Option<Amendment>, AmendmentDaoError> {
+        let agreement_id = agreement_id.clone();
+        do_with_transaction(self.pool, move |conn| {
+            Ok(market_agreement_amendment
+                .filter(amendment::agreement_id.eq(&agreement_id))
+                .filter(amendment::version.eq(version))
+                .first::<Amendment>(conn)
+                .optional()?)
+        })
+        .await
+    }
+
+    /// Returns amendment waiting for approval of the other party, if there is any.
+    pub async fn pending(
+        &self,
+        agreement_id: &AgreementId,
+    ) -> Result<Option<Amendment>, AmendmentDaoError> {
+        let agreement_id = agreement_id.clone();
+        do_with_transaction(self.pool, move |conn| {
+            Ok(market_agreement_amendment
+                .filter(amendment::agreement_id.eq(&agreement_id))
+                .filter(amendment::state.eq(AmendmentState::Proposed))
+                .first::<Amendment>(conn)
+                .optional()?)
+        })
+        .await
+    }
+
+    /// Returns all amendments of Agreement ordered by version.
+    pub async fn list(
+        &self,
+        agreement_id: &AgreementId,
+    ) -> Result<Vec<Amendment>, AmendmentDaoError> {
+        let agreement_id = agreement_id.clone();
+        do_with_transaction(self.pool, move |conn| {
+            Ok(market_agreement_amendment
+                .filter(amendment::agreement_id.eq(&agreement_id))
+                .order_by(amendment::version.asc())
+                .load::<Amendment>(conn)?)
+        })
+        .await
+    }
+
+    /// Applies amendment to Agreement. Agreement gets amended properties and version
+    /// and signatures of both parties made over amended terms.
+    pub async fn approve(
+        &self,
+        agreement_id: &AgreementId,
+        version: i32,
+        signature: &str,
+        timestamp: &NaiveDateTime,
+    ) -> Result<(Amendment, Agreement), AmendmentDaoError> {
+        let agreement_id = agreement_id.clone();
+        let signature = signature.to_string();
+        let timestamp = timestamp.clone();
+
+        do_with_transaction(self.pool, move |conn| {
+            let mut proposed = select_pending(conn, &agreement_id, version)?;
+            let agreement = select_agreement(conn, &agreement_id)?;
+            if agreement.state != AgreementState::Approved {
+                return Err(AmendmentDaoError::InvalidState(
+                    agreement.id,
+                    agreement.state,
+                ));
+            }
+
+            proposed.state = AmendmentState::Approved;
+            proposed.resolved_ts = Some(timestamp);
+            proposed.approved_signature = Some(signature.clone());
+
+            diesel::update(
+                market_agreement_amendment
+                    .filter(amendment::agreement_id.eq(&agreement_id))
+                    .filter(amendment::version.eq(version)),
+            )
+            .set((
+                amendment::state.eq(&proposed.state),
+                amendment::resolved_ts.eq(&proposed.resolved_ts),
+                amendment::approved_signature.eq(&proposed.approved_signature),
+            ))
+            .execute(conn)?;
+
+            // Requestor signature always goes to `proposed_signature` and Provider's
+            // to `approved_signature`, so amended Agreement can be verified the same way.
+            let (requestor_signature, provider_signature) = match proposed.issuer {
+                Owner::Requestor => (proposed.proposed_signature.clone(), signature),
+                Owner::Provider => (signature, proposed.proposed_signature.clone()),
+            };
+
+            let mut amended = proposed.apply(&agreement);
+            amended.proposed_signature = Some(requestor_signature);
+            amended.approved_signature = Some(provider_signature);
+            amended.committed_signature = None;
+
+            diesel::update(market_agreement.find(&agreement_id))
+                .set((
+                    agreement::offer_properties.eq(&amended.offer_properties),
+                    agreement::demand_properties.eq(&amended.demand_properties),
+                    agreement::version.eq(amended.version),
+                    agreement::proposed_signature.eq(&amended.proposed_signature),
+                    agreement::approved_signature.eq(&amended.approved_signature),
+                    agreement::committed_signature.eq(&amended.committed_signature),
+                ))
+                .execute(conn)?;
+
+            create_event(
+                conn,
+                &agreement_id,
+                AgreementEventType::AmendmentApproved,
+                proposed.issuer.swap(),
+                None,
+                timestamp,
+            )?;
+            Ok((proposed, amended))
+        })
+        .await
+    }
+
+    /// Removes own amendment, that the other party didn't receive, together with
+    /// its event. Does nothing, if amendment was resolved in the meantime.
+    /// Returns true, if amendment was removed.
+    pub async fn withdraw(
+        &self,
+        agreement_id: &AgreementId,
+        version: i32,
+    ) -> Result<bool, AmendmentDaoError> {
+        let agreement_id = agreement_id.clone();
+
+        do_with_transaction(self.pool, move |conn| {
+            let proposed = match select_pending(conn, &agreement_id, version) {
+                Ok(proposed) => proposed,
+                Err(AmendmentDaoError::AlreadyResolved(..)) => return Ok(false),
+                Err(e) => return Err(e),
+            };
+
+            diesel::delete(
+                market_agreement_amendment
+                    .filter(amendment::agreement_id.eq(&agreement_id))
+                    .filter(amendment::version.eq(version)),
+            )
+            .execute(conn)?;
+
+            // Amendment is pending, so its event is the last one proposed by issuer.
+            let event_id = market_agreement_event
+                .select(event::id)
+                .filter(event::agreement_id.eq(&agreement_id))
+                .filter(event::event_type.eq(AgreementEventType::AmendmentProposed))
+                .filter(event::issuer.eq(proposed.issuer))
+                .order_by(event::id.desc())
+                .first::<i32>(conn)
+                .optional()?;
+            if let Some(event_id) = event_id {
+                diesel::delete(market_agreement_event.filter(event::id.eq(event_id)))
+                    .execute(conn)?;
+            }
+            Ok(true)
+        })
+        .await
+    }
+
+    /// Rejects pending amendment. Agreement terms stay unchanged.
+    pub async fn reject(
+        &self,
+        agreement_id: &AgreementId,
+        version: i32,
+        reason: Option<Reason>,
+        timestamp: &NaiveDateTime,
+    ) -> Result<Amendment, AmendmentDaoError> {
+        let agreement_id = agreement_id.clone();
+        let timestamp = timestamp.clone();
+
+        do_with_transaction(self.pool, move |conn| {
+            let mut proposed = select_pending(conn, &agreement_id, version)?;
+
+            proposed.state = AmendmentState::Rejected;
+            proposed.resolved_ts = Some(timestamp);
+            proposed.reason = reason.clone().map(DbReason);
+
+            diesel::update(
+                market_agreement_amendment
+                    .filter(amendment::agreement_id.eq(&agreement_id))
+                    .filter(amendment::version.eq(version)),
+            )
+            .set((
+                amendment::state.eq(&proposed.state),
+                amendment::resolved_ts.eq(&proposed.resolved_ts),
+                amendment::reason.eq(&proposed.reason),
+            ))
+            .execute(conn)?;
+
+            create_event(
+                conn,
+                &agreement_id,
+                AgreementEventType::AmendmentRejected,
+                proposed.issuer.swap(),
+                reason,
+                timestamp,
+            )?;
+            Ok(proposed)
+        })
+        .await
+    }
+}
+
+fn select_agreement(conn: &ConnType, id: &AgreementId) -> Result<Agreement, AmendmentDaoError> {
+    market_agreement
+        .filter(agreement::id.eq(id))
+        .first::<Agreement>(conn)
+        .optional()?
+        .ok_or_else(|| AmendmentDaoError::AgreementNotFound(id.clone()))
+}
+
+fn select_pending(
+    conn: &ConnType,
+    agreement_id: &AgreementId,
+    version: i32,
+) -> Result<Amendment, AmendmentDaoError> {
+    let amendment = market_agreement_amendment
+        .filter(amendment::agreement_id.eq(agreement_id))
+        .filter(amendment::version.eq(version))
+        .first::<Amendment>(conn)
+        .optional()?
+        .ok_or_else(|| AmendmentDaoError::NotFound(agreement_id.clone(), version))?;
+
+    match amendment.state {
+        AmendmentState::Proposed => Ok(amendment),
+        state => Err(AmendmentDaoError::AlreadyResolved(
+            agreement_id.clone(),
+            version,
+            state,
+        )),
+    }
+}
+
+fn create_event(
+    conn: &ConnType,
+    agreement_id: &AgreementId,
+    event_type: AgreementEventType,
+    issuer: Owner,
+    reason: Option<Reason>,
+    timestamp: NaiveDateTime,
+) -> Result<(), AmendmentDaoError> {
+    let event = NewAgreementEvent::amendment(agreement_id, event_type, issuer, reason, timestamp);
+    diesel::insert_into(market_agreement_event)
+        .values(&event)
+        .execute(conn)?;
+    Ok(())
+}
+
+impl<ErrorType: Into<DbError>> From<ErrorType> for AmendmentDaoError {
+    fn from(err: ErrorType) -> Self {
+        AmendmentDaoError::DbError(err.into())
+    }
+}
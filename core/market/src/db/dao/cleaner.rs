@@ -27,9 +27,8 @@ pub async fn clean(db: DbExecutor) {
     }
 }
 
-pub async fn clean_forever(db: DbExecutor) {
-    // TODO: Use value from market config once #460 is merged
-    let mut interval = time::interval(Duration::from_secs(3600 * 24));
+pub async fn clean_forever(db: DbExecutor, interval: Duration) {
+    let mut interval = time::interval(interval);
     loop {
         interval.tick().await;
         log::debug!("Market database cleaner job started");
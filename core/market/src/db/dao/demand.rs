@@ -1,4 +1,4 @@
-use chrono::{NaiveDateTime, Utc};
+use chrono::NaiveDateTime;
 use diesel::expression::dsl::now as sql_now;
 use diesel::{ExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl};
 
@@ -8,6 +8,7 @@ use ya_persistence::executor::{do_with_transaction, readonly_transaction, AsDao,
 
 use crate::db::model::{Demand, SubscriptionId};
 use crate::db::schema::market_demand::dsl;
+use crate::db::schema::market_demand_delivered::dsl as delivered_dsl;
 use crate::db::{DbError, DbResult};
 
 #[allow(unused)]
@@ -30,14 +31,17 @@ pub enum DemandState {
 
 impl<'c> DemandDao<'c> {
     // TODO: return DemandState
-    pub async fn select(&self, id: &SubscriptionId) -> DbResult<Option<Demand>> {
+    pub async fn select(
+        &self,
+        id: &SubscriptionId,
+        validation_ts: NaiveDateTime,
+    ) -> DbResult<Option<Demand>> {
         let id = id.clone();
-        let now = Utc::now().naive_utc();
 
         readonly_transaction(self.pool, move |conn| {
             Ok(dsl::market_demand
                 .filter(dsl::id.eq(&id))
-                .filter(dsl::expiration_ts.ge(now))
+                .filter(dsl::expiration_ts.ge(validation_ts))
                 .first(conn)
                 .optional()?)
         })
@@ -82,10 +86,30 @@ impl<'c> DemandDao<'c> {
         .await
     }
 
+    /// Returns `true` if Demand was found.
+    pub async fn update_expiration(
+        &self,
+        id: &SubscriptionId,
+        expiration_ts: NaiveDateTime,
+    ) -> DbResult<bool> {
+        let id = id.clone();
+        do_with_transaction(self.pool, move |conn| {
+            let num_updated = diesel::update(dsl::market_demand.filter(dsl::id.eq(id)))
+                .set(dsl::expiration_ts.eq(expiration_ts))
+                .execute(conn)?;
+            Ok(num_updated > 0)
+        })
+        .await
+    }
+
     pub async fn delete(&self, id: &SubscriptionId) -> DbResult<bool> {
         let id = id.clone();
 
         do_with_transaction(self.pool, move |conn| {
+            diesel::delete(
+                delivered_dsl::market_demand_delivered.filter(delivered_dsl::demand_id.eq(&id)),
+            )
+            .execute(conn)?;
             let num_deleted =
                 diesel::delete(dsl::market_demand.filter(dsl::id.eq(id))).execute(conn)?;
             Ok(num_deleted > 0)
@@ -93,11 +117,46 @@ impl<'c> DemandDao<'c> {
         .await
     }
 
+    /// Counts up to `count` Proposals delivered for Demand, so that all delivered
+    /// don't exceed `limit`. Returns number of counted Proposals.
+    pub async fn admit_proposals(
+        &self,
+        id: &SubscriptionId,
+        count: usize,
+        limit: usize,
+    ) -> DbResult<usize> {
+        let id = id.clone();
+        do_with_transaction(self.pool, move |conn| {
+            let delivered = delivered_dsl::market_demand_delivered
+                .select(delivered_dsl::delivered)
+                .filter(delivered_dsl::demand_id.eq(&id))
+                .first::<i32>(conn)
+                .optional()?
+                .unwrap_or(0);
+            let admitted = count.min(limit.saturating_sub(delivered as usize));
+            if admitted > 0 {
+                diesel::replace_into(delivered_dsl::market_demand_delivered)
+                    .values((
+                        delivered_dsl::demand_id.eq(&id),
+                        delivered_dsl::delivered.eq(delivered + admitted as i32),
+                    ))
+                    .execute(conn)?;
+            }
+            Ok(admitted)
+        })
+        .await
+    }
+
     pub async fn clean(&self) -> DbResult<()> {
         log::debug!("Clean market demands: start");
         let num_deleted = do_with_transaction(self.pool, move |conn| {
             let nd = diesel::delete(dsl::market_demand.filter(dsl::expiration_ts.lt(sql_now)))
                 .execute(conn)?;
+            diesel::delete(
+                delivered_dsl::market_demand_delivered
+                    .filter(delivered_dsl::demand_id.ne_all(dsl::market_demand.select(dsl::id))),
+            )
+            .execute(conn)?;
             Result::<usize, DbError>::Ok(nd)
         })
         .await?;
@@ -108,20 +167,31 @@ impl<'c> DemandDao<'c> {
         Ok(())
     }
 
-    pub async fn demand_state(self, id: &SubscriptionId) -> DbResult<DemandState> {
+    pub async fn demand_state(
+        self,
+        id: &SubscriptionId,
+        validation_ts: NaiveDateTime,
+    ) -> DbResult<DemandState> {
         let id = id.clone();
-        do_with_transaction(self.pool, move |conn| demand_status(conn, &id)).await
+        do_with_transaction(self.pool, move |conn| {
+            demand_status(conn, &id, &validation_ts)
+        })
+        .await
     }
 }
 
-pub(super) fn demand_status(conn: &ConnType, id: &SubscriptionId) -> DbResult<DemandState> {
+pub(super) fn demand_status(
+    conn: &ConnType,
+    id: &SubscriptionId,
+    validation_ts: &NaiveDateTime,
+) -> DbResult<DemandState> {
     let demand: Option<Demand> = dsl::market_demand
         .filter(dsl::id.eq(&id))
         .first(conn)
         .optional()?;
 
     match demand {
-        Some(demand) => match demand.expiration_ts > Utc::now().naive_utc() {
+        Some(demand) => match demand.expiration_ts > *validation_ts {
             true => Ok(DemandState::Active(demand)),
             false => Ok(DemandState::Expired(Some(demand))),
         },
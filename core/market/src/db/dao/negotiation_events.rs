@@ -1,11 +1,11 @@
-use chrono::Utc;
+use chrono::NaiveDateTime;
 use diesel::dsl::sql;
 use diesel::{sql_types, ExpressionMethods, QueryDsl, RunQueryDsl};
 use thiserror::Error;
 
 use ya_client::model::market::Reason;
 use ya_persistence::executor::ConnType;
-use ya_persistence::executor::{do_with_transaction, AsDao, PoolType};
+use ya_persistence::executor::{do_with_transaction, readonly_transaction, AsDao, PoolType};
 
 use crate::db::dao::demand::{demand_status, DemandState};
 use crate::db::dao::offer::{query_state, OfferState};
@@ -84,11 +84,12 @@ impl<'c> NegotiationEventsDao<'c> {
         subscription_id: &SubscriptionId,
         max_events: i32,
         owner: Owner,
+        now: NaiveDateTime,
     ) -> Result<Vec<MarketEvent>, TakeEventsError> {
         let subscription_id = subscription_id.clone();
         do_with_transaction(self.pool, move |conn| {
             // Check subscription wasn't unsubscribed or expired.
-            validate_subscription(conn, &subscription_id, owner)?;
+            validate_subscription(conn, &subscription_id, owner, &now)?;
 
             // Only ProposalEvents should be in random order.
             //  AgreementEvent and rejections events should be sorted with higher
@@ -130,6 +131,50 @@ impl<'c> NegotiationEventsDao<'c> {
         .await
     }
 
+    /// Returns events stored after event `after_id` in insertion order.
+    /// Unlike `take_events`, events are left in queue, until streaming
+    /// client acknowledges them with `ack_events`.
+    pub async fn select_events_after(
+        &self,
+        subscription_id: &SubscriptionId,
+        after_id: Option<i32>,
+        max_events: i32,
+        owner: Owner,
+        now: NaiveDateTime,
+    ) -> Result<Vec<MarketEvent>, TakeEventsError> {
+        let subscription_id = subscription_id.clone();
+        readonly_transaction(self.pool, move |conn| {
+            validate_subscription(conn, &subscription_id, owner, &now)?;
+
+            Ok(dsl::market_negotiation_event
+                .filter(dsl::subscription_id.eq(&subscription_id))
+                .filter(dsl::id.gt(after_id.unwrap_or(0)))
+                .order_by(dsl::id.asc())
+                .limit(max_events as i64)
+                .load::<MarketEvent>(conn)?)
+        })
+        .await
+    }
+
+    /// Removes events up to event `up_to_id`, which streaming client acknowledged
+    /// by resuming after it. Returns number of removed events.
+    pub async fn ack_events(
+        &self,
+        subscription_id: &SubscriptionId,
+        up_to_id: i32,
+    ) -> DbResult<usize> {
+        let subscription_id = subscription_id.clone();
+        do_with_transaction(self.pool, move |conn| {
+            Ok(diesel::delete(
+                dsl::market_negotiation_event
+                    .filter(dsl::subscription_id.eq(&subscription_id))
+                    .filter(dsl::id.le(up_to_id)),
+            )
+            .execute(conn)?)
+        })
+        .await
+    }
+
     pub async fn remove_events(&self, subscription_id: &SubscriptionId) -> DbResult<()> {
         let subscription_id = subscription_id.clone();
         do_with_transaction(self.pool, move |conn| {
@@ -166,14 +211,15 @@ fn validate_subscription(
     conn: &ConnType,
     subscription_id: &SubscriptionId,
     owner: Owner,
+    now: &NaiveDateTime,
 ) -> Result<(), TakeEventsError> {
     match owner {
-        Owner::Requestor => match demand_status(conn, &subscription_id)? {
+        Owner::Requestor => match demand_status(conn, &subscription_id, now)? {
             DemandState::NotFound => Err(TakeEventsError::NotFound(subscription_id.clone()))?,
             DemandState::Expired(_) => Err(TakeEventsError::Expired(subscription_id.clone()))?,
             _ => Ok(()),
         },
-        Owner::Provider => match query_state(conn, &subscription_id, &Utc::now().naive_utc())? {
+        Owner::Provider => match query_state(conn, &subscription_id, now)? {
             OfferState::NotFound => Err(TakeEventsError::NotFound(subscription_id.clone()))?,
             OfferState::Expired(_) => Err(TakeEventsError::Expired(subscription_id.clone()))?,
             _ => Ok(()),
@@ -0,0 +1,59 @@
+use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl};
+
+use ya_client::model::NodeId;
+use ya_persistence::executor::{do_with_transaction, readonly_transaction, AsDao, PoolType};
+
+use crate::db::model::NodePolicy;
+use crate::db::schema::market_node_policy::dsl;
+use crate::db::DbResult;
+
+pub struct NodePolicyDao<'c> {
+    pool: &'c PoolType,
+}
+
+impl<'c> AsDao<'c> for NodePolicyDao<'c> {
+    fn as_dao(pool: &'c PoolType) -> Self {
+        Self { pool }
+    }
+}
+
+impl<'c> NodePolicyDao<'c> {
+    /// Lists policies of `owner_id` identity or of all identities, if not specified.
+    pub async fn list(&self, owner_id: Option<NodeId>) -> DbResult<Vec<NodePolicy>> {
+        readonly_transaction(self.pool, move |conn| {
+            let mut query = dsl::market_node_policy.into_boxed();
+            if let Some(owner_id) = owner_id {
+                query = query.filter(dsl::owner_id.eq(owner_id));
+            }
+            Ok(query
+                .order_by(dsl::insertion_ts.asc())
+                .load::<NodePolicy>(conn)?)
+        })
+        .await
+    }
+
+    /// Inserts policy replacing previous policy of the same node.
+    pub async fn set(&self, policy: NodePolicy) -> DbResult<NodePolicy> {
+        do_with_transaction(self.pool, move |conn| {
+            diesel::replace_into(dsl::market_node_policy)
+                .values(&policy)
+                .execute(conn)?;
+            Ok(policy)
+        })
+        .await
+    }
+
+    /// Returns false, if there was no policy to remove.
+    pub async fn remove(&self, owner_id: NodeId, node_id: NodeId) -> DbResult<bool> {
+        do_with_transaction(self.pool, move |conn| {
+            let num_removed = diesel::delete(
+                dsl::market_node_policy
+                    .filter(dsl::owner_id.eq(owner_id))
+                    .filter(dsl::node_id.eq(node_id)),
+            )
+            .execute(conn)?;
+            Ok(num_removed > 0)
+        })
+        .await
+    }
+}
@@ -98,6 +98,7 @@ impl<'c> OfferDao<'c> {
     pub async fn get_offer_ids(
         &self,
         node_ids: Option<Vec<NodeId>>,
+        inserted_before_ts: Option<NaiveDateTime>,
         expiry_validation_ts: NaiveDateTime,
     ) -> DbResult<Vec<SubscriptionId>> {
         readonly_transaction(self.pool, move |conn| {
@@ -118,6 +119,10 @@ impl<'c> OfferDao<'c> {
                 query = query.filter(offer::node_id.eq_any(ids));
             };
 
+            if let Some(ts) = inserted_before_ts {
+                query = query.filter(offer::insertion_ts.le(ts))
+            };
+
             Ok(query.load(conn)?)
         })
         .await
@@ -200,6 +205,34 @@ impl<'c> OfferDao<'c> {
         .await
     }
 
+    /// Replaces expiration and signature of active Offer with those of refreshed `offer`.
+    /// Returns pair `(false, offer_state)` if Offer isn't active or refreshed `offer`
+    /// doesn't extend its expiration, or `(true, Active(offer))` after successful update.
+    pub async fn refresh(
+        &self,
+        offer: Offer,
+        expiry_validation_ts: NaiveDateTime,
+    ) -> DbResult<(bool, OfferState)> {
+        do_with_transaction(self.pool, move |conn| {
+            let id = offer.id.clone();
+            match query_state(conn, &id, &expiry_validation_ts)? {
+                OfferState::Active(stored) if stored.expiration_ts < offer.expiration_ts => {
+                    diesel::update(market_offer.filter(offer::id.eq(&id)))
+                        .set((
+                            offer::expiration_ts.eq(offer.expiration_ts),
+                            offer::initial_expiration_ts.eq(offer.initial_expiration_ts),
+                            offer::signature.eq(&offer.signature),
+                        ))
+                        .execute(conn)?;
+                    let offer = query_offer(conn, &id)?.unwrap();
+                    Ok((true, OfferState::Active(offer)))
+                }
+                state => Ok((false, state)),
+            }
+        })
+        .await
+    }
+
     /// Inserts Offer unsubscription marker.
     /// Returns Offer state as before operation
     /// (`Active` means unsubscription has succeeded).
@@ -119,6 +119,47 @@ impl<'c> ProposalDao<'c> {
         .await
     }
 
+    /// Returns Proposal with all its predecessors, starting from initial Proposal.
+    /// Empty if Proposal doesn't exist.
+    pub async fn get_proposal_chain(&self, proposal_id: &ProposalId) -> DbResult<Vec<Proposal>> {
+        let proposal_id = proposal_id.clone();
+        readonly_transaction(self.pool, move |conn| {
+            let mut chain: Vec<DbProposal> = vec![];
+            let mut next_id = Some(proposal_id);
+            while let Some(id) = next_id {
+                let proposal: Option<DbProposal> = dsl::market_proposal
+                    .filter(dsl::id.eq(&id))
+                    .first(conn)
+                    .optional()?;
+
+                // Predecessor could be already cleaned up.
+                let proposal = match proposal {
+                    Some(proposal) => proposal,
+                    None => break,
+                };
+                next_id = proposal.prev_proposal_id.clone();
+                chain.push(proposal);
+            }
+
+            let negotiation: Negotiation = match chain.first() {
+                Some(proposal) => dsl_negotiation::market_negotiation
+                    .filter(dsl_negotiation::id.eq(&proposal.negotiation_id))
+                    .first(conn)?,
+                None => return Ok(vec![]),
+            };
+
+            Ok(chain
+                .into_iter()
+                .rev()
+                .map(|body| Proposal {
+                    negotiation: negotiation.clone(),
+                    body,
+                })
+                .collect())
+        })
+        .await
+    }
+
     pub async fn clean(&self) -> DbResult<()> {
         // FIXME clean negotiations also
         log::debug!("Clean market proposals: start");
@@ -1,17 +1,21 @@
 mod agreement;
 mod agreement_events;
+mod amendment;
 mod demand;
 mod negotiation_events;
+mod node_policy;
 mod offer;
 mod proposal;
 mod proposal_id;
 mod subscription_id;
 
 pub use agreement::{check_transition, Agreement, AgreementId, AgreementState, AppSessionId};
-pub use agreement_events::{AgreementEvent, AgreementEventType, NewAgreementEvent};
+pub use agreement_events::{AgreementEvent, AgreementEventType, DbReason, NewAgreementEvent};
+pub use amendment::{Amendment, AmendmentState};
 pub use demand::Demand;
 pub use negotiation_events::{EventError, EventType, MarketEvent};
-pub use offer::{Offer, OfferUnsubscribed};
+pub use node_policy::{NodePolicy, PolicyKind};
+pub use offer::{Offer, OfferSignatureError, OfferUnsubscribed};
 pub use proposal::{DbProposal, Issuer, Negotiation, Proposal, ProposalState};
 
 pub use proposal_id::{Owner, ProposalId, ProposalIdParseError, ProposalIdValidationError};
@@ -2,6 +2,7 @@ use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
 use diesel::sql_types::Text;
 use serde::{Deserialize, Serialize};
 
+use ya_agreement_utils::signature::{agreement_hash, verify_signature};
 use ya_client::model::market::agreement::{
     Agreement as ClientAgreement, State as ClientAgreementState,
 };
@@ -87,6 +88,9 @@ pub struct Agreement {
     pub proposed_signature: Option<String>,
     pub approved_signature: Option<String>,
     pub committed_signature: Option<String>,
+
+    /// Version of Agreement terms. Incremented with every approved amendment.
+    pub version: i32,
 }
 
 impl Agreement {
@@ -140,6 +144,7 @@ impl Agreement {
             proposed_signature: None,
             approved_signature: None,
             committed_signature: None,
+            version: 0,
         }
     }
 
@@ -177,6 +182,21 @@ impl Agreement {
             committed_signature: self.committed_signature,
         })
     }
+
+    /// Hash of canonical Agreement terms. Both parties sign it, so that
+    /// Agreement can be verified offline with `ya_agreement_utils::verify_agreement`.
+    pub fn signed_payload(&self) -> Result<Vec<u8>, ErrorMessage> {
+        Ok(agreement_hash(
+            &self.clone().into_client()?,
+            self.version as u32,
+        ))
+    }
+
+    /// Checks, that hex encoded `signature` of Agreement terms was made by `signer`.
+    pub fn verify_signature(&self, signature: &str, signer: &NodeId) -> Result<(), String> {
+        let payload = self.signed_payload().map_err(|e| e.to_string())?;
+        verify_signature(&payload, signature, signer)
+    }
 }
 
 impl From<AgreementState> for ClientAgreementState {
@@ -194,6 +214,23 @@ impl From<AgreementState> for ClientAgreementState {
     }
 }
 
+impl AgreementState {
+    /// Database states represented by given client state.
+    pub fn from_client(state: &ClientAgreementState) -> Vec<AgreementState> {
+        match state {
+            ClientAgreementState::Proposal => vec![AgreementState::Proposal],
+            ClientAgreementState::Pending => {
+                vec![AgreementState::Pending, AgreementState::Approving]
+            }
+            ClientAgreementState::Cancelled => vec![AgreementState::Cancelled],
+            ClientAgreementState::Rejected => vec![AgreementState::Rejected],
+            ClientAgreementState::Approved => vec![AgreementState::Approved],
+            ClientAgreementState::Expired => vec![AgreementState::Expired],
+            ClientAgreementState::Terminated => vec![AgreementState::Terminated],
+        }
+    }
+}
+
 pub fn check_transition(from: AgreementState, to: AgreementState) -> Result<(), AgreementDaoError> {
     log::trace!("Checking Agreement state transition: {} => {}", from, to);
     match from {
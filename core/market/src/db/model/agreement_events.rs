@@ -11,6 +11,10 @@ use ya_client::model::market::agreement_event::AgreementTerminator;
 use ya_client::model::market::{
     AgreementEventType as ClientEventType, AgreementOperationEvent as ClientEvent, Reason,
 };
+use ya_core_model::market::{
+    AmendmentEvent as ClientAmendmentEvent, AmendmentEventType as ClientAmendmentEventType,
+};
+use ya_core_model::Role;
 use ya_diesel_utils::DbTextField;
 
 #[derive(
@@ -30,6 +34,20 @@ pub enum AgreementEventType {
     Rejected,
     Cancelled,
     Terminated,
+    AmendmentProposed,
+    AmendmentApproved,
+    AmendmentRejected,
+}
+
+impl AgreementEventType {
+    /// Events of amendment flow. They don't have representation in client
+    /// `AgreementOperationEvent`, so they are available only through
+    /// Agreement amendment events endpoint (see `AgreementEvent::into_amendment_client`).
+    pub const AMENDMENT_EVENTS: [AgreementEventType; 3] = [
+        AgreementEventType::AmendmentProposed,
+        AgreementEventType::AmendmentApproved,
+        AgreementEventType::AmendmentRejected,
+    ];
 }
 
 #[derive(DbTextField, Debug, Clone, AsExpression, FromSqlRow)]
@@ -66,7 +84,7 @@ impl NewAgreementEvent {
         agreement: &Agreement,
         reason: Option<Reason>,
         terminator: Owner,
-        _timestamp: NaiveDateTime,
+        timestamp: NaiveDateTime,
     ) -> Result<Self, EventFromAgreementError> {
         Ok(Self {
             agreement_id: agreement.id.clone(),
@@ -84,26 +102,43 @@ impl NewAgreementEvent {
                 AgreementState::Approved => AgreementEventType::Approved,
                 AgreementState::Terminated => AgreementEventType::Terminated,
             },
-            // We don't use timestamp from parameter here, because it came from other party
-            // and we use this timestamp for sorting events, when returning them to caller.
+            // Callers pass local time of recording the event, not timestamp that came from other
+            // party, because we use this timestamp for sorting events, when returning them to caller.
             // On the other side, we should sign AgreementTerminated event together with timestamp,
             // so we need to have the same value on both nodes.
-            // I don't know, how to solve this problem now, so I leave code that makes it possible to
-            // add this external timestamp to database, but here I will use generated value.
-            timestamp: Utc::now().naive_utc(),
+            // I don't know, how to solve this problem now.
+            timestamp,
             issuer: terminator,
             reason: reason.map(|reason| DbReason(reason)),
         })
     }
+
+    pub(crate) fn amendment(
+        agreement_id: &AgreementId,
+        event_type: AgreementEventType,
+        issuer: Owner,
+        reason: Option<Reason>,
+        timestamp: NaiveDateTime,
+    ) -> Self {
+        Self {
+            agreement_id: agreement_id.clone(),
+            event_type,
+            timestamp,
+            issuer,
+            reason: reason.map(|reason| DbReason(reason)),
+        }
+    }
 }
 
 impl AgreementEvent {
-    pub fn into_client(self) -> ClientEvent {
+    /// Returns None for amendment events, that can't be represented in client
+    /// `AgreementOperationEvent`. They are converted by `into_amendment_client`.
+    pub fn into_client(self) -> Option<ClientEvent> {
         let agreement_id = self.agreement_id.into_client();
         let event_date = DateTime::<Utc>::from_utc(self.timestamp, Utc);
         let reason = self.reason.map(|reason| reason.0);
 
-        match self.event_type {
+        Some(match self.event_type {
             AgreementEventType::Approved => ClientEvent {
                 agreement_id,
                 event_date,
@@ -135,7 +170,41 @@ impl AgreementEvent {
                     }),
                 }
             },
-        }
+            AgreementEventType::AmendmentProposed
+            | AgreementEventType::AmendmentApproved
+            | AgreementEventType::AmendmentRejected => return None,
+        })
+    }
+
+    /// Returns None for events, that aren't part of amendment flow.
+    pub fn into_amendment_client(self) -> Option<ClientAmendmentEvent> {
+        let event_type = match self.event_type {
+            AgreementEventType::AmendmentProposed => {
+                ClientAmendmentEventType::AmendmentProposedEvent
+            }
+            AgreementEventType::AmendmentApproved => {
+                ClientAmendmentEventType::AmendmentApprovedEvent
+            }
+            AgreementEventType::AmendmentRejected => {
+                ClientAmendmentEventType::AmendmentRejectedEvent {
+                    reason: self.reason.map(|reason| reason.0),
+                }
+            }
+            AgreementEventType::Approved
+            | AgreementEventType::Rejected
+            | AgreementEventType::Cancelled
+            | AgreementEventType::Terminated => return None,
+        };
+
+        Some(ClientAmendmentEvent {
+            agreement_id: self.agreement_id.into_client(),
+            event_date: DateTime::<Utc>::from_utc(self.timestamp, Utc),
+            issuer: match self.issuer {
+                Owner::Provider => Role::Provider,
+                Owner::Requestor => Role::Requestor,
+            },
+            event_type,
+        })
     }
 }
 
@@ -0,0 +1,143 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
+use diesel::sql_types::Text;
+use serde_json::Value;
+
+use ya_agreement_utils::agreement::flatten;
+use ya_client::model::ErrorMessage;
+use ya_core_model::market::{
+    AgreementAmendment as ClientAmendment, AmendmentState as ClientAmendmentState,
+};
+use ya_core_model::Role;
+use ya_diesel_utils::DbTextField;
+
+use crate::db::model::agreement_events::DbReason;
+use crate::db::model::{Agreement, AgreementId, Owner};
+use crate::db::schema::market_agreement_amendment;
+
+#[derive(
+    strum_macros::EnumString,
+    DbTextField,
+    derive_more::Display,
+    AsExpression,
+    FromSqlRow,
+    PartialEq,
+    Debug,
+    Clone,
+    Copy,
+)]
+#[sql_type = "Text"]
+pub enum AmendmentState {
+    /// Waiting for approval of the other party
+    Proposed,
+    /// Approved by the other party and applied to Agreement
+    Approved,
+    /// Rejected by the other party
+    Rejected,
+}
+
+/// Proposed change of approved Agreement terms. Contains full properties
+/// of Agreement after amendment, so both parties sign exactly the same terms.
+#[derive(Clone, Debug, Insertable, Queryable)]
+#[table_name = "market_agreement_amendment"]
+pub struct Amendment {
+    pub agreement_id: AgreementId,
+    /// Version of Agreement after applying this amendment.
+    pub version: i32,
+    pub issuer: Owner,
+    pub state: AmendmentState,
+
+    pub offer_properties: String,
+    pub demand_properties: String,
+
+    pub creation_ts: NaiveDateTime,
+    pub resolved_ts: Option<NaiveDateTime>,
+    pub reason: Option<DbReason>,
+
+    /// Signature of amended Agreement terms made by issuer.
+    pub proposed_signature: String,
+    /// Signature of amended Agreement terms made by the other party.
+    pub approved_signature: Option<String>,
+}
+
+impl Amendment {
+    /// Amendment of the next Agreement version. `offer_changes` and `demand_changes`
+    /// are merged into current properties; properties with `null` value are removed.
+    pub fn new(
+        agreement: &Agreement,
+        issuer: Owner,
+        offer_changes: &Value,
+        demand_changes: &Value,
+        creation_ts: NaiveDateTime,
+    ) -> Result<Amendment, String> {
+        Ok(Amendment {
+            agreement_id: agreement.id.clone(),
+            version: agreement.version + 1,
+            issuer,
+            state: AmendmentState::Proposed,
+            offer_properties: amend_properties(&agreement.offer_properties, offer_changes)
+                .map_err(|e| format!("Invalid Offer properties. {}", e))?,
+            demand_properties: amend_properties(&agreement.demand_properties, demand_changes)
+                .map_err(|e| format!("Invalid Demand properties. {}", e))?,
+            creation_ts,
+            resolved_ts: None,
+            reason: None,
+            proposed_signature: String::new(),
+            approved_signature: None,
+        })
+    }
+
+    /// Agreement with amended terms. Signatures aren't updated.
+    pub fn apply(&self, agreement: &Agreement) -> Agreement {
+        let mut amended = agreement.clone();
+        amended.offer_properties = self.offer_properties.clone();
+        amended.demand_properties = self.demand_properties.clone();
+        amended.version = self.version;
+        amended
+    }
+
+    pub fn into_client(self) -> Result<ClientAmendment, ErrorMessage> {
+        let offer_properties = serde_json::from_str(&self.offer_properties)
+            .map_err(|e| format!("Can't serialize Offer properties. Error: {}", e))?;
+        let demand_properties = serde_json::from_str(&self.demand_properties)
+            .map_err(|e| format!("Can't serialize Demand properties. Error: {}", e))?;
+
+        Ok(ClientAmendment {
+            agreement_id: self.agreement_id.into_client(),
+            version: self.version as u32,
+            issuer: match self.issuer {
+                Owner::Provider => Role::Provider,
+                Owner::Requestor => Role::Requestor,
+            },
+            state: match self.state {
+                AmendmentState::Proposed => ClientAmendmentState::Proposed,
+                AmendmentState::Approved => ClientAmendmentState::Approved,
+                AmendmentState::Rejected => ClientAmendmentState::Rejected,
+            },
+            offer_properties,
+            demand_properties,
+            proposed_date: DateTime::<Utc>::from_utc(self.creation_ts, Utc),
+            resolved_date: self
+                .resolved_ts
+                .map(|ts| DateTime::<Utc>::from_utc(ts, Utc)),
+            reason: self.reason.map(|reason| reason.0),
+        })
+    }
+}
+
+fn amend_properties(properties: &str, changes: &Value) -> Result<String, String> {
+    let mut properties = flatten(serde_json::from_str(properties).map_err(|e| e.to_string())?);
+    match changes {
+        Value::Null => (),
+        Value::Object(_) => {
+            for (name, value) in flatten(changes.clone()) {
+                if value.is_null() {
+                    properties.remove(&name);
+                } else {
+                    properties.insert(name, value);
+                }
+            }
+        }
+        _ => return Err("Properties should be a JSON object.".to_string()),
+    }
+    serde_json::to_string(&properties).map_err(|e| e.to_string())
+}
@@ -0,0 +1,70 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
+use diesel::sql_types::Text;
+
+use ya_client::model::NodeId;
+use ya_core_model::market::{NodePolicy as ClientNodePolicy, NodePolicyEntry};
+use ya_diesel_utils::DbTextField;
+
+use crate::db::schema::market_node_policy;
+
+#[derive(
+    strum_macros::EnumString,
+    DbTextField,
+    derive_more::Display,
+    AsExpression,
+    FromSqlRow,
+    PartialEq,
+    Debug,
+    Clone,
+    Copy,
+)]
+#[sql_type = "Text"]
+pub enum PolicyKind {
+    Allow,
+    Block,
+}
+
+/// Allowed or blocked node of local identity.
+#[derive(Clone, Debug, Insertable, Queryable)]
+#[table_name = "market_node_policy"]
+pub struct NodePolicy {
+    /// Local identity, that the policy applies to.
+    pub owner_id: NodeId,
+    pub node_id: NodeId,
+    pub policy: PolicyKind,
+    pub description: Option<String>,
+    pub insertion_ts: NaiveDateTime,
+}
+
+impl NodePolicy {
+    pub fn new(
+        owner_id: NodeId,
+        node_id: NodeId,
+        policy: ClientNodePolicy,
+        description: Option<String>,
+    ) -> NodePolicy {
+        NodePolicy {
+            owner_id,
+            node_id,
+            policy: match policy {
+                ClientNodePolicy::Allow => PolicyKind::Allow,
+                ClientNodePolicy::Block => PolicyKind::Block,
+            },
+            description,
+            insertion_ts: Utc::now().naive_utc(),
+        }
+    }
+
+    pub fn into_client(self) -> NodePolicyEntry {
+        NodePolicyEntry {
+            owner_id: self.owner_id,
+            node_id: self.node_id,
+            policy: match self.policy {
+                PolicyKind::Allow => ClientNodePolicy::Allow,
+                PolicyKind::Block => ClientNodePolicy::Block,
+            },
+            description: self.description,
+            insertion_date: DateTime::<Utc>::from_utc(self.insertion_ts, Utc),
+        }
+    }
+}
@@ -1,7 +1,10 @@
 use chrono::{NaiveDateTime, TimeZone, Utc};
+use digest::Digest;
 use serde::{Deserialize, Serialize};
 use serde_json;
+use sha3::Sha3_256;
 
+use ya_agreement_utils::signature::verify_signature;
 use ya_client::model::{market::Offer as ClientOffer, ErrorMessage, NodeId};
 use ya_service_api_web::middleware::Identity;
 
@@ -24,6 +27,22 @@ pub struct Offer {
     pub insertion_ts: Option<NaiveDateTime>,
     /// Time when Offer expires; set by Provider.
     pub expiration_ts: NaiveDateTime,
+    /// Hex encoded signature of `signed_payload` made by `node_id` identity.
+    /// Offers from nodes using previous discovery protocol version aren't signed.
+    #[serde(default)]
+    pub signature: Option<String>,
+    /// Expiration set on creation, covered by subscription id hash. Set only
+    /// if Offer was refreshed by Provider; `expiration_ts` is extended then.
+    #[serde(default)]
+    pub initial_expiration_ts: Option<NaiveDateTime>,
+}
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum OfferSignatureError {
+    #[error("Offer [{0}] isn't signed.")]
+    Missing(SubscriptionId),
+    #[error("Offer [{0}] has invalid signature: {1}.")]
+    Invalid(SubscriptionId, String),
 }
 
 /// Keeps track of Offers, that were already unsubscribed.
@@ -76,6 +95,8 @@ impl Offer {
             creation_ts,
             insertion_ts: None, // Database will insert this timestamp.
             expiration_ts,
+            signature: None, // Offer must be signed by issuing identity.
+            initial_expiration_ts: None,
         })
     }
 
@@ -111,9 +132,44 @@ impl Offer {
             &self.constraints,
             &self.node_id,
             &self.creation_ts,
-            &self.expiration_ts,
+            self.initial_expiration_ts
+                .as_ref()
+                .unwrap_or(&self.expiration_ts),
         )
     }
+
+    /// Payload to be signed by Offer issuer. Subscription id contains hash of all
+    /// Offer fields, so signing it proves Offer content, as long as id is validated.
+    /// Extended expiration of refreshed Offer isn't part of the hash, so it is signed too.
+    pub fn signed_payload(&self) -> Vec<u8> {
+        match self.initial_expiration_ts {
+            None => Sha3_256::digest(self.id.to_string().as_bytes()).to_vec(),
+            Some(_) => {
+                let payload = format!("{}/{}", self.id, self.expiration_ts.timestamp_millis());
+                Sha3_256::digest(payload.as_bytes()).to_vec()
+            }
+        }
+    }
+
+    /// Offer with expiration extended by issuer. It must be signed again.
+    pub fn refreshed(&self, expiration_ts: NaiveDateTime) -> Offer {
+        let mut offer = self.clone();
+        offer.initial_expiration_ts =
+            Some(self.initial_expiration_ts.unwrap_or(self.expiration_ts));
+        offer.expiration_ts = expiration_ts;
+        offer.signature = None;
+        offer
+    }
+
+    /// Checks, if Offer was signed by identity, that it claims to be issued by.
+    pub fn verify_signature(&self) -> Result<(), OfferSignatureError> {
+        let signature = match &self.signature {
+            Some(signature) => signature,
+            None => return Err(OfferSignatureError::Missing(self.id.clone())),
+        };
+        verify_signature(&self.signed_payload(), signature, &self.node_id)
+            .map_err(|e| OfferSignatureError::Invalid(self.id.clone(), e))
+    }
 }
 
 /// PartialEq implementation that ignores insertion_ts.
@@ -125,6 +181,8 @@ impl PartialEq for Offer {
             && self.expiration_ts == other.expiration_ts
             && self.properties == other.properties
             && self.node_id == other.node_id
+            && self.signature == other.signature
+            && self.initial_expiration_ts == other.initial_expiration_ts
     }
 }
 
@@ -155,6 +213,8 @@ mod tests {
                 NaiveDate::from_ymd(1970, 1, 1),
                 NaiveTime::from_hms(15, 1, 1),
             ),
+            signature: None,
+            initial_expiration_ts: None,
         };
         assert!(offer.validate().is_err());
     }
@@ -178,6 +238,8 @@ mod tests {
                 NaiveDate::from_ymd(1970, 1, 1),
                 NaiveTime::from_hms(15, 1, 1),
             ),
+            signature: None,
+            initial_expiration_ts: None,
         };
         let id = SubscriptionId::generate_id(
             &offer.properties,
@@ -191,5 +253,29 @@ mod tests {
         offer.validate().unwrap();
     }
 
+    #[test]
+    fn test_refreshed_offer_validation() {
+        use crate::testing::mock_offer::{sample_offer, sign_offer};
+
+        let offer = sample_offer();
+        let refreshed = offer.refreshed(offer.expiration_ts + chrono::Duration::hours(1));
+        assert_eq!(refreshed.initial_expiration_ts, Some(offer.expiration_ts));
+        refreshed.validate().unwrap();
+        // Refreshed Offer must be signed again.
+        assert!(refreshed.verify_signature().is_err());
+
+        let mut refreshed = sign_offer(refreshed);
+        refreshed.verify_signature().unwrap();
+
+        // Nobody else can extend expiration.
+        refreshed.expiration_ts = refreshed.expiration_ts + chrono::Duration::hours(1);
+        assert!(refreshed.verify_signature().is_err());
+
+        // Refreshing again keeps expiration covered by hash.
+        let refreshed = refreshed.refreshed(refreshed.expiration_ts + chrono::Duration::hours(1));
+        assert_eq!(refreshed.initial_expiration_ts, Some(offer.expiration_ts));
+        refreshed.validate().unwrap();
+    }
+
     // TODO: test from_new
 }
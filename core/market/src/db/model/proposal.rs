@@ -1,10 +1,12 @@
 use chrono::{Duration, NaiveDateTime, TimeZone, Utc};
 use diesel::sql_types::Text;
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 
 use ya_client::model::market::proposal::{Proposal as ClientProposal, State};
 use ya_client::model::market::NewProposal;
 use ya_client::model::{ErrorMessage, NodeId};
+use ya_core_model::market::{NegotiationStep, PropertyChange};
 use ya_diesel_utils::DbTextField;
 
 use super::{generate_random_id, SubscriptionId};
@@ -261,6 +263,37 @@ impl Proposal {
         })
     }
 
+    /// Converts Proposal to negotiation history step with properties
+    /// compared to `previous` Proposal of the same issuer.
+    pub fn to_negotiation_step(
+        &self,
+        previous: Option<&Proposal>,
+    ) -> Result<NegotiationStep, ErrorMessage> {
+        let properties = flat_properties(&self.body.properties)?;
+        let (prev_properties, prev_constraints) = match previous {
+            Some(previous) => (
+                flat_properties(&previous.body.properties)?,
+                Some(previous.body.constraints.as_str()),
+            ),
+            None => (Map::new(), None),
+        };
+        let constraints = match prev_constraints {
+            Some(constraints) if constraints == self.body.constraints => None,
+            _ => Some(self.body.constraints.clone()),
+        };
+
+        Ok(NegotiationStep {
+            issuer_id: self.issuer(),
+            proposal_id: self.body.id.to_string(),
+            prev_proposal_id: self.body.prev_proposal_id.as_ref().map(|id| id.to_string()),
+            issuer_prev_proposal_id: previous.map(|previous| previous.body.id.to_string()),
+            timestamp: Utc.from_utc_datetime(&self.body.creation_ts),
+            state: State::from(self.body.state),
+            issuer_changes: diff_properties(&prev_properties, &properties),
+            issuer_constraints: constraints,
+        })
+    }
+
     pub fn issuer(&self) -> NodeId {
         match self.body.issuer {
             Issuer::Us => match self.body.id.owner() {
@@ -283,6 +316,44 @@ impl Proposal {
     }
 }
 
+fn flat_properties(properties: &str) -> Result<Map<String, Value>, ErrorMessage> {
+    let properties: Value = serde_json::from_str(properties).map_err(|error| {
+        format!(
+            "Can't deserialize Proposal properties from database!!! Error: {}",
+            error
+        )
+    })?;
+    Ok(ya_agreement_utils::agreement::flatten(properties))
+}
+
+/// Changed, added and removed properties, sorted by name.
+fn diff_properties(
+    previous: &Map<String, Value>,
+    current: &Map<String, Value>,
+) -> Vec<PropertyChange> {
+    let mut changes = current
+        .iter()
+        .filter(|(name, value)| previous.get(name.as_str()) != Some(*value))
+        .map(|(name, value)| PropertyChange {
+            name: name.clone(),
+            previous: previous.get(name.as_str()).cloned(),
+            current: Some(value.clone()),
+        })
+        .chain(
+            previous
+                .iter()
+                .filter(|(name, _)| !current.contains_key(name.as_str()))
+                .map(|(name, value)| PropertyChange {
+                    name: name.clone(),
+                    previous: Some(value.clone()),
+                    current: None,
+                }),
+        )
+        .collect::<Vec<_>>();
+    changes.sort_by(|a, b| a.name.cmp(&b.name));
+    changes
+}
+
 impl Negotiation {
     fn from_subscriptions(demand: &ModelDemand, offer: &ModelOffer, role: Owner) -> Negotiation {
         Negotiation::new(&demand.id, demand.node_id, &offer.id, offer.node_id, role)
@@ -310,6 +381,14 @@ impl Negotiation {
             agreement_id: None,
         }
     }
+
+    /// Identity of our side of negotiation, owning `subscription_id`.
+    pub fn owner_id(&self) -> NodeId {
+        match self.subscription_id == self.offer_id {
+            true => self.provider_id,
+            false => self.requestor_id,
+        }
+    }
 }
 
 impl From<ProposalState> for State {
@@ -323,3 +402,47 @@ impl From<ProposalState> for State {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn diff_changed_added_and_removed_properties() {
+        let previous = flat_properties(
+            &json!({"golem": {"inf.mem.gib": 1, "srv.caps.multi-activity": true, "com.pricing": "linear"}})
+                .to_string(),
+        )
+        .unwrap();
+        let current = flat_properties(
+            &json!({"golem.inf.mem.gib": 2, "golem.com.pricing": "linear", "golem.node.id.name": "node"})
+                .to_string(),
+        )
+        .unwrap();
+
+        let changes = diff_properties(&previous, &current);
+        assert_eq!(
+            changes,
+            vec![
+                PropertyChange {
+                    name: "golem.inf.mem.gib".to_string(),
+                    previous: Some(json!(1)),
+                    current: Some(json!(2)),
+                },
+                PropertyChange {
+                    name: "golem.node.id.name".to_string(),
+                    previous: None,
+                    current: Some(json!("node")),
+                },
+                PropertyChange {
+                    name: "golem.srv.caps.multi-activity".to_string(),
+                    previous: Some(json!(true)),
+                    current: None,
+                },
+            ]
+        );
+        assert!(diff_properties(&current, &current).is_empty());
+    }
+}
@@ -11,6 +11,13 @@ table! {
     }
 }
 
+table! {
+    market_demand_delivered (demand_id) {
+        demand_id -> Text,
+        delivered -> Integer,
+    }
+}
+
 table! {
     market_offer (id) {
         id -> Text,
@@ -21,6 +28,8 @@ table! {
         creation_ts -> Timestamp,
         insertion_ts -> Nullable<Timestamp>,
         expiration_ts -> Timestamp,
+        signature -> Nullable<Text>,
+        initial_expiration_ts -> Nullable<Timestamp>,
     }
 }
 
@@ -118,14 +127,47 @@ table! {
         proposed_signature -> Nullable<Text>,
         approved_signature -> Nullable<Text>,
         committed_signature -> Nullable<Text>,
+        version -> Integer,
+    }
+}
+
+table! {
+    market_agreement_amendment (agreement_id, version) {
+        agreement_id -> Text,
+        version -> Integer,
+        issuer -> Text,
+        state -> Text,
+
+        offer_properties -> Text,
+        demand_properties -> Text,
+
+        creation_ts -> Timestamp,
+        resolved_ts -> Nullable<Timestamp>,
+        reason -> Nullable<Text>,
+
+        proposed_signature -> Text,
+        approved_signature -> Nullable<Text>,
+    }
+}
+
+table! {
+    market_node_policy (owner_id, node_id) {
+        owner_id -> Text,
+        node_id -> Text,
+        policy -> Text,
+        description -> Nullable<Text>,
+        insertion_ts -> Timestamp,
     }
 }
 
 allow_tables_to_appear_in_same_query!(market_demand, market_offer, market_offer_unsubscribed);
+allow_tables_to_appear_in_same_query!(market_demand, market_demand_delivered);
 allow_tables_to_appear_in_same_query!(market_proposal, market_negotiation);
 allow_tables_to_appear_in_same_query!(market_agreement, market_agreement_event);
+allow_tables_to_appear_in_same_query!(market_agreement, market_agreement_amendment);
 
 joinable!(market_agreement_event -> market_agreement (agreement_id));
+joinable!(market_agreement_amendment -> market_agreement (agreement_id));
 joinable!(market_negotiation -> market_agreement (agreement_id));
 joinable!(market_offer -> market_offer_unsubscribed (id));
 joinable!(market_proposal -> market_negotiation (negotiation_id));
@@ -1,6 +1,7 @@
 #[macro_use]
 extern crate diesel;
 
+mod cli;
 mod config;
 mod db;
 mod identity;
@@ -15,3 +16,4 @@ mod utils;
 pub mod testing;
 
 pub use market::MarketService;
+pub use ya_market_resolver::PropertyResolver;
@@ -1,20 +1,21 @@
 use chrono::{DateTime, Utc};
+use futures::{Stream, StreamExt};
 use lazy_static::lazy_static;
 use metrics::counter;
 use std::sync::{Arc, Mutex};
 use thiserror::Error;
 
-use crate::config::Config;
+use crate::config::{Config, ConfigError};
 use crate::db::dao::AgreementDao;
 use crate::db::model::{AgreementId, AppSessionId, SubscriptionId};
 use crate::identity::{IdentityApi, IdentityGSB};
 use crate::matcher::error::{
-    DemandError, MatcherError, MatcherInitError, QueryDemandsError, QueryOfferError,
-    QueryOffersError,
+    DemandError, MatcherError, MatcherInitError, NodePolicyError, QueryDemandsError,
+    QueryOfferError, QueryOffersError,
 };
 use crate::matcher::{store::SubscriptionStore, Matcher};
 use crate::negotiation::error::{
-    AgreementError, AgreementEventsError, NegotiationError, NegotiationInitError,
+    AgreementError, AgreementEventsError, AmendmentError, NegotiationError, NegotiationInitError,
 };
 use crate::negotiation::{EventNotifier, ProviderBroker, RequestorBroker};
 use crate::rest_api;
@@ -23,14 +24,24 @@ use ya_client::model::market::{
     Agreement, AgreementOperationEvent as ClientAgreementEvent, Demand, NewDemand, NewOffer, Offer,
     Reason,
 };
-use ya_core_model::market::{local, BUS_ID};
+use ya_client::model::NodeId;
+use ya_core_model::market::{
+    local, AgreementAmendment, AmendmentEvent, AmendmentProposal, GetConfig, ListAgreements,
+    MarketStats, NegotiationStep, NodePolicy, NodePolicyEntry, RpcMessageError, SetNodePolicy,
+    BUS_ID,
+};
+use ya_market_resolver::PropertyResolver;
 use ya_persistence::executor::DbExecutor;
+use ya_service_api::CliCtx;
 use ya_service_api_interfaces::{Provider, Service};
 use ya_service_api_web::middleware::Identity;
+use ya_service_bus::typed::ServiceBinder;
+use ya_utils_actix::clock::Clock;
 
 use ya_service_api_web::scope::ExtendableScope;
 
 pub mod agreement;
+pub mod stats;
 
 pub struct EnvConfig<'a, T> {
     pub name: &'a str,
@@ -71,6 +82,8 @@ pub enum MarketInitError {
     Negotiation(#[from] NegotiationInitError),
     #[error("Failed to migrate market database. Error: {0}.")]
     Migration(#[from] anyhow::Error),
+    #[error("Invalid market configuration. {0}")]
+    Config(#[from] ConfigError),
 }
 
 /// Structure connecting all market objects.
@@ -79,6 +92,7 @@ pub struct MarketService {
     pub matcher: Matcher,
     pub provider_engine: ProviderBroker,
     pub requestor_engine: RequestorBroker,
+    pub config: Arc<Config>,
 }
 
 impl MarketService {
@@ -86,6 +100,16 @@ impl MarketService {
         db: &DbExecutor,
         identity_api: Arc<dyn IdentityApi>,
         config: Arc<Config>,
+    ) -> Result<Self, MarketInitError> {
+        MarketService::with_clock(db, identity_api, config, Clock::system())
+    }
+
+    /// Offers and Demands expire according to `clock` time.
+    pub fn with_clock(
+        db: &DbExecutor,
+        identity_api: Arc<dyn IdentityApi>,
+        config: Arc<Config>,
+        clock: Clock,
     ) -> Result<Self, MarketInitError> {
         counter!("market.offers.subscribed", 0);
         counter!("market.offers.unsubscribed", 0);
@@ -96,8 +120,9 @@ impl MarketService {
 
         db.apply_migration(crate::db::migrations::run_with_output)?;
 
-        let store = SubscriptionStore::new(db.clone(), config.clone());
-        let (matcher, listeners) = Matcher::new(store.clone(), identity_api, config.clone())?;
+        let store = SubscriptionStore::new(db.clone(), config.clone(), clock);
+        let (matcher, listeners) =
+            Matcher::new(store.clone(), identity_api.clone(), config.clone())?;
 
         // We need the same notifier for both Provider and Requestor implementation since we have
         // single endpoint and both implementations are able to add events.
@@ -108,6 +133,7 @@ impl MarketService {
             store.clone(),
             agreement_notifier.clone(),
             config.clone(),
+            identity_api.clone(),
         )?;
         let requestor_engine = RequestorBroker::new(
             db.clone(),
@@ -115,10 +141,12 @@ impl MarketService {
             listeners.proposal_receiver,
             agreement_notifier,
             config.clone(),
+            identity_api,
         )?;
         let cleaner_db = db.clone();
+        let cleaner_interval = config.cleaner.interval;
         tokio::spawn(async move {
-            crate::db::dao::cleaner::clean_forever(cleaner_db).await;
+            crate::db::dao::cleaner::clean_forever(cleaner_db, cleaner_interval).await;
         });
 
         Ok(MarketService {
@@ -126,6 +154,7 @@ impl MarketService {
             matcher,
             provider_engine,
             requestor_engine,
+            config,
         })
     }
 
@@ -141,17 +170,42 @@ impl MarketService {
         self.requestor_engine
             .bind_gsb(public_prefix, local_prefix)
             .await?;
-        agreement::bind_gsb(self.db.clone(), public_prefix, local_prefix).await;
+        agreement::bind_gsb(
+            self.db.clone(),
+            self.matcher.store.clock.clone(),
+            public_prefix,
+            local_prefix,
+        )
+        .await;
+        stats::bind_gsb(
+            self.db.clone(),
+            self.matcher.store.clock.clone(),
+            local_prefix,
+        )
+        .await;
+
+        ServiceBinder::new(local_prefix, &(), self.config.clone()).bind_with_processor(
+            move |_, config, _caller: String, _msg: GetConfig| {
+                let config = config.clone();
+                async move { Ok::<_, RpcMessageError>(config.to_toml()) }
+            },
+        );
         Ok(())
     }
 
-    pub async fn gsb<Context: Provider<Self, DbExecutor>>(ctx: &Context) -> anyhow::Result<()> {
-        let market = MARKET.get_or_init_market(&ctx.component())?;
+    pub async fn gsb<Context>(ctx: &Context) -> anyhow::Result<()>
+    where
+        Context: Provider<Self, DbExecutor> + Provider<Self, CliCtx>,
+    {
+        let market = MARKET.get_or_init_market(ctx)?;
         Ok(market.bind_gsb(BUS_ID, local::BUS_ID).await?)
     }
 
-    pub fn rest<Context: Provider<Self, DbExecutor>>(ctx: &Context) -> actix_web::Scope {
-        match MARKET.get_or_init_market(&ctx.component()) {
+    pub fn rest<Context>(ctx: &Context) -> actix_web::Scope
+    where
+        Context: Provider<Self, DbExecutor> + Provider<Self, CliCtx>,
+    {
+        match MARKET.get_or_init_market(ctx) {
             Ok(market) => MarketService::bind_rest(market),
             Err(e) => {
                 log::error!("REST API initialization failed: {}", e);
@@ -170,6 +224,13 @@ impl MarketService {
             .extend(rest_api::requestor::register_endpoints)
     }
 
+    /// Registers node-local resolver of dynamic properties, like current free slots
+    /// or price, computed at match time. It answers properties absent in (or declared
+    /// without value by) Offers and Demands of this node, never of other nodes.
+    pub fn register_property_resolver(&self, resolver: Arc<dyn PropertyResolver>) {
+        self.matcher.resolver.register_property_resolver(resolver)
+    }
+
     // TODO: (re)move this
     pub async fn get_offers(&self, id: Option<Identity>) -> Result<Vec<Offer>, MarketError> {
         Ok(self
@@ -192,13 +253,36 @@ impl MarketService {
         offer: &NewOffer,
         id: &Identity,
     ) -> Result<SubscriptionId, MarketError> {
-        let offer = self.matcher.subscribe_offer(offer, id).await?;
+        self.subscribe_offer_with_ttl(offer, id, None).await
+    }
+
+    /// Offer expires after `ttl` or after default TTL from config, if not specified.
+    pub async fn subscribe_offer_with_ttl(
+        &self,
+        offer: &NewOffer,
+        id: &Identity,
+        ttl: Option<chrono::Duration>,
+    ) -> Result<SubscriptionId, MarketError> {
+        let offer = self.matcher.subscribe_offer(offer, id, ttl).await?;
         self.provider_engine.subscribe_offer(&offer).await?;
 
         counter!("market.offers.subscribed", 1);
         Ok(offer.id)
     }
 
+    /// Extends Offer expiration to `ttl` from now. Returns new expiration.
+    pub async fn refresh_offer(
+        &self,
+        offer_id: &SubscriptionId,
+        id: &Identity,
+        ttl: Option<chrono::Duration>,
+    ) -> Result<DateTime<Utc>, MarketError> {
+        let offer = self.matcher.refresh_offer(offer_id, id, ttl).await?;
+
+        counter!("market.offers.refreshed", 1);
+        Ok(DateTime::from_utc(offer.expiration_ts, Utc))
+    }
+
     pub async fn unsubscribe_offer(
         &self,
         offer_id: &SubscriptionId,
@@ -217,13 +301,36 @@ impl MarketService {
         demand: &NewDemand,
         id: &Identity,
     ) -> Result<SubscriptionId, MarketError> {
-        let demand = self.matcher.subscribe_demand(demand, id).await?;
+        self.subscribe_demand_with_ttl(demand, id, None).await
+    }
+
+    /// Demand expires after `ttl` or after default TTL from config, if not specified.
+    pub async fn subscribe_demand_with_ttl(
+        &self,
+        demand: &NewDemand,
+        id: &Identity,
+        ttl: Option<chrono::Duration>,
+    ) -> Result<SubscriptionId, MarketError> {
+        let demand = self.matcher.subscribe_demand(demand, id, ttl).await?;
         self.requestor_engine.subscribe_demand(&demand).await?;
 
         counter!("market.demands.subscribed", 1);
         Ok(demand.id)
     }
 
+    /// Extends Demand expiration to `ttl` from now. Returns new expiration.
+    pub async fn refresh_demand(
+        &self,
+        demand_id: &SubscriptionId,
+        id: &Identity,
+        ttl: Option<chrono::Duration>,
+    ) -> Result<DateTime<Utc>, MarketError> {
+        let demand = self.matcher.refresh_demand(demand_id, id, ttl).await?;
+
+        counter!("market.demands.refreshed", 1);
+        Ok(DateTime::from_utc(demand.expiration_ts, Utc))
+    }
+
     pub async fn unsubscribe_demand(
         &self,
         demand_id: &SubscriptionId,
@@ -247,7 +354,7 @@ impl MarketService {
         match self
             .db
             .as_dao::<AgreementDao>()
-            .select(agreement_id, Some(id.identity), Utc::now().naive_utc())
+            .select(agreement_id, Some(id.identity), self.matcher.store.now())
             .await
             .map_err(|e| AgreementError::Get(agreement_id.to_string(), e))?
         {
@@ -258,6 +365,20 @@ impl MarketService {
         }
     }
 
+    pub async fn list_agreements(
+        &self,
+        mut query: ListAgreements,
+        id: &Identity,
+    ) -> Result<Vec<Agreement>, AgreementError> {
+        query.node_id = Some(id.identity);
+        agreement::list_agreements(&self.db, &self.matcher.store.clock, query).await
+    }
+
+    /// Statistics of all active Offers and of Agreements owned by `id`.
+    pub async fn market_stats(&self, id: &Identity) -> Result<MarketStats, stats::StatsError> {
+        stats::market_stats(&self.db, &self.matcher.store.clock, Some(id.identity)).await
+    }
+
     pub async fn query_agreement_events(
         &self,
         session_id: &AppSessionId,
@@ -272,10 +393,38 @@ impl MarketService {
             .query_agreement_events(session_id, timeout, max_events, after_timestamp, id)
             .await?
             .into_iter()
-            .map(|event| event.into_client())
+            .filter_map(|event| event.into_client())
             .collect())
     }
 
+    /// Streams Agreement events paired with cursors, that allow to resume the stream.
+    pub async fn stream_agreement_events(
+        &self,
+        session_id: &AppSessionId,
+        cursor: Option<i32>,
+        id: &Identity,
+    ) -> Result<
+        impl Stream<Item = Result<Vec<(i32, ClientAgreementEvent)>, AgreementEventsError>>,
+        AgreementEventsError,
+    > {
+        Ok(self
+            .requestor_engine
+            .common
+            .stream_agreement_events(session_id, cursor, id)
+            .await?
+            .map(|batch| {
+                batch.map(|events| {
+                    events
+                        .into_iter()
+                        .filter_map(|event| {
+                            let cursor = event.id;
+                            event.into_client().map(|event| (cursor, event))
+                        })
+                        .collect()
+                })
+            }))
+    }
+
     pub async fn terminate_agreement(
         &self,
         id: Identity,
@@ -287,10 +436,113 @@ impl MarketService {
             .terminate_agreement(id, client_agreement_id, reason)
             .await
     }
+
+    pub async fn propose_amendment(
+        &self,
+        id: Identity,
+        client_agreement_id: String,
+        proposal: AmendmentProposal,
+    ) -> Result<AgreementAmendment, AmendmentError> {
+        self.requestor_engine
+            .common
+            .propose_amendment(id, client_agreement_id, proposal)
+            .await
+    }
+
+    pub async fn approve_amendment(
+        &self,
+        id: Identity,
+        client_agreement_id: String,
+        version: u32,
+    ) -> Result<AgreementAmendment, AmendmentError> {
+        self.requestor_engine
+            .common
+            .approve_amendment(id, client_agreement_id, version)
+            .await
+    }
+
+    pub async fn reject_amendment(
+        &self,
+        id: Identity,
+        client_agreement_id: String,
+        version: u32,
+        reason: Option<Reason>,
+    ) -> Result<AgreementAmendment, AmendmentError> {
+        self.requestor_engine
+            .common
+            .reject_amendment(id, client_agreement_id, version, reason)
+            .await
+    }
+
+    pub async fn list_amendments(
+        &self,
+        id: Identity,
+        client_agreement_id: String,
+    ) -> Result<Vec<AgreementAmendment>, AmendmentError> {
+        self.requestor_engine
+            .common
+            .list_amendments(id, client_agreement_id)
+            .await
+    }
+
+    pub async fn list_amendment_events(
+        &self,
+        id: Identity,
+        client_agreement_id: String,
+    ) -> Result<Vec<AmendmentEvent>, AmendmentError> {
+        self.requestor_engine
+            .common
+            .list_amendment_events(id, client_agreement_id)
+            .await
+    }
+
+    pub async fn agreement_negotiation(
+        &self,
+        id: Identity,
+        client_agreement_id: String,
+    ) -> Result<Vec<NegotiationStep>, AgreementError> {
+        self.requestor_engine
+            .common
+            .agreement_negotiation(&id, &client_agreement_id)
+            .await
+    }
+
+    pub async fn list_node_policies(
+        &self,
+        id: Identity,
+    ) -> Result<Vec<NodePolicyEntry>, NodePolicyError> {
+        self.matcher.list_node_policies(Some(id.identity)).await
+    }
+
+    pub async fn set_node_policy(
+        &self,
+        id: Identity,
+        node_id: NodeId,
+        policy: NodePolicy,
+        description: Option<String>,
+    ) -> Result<NodePolicyEntry, NodePolicyError> {
+        let msg = SetNodePolicy {
+            owner_id: Some(id.identity),
+            node_id,
+            policy,
+            description,
+        };
+        self.matcher.set_node_policy(msg).await
+    }
+
+    pub async fn remove_node_policy(
+        &self,
+        id: Identity,
+        node_id: NodeId,
+    ) -> Result<(), NodePolicyError> {
+        self.matcher
+            .remove_node_policy(Some(id.identity), node_id)
+            .await
+    }
 }
 
 impl Service for MarketService {
-    type Cli = ();
+    type Cli = crate::cli::MarketCli;
 }
 
 // =========================================== //
@@ -309,17 +561,24 @@ impl StaticMarket {
         }
     }
 
-    pub fn get_or_init_market(
+    pub fn get_or_init_market<Context>(
         &self,
-        db: &DbExecutor,
-    ) -> Result<Arc<MarketService>, MarketInitError> {
+        ctx: &Context,
+    ) -> Result<Arc<MarketService>, MarketInitError>
+    where
+        Context: Provider<MarketService, DbExecutor> + Provider<MarketService, CliCtx>,
+    {
         let mut guarded_market = self.locked_market.lock().unwrap();
         if let Some(market) = &*guarded_market {
             Ok(market.clone())
         } else {
+            let db: DbExecutor = Provider::<MarketService, DbExecutor>::component(ctx);
+            let cli_ctx: CliCtx = Provider::<MarketService, CliCtx>::component(ctx);
+            let config = Config::load(&cli_ctx.data_dir)?;
+            log::info!("Market configuration:\n{}", config.to_toml());
+
             let identity_api = IdentityGSB::new();
-            let config = Arc::new(Config::default());
-            let market = Arc::new(MarketService::new(db, identity_api, config)?);
+            let market = Arc::new(MarketService::new(&db, identity_api, Arc::new(config))?);
             *guarded_market = Some(market.clone());
             Ok(market)
         }
@@ -1,24 +1,73 @@
-use chrono;
-
 use ya_client::model::market::Agreement as ClientAgreement;
 use ya_core_model::{
-    market::{GetAgreement, RpcMessageError},
+    market::{GetAgreement, ListAgreements, RpcMessageError},
     Role,
 };
 use ya_persistence::executor::DbExecutor;
 use ya_service_bus::typed::ServiceBinder;
+use ya_utils_actix::clock::Clock;
 
-use crate::db::dao::AgreementDao;
-use crate::db::model::{AgreementId, Owner};
+use crate::db::dao::{AgreementDao, AgreementFilter};
+use crate::db::model::{AgreementId, AgreementState, Owner};
+use crate::negotiation::error::AgreementError;
 
-pub async fn bind_gsb(db: DbExecutor, public_prefix: &str, _local_prefix: &str) {
+pub async fn bind_gsb(db: DbExecutor, clock: Clock, public_prefix: &str, local_prefix: &str) {
     log::trace!("Binding market agreement public service to service bus");
-    ServiceBinder::new(public_prefix, &db, ()).bind(get_agreement);
+    ServiceBinder::new(public_prefix, &db, clock.clone()).bind_with_processor(get_agreement);
     log::debug!("Successfully bound market agreement public service to service bus");
+
+    log::trace!("Binding market agreement local service to service bus");
+    ServiceBinder::new(local_prefix, &db, clock).bind_with_processor(list_agreements_gsb);
+    log::debug!("Successfully bound market agreement local service to service bus");
+}
+
+/// Lists Agreements matching all criteria of the query. Shared by REST and GSB API.
+pub async fn list_agreements(
+    db: &DbExecutor,
+    clock: &Clock,
+    query: ListAgreements,
+) -> Result<Vec<ClientAgreement>, AgreementError> {
+    let filter = AgreementFilter {
+        node_id: query.node_id,
+        states: query.state.as_ref().map(AgreementState::from_client),
+        peer_id: query.peer_id,
+        app_session_id: query.app_session_id,
+        created_after: query.after_date.map(|date| date.naive_utc()),
+        created_before: query.before_date.map(|date| date.naive_utc()),
+        valid_after: query.valid_after.map(|date| date.naive_utc()),
+        valid_before: query.valid_before.map(|date| date.naive_utc()),
+        offset: query.offset.map(i64::from),
+        limit: query.limit.map(i64::from),
+    };
+
+    let now = clock.now().naive_utc();
+    db.as_dao::<AgreementDao>()
+        .list(filter, now)
+        .await
+        .map_err(AgreementError::List)?
+        .into_iter()
+        .map(|agreement| {
+            agreement
+                .into_client()
+                .map_err(|e| AgreementError::Internal(e.to_string()))
+        })
+        .collect()
+}
+
+async fn list_agreements_gsb(
+    db: DbExecutor,
+    clock: Clock,
+    _caller: String,
+    msg: ListAgreements,
+) -> Result<Vec<ClientAgreement>, RpcMessageError> {
+    list_agreements(&db, &clock, msg)
+        .await
+        .map_err(|e| RpcMessageError::Market(e.to_string()))
 }
 
 async fn get_agreement(
     db: DbExecutor,
+    clock: Clock,
     _sender_id: String,
     msg: GetAgreement,
 ) -> Result<ClientAgreement, RpcMessageError> {
@@ -33,7 +82,7 @@ async fn get_agreement(
     // TODO: We should check Agreement owner, like in REST get_agreement implementation, but
     //  I'm not sure we can trust `sender_id` value from gsb now.
     let dao = db.as_dao::<AgreementDao>();
-    let now = chrono::Utc::now().naive_utc();
+    let now = clock.now().naive_utc();
     Ok(dao
         .select(&agreement_id, None, now)
         .await
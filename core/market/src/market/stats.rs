@@ -0,0 +1,302 @@
+//! Statistics of Offers known to the market and of local Agreements.
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, HashSet};
+
+use ya_agreement_utils::agreement::flatten;
+use ya_client::model::market::agreement::State as ClientAgreementState;
+use ya_client::model::NodeId;
+use ya_core_model::market::{GetMarketStats, MarketStats, OfferStats, PriceStats, RpcMessageError};
+use ya_persistence::executor::DbExecutor;
+use ya_service_bus::typed::ServiceBinder;
+use ya_utils_actix::clock::Clock;
+
+use crate::db::dao::{AgreementDao, AgreementDaoError, OfferDao};
+use crate::db::model::Offer;
+use crate::db::DbError;
+
+const RUNTIME_NAME: &str = "golem.runtime.name";
+const SUBNET: &str = "golem.node.debug.subnet";
+const PAYMENT_PLATFORM_PREFIX: &str = "golem.com.payment.platform.";
+const CPU_THREADS: &str = "golem.inf.cpu.threads";
+const MEMORY_GIB: &str = "golem.inf.mem.gib";
+const LINEAR_COEFFS: &str = "golem.com.pricing.model.linear.coeffs";
+const USAGE_VECTOR: &str = "golem.com.usage.vector";
+
+const UNKNOWN: &str = "unknown";
+const FIXED_PRICE: &str = "fixed";
+
+#[derive(thiserror::Error, Debug)]
+pub enum StatsError {
+    #[error("Failed to get Offers. Error: {0}.")]
+    Offers(DbError),
+    #[error("Failed to count Agreements. Error: {0}.")]
+    Agreements(AgreementDaoError),
+}
+
+pub async fn bind_gsb(db: DbExecutor, clock: Clock, local_prefix: &str) {
+    ServiceBinder::new(local_prefix, &db, clock).bind_with_processor(get_market_stats_gsb);
+}
+
+/// Aggregates all active Offers and Agreements of `node_id`
+/// or of all local identities. Shared by REST and GSB API.
+pub async fn market_stats(
+    db: &DbExecutor,
+    clock: &Clock,
+    node_id: Option<NodeId>,
+) -> Result<MarketStats, StatsError> {
+    let now = clock.now().naive_utc();
+    let offers = db
+        .as_dao::<OfferDao>()
+        .get_offers(None, None, None, now)
+        .await
+        .map_err(StatsError::Offers)?;
+
+    let mut agreements = BTreeMap::new();
+    for (state, count) in db
+        .as_dao::<AgreementDao>()
+        .count_by_state(node_id, now)
+        .await
+        .map_err(StatsError::Agreements)?
+    {
+        // Internal states are merged with client states they are mapped to.
+        let state = serde_json::to_value(ClientAgreementState::from(state))
+            .ok()
+            .and_then(|state| state.as_str().map(str::to_string))
+            .unwrap_or_else(|| UNKNOWN.to_string());
+        *agreements.entry(state).or_default() += count as u64;
+    }
+
+    Ok(MarketStats {
+        offers: offer_stats(&offers),
+        agreements,
+    })
+}
+
+async fn get_market_stats_gsb(
+    db: DbExecutor,
+    clock: Clock,
+    _caller: String,
+    msg: GetMarketStats,
+) -> Result<MarketStats, RpcMessageError> {
+    market_stats(&db, &clock, msg.node_id)
+        .await
+        .map_err(|e| RpcMessageError::Market(e.to_string()))
+}
+
+pub(crate) fn offer_stats(offers: &[Offer]) -> OfferStats {
+    let mut stats = OfferStats::default();
+    let mut providers = HashSet::new();
+    let mut prices: BTreeMap<String, Vec<f64>> = BTreeMap::new();
+
+    for offer in offers {
+        let properties = match serde_json::from_str::<Value>(&offer.properties) {
+            Ok(properties) => flatten(properties),
+            Err(e) => {
+                log::warn!("Skipping Offer [{}] in stats. Error: {}", offer.id, e);
+                continue;
+            }
+        };
+        stats.total += 1;
+        providers.insert(offer.node_id);
+
+        *stats
+            .runtimes
+            .entry(string_property(&properties, RUNTIME_NAME))
+            .or_default() += 1;
+        *stats
+            .subnets
+            .entry(string_property(&properties, SUBNET))
+            .or_default() += 1;
+
+        let platforms = payment_platforms(&properties);
+        if platforms.is_empty() {
+            *stats
+                .payment_platforms
+                .entry(UNKNOWN.to_string())
+                .or_default() += 1;
+        }
+        for platform in platforms {
+            *stats.payment_platforms.entry(platform).or_default() += 1;
+        }
+
+        if let Some(threads) = properties.get(CPU_THREADS).and_then(Value::as_f64) {
+            *stats.cpu_threads.entry(bucket(threads)).or_default() += 1;
+        }
+        if let Some(memory) = properties.get(MEMORY_GIB).and_then(Value::as_f64) {
+            *stats.memory_gib.entry(bucket(memory)).or_default() += 1;
+        }
+
+        for (counter, coeff) in linear_coeffs(&properties) {
+            prices.entry(counter).or_default().push(coeff);
+        }
+    }
+
+    stats.unique_providers = providers.len() as u64;
+    stats.linear_pricing = prices
+        .into_iter()
+        .map(|(counter, coeffs)| (counter, price_stats(coeffs)))
+        .collect();
+    stats
+}
+
+fn string_property(properties: &Map<String, Value>, name: &str) -> String {
+    properties
+        .get(name)
+        .and_then(Value::as_str)
+        .unwrap_or(UNKNOWN)
+        .to_string()
+}
+
+/// Platforms with address set, like `golem.com.payment.platform.erc20-rinkeby-tglm.address`.
+fn payment_platforms(properties: &Map<String, Value>) -> HashSet<String> {
+    properties
+        .keys()
+        .filter_map(|name| name.strip_prefix(PAYMENT_PLATFORM_PREFIX))
+        .filter_map(|name| name.strip_suffix(".address"))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Lower bound of power of two range containing `value`.
+fn bucket(value: f64) -> u64 {
+    if value < 1.0 {
+        return 0;
+    }
+    2u64.pow(value.log2().floor() as u32)
+}
+
+/// Coefficients paired with usage counters. Coefficient without counter
+/// is a fixed price, if it is the last one.
+fn linear_coeffs(properties: &Map<String, Value>) -> Vec<(String, f64)> {
+    let coeffs = match properties.get(LINEAR_COEFFS).and_then(Value::as_array) {
+        Some(coeffs) => coeffs,
+        None => return vec![],
+    };
+    let usage_vector = properties
+        .get(USAGE_VECTOR)
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or(&[]);
+
+    coeffs
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, coeff)| {
+            let counter = match usage_vector.get(idx) {
+                Some(counter) => counter.as_str()?.to_string(),
+                None if idx + 1 == coeffs.len() => FIXED_PRICE.to_string(),
+                None => return None,
+            };
+            Some((counter, coeff.as_f64()?))
+        })
+        .collect()
+}
+
+fn price_stats(mut coeffs: Vec<f64>) -> PriceStats {
+    coeffs.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    let len = coeffs.len();
+    let median = if len % 2 == 0 {
+        (coeffs[len / 2 - 1] + coeffs[len / 2]) / 2.0
+    } else {
+        coeffs[len / 2]
+    };
+    PriceStats {
+        offers: len as u64,
+        min: coeffs[0],
+        median,
+        max: coeffs[len - 1],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use serde_json::json;
+
+    use ya_client::model::market::NewOffer;
+    use ya_service_api_web::middleware::Identity;
+
+    use super::*;
+    use crate::testing::mock_identity::generate_identity;
+
+    fn offer(id: &Identity, properties: Value) -> Offer {
+        let ts = NaiveDate::from_ymd(2021, 1, 1).and_hms(0, 0, 0);
+        let offer = NewOffer::new(properties, "()".to_string());
+        Offer::from_new(&offer, id, ts, ts).unwrap()
+    }
+
+    fn wasm_offer(id: &Identity, threads: u32, memory: f64, coeffs: Value) -> Offer {
+        offer(
+            id,
+            json!({
+                "golem.runtime.name": "wasmtime",
+                "golem.node.debug.subnet": "devnet",
+                "golem.com.payment.platform.erc20-rinkeby-tglm.address": "0x01",
+                "golem.com.payment.platform.zksync-rinkeby-tglm.address": "0x01",
+                "golem.inf.cpu.threads": threads,
+                "golem.inf.mem.gib": memory,
+                "golem.com.usage.vector": ["golem.usage.duration_sec", "golem.usage.cpu_sec"],
+                "golem.com.pricing.model.linear.coeffs": coeffs,
+            }),
+        )
+    }
+
+    #[test]
+    fn aggregate_offers() {
+        let prov1 = generate_identity("prov-1");
+        let offers = vec![
+            wasm_offer(&prov1, 4, 8.0, json!([0.1, 0.2, 1.0])),
+            wasm_offer(&prov1, 7, 0.5, json!([0.3, 0.4, 0.0])),
+            wasm_offer(
+                &generate_identity("prov-2"),
+                16,
+                31.9,
+                json!([0.2, 0.6, 2.0]),
+            ),
+            offer(
+                &generate_identity("prov-3"),
+                json!({"golem.inf.cpu.threads": 1}),
+            ),
+        ];
+        let stats = offer_stats(&offers);
+
+        assert_eq!(stats.total, 4);
+        assert_eq!(stats.unique_providers, 3);
+        assert_eq!(stats.runtimes["wasmtime"], 3);
+        assert_eq!(stats.runtimes[UNKNOWN], 1);
+        assert_eq!(stats.subnets["devnet"], 3);
+        assert_eq!(stats.payment_platforms["erc20-rinkeby-tglm"], 3);
+        assert_eq!(stats.payment_platforms["zksync-rinkeby-tglm"], 3);
+        assert_eq!(stats.payment_platforms[UNKNOWN], 1);
+
+        let cpu: Vec<(u64, u64)> = stats.cpu_threads.into_iter().collect();
+        assert_eq!(cpu, vec![(1, 1), (4, 2), (16, 1)]);
+        let memory: Vec<(u64, u64)> = stats.memory_gib.into_iter().collect();
+        assert_eq!(memory, vec![(0, 1), (8, 1), (16, 1)]);
+
+        assert_eq!(
+            stats.linear_pricing["golem.usage.duration_sec"],
+            PriceStats {
+                offers: 3,
+                min: 0.1,
+                median: 0.2,
+                max: 0.3
+            }
+        );
+        assert_eq!(stats.linear_pricing["golem.usage.cpu_sec"].median, 0.4);
+        assert_eq!(
+            stats.linear_pricing[FIXED_PRICE],
+            PriceStats {
+                offers: 3,
+                min: 0.0,
+                median: 1.0,
+                max: 2.0
+            }
+        );
+    }
+
+    #[test]
+    fn median_of_even_number_of_prices() {
+        assert_eq!(price_stats(vec![4.0, 1.0, 2.0, 3.0]).median, 2.5);
+    }
+}
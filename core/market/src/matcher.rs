@@ -5,25 +5,38 @@ use std::str::FromStr;
 use std::sync::Arc;
 use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
 
-use ya_client::model::market::{NewDemand, NewOffer};
+use ya_client::model::market::{NewDemand, NewOffer, Offer as ClientOffer};
+use ya_client::model::NodeId;
+use ya_core_model::market::{
+    ListNodePolicies, NodePolicyEntry, RemoveNodePolicy, RpcMessageError, ScanOffers, SetNodePolicy,
+};
+use ya_market_resolver::{LintWarning, MatchExplanation};
 use ya_service_api_web::middleware::Identity;
+use ya_service_bus::typed::ServiceBinder;
 use ya_utils_actix::deadline_checker::{
     bind_deadline_reaction, DeadlineChecker, StopTracking, TrackDeadline,
 };
 
 use crate::config::Config;
-use crate::db::model::{Demand, Offer, SubscriptionId};
+use crate::db::model::{Demand, NodePolicy, Offer, SubscriptionId};
 use crate::identity::IdentityApi;
-use crate::protocol::discovery::{builder::DiscoveryBuilder, Discovery};
+use crate::protocol::discovery::{
+    builder::DiscoveryBuilder, message::OfferRefresh, quota::BcastQuotas, Discovery,
+};
 
 pub(crate) mod cyclic;
 pub mod error;
 pub(crate) mod handlers;
+pub(crate) mod index;
+pub(crate) mod policy;
 pub(crate) mod resolver;
 pub(crate) mod store;
 
 use crate::db::dao::{DemandDao, DemandState};
-use error::{MatcherError, MatcherInitError, QueryOfferError, QueryOffersError};
+use error::{
+    DemandError, ExplainMatchError, MatcherError, MatcherInitError, NodePolicyError,
+    QueryOfferError, QueryOffersError, ScanOffersError,
+};
 use futures::FutureExt;
 use resolver::Resolver;
 use store::SubscriptionStore;
@@ -35,6 +48,11 @@ pub struct RawProposal {
     pub demand: Demand,
 }
 
+/// Number of Offers returned by scan, if limit wasn't specified.
+pub const DEFAULT_SCAN_LIMIT: u32 = 100;
+/// Maximum number of Offers returned by single scan.
+pub const MAX_SCAN_LIMIT: u32 = 1000;
+
 /// Receivers for events, that can be emitted from Matcher.
 pub struct EventsListeners {
     pub proposal_receiver: UnboundedReceiver<RawProposal>,
@@ -58,9 +76,14 @@ impl Matcher {
         config: Arc<Config>,
     ) -> Result<(Matcher, EventsListeners), MatcherInitError> {
         let (proposal_sender, proposal_receiver) = unbounded_channel::<RawProposal>();
-        let resolver = Resolver::new(store.clone(), proposal_sender);
+        let resolver = Resolver::new(store.clone(), identity_api.clone(), proposal_sender);
 
         let discovery = DiscoveryBuilder::default()
+            .with_legacy_protocol(config.discovery.accept_unsigned_offers)
+            .with_quotas(BcastQuotas::new(
+                config.discovery.clone(),
+                store.clock.clone(),
+            ))
             .add_data(identity_api.clone())
             .add_data(store.clone())
             .add_data(resolver.clone())
@@ -68,15 +91,17 @@ impl Matcher {
             .add_data_handler(handlers::receive_remote_offers)
             .add_data_handler(handlers::get_local_offers)
             .add_data_handler(handlers::receive_remote_offer_unsubscribes)
+            .add_data_handler(handlers::receive_remote_offer_refreshes)
             .build();
 
+        let expiration_tracker = DeadlineChecker::with_clock(store.clock.clone()).start();
         let matcher = Matcher {
             store,
             resolver,
             discovery,
             config,
             identity: identity_api,
-            expiration_tracker: DeadlineChecker::new().start(),
+            expiration_tracker,
         };
 
         let listeners = EventsListeners { proposal_receiver };
@@ -84,9 +109,16 @@ impl Matcher {
         // Initialize counters to 0 value. Otherwise they won't appear on metrics endpoint
         // until first change to value will be made.
         counter!("market.offers.incoming", 0);
+        counter!("market.offers.signature.rejected", 0);
         counter!("market.offers.broadcasts", 0);
         counter!("market.offers.unsubscribes.incoming", 0);
         counter!("market.offers.unsubscribes.broadcasts", 0);
+        counter!("market.offers.incoming.ignored", 0);
+        counter!("market.offers.stored-limit.rejected", 0);
+        counter!("market.offers.unsubscribes.ignored", 0);
+        counter!("market.discovery.offenders.banned", 0);
+        counter!("market.offers.refreshes.incoming", 0);
+        counter!("market.offers.refreshes.broadcasts", 0);
 
         Ok((matcher, listeners))
     }
@@ -96,8 +128,50 @@ impl Matcher {
         public_prefix: &str,
         local_prefix: &str,
     ) -> Result<(), MatcherInitError> {
+        // Policies must be known before we receive any Offer.
+        self.store.load_node_policies().await?;
         self.discovery.bind_gsb(public_prefix, local_prefix).await?;
 
+        ServiceBinder::new(local_prefix, &(), self.clone())
+            .bind_with_processor(move |_, myself, _caller: String, msg: ScanOffers| {
+                let myself = myself.clone();
+                async move {
+                    myself.scan_offers(&msg).await.map_err(|e| match e {
+                        ScanOffersError::InvalidConstraints(_) => {
+                            RpcMessageError::BadRequest(e.to_string())
+                        }
+                        _ => RpcMessageError::Market(e.to_string()),
+                    })
+                }
+            })
+            .bind_with_processor(move |_, myself, _caller: String, msg: ListNodePolicies| {
+                let myself = myself.clone();
+                async move {
+                    myself
+                        .list_node_policies(msg.owner_id)
+                        .await
+                        .map_err(RpcMessageError::from)
+                }
+            })
+            .bind_with_processor(move |_, myself, _caller: String, msg: SetNodePolicy| {
+                let myself = myself.clone();
+                async move {
+                    myself
+                        .set_node_policy(msg)
+                        .await
+                        .map_err(RpcMessageError::from)
+                }
+            })
+            .bind_with_processor(move |_, myself, _caller: String, msg: RemoveNodePolicy| {
+                let myself = myself.clone();
+                async move {
+                    myself
+                        .remove_node_policy(msg.owner_id, msg.node_id)
+                        .await
+                        .map_err(RpcMessageError::from)
+                }
+            });
+
         // We can't spawn broadcasts, before gsb is bound.
         // That's why we don't spawn this in Matcher::new.
         tokio::task::spawn_local(cyclic::bcast_offers(self.clone()));
@@ -124,7 +198,8 @@ impl Matcher {
                         _ => (),
                     },
                     ("Demand", Ok(id)) => {
-                        match store.db.as_dao::<DemandDao>().demand_state(id).await {
+                        let now = store.now();
+                        match store.db.as_dao::<DemandDao>().demand_state(id, now).await {
                             Ok(DemandState::Expired(_)) => {
                                 log::info!("Demand [{}] expired.", id);
                                 counter!("market.demands.expired", 1)
@@ -149,9 +224,13 @@ impl Matcher {
         &self,
         offer: &NewOffer,
         id: &Identity,
+        ttl: Option<chrono::Duration>,
     ) -> Result<Offer, MatcherError> {
-        let offer = self.store.create_offer(id, offer).await?;
-        self.resolver.receive(&offer);
+        let offer = self
+            .store
+            .create_offer(id, offer, ttl, self.identity.as_ref())
+            .await?;
+        self.resolver.receive(&offer).await;
 
         log::info!(
             "Subscribed new Offer: [{}] using identity: {} [{}]",
@@ -181,6 +260,64 @@ impl Matcher {
         Ok(offer)
     }
 
+    /// Extends expiration of our Offer, keeping its subscription id. Offer is
+    /// broadcasted again only if its expiration changed.
+    pub async fn refresh_offer(
+        &self,
+        offer_id: &SubscriptionId,
+        id: &Identity,
+        ttl: Option<chrono::Duration>,
+    ) -> Result<Offer, MatcherError> {
+        let (offer, refreshed) = self
+            .store
+            .refresh_offer(offer_id, id, ttl, self.identity.as_ref())
+            .await?;
+        if !refreshed {
+            return Ok(offer);
+        }
+        self.resolver
+            .index
+            .extend_offer(offer_id, offer.expiration_ts);
+
+        log::info!(
+            "Refreshed Offer: [{}] using identity: {} [{}]. Expires at {}.",
+            &offer_id,
+            id.name,
+            id.identity,
+            offer.expiration_ts
+        );
+
+        self.expiration_tracker
+            .send(StopTracking {
+                category: Some("Offer".to_string()),
+                id: offer_id.to_string(),
+            })
+            .await
+            .ok();
+        self.expiration_tracker
+            .send(TrackDeadline {
+                category: "Offer".to_string(),
+                deadline: Utc.from_utc_datetime(&offer.expiration_ts),
+                id: offer_id.to_string(),
+            })
+            .await
+            .ok();
+
+        counter!("market.offers.refreshes.broadcasts", 1);
+        let _ = self
+            .discovery
+            .bcast_refreshes(vec![OfferRefresh::new(&offer)])
+            .await
+            .map_err(|e| {
+                log::warn!(
+                    "Failed to bcast Offer [{}] refresh. Error: {}.",
+                    offer_id,
+                    e
+                );
+            });
+        Ok(offer)
+    }
+
     pub async fn unsubscribe_offer(
         &self,
         offer_id: &SubscriptionId,
@@ -189,6 +326,7 @@ impl Matcher {
         self.store
             .unsubscribe_offer(offer_id, true, Some(id.identity))
             .await?;
+        self.resolver.index.remove_offer(offer_id);
 
         log::info!(
             "Unsubscribed Offer: [{}] using identity: {} [{}]",
@@ -227,9 +365,10 @@ impl Matcher {
         &self,
         demand: &NewDemand,
         id: &Identity,
+        ttl: Option<chrono::Duration>,
     ) -> Result<Demand, MatcherError> {
-        let demand = self.store.create_demand(id, demand).await?;
-        self.resolver.receive(&demand);
+        let demand = self.store.create_demand(id, demand, ttl).await?;
+        self.resolver.receive(&demand).await;
 
         log::info!(
             "Subscribed new Demand: [{}] using identity: {} [{}]",
@@ -240,12 +379,37 @@ impl Matcher {
         Ok(demand)
     }
 
+    /// Extends expiration of our Demand, keeping its subscription id.
+    pub async fn refresh_demand(
+        &self,
+        demand_id: &SubscriptionId,
+        id: &Identity,
+        ttl: Option<chrono::Duration>,
+    ) -> Result<Demand, MatcherError> {
+        let (demand, refreshed) = self.store.refresh_demand(demand_id, id, ttl).await?;
+        if refreshed {
+            self.resolver
+                .index
+                .extend_demand(demand_id, demand.expiration_ts);
+
+            log::info!(
+                "Refreshed Demand: [{}] using identity: {} [{}]. Expires at {}.",
+                &demand_id,
+                id.name,
+                id.identity,
+                demand.expiration_ts
+            );
+        }
+        Ok(demand)
+    }
+
     pub async fn unsubscribe_demand(
         &self,
         demand_id: &SubscriptionId,
         id: &Identity,
     ) -> Result<(), MatcherError> {
         self.store.remove_demand(demand_id, id).await?;
+        self.resolver.index.remove_demand(demand_id);
 
         log::info!(
             "Unsubscribed Demand: [{}] using identity: {} [{}]",
@@ -256,6 +420,121 @@ impl Matcher {
         Ok(())
     }
 
+    /// Explains why given Offer matches (or doesn't match) our Demand.
+    /// Only Demand owner can ask for explanation.
+    pub async fn explain_match(
+        &self,
+        demand_id: &SubscriptionId,
+        offer_id: &SubscriptionId,
+        id: &Identity,
+    ) -> Result<MatchExplanation, ExplainMatchError> {
+        let demand = self.store.get_demand(demand_id).await?;
+        if demand.node_id != id.identity {
+            return Err(DemandError::NotFound(demand_id.clone()).into());
+        }
+
+        let offer = self.store.get_offer(offer_id).await?;
+        resolver::explain(&offer, &demand, &self.resolver.match_resolvers().await)
+    }
+
+    /// Returns warnings about Offer/Demand constraints, which are malformed,
+    /// can never be satisfied or reference properties unknown to the market.
+    pub fn lint_constraints(&self, constraints: &str) -> Vec<LintWarning> {
+        let warnings = self.resolver.lint(constraints);
+        for warning in warnings.iter() {
+            log::debug!("Constraints {} lint warning: {}", constraints, warning);
+        }
+        warnings
+    }
+
+    /// Returns page of active Offers, which properties satisfy scan constraints.
+    /// Offers are ordered by creation time. Nothing is stored and no Proposals are generated.
+    /// Page size is limited to `MAX_SCAN_LIMIT`.
+    pub async fn scan_offers(
+        &self,
+        scan: &ScanOffers,
+    ) -> Result<Vec<ClientOffer>, ScanOffersError> {
+        let offers = self.store.get_all_offers().await?;
+        let limit = scan.limit.unwrap_or(DEFAULT_SCAN_LIMIT).min(MAX_SCAN_LIMIT) as usize;
+
+        // Offers, which can't be returned, don't take place on the page.
+        Ok(self
+            .resolver
+            .scan(&scan.constraints, offers)
+            .await?
+            .into_iter()
+            .filter_map(|offer| match offer.into_client_offer() {
+                Err(e) => {
+                    log::error!("Skipping Offer because of: {}", e);
+                    None
+                }
+                Ok(offer) => Some(offer),
+            })
+            .skip(scan.offset as usize)
+            .take(limit)
+            .collect())
+    }
+
+    // =========================================== //
+    // Node policies
+    // =========================================== //
+
+    /// Lists policies of `owner_id` or of all local identities, if not specified.
+    pub async fn list_node_policies(
+        &self,
+        owner_id: Option<NodeId>,
+    ) -> Result<Vec<NodePolicyEntry>, NodePolicyError> {
+        Ok(self
+            .store
+            .list_node_policies(owner_id)
+            .await?
+            .into_iter()
+            .map(NodePolicy::into_client)
+            .collect())
+    }
+
+    /// Policy is applied to Offers and Proposals received from now on. Proposals
+    /// already generated for blocked nodes can't be countered anymore.
+    pub async fn set_node_policy(
+        &self,
+        msg: SetNodePolicy,
+    ) -> Result<NodePolicyEntry, NodePolicyError> {
+        let owner_id = self.policy_owner(msg.owner_id).await?;
+        let policy = NodePolicy::new(owner_id, msg.node_id, msg.policy, msg.description);
+        let policy = self.store.set_node_policy(policy).await?;
+
+        log::info!(
+            "Node [{}] policy of identity [{}] set to: {}.",
+            policy.node_id,
+            policy.owner_id,
+            policy.policy
+        );
+        Ok(policy.into_client())
+    }
+
+    pub async fn remove_node_policy(
+        &self,
+        owner_id: Option<NodeId>,
+        node_id: NodeId,
+    ) -> Result<(), NodePolicyError> {
+        let owner_id = self.policy_owner(owner_id).await?;
+        self.store.remove_node_policy(owner_id, node_id).await?;
+
+        log::info!(
+            "Node [{}] policy of identity [{}] removed.",
+            node_id,
+            owner_id
+        );
+        Ok(())
+    }
+
+    async fn policy_owner(&self, owner_id: Option<NodeId>) -> Result<NodeId, NodePolicyError> {
+        match owner_id {
+            Some(owner_id) => Ok(owner_id),
+            None => Ok(self.identity.default_identity().await?),
+        }
+    }
+
     pub async fn get_our_active_offer_ids(&self) -> Result<Vec<SubscriptionId>, QueryOffersError> {
         let our_node_ids = self.identity.list().await?;
         Ok(self.store.get_active_offer_ids(Some(our_node_ids)).await?)
@@ -1,8 +1,20 @@
-use crate::db::model::{SubscriptionId, SubscriptionValidationError};
+use ya_client::model::NodeId;
+use ya_core_model::market::RpcMessageError;
+
+use crate::db::model::{OfferSignatureError, SubscriptionId, SubscriptionValidationError};
 use crate::db::DbError;
 use crate::identity::IdentityError;
 use crate::protocol::discovery::error::DiscoveryInitError;
 
+/// Requested subscription TTL is out of `SubscriptionConfig` bounds.
+#[derive(thiserror::Error, Debug)]
+#[error("Subscription TTL {requested}s is out of allowed range [{min}s, {max}s].")]
+pub struct TtlError {
+    pub requested: i64,
+    pub min: i64,
+    pub max: i64,
+}
+
 #[derive(thiserror::Error, Debug)]
 pub enum DemandError {
     #[error("Failed to get Demands. Error: {0}.")]
@@ -15,6 +27,12 @@ pub enum DemandError {
     Remove(DbError, SubscriptionId),
     #[error("Demand [{0}] not found.")]
     NotFound(SubscriptionId),
+    #[error("Failed to refresh Demand [{1}]. Error: {0}.")]
+    Refresh(DbError, SubscriptionId),
+    #[error("Failed to count Proposals delivered for Demand [{1}]. Error: {0}.")]
+    Delivered(DbError, SubscriptionId),
+    #[error(transparent)]
+    Ttl(#[from] TtlError),
     #[error(transparent)]
     JsonObjectExpected(#[from] serde_json::error::Error),
 }
@@ -58,6 +76,14 @@ pub enum SaveOfferError {
     #[error(transparent)]
     SubscriptionValidation(#[from] SubscriptionValidationError),
     #[error(transparent)]
+    Signature(#[from] OfferSignatureError),
+    #[error("Failed to sign Offer [{0}]. Error: {1}.")]
+    Sign(SubscriptionId, IdentityError),
+    #[error("Offer [{0}] rejected, we store too many Offers of node [{1}].")]
+    TooManyOffers(SubscriptionId, NodeId),
+    #[error(transparent)]
+    Ttl(#[from] TtlError),
+    #[error(transparent)]
     JsonObjectExpected(#[from] serde_json::error::Error),
     #[error("Wrong Offer [{id}] state {state:?} after inserted: {inserted}.")]
     WrongState {
@@ -81,6 +107,14 @@ pub enum ModifyOfferError {
     Remove(DbError, SubscriptionId),
     #[error("Offer [{0}] marked as unsubscribed, but not removed")]
     UnsubscribedNotRemoved(SubscriptionId),
+    #[error("Failed to refresh Offer [{1}]. Error: {0}.")]
+    Refresh(DbError, SubscriptionId),
+    #[error("Failed to sign refreshed Offer [{0}]. Error: {1}.")]
+    Sign(SubscriptionId, IdentityError),
+    #[error(transparent)]
+    Signature(#[from] OfferSignatureError),
+    #[error(transparent)]
+    Ttl(#[from] TtlError),
 }
 
 impl From<QueryOfferError> for ModifyOfferError {
@@ -95,6 +129,51 @@ impl From<QueryOfferError> for ModifyOfferError {
     }
 }
 
+#[derive(thiserror::Error, Debug)]
+pub enum ExplainMatchError {
+    #[error(transparent)]
+    Demand(#[from] DemandError),
+    #[error(transparent)]
+    QueryOffer(#[from] QueryOfferError),
+    #[error("Failed to resolve Offer [{offer_id}] against Demand [{demand_id}]. Error: {error}.")]
+    Resolve {
+        offer_id: SubscriptionId,
+        demand_id: SubscriptionId,
+        error: String,
+    },
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ScanOffersError {
+    #[error("Invalid scan constraints. Error: {0}.")]
+    InvalidConstraints(String),
+    #[error(transparent)]
+    QueryOffers(#[from] QueryOffersError),
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum NodePolicyError {
+    #[error("Failed to get node policies. Error: {0}.")]
+    Get(DbError),
+    #[error("Failed to set policy of node [{1}]. Error: {0}.")]
+    Save(DbError, NodeId),
+    #[error("Failed to remove policy of node [{1}]. Error: {0}.")]
+    Remove(DbError, NodeId),
+    #[error("Policy of node [{0}] not found.")]
+    NotFound(NodeId),
+    #[error("Failed to get default identity. Error: {0}.")]
+    Identity(#[from] IdentityError),
+}
+
+impl From<NodePolicyError> for RpcMessageError {
+    fn from(e: NodePolicyError) -> Self {
+        match e {
+            NodePolicyError::NotFound(_) => RpcMessageError::NotFound(e.to_string()),
+            _ => RpcMessageError::Market(e.to_string()),
+        }
+    }
+}
+
 #[derive(thiserror::Error, Debug)]
 pub enum MatcherError {
     #[error(transparent)]
@@ -115,6 +194,8 @@ pub enum MatcherInitError {
     DiscoveryInitError(#[from] DiscoveryInitError),
     #[error("Failed to initialize expiration tracker. Error: {0}.")]
     ExpirationTrackerError(String),
+    #[error("Failed to load node policies. Error: {0}.")]
+    NodePolicies(#[from] NodePolicyError),
 }
 
 #[derive(thiserror::Error, Debug)]
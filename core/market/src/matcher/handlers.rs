@@ -3,10 +3,13 @@ use futures::StreamExt;
 use metrics::{counter, value};
 
 use crate::db::model::{Offer, SubscriptionId};
-use crate::matcher::error::ModifyOfferError;
+use crate::matcher::error::{ModifyOfferError, SaveOfferError};
 use crate::protocol::discovery::{
     error::DiscoveryRemoteError,
-    message::{OffersBcast, OffersRetrieved, RetrieveOffers, UnsubscribedOffersBcast},
+    message::{
+        OfferRefresh, OffersBcast, OffersRetrieved, RefreshedOffersBcast, RetrieveOffers,
+        UnsubscribedOffersBcast,
+    },
 };
 
 use super::{resolver::Resolver, store::SubscriptionStore};
@@ -20,14 +23,15 @@ pub(super) async fn filter_out_known_offer_ids(
     // We shouldn't propagate Offer, if we already have it in our database.
     // Note that when we broadcast our Offer, it will reach us too, so it concerns
     // not only Offers from other nodes.
-    Ok(store
+    store
         .filter_out_known_offer_ids(msg.offer_ids)
         .await
-        .map_err(|e| log::warn!("Error filtering Offers. Error: {}", e))?)
+        .map_err(|e| log::warn!("Error filtering Offers. Error: {}", e))
 }
 
 /// Returns only ids of those from input offers, that was successfully stored locally.
 /// Also triggers Resolver to match newly stored Offers against local Demands.
+/// Offers of nodes blocked by our policies are neither stored nor propagated.
 pub(super) async fn receive_remote_offers(
     resolver: Resolver,
     caller: String,
@@ -38,19 +42,35 @@ pub(super) async fn receive_remote_offers(
             let resolver = resolver.clone();
             let offer_id = offer.id.clone();
             async move {
-                resolver
+                if !resolver.permits_node(&offer.node_id).await {
+                    log::trace!(
+                        "Ignoring Offer [{}] of node [{}] blocked by policy.",
+                        &offer_id,
+                        &offer.node_id
+                    );
+                    return None;
+                }
+                let offer = resolver
                     .store
                     .save_offer(offer)
                     .await
-                    .map(|offer| {
-                        resolver.receive(&offer);
-                        offer.id
-                    })
                     .map_err(|e| {
-                        log::warn!("Failed to save Offer [{}]. Error: {}", &offer_id, &e);
+                        match e {
+                            SaveOfferError::Signature(_) => {
+                                counter!("market.offers.signature.rejected", 1);
+                                log::warn!("Failed to save Offer [{}]. Error: {}", &offer_id, &e)
+                            }
+                            SaveOfferError::TooManyOffers(..) => {
+                                counter!("market.offers.stored-limit.rejected", 1);
+                                log::debug!("Failed to save Offer [{}]. Error: {}", &offer_id, &e)
+                            }
+                            _ => log::warn!("Failed to save Offer [{}]. Error: {}", &offer_id, &e),
+                        };
                         e
                     })
-                    .ok()
+                    .ok()?;
+                resolver.receive(&offer).await;
+                Some(offer.id)
             }
         })
         .collect::<Vec<SubscriptionId>>()
@@ -87,17 +107,19 @@ pub(super) async fn get_local_offers(
 }
 
 /// Returns only those of input offer ids, that were able to be unsubscribed locally.
+/// Number of unsubscribes accepted for Offers of single issuer is limited.
 pub(super) async fn receive_remote_offer_unsubscribes(
-    store: SubscriptionStore,
+    resolver: Resolver,
     caller: String,
     msg: UnsubscribedOffersBcast,
 ) -> Result<Vec<SubscriptionId>, ()> {
     let new_unsubscribes = futures::stream::iter(msg.offer_ids.into_iter())
         .filter_map(|offer_id| {
-            let store = store.clone();
+            let resolver = resolver.clone();
             let caller = caller.parse().ok();
             async move {
-                store
+                resolver
+                    .store
                     .unsubscribe_offer(&offer_id, false, caller)
                     .await
                     // Some errors don't mean we shouldn't propagate unsubscription.
@@ -106,7 +128,10 @@ pub(super) async fn receive_remote_offer_unsubscribes(
                         _ => Err(e),
                     })
                     // Collect Offers, that were correctly unsubscribed.
-                    .map(|_| offer_id.clone())
+                    .map(|_| {
+                        resolver.index.remove_offer(&offer_id);
+                        offer_id.clone()
+                    })
                     .map_err(|e| match e {
                         // We don't want to warn about normal situations.
                         ModifyOfferError::AlreadyUnsubscribed(..)
@@ -140,3 +165,49 @@ pub(super) async fn receive_remote_offer_unsubscribes(
     }
     Ok(new_unsubscribes)
 }
+
+/// Returns only those of input refreshes, that extended expiration of stored Offers.
+pub(super) async fn receive_remote_offer_refreshes(
+    resolver: Resolver,
+    caller: String,
+    msg: RefreshedOffersBcast,
+) -> Result<Vec<OfferRefresh>, ()> {
+    let applied = futures::stream::iter(msg.refreshes.into_iter())
+        .filter_map(|refresh| {
+            let resolver = resolver.clone();
+            async move {
+                let offer_id = refresh.offer_id.clone();
+                let expiration_ts = refresh.expiration_ts;
+                match resolver.store.refresh_remote_offer(refresh.clone()).await {
+                    Ok(true) => {
+                        resolver.index.extend_offer(&offer_id, expiration_ts);
+                        Some(refresh)
+                    }
+                    Ok(false) => None,
+                    // We don't want to warn about normal situations.
+                    Err(ModifyOfferError::AlreadyUnsubscribed(..))
+                    | Err(ModifyOfferError::Expired(..))
+                    | Err(ModifyOfferError::NotFound(..)) => None,
+                    Err(e) => {
+                        if let ModifyOfferError::Signature(_) = e {
+                            counter!("market.offers.signature.rejected", 1);
+                        }
+                        log::warn!("Failed to refresh Offer [{}]. Error: {}", &offer_id, &e);
+                        None
+                    }
+                }
+            }
+        })
+        .collect::<Vec<OfferRefresh>>()
+        .await;
+
+    if !applied.is_empty() {
+        counter!("market.offers.refreshes.incoming", applied.len() as u64);
+        log::trace!(
+            "Received {} new Offer refreshes from [{}]",
+            applied.len(),
+            caller,
+        );
+    }
+    Ok(applied)
+}
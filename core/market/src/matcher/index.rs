@@ -0,0 +1,176 @@
+use chrono::NaiveDateTime;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+
+use ya_market_resolver::{
+    prepare_subscription, MatchError, Prefilter, PreparedSubscription, PropertyResolvers, Ranking,
+    DEFAULT_INDEXED_PROPERTIES,
+};
+
+use ya_utils_actix::clock::Clock;
+
+use super::resolver::MatchResolvers;
+use crate::db::model::{Demand, Offer, SubscriptionId};
+
+/// Offer or Demand prepared for matching.
+pub struct IndexEntry {
+    pub prepared: PreparedSubscription,
+    pub prefilter: Prefilter,
+    /// Ranking of matched Offers requested by Demand.
+    pub ranking: Option<Ranking>,
+    expiration_ts: Mutex<NaiveDateTime>,
+}
+
+impl IndexEntry {
+    /// Limit of Proposals delivered for Demand, if requested.
+    pub fn proposals_limit(&self) -> Option<usize> {
+        self.ranking.as_ref().and_then(|ranking| ranking.limit)
+    }
+
+    fn is_expired(&self, now: NaiveDateTime) -> bool {
+        *self.expiration_ts.lock().unwrap() <= now
+    }
+}
+
+type Entries = Arc<RwLock<HashMap<SubscriptionId, Arc<IndexEntry>>>>;
+
+/// In-memory index of prepared Offers and Demands.
+/// Properties of each subscription are parsed and its constraints are built only once,
+/// instead of for every matched pair. Dynamic property resolvers are bound to the entry,
+/// when it is prepared. Entries are dropped on unsubscribe or after expiration.
+/// Database remains the only source of truth about which subscriptions are active.
+#[derive(Clone, Default)]
+pub struct SubscriptionIndex {
+    offers: Entries,
+    demands: Entries,
+    clock: Clock,
+}
+
+impl SubscriptionIndex {
+    pub fn new(clock: Clock) -> Self {
+        SubscriptionIndex {
+            clock,
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn offer(
+        &self,
+        offer: &Offer,
+        resolvers: &MatchResolvers,
+    ) -> Result<Arc<IndexEntry>, MatchError> {
+        get_or_prepare(
+            &self.offers,
+            &offer.id,
+            &offer.properties,
+            &offer.constraints,
+            offer.expiration_ts,
+            resolvers.of(&offer.node_id),
+            false,
+            self.clock.now().naive_utc(),
+        )
+    }
+
+    pub(crate) fn demand(
+        &self,
+        demand: &Demand,
+        resolvers: &MatchResolvers,
+    ) -> Result<Arc<IndexEntry>, MatchError> {
+        get_or_prepare(
+            &self.demands,
+            &demand.id,
+            &demand.properties,
+            &demand.constraints,
+            demand.expiration_ts,
+            resolvers.of(&demand.node_id),
+            true,
+            self.clock.now().naive_utc(),
+        )
+    }
+
+    /// Selects Offers, which may match Demand with given `prefilter`.
+    /// Offers, which weren't indexed yet, are always selected.
+    pub fn offer_candidates(
+        &self,
+        offer_ids: Vec<SubscriptionId>,
+        prefilter: &Prefilter,
+    ) -> Vec<SubscriptionId> {
+        let offers = self.offers.read().unwrap();
+        offer_ids
+            .into_iter()
+            .filter(|id| match offers.get(id) {
+                Some(entry) => entry.prefilter.may_match(prefilter),
+                None => true,
+            })
+            .collect()
+    }
+
+    /// Keeps entry of refreshed Offer until its new expiration.
+    pub fn extend_offer(&self, id: &SubscriptionId, expiration_ts: NaiveDateTime) {
+        extend(&self.offers, id, expiration_ts)
+    }
+
+    pub fn extend_demand(&self, id: &SubscriptionId, expiration_ts: NaiveDateTime) {
+        extend(&self.demands, id, expiration_ts)
+    }
+
+    pub fn remove_offer(&self, id: &SubscriptionId) {
+        self.offers.write().unwrap().remove(id);
+    }
+
+    pub fn remove_demand(&self, id: &SubscriptionId) {
+        self.demands.write().unwrap().remove(id);
+    }
+
+    /// Drops all entries, so that subscriptions are prepared again with current resolvers.
+    pub fn clear(&self) {
+        self.offers.write().unwrap().clear();
+        self.demands.write().unwrap().clear();
+    }
+}
+
+fn get_or_prepare(
+    entries: &Entries,
+    id: &SubscriptionId,
+    properties: &str,
+    constraints: &str,
+    expiration_ts: NaiveDateTime,
+    resolvers: &PropertyResolvers,
+    with_ranking: bool,
+    now: NaiveDateTime,
+) -> Result<Arc<IndexEntry>, MatchError> {
+    if let Some(entry) = entries.read().unwrap().get(id) {
+        return Ok(entry.clone());
+    }
+
+    // Subscription id is a hash of its content, so entry never needs to be updated.
+    let prepared = prepare_subscription(properties, constraints)?.with_resolvers(resolvers.clone());
+    let ranking = if with_ranking {
+        Ranking::from(&prepared).unwrap_or_else(|e| {
+            log::warn!("Ignoring invalid ranking of [{}]. Error: {}", id, e);
+            None
+        })
+    } else {
+        None
+    };
+    let entry = Arc::new(IndexEntry {
+        prefilter: Prefilter::from(&prepared, DEFAULT_INDEXED_PROPERTIES),
+        prepared,
+        ranking,
+        expiration_ts: Mutex::new(expiration_ts),
+    });
+
+    let mut entries = entries.write().unwrap();
+    entries.retain(|_, entry| !entry.is_expired(now));
+    // Entry could have been inserted concurrently; keep the first one.
+    Ok(entries.entry(id.clone()).or_insert(entry).clone())
+}
+
+fn extend(entries: &Entries, id: &SubscriptionId, expiration_ts: NaiveDateTime) {
+    if let Some(entry) = entries.read().unwrap().get(id) {
+        let mut current = entry.expiration_ts.lock().unwrap();
+        if *current < expiration_ts {
+            *current = expiration_ts;
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+
+use ya_client::model::NodeId;
+
+use crate::db::model::{NodePolicy, PolicyKind};
+
+/// Allowed and blocked nodes of single local identity.
+#[derive(Default)]
+struct IdentityPolicy {
+    allowed: HashSet<NodeId>,
+    blocked: HashSet<NodeId>,
+}
+
+impl IdentityPolicy {
+    fn permits(&self, node_id: &NodeId) -> bool {
+        !self.blocked.contains(node_id)
+            && (self.allowed.is_empty() || self.allowed.contains(node_id))
+    }
+}
+
+/// In-memory view of node policies stored in database, consulted on every match,
+/// so it must be cheap. Identities without any policy permit all nodes.
+#[derive(Clone, Default)]
+pub struct NodePolicies {
+    identities: Arc<RwLock<HashMap<NodeId, IdentityPolicy>>>,
+}
+
+impl NodePolicies {
+    /// Replaces all cached policies.
+    pub fn reset(&self, policies: Vec<NodePolicy>) {
+        let mut identities = HashMap::<NodeId, IdentityPolicy>::new();
+        for policy in policies {
+            let entry = identities.entry(policy.owner_id).or_default();
+            match policy.policy {
+                PolicyKind::Allow => entry.allowed.insert(policy.node_id),
+                PolicyKind::Block => entry.blocked.insert(policy.node_id),
+            };
+        }
+        *self.identities.write().unwrap() = identities;
+    }
+
+    /// Checks, if `owner_id` local identity can match and negotiate with `node_id`.
+    pub fn permits(&self, owner_id: &NodeId, node_id: &NodeId) -> bool {
+        match self.identities.read().unwrap().get(owner_id) {
+            Some(policy) => policy.permits(node_id),
+            None => true,
+        }
+    }
+
+    /// Both sides of the pair must permit each other. Only policies
+    /// of local identities are known, so it is enough, that one side is local.
+    pub fn permits_pair(&self, offer_node_id: &NodeId, demand_node_id: &NodeId) -> bool {
+        self.permits(offer_node_id, demand_node_id) && self.permits(demand_node_id, offer_node_id)
+    }
+
+    /// Checks, if any of local identities would match with `node_id`.
+    pub fn permits_any(&self, local_ids: &[NodeId], node_id: &NodeId) -> bool {
+        local_ids
+            .iter()
+            .any(|owner_id| owner_id == node_id || self.permits(owner_id, node_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ya_client::model::NodeId;
+    use ya_core_model::market::NodePolicy as ClientNodePolicy;
+
+    use super::NodePolicies;
+    use crate::db::model::NodePolicy;
+
+    fn node(n: u8) -> NodeId {
+        NodeId::from(&[n; 20][..])
+    }
+
+    #[test]
+    fn blocked_nodes_are_not_permitted() {
+        let policies = NodePolicies::default();
+        policies.reset(vec![NodePolicy::new(
+            node(1),
+            node(2),
+            ClientNodePolicy::Block,
+            None,
+        )]);
+
+        assert!(!policies.permits(&node(1), &node(2)));
+        assert!(policies.permits(&node(1), &node(3)));
+        // Identities without policies permit everyone.
+        assert!(policies.permits(&node(4), &node(2)));
+
+        assert!(!policies.permits_pair(&node(1), &node(2)));
+        assert!(!policies.permits_pair(&node(2), &node(1)));
+        assert!(policies.permits_any(&[node(1), node(4)], &node(2)));
+        assert!(!policies.permits_any(&[node(1)], &node(2)));
+    }
+
+    #[test]
+    fn allowed_nodes_exclude_others() {
+        let policies = NodePolicies::default();
+        policies.reset(vec![
+            NodePolicy::new(node(1), node(2), ClientNodePolicy::Allow, None),
+            NodePolicy::new(node(1), node(3), ClientNodePolicy::Allow, None),
+            NodePolicy::new(node(1), node(3), ClientNodePolicy::Block, None),
+        ]);
+
+        assert!(policies.permits(&node(1), &node(2)));
+        // Block takes precedence over allow.
+        assert!(!policies.permits(&node(1), &node(3)));
+        assert!(!policies.permits(&node(1), &node(4)));
+
+        policies.reset(vec![]);
+        assert!(policies.permits(&node(1), &node(4)));
+    }
+}
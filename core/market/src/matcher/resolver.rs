@@ -1,9 +1,22 @@
+use std::sync::{Arc, RwLock};
 use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
 
-use ya_market_resolver::{match_demand_offer, Match};
+use ya_market_resolver::{
+    explain_prepared, lint_constraints, match_prepared, prepare_subscription, LintWarning, Match,
+    MatchError, MatchExplanation, Prefilter, PreparedSubscription, PropertyResolver,
+    PropertyResolvers, PropertySchema, DEFAULT_INDEXED_PROPERTIES,
+};
 
-use super::{error::ResolverError, RawProposal, SubscriptionStore};
+use ya_client::model::NodeId;
+
+use super::{
+    error::{ExplainMatchError, ResolverError, ScanOffersError},
+    index::SubscriptionIndex,
+    policy::NodePolicies,
+    RawProposal, SubscriptionStore,
+};
 use crate::db::model::{Demand, Offer, SubscriptionId};
+use crate::identity::IdentityApi;
 
 #[derive(Clone, Debug, derive_more::Display)]
 pub enum Subscription {
@@ -25,22 +38,71 @@ impl From<&Demand> for Subscription {
     }
 }
 
+/// Dynamic property resolvers used for matching. Node-local resolvers answer
+/// properties of own subscriptions only, so local state doesn't change how
+/// subscriptions of other nodes match. Those get only built-in resolvers,
+/// which compute node-independent properties.
+#[derive(Clone)]
+pub(crate) struct MatchResolvers {
+    own: PropertyResolvers,
+    remote: PropertyResolvers,
+    local_ids: Vec<NodeId>,
+}
+
+impl MatchResolvers {
+    pub fn new(own: PropertyResolvers, remote: PropertyResolvers, local_ids: Vec<NodeId>) -> Self {
+        MatchResolvers {
+            own,
+            remote,
+            local_ids,
+        }
+    }
+
+    /// Resolvers of properties of subscription issued by `node_id`.
+    pub fn of(&self, node_id: &NodeId) -> &PropertyResolvers {
+        if self.local_ids.contains(node_id) {
+            &self.own
+        } else {
+            &self.remote
+        }
+    }
+}
+
+/// Subscription to be resolved together with our identities at the time it was received.
+type IncomingSubscription = (Subscription, Vec<NodeId>);
+
+/// Number of Offers loaded from database by single query.
+const OFFERS_QUERY_CHUNK: usize = 500;
+
 /// Resolves the match relation for the specific Offer-Demand pair.
 #[derive(Clone)]
 pub struct Resolver {
     pub(crate) store: SubscriptionStore,
-    subscription_tx: UnboundedSender<Subscription>,
+    pub(crate) index: SubscriptionIndex,
+    identity: Arc<dyn IdentityApi>,
+    subscription_tx: UnboundedSender<IncomingSubscription>,
     proposal_tx: UnboundedSender<RawProposal>,
+    /// Built-in and node-local resolvers of own subscriptions' properties.
+    property_resolvers: Arc<RwLock<PropertyResolvers>>,
+    property_schema: Arc<PropertySchema>,
 }
 
 impl Resolver {
-    pub fn new(store: SubscriptionStore, proposal_tx: UnboundedSender<RawProposal>) -> Self {
-        let (subscription_tx, subscription_rx) = unbounded_channel::<Subscription>();
+    pub fn new(
+        store: SubscriptionStore,
+        identity: Arc<dyn IdentityApi>,
+        proposal_tx: UnboundedSender<RawProposal>,
+    ) -> Self {
+        let (subscription_tx, subscription_rx) = unbounded_channel::<IncomingSubscription>();
 
         let myself = Resolver {
+            index: SubscriptionIndex::new(store.clock.clone()),
             store,
+            identity,
             subscription_tx,
             proposal_tx,
+            property_resolvers: Arc::new(RwLock::new(PropertyResolvers::builtin())),
+            property_schema: Arc::new(PropertySchema::builtin()),
         };
 
         let resolver = myself.clone();
@@ -49,20 +111,94 @@ impl Resolver {
         myself
     }
 
-    pub fn receive(&self, subscription: impl Into<Subscription>) {
+    /// Queues subscription to be matched. Our identities are listed here, in the caller's
+    /// context, since futures of identity service calls can't be sent to the resolving task.
+    pub async fn receive(&self, subscription: impl Into<Subscription>) {
         let s = subscription.into();
-        if let Err(e) = self.subscription_tx.send(s.clone()) {
+        let local_ids = self.local_ids().await;
+        if let Err(e) = self.subscription_tx.send((s.clone(), local_ids)) {
             log::error!("Receiving incoming {:?} error: {:?}", s, e);
         };
     }
 
+    /// Checks, if any of our identities permits matching with `node_id`.
+    /// Offers of nodes, that nobody permits, are not worth storing.
+    pub async fn permits_node(&self, node_id: &NodeId) -> bool {
+        match self.identity.list().await {
+            Ok(local_ids) => self.store.policies.permits_any(&local_ids, node_id),
+            Err(e) => {
+                log::warn!("Failed to list identities. Error: {}", e);
+                true
+            }
+        }
+    }
+
+    /// Registers node-local resolver of dynamic properties. It will be consulted
+    /// for properties absent in (or declared without value by) own Offers and Demands.
+    pub fn register_property_resolver(&self, resolver: Arc<dyn PropertyResolver>) {
+        self.property_resolvers.write().unwrap().add(resolver);
+        // Indexed subscriptions are bound to resolvers registered before.
+        self.index.clear();
+    }
+
+    pub(crate) async fn match_resolvers(&self) -> MatchResolvers {
+        self.resolvers_of(self.local_ids().await)
+    }
+
+    async fn local_ids(&self) -> Vec<NodeId> {
+        self.identity.list().await.unwrap_or_else(|e| {
+            log::warn!("Failed to list identities. Error: {}", e);
+            vec![]
+        })
+    }
+
+    fn resolvers_of(&self, local_ids: Vec<NodeId>) -> MatchResolvers {
+        MatchResolvers::new(
+            self.property_resolvers.read().unwrap().clone(),
+            PropertyResolvers::builtin(),
+            local_ids,
+        )
+    }
+
+    /// Static analysis of constraints against schema of known properties.
+    pub fn lint(&self, constraints: &str) -> Vec<LintWarning> {
+        lint_constraints(constraints, &self.property_schema)
+    }
+
+    /// Selects Offers, which properties satisfy given constraints.
+    /// Offers' own constraints are ignored and no Proposals are emitted.
+    pub async fn scan(
+        &self,
+        constraints: &str,
+        offers: Vec<Offer>,
+    ) -> Result<Vec<Offer>, ScanOffersError> {
+        let query = PreparedSubscription::from(vec![], constraints)
+            .map_err(|e| ScanOffersError::InvalidConstraints(e.to_string()))?;
+        let prefilter = Prefilter::from(&query, DEFAULT_INDEXED_PROPERTIES);
+        let resolvers = self.match_resolvers().await;
+
+        Ok(offers
+            .into_iter()
+            .filter(|offer| match self.index.offer(offer, &resolvers) {
+                Ok(prepared) => {
+                    prefilter.may_match(&prepared.prefilter)
+                        && prepared.prepared.satisfies(&query.constraints)
+                }
+                Err(e) => {
+                    log::debug!("Skipping Offer [{}] in scan. Error: {}", offer.id, e);
+                    false
+                }
+            })
+            .collect())
+    }
+
     async fn process_incoming_subscriptions(
         self,
-        mut subscription_rx: UnboundedReceiver<Subscription>,
+        mut subscription_rx: UnboundedReceiver<IncomingSubscription>,
     ) {
-        while let Some(s) = subscription_rx.recv().await {
+        while let Some((s, local_ids)) = subscription_rx.recv().await {
             log::debug!("Resolving incoming {}", s);
-            if let Err(e) = self.process_single_subscription(&s).await {
+            if let Err(e) = self.process_single_subscription(&s, local_ids).await {
                 log::warn!("Failed resolve [{}]. Error: {}", s, e);
             }
         }
@@ -71,30 +207,100 @@ impl Resolver {
     async fn process_single_subscription(
         &self,
         subscription: &Subscription,
+        local_ids: Vec<NodeId>,
     ) -> Result<(), ResolverError> {
+        let resolvers = self.resolvers_of(local_ids);
+        let policies = &self.store.policies;
         match subscription {
             Subscription::Offer(id) => {
                 let offer = self.store.get_offer(id).await?;
-                self.store
+                let demands = self
+                    .store
                     .get_demands_before(offer.insertion_ts.unwrap())
                     .await?
                     .into_iter()
-                    .filter(|demand| matches(&offer, &demand))
-                    .for_each(|demand| self.emit_proposal(offer.clone(), demand));
+                    .filter(|demand| matches(&offer, &demand, &self.index, policies, &resolvers));
+                for demand in demands {
+                    if self.admit_proposals(&demand, 1, &resolvers).await? > 0 {
+                        self.emit_proposal(offer.clone(), demand);
+                    }
+                }
             }
             Subscription::Demand(id) => {
                 let demand = self.store.get_demand(id).await?;
-                self.store
-                    .get_offers_before(demand.insertion_ts.unwrap())
+                let offers = self
+                    .offer_candidates(&demand, &resolvers)
                     .await?
                     .into_iter()
-                    .filter(|offer| matches(&offer, &demand))
+                    .filter(|offer| matches(&offer, &demand, &self.index, policies, &resolvers))
+                    .collect();
+                let offers = rank(&demand, offers, &self.index, &resolvers);
+                let admitted = self
+                    .admit_proposals(&demand, offers.len(), &resolvers)
+                    .await?;
+                offers
+                    .into_iter()
+                    .take(admitted)
                     .for_each(|offer| self.emit_proposal(offer, demand.clone()));
             }
         }
         Ok(())
     }
 
+    /// Counts up to `count` Proposals to be delivered for Demand. Returns number
+    /// of admitted ones, which is lower if limit of delivered Proposals requested
+    /// by Demand is reached. Delivered Proposals are counted in database, so
+    /// the limit holds regardless of index entries being dropped.
+    async fn admit_proposals(
+        &self,
+        demand: &Demand,
+        count: usize,
+        resolvers: &MatchResolvers,
+    ) -> Result<usize, ResolverError> {
+        let limit = match self
+            .index
+            .demand(demand, resolvers)
+            .ok()
+            .and_then(|prepared| prepared.proposals_limit())
+        {
+            Some(limit) => limit,
+            None => return Ok(count),
+        };
+        let admitted = self
+            .store
+            .admit_demand_proposals(&demand.id, count, limit)
+            .await?;
+        if admitted < count {
+            log::debug!("Demand [{}] Proposals limit reached.", demand.id);
+        }
+        Ok(admitted)
+    }
+
+    /// Active Offers inserted before Demand, which may match it. Offers rejected
+    /// by prefilters of the index are not even loaded from database.
+    async fn offer_candidates(
+        &self,
+        demand: &Demand,
+        resolvers: &MatchResolvers,
+    ) -> Result<Vec<Offer>, ResolverError> {
+        let prepared = match self.index.demand(demand, resolvers) {
+            Ok(prepared) => prepared,
+            // Demand, which can't be prepared, doesn't match any Offer.
+            Err(_) => return Ok(vec![]),
+        };
+        let offer_ids = self
+            .store
+            .get_offer_ids_before(demand.insertion_ts.unwrap())
+            .await?;
+        let offer_ids = self.index.offer_candidates(offer_ids, &prepared.prefilter);
+
+        let mut offers = Vec::with_capacity(offer_ids.len());
+        for ids in offer_ids.chunks(OFFERS_QUERY_CHUNK) {
+            offers.extend(self.store.get_offers(ids.to_vec()).await?);
+        }
+        Ok(offers)
+    }
+
     pub fn emit_proposal(&self, offer: Offer, demand: Demand) {
         let offer_id = offer.id.clone();
         let demand_id = demand.id.clone();
@@ -109,7 +315,13 @@ impl Resolver {
     }
 }
 
-fn matches(offer: &Offer, demand: &Demand) -> bool {
+fn matches(
+    offer: &Offer,
+    demand: &Demand,
+    index: &SubscriptionIndex,
+    policies: &NodePolicies,
+    resolvers: &MatchResolvers,
+) -> bool {
     if offer.node_id == demand.node_id {
         log::info!(
             "Rejecting Demand Offer pair from single identity. node_id: {}",
@@ -117,12 +329,32 @@ fn matches(offer: &Offer, demand: &Demand) -> bool {
         );
         return false;
     }
-    match match_demand_offer(
-        &demand.properties,
-        &demand.constraints,
-        &offer.properties,
-        &offer.constraints,
+    if !policies.permits_pair(&offer.node_id, &demand.node_id) {
+        log::debug!(
+            "Rejecting Offer [{}] and Demand [{}] pair blocked by node policy.",
+            offer.id,
+            demand.id
+        );
+        return false;
+    }
+    let (prepared_offer, prepared_demand) = match (
+        index.offer(offer, resolvers),
+        index.demand(demand, resolvers),
     ) {
+        (Ok(prepared_offer), Ok(prepared_demand)) => (prepared_offer, prepared_demand),
+        (Err(e), _) | (_, Err(e)) => {
+            log::warn!("Matching [{:?}] vs [{:?}] error: {}", offer, demand, e);
+            return false;
+        }
+    };
+    // Cheap rejection of pairs, which surely don't match, without resolving constraints.
+    if !prepared_offer
+        .prefilter
+        .may_match(&prepared_demand.prefilter)
+    {
+        return false;
+    }
+    match match_prepared(&prepared_demand.prepared, &prepared_offer.prepared) {
         Ok(Match::Yes) => true,
         Err(e) => {
             log::warn!("Matching [{:?}] vs [{:?}] error: {}", offer, demand, e);
@@ -132,13 +364,304 @@ fn matches(offer: &Offer, demand: &Demand) -> bool {
     }
 }
 
+/// Orders matched Offers best-first according to scoring expression of Demand.
+/// Order is left unchanged, if Demand doesn't request scoring.
+fn rank(
+    demand: &Demand,
+    offers: Vec<Offer>,
+    index: &SubscriptionIndex,
+    resolvers: &MatchResolvers,
+) -> Vec<Offer> {
+    let ranking = match index.demand(demand, resolvers) {
+        Ok(prepared) => match &prepared.ranking {
+            Some(ranking) if ranking.score.is_some() => ranking.clone(),
+            _ => return offers,
+        },
+        Err(_) => return offers,
+    };
+
+    let scored = offers
+        .into_iter()
+        .map(|offer| {
+            let score = index
+                .offer(&offer, resolvers)
+                .ok()
+                .and_then(|prepared| ranking.score(&prepared.prepared));
+            (offer, score)
+        })
+        .collect();
+    ranking.rank(scored)
+}
+
+pub(crate) fn explain(
+    offer: &Offer,
+    demand: &Demand,
+    resolvers: &MatchResolvers,
+) -> Result<MatchExplanation, ExplainMatchError> {
+    let error = |e: MatchError| ExplainMatchError::Resolve {
+        offer_id: offer.id.clone(),
+        demand_id: demand.id.clone(),
+        error: e.to_string(),
+    };
+    let prepared_demand = prepare_subscription(&demand.properties, &demand.constraints)
+        .map_err(error)?
+        .with_resolvers(resolvers.of(&demand.node_id).clone());
+    let prepared_offer = prepare_subscription(&offer.properties, &offer.constraints)
+        .map_err(error)?
+        .with_resolvers(resolvers.of(&offer.node_id).clone());
+
+    Ok(explain_prepared(&prepared_demand, &prepared_offer))
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::matcher::resolver::matches;
+    use std::sync::Arc;
+
+    use ya_client::model::NodeId;
+    use ya_market_resolver::{PropertyResolver, PropertyResolvers};
+
+    use ya_core_model::market::NodePolicy as ClientNodePolicy;
+
+    use crate::db::model::NodePolicy;
+    use crate::matcher::index::SubscriptionIndex;
+    use crate::matcher::policy::NodePolicies;
+    use crate::matcher::resolver::{matches, rank, MatchResolvers};
     use crate::testing::mock_offer::{sample_demand, sample_offer};
 
+    struct FreeSlots;
+
+    impl PropertyResolver for FreeSlots {
+        fn resolve(&self, name: &str) -> Option<String> {
+            match name {
+                "golem.node.free_slots" => Some("1".to_string()),
+                _ => None,
+            }
+        }
+    }
+
+    fn builtin() -> MatchResolvers {
+        MatchResolvers::new(
+            PropertyResolvers::builtin(),
+            PropertyResolvers::builtin(),
+            vec![],
+        )
+    }
+
+    /// Node-local resolver of free slots, answering properties of subscriptions of `local_ids`.
+    fn free_slots(local_ids: Vec<NodeId>) -> MatchResolvers {
+        let mut own = PropertyResolvers::builtin();
+        own.add(Arc::new(FreeSlots));
+        MatchResolvers::new(own, PropertyResolvers::builtin(), local_ids)
+    }
+
     #[test]
     fn matches_empty() {
-        assert!(matches(&sample_offer(), &sample_demand()))
+        assert!(matches(
+            &sample_offer(),
+            &sample_demand(),
+            &SubscriptionIndex::default(),
+            &NodePolicies::default(),
+            &builtin()
+        ))
+    }
+
+    #[test]
+    fn matches_dynamic_property() {
+        let mut demand = sample_demand();
+        demand.constraints = "(golem.node.free_slots>0)".to_string();
+
+        assert!(!matches(
+            &sample_offer(),
+            &demand,
+            &SubscriptionIndex::default(),
+            &NodePolicies::default(),
+            &builtin()
+        ));
+
+        let offer = sample_offer();
+        assert!(matches(
+            &offer,
+            &demand,
+            &SubscriptionIndex::default(),
+            &NodePolicies::default(),
+            &free_slots(vec![offer.node_id])
+        ));
+
+        // Node-local resolvers don't answer properties of other nodes' Offers.
+        assert!(!matches(
+            &offer,
+            &demand,
+            &SubscriptionIndex::default(),
+            &NodePolicies::default(),
+            &free_slots(vec![demand.node_id])
+        ))
+    }
+
+    #[test]
+    fn matches_rejected_by_prefilter() {
+        let index = SubscriptionIndex::default();
+        let mut offer = sample_offer();
+        offer.properties = r#"{"golem":{"inf":{"mem":{"gib":1}}}}"#.to_string();
+        let mut demand = sample_demand();
+        demand.constraints = "(&(golem.inf.mem.gib>=2)(golem.node.free_slots>0))".to_string();
+
+        let prepared_offer = index.offer(&offer, &builtin()).unwrap();
+        let prepared_demand = index.demand(&demand, &builtin()).unwrap();
+        assert!(!prepared_offer
+            .prefilter
+            .may_match(&prepared_demand.prefilter));
+        assert!(!matches(
+            &offer,
+            &demand,
+            &index,
+            &NodePolicies::default(),
+            &builtin()
+        ));
+
+        demand.constraints = "(&(golem.inf.mem.gib>=1)(golem.node.free_slots>0))".to_string();
+        assert!(matches(
+            &offer,
+            &demand,
+            &SubscriptionIndex::default(),
+            &NodePolicies::default(),
+            &free_slots(vec![offer.node_id])
+        ));
+    }
+
+    #[test]
+    fn matches_rejected_by_node_policy() {
+        let offer = sample_offer();
+        let demand = sample_demand();
+        let policies = NodePolicies::default();
+        policies.reset(vec![NodePolicy::new(
+            demand.node_id,
+            offer.node_id,
+            ClientNodePolicy::Block,
+            None,
+        )]);
+        assert!(!matches(
+            &offer,
+            &demand,
+            &SubscriptionIndex::default(),
+            &policies,
+            &builtin()
+        ));
+
+        // Allow list of Offer owner doesn't contain Demand owner.
+        policies.reset(vec![NodePolicy::new(
+            offer.node_id,
+            NodeId::from(&[7u8; 20][..]),
+            ClientNodePolicy::Allow,
+            None,
+        )]);
+        assert!(!matches(
+            &offer,
+            &demand,
+            &SubscriptionIndex::default(),
+            &policies,
+            &builtin()
+        ));
+
+        policies.reset(vec![NodePolicy::new(
+            offer.node_id,
+            demand.node_id,
+            ClientNodePolicy::Allow,
+            None,
+        )]);
+        assert!(matches(
+            &offer,
+            &demand,
+            &SubscriptionIndex::default(),
+            &policies,
+            &builtin()
+        ));
+    }
+
+    #[test]
+    fn index_prepares_subscription_once() {
+        let index = SubscriptionIndex::default();
+        let offer = sample_offer();
+
+        let prepared = index.offer(&offer, &builtin()).unwrap();
+        assert!(Arc::ptr_eq(
+            &prepared,
+            &index.offer(&offer, &builtin()).unwrap()
+        ));
+
+        index.remove_offer(&offer.id);
+        assert!(!Arc::ptr_eq(
+            &prepared,
+            &index.offer(&offer, &builtin()).unwrap()
+        ));
+
+        let prepared = index.offer(&offer, &builtin()).unwrap();
+        index.clear();
+        assert!(!Arc::ptr_eq(
+            &prepared,
+            &index.offer(&offer, &builtin()).unwrap()
+        ));
+    }
+
+    #[test]
+    fn index_selects_offer_candidates() {
+        let index = SubscriptionIndex::default();
+        let mut small = sample_offer();
+        small.properties = r#"{"golem":{"inf":{"mem":{"gib":1}}}}"#.to_string();
+        let mut big = sample_offer();
+        big.properties = r#"{"golem":{"inf":{"mem":{"gib":8}}}}"#.to_string();
+        let not_indexed = sample_offer();
+        let mut demand = sample_demand();
+        demand.constraints = "(golem.inf.mem.gib>=2)".to_string();
+
+        index.offer(&small, &builtin()).unwrap();
+        index.offer(&big, &builtin()).unwrap();
+        let prepared = index.demand(&demand, &builtin()).unwrap();
+        let ids = vec![small.id.clone(), big.id.clone(), not_indexed.id.clone()];
+        assert_eq!(
+            index.offer_candidates(ids, &prepared.prefilter),
+            vec![big.id.clone(), not_indexed.id.clone()]
+        );
+    }
+
+    #[test]
+    fn rank_offers_by_demand_score() {
+        let index = SubscriptionIndex::default();
+        let mut demand = sample_demand();
+        demand.properties =
+            r#"{"golem.market.rank.score": "golem.inf.cpu.threads / golem.inf.mem.gib"}"#
+                .to_string();
+
+        let offers: Vec<_> = vec![
+            r#"{"golem.inf.cpu.threads": 2, "golem.inf.mem.gib": 2}"#,
+            r#"{"golem.inf.cpu.threads": 4}"#,
+            r#"{"golem.inf.cpu.threads": 8, "golem.inf.mem.gib": 2}"#,
+        ]
+        .into_iter()
+        .map(|properties| {
+            let mut offer = sample_offer();
+            offer.properties = properties.to_string();
+            offer
+        })
+        .collect();
+        let ids: Vec<_> = offers.iter().map(|offer| offer.id.clone()).collect();
+
+        let ranked = rank(&demand, offers, &index, &builtin());
+        assert_eq!(
+            ranked.into_iter().map(|offer| offer.id).collect::<Vec<_>>(),
+            vec![ids[2].clone(), ids[0].clone(), ids[1].clone()]
+        );
+    }
+
+    #[test]
+    fn index_reads_proposals_limit_of_demand() {
+        let index = SubscriptionIndex::default();
+        let mut demand = sample_demand();
+        demand.properties = r#"{"golem.market.rank.limit": 2}"#.to_string();
+
+        let prepared = index.demand(&demand, &builtin()).unwrap();
+        assert_eq!(prepared.proposals_limit(), Some(2));
+
+        let prepared = index.demand(&sample_demand(), &builtin()).unwrap();
+        assert_eq!(prepared.proposals_limit(), None);
     }
 }
@@ -1,4 +1,4 @@
-use chrono::{NaiveDateTime, Utc};
+use chrono::NaiveDateTime;
 use std::collections::HashSet;
 use std::sync::Arc;
 
@@ -6,42 +6,106 @@ use ya_client::model::market::{Demand as ClientDemand, NewDemand, NewOffer, Offe
 use ya_client::model::NodeId;
 use ya_persistence::executor::DbExecutor;
 use ya_service_api_web::middleware::Identity;
+use ya_utils_actix::clock::Clock;
 
 use crate::config::Config;
 use crate::db::dao::*;
-use crate::db::model::{Demand, Offer, SubscriptionId};
+use crate::db::model::{Demand, NodePolicy, Offer, OfferSignatureError, SubscriptionId};
+use crate::identity::IdentityApi;
 use crate::matcher::error::{
-    DemandError, ModifyOfferError, QueryDemandsError, QueryOfferError, QueryOffersError,
-    SaveOfferError,
+    DemandError, ModifyOfferError, NodePolicyError, QueryDemandsError, QueryOfferError,
+    QueryOffersError, SaveOfferError, TtlError,
 };
+use crate::matcher::policy::NodePolicies;
+use crate::protocol::discovery::message::OfferRefresh;
 
 #[derive(Clone)]
 pub struct SubscriptionStore {
     pub(crate) db: DbExecutor,
+    pub(crate) policies: NodePolicies,
+    /// Source of time for expiration of Offers and Demands.
+    pub(crate) clock: Clock,
     config: Arc<Config>,
 }
 
 impl SubscriptionStore {
-    pub fn new(db: DbExecutor, config: Arc<Config>) -> Self {
-        SubscriptionStore { db, config }
+    pub fn new(db: DbExecutor, config: Arc<Config>, clock: Clock) -> Self {
+        SubscriptionStore {
+            db,
+            policies: NodePolicies::default(),
+            clock,
+            config,
+        }
+    }
+
+    pub(crate) fn now(&self) -> NaiveDateTime {
+        self.clock.now().naive_utc()
+    }
+
+    /// Expiration of subscription created or refreshed at `now`. Subscriptions
+    /// without requested TTL get default one.
+    fn expiration_ts(
+        &self,
+        now: NaiveDateTime,
+        ttl: Option<chrono::Duration>,
+    ) -> Result<NaiveDateTime, TtlError> {
+        let config = &self.config.subscription;
+        let ttl = match ttl {
+            Some(ttl) => ttl,
+            None => return Ok(now + config.default_ttl),
+        };
+        if ttl < config.min_ttl || ttl > config.max_ttl {
+            return Err(TtlError {
+                requested: ttl.num_seconds(),
+                min: config.min_ttl.num_seconds(),
+                max: config.max_ttl.num_seconds(),
+            });
+        }
+        Ok(now + ttl)
     }
 
     /// returns newly created offer with insertion_ts
+    /// Offer is signed by issuing identity, so that other nodes can verify its origin.
     pub async fn create_offer(
         &self,
         id: &Identity,
         offer: &NewOffer,
+        ttl: Option<chrono::Duration>,
+        identity_api: &dyn IdentityApi,
     ) -> Result<Offer, SaveOfferError> {
-        let creation_ts = Utc::now().naive_utc();
-        // TODO: provider agent should set expiration.
-        let expiration_ts = creation_ts + self.config.subscription.default_ttl;
-        let offer = Offer::from_new(offer, &id, creation_ts, expiration_ts)?;
+        let creation_ts = self.now();
+        let expiration_ts = self.expiration_ts(creation_ts, ttl)?;
+        let mut offer = Offer::from_new(offer, &id, creation_ts, expiration_ts)?;
+        let signature = identity_api
+            .sign(offer.node_id, offer.signed_payload())
+            .await
+            .map_err(|e| SaveOfferError::Sign(offer.id.clone(), e))?;
+        offer.signature = Some(hex::encode(signature));
         self.insert_offer(offer).await
     }
 
     /// returns saved offer with insertion_ts
     pub async fn save_offer(&self, offer: Offer) -> Result<Offer, SaveOfferError> {
         offer.validate()?;
+        match offer.verify_signature() {
+            // Nodes using previous discovery protocol version don't sign Offers.
+            Err(OfferSignatureError::Missing(_))
+                if self.config.discovery.accept_unsigned_offers => {}
+            result => result?,
+        }
+
+        let max_stored = self.config.discovery.max_stored_offers_per_node as usize;
+        if max_stored > 0 {
+            let stored = self
+                .db
+                .as_dao::<OfferDao>()
+                .get_offer_ids(Some(vec![offer.node_id]), None, self.now())
+                .await
+                .map_err(|e| SaveOfferError::Save(e, offer.id.clone()))?;
+            if stored.len() >= max_stored {
+                return Err(SaveOfferError::TooManyOffers(offer.id, offer.node_id));
+            }
+        }
         self.insert_offer(offer).await
     }
 
@@ -51,12 +115,7 @@ impl SubscriptionStore {
         offer.insertion_ts = None;
         let id = offer.id.clone();
 
-        match self
-            .db
-            .as_dao::<OfferDao>()
-            .put(offer, Utc::now().naive_utc())
-            .await
-        {
+        match self.db.as_dao::<OfferDao>().put(offer, self.now()).await {
             Ok((true, OfferState::Active(offer))) => Ok(offer),
             Ok((false, OfferState::Active(_))) => Err(SaveOfferError::Exists(id)),
             Ok((false, OfferState::Unsubscribed(_))) => Err(SaveOfferError::Unsubscribed(id)),
@@ -77,7 +136,7 @@ impl SubscriptionStore {
         Ok(self
             .db
             .as_dao::<OfferDao>()
-            .get_offer_ids(node_ids, Utc::now().naive_utc())
+            .get_offer_ids(node_ids, None, self.now())
             .await
             .map_err(QueryOffersError::from)?)
     }
@@ -89,7 +148,7 @@ impl SubscriptionStore {
         Ok(self
             .db
             .as_dao::<OfferDao>()
-            .get_unsubscribed_ids(node_ids, Utc::now().naive_utc())
+            .get_unsubscribed_ids(node_ids, self.now())
             .await
             .map_err(QueryOffersError::from)?)
     }
@@ -101,12 +160,7 @@ impl SubscriptionStore {
         Ok(self
             .db
             .as_dao::<OfferDao>()
-            .get_offers(
-                None,
-                node_id.map(|id| vec![id]),
-                None,
-                Utc::now().naive_utc(),
-            )
+            .get_offers(None, node_id.map(|id| vec![id]), None, self.now())
             .await
             .map_err(QueryOffersError::from)?
             .into_iter()
@@ -127,7 +181,17 @@ impl SubscriptionStore {
         Ok(self
             .db
             .as_dao::<OfferDao>()
-            .get_offers(Some(ids), None, None, Utc::now().naive_utc())
+            .get_offers(Some(ids), None, None, self.now())
+            .await
+            .map_err(QueryOffersError::from)?)
+    }
+
+    /// Returns all active Offers, both ours and received from other nodes.
+    pub async fn get_all_offers(&self) -> Result<Vec<Offer>, QueryOffersError> {
+        Ok(self
+            .db
+            .as_dao::<OfferDao>()
+            .get_offers(None, None, None, self.now())
             .await
             .map_err(QueryOffersError::from)?)
     }
@@ -139,7 +203,20 @@ impl SubscriptionStore {
         Ok(self
             .db
             .as_dao::<OfferDao>()
-            .get_offers(None, None, Some(inserted_before_ts), Utc::now().naive_utc())
+            .get_offers(None, None, Some(inserted_before_ts), self.now())
+            .await
+            .map_err(QueryOffersError::from)?)
+    }
+
+    /// Returns ids of active Offers inserted before given time.
+    pub async fn get_offer_ids_before(
+        &self,
+        inserted_before_ts: NaiveDateTime,
+    ) -> Result<Vec<SubscriptionId>, QueryOffersError> {
+        Ok(self
+            .db
+            .as_dao::<OfferDao>()
+            .get_offer_ids(None, Some(inserted_before_ts), self.now())
             .await
             .map_err(QueryOffersError::from)?)
     }
@@ -166,7 +243,7 @@ impl SubscriptionStore {
     }
 
     pub async fn get_offer(&self, id: &SubscriptionId) -> Result<Offer, QueryOfferError> {
-        let now = Utc::now().naive_utc();
+        let now = self.now();
         match self.db.as_dao::<OfferDao>().get_state(id, now).await {
             Err(e) => Err(QueryOfferError::Get(e, id.clone())),
             Ok(OfferState::Active(offer)) => Ok(offer),
@@ -179,7 +256,7 @@ impl SubscriptionStore {
     async fn mark_offer_unsubscribed(&self, id: &SubscriptionId) -> Result<(), ModifyOfferError> {
         self.db
             .as_dao::<OfferDao>()
-            .unsubscribe(id, Utc::now().naive_utc())
+            .unsubscribe(id, self.now())
             .await
             .map_err(|e| ModifyOfferError::Unsubscribe(e.into(), id.clone()))
             .and_then(|state| match state {
@@ -192,6 +269,67 @@ impl SubscriptionStore {
             })
     }
 
+    /// Extends expiration of our active Offer and signs it again. Offer already
+    /// expiring later than requested is returned unchanged with `false` flag.
+    pub async fn refresh_offer(
+        &self,
+        offer_id: &SubscriptionId,
+        id: &Identity,
+        ttl: Option<chrono::Duration>,
+        identity_api: &dyn IdentityApi,
+    ) -> Result<(Offer, bool), ModifyOfferError> {
+        let offer = self.get_offer(offer_id).await?;
+        if offer.node_id != id.identity {
+            return Err(ModifyOfferError::NotFound(offer_id.clone()));
+        }
+
+        let expiration_ts = self.expiration_ts(self.now(), ttl)?;
+        if expiration_ts <= offer.expiration_ts {
+            return Ok((offer, false));
+        }
+
+        let mut offer = offer.refreshed(expiration_ts);
+        let signature = identity_api
+            .sign(offer.node_id, offer.signed_payload())
+            .await
+            .map_err(|e| ModifyOfferError::Sign(offer_id.clone(), e))?;
+        offer.signature = Some(hex::encode(signature));
+        self.update_refreshed_offer(offer).await
+    }
+
+    /// Applies expiration extension of Offer received from other node.
+    /// Returns `true` if stored Offer was extended.
+    pub async fn refresh_remote_offer(
+        &self,
+        refresh: OfferRefresh,
+    ) -> Result<bool, ModifyOfferError> {
+        let offer = self.get_offer(&refresh.offer_id).await?;
+        let mut offer = offer.refreshed(refresh.expiration_ts);
+        offer.signature = Some(refresh.signature);
+        offer.verify_signature()?;
+
+        Ok(self.update_refreshed_offer(offer).await?.1)
+    }
+
+    async fn update_refreshed_offer(
+        &self,
+        offer: Offer,
+    ) -> Result<(Offer, bool), ModifyOfferError> {
+        let id = offer.id.clone();
+        match self
+            .db
+            .as_dao::<OfferDao>()
+            .refresh(offer, self.now())
+            .await
+            .map_err(|e| ModifyOfferError::Refresh(e, id.clone()))?
+        {
+            (refreshed, OfferState::Active(offer)) => Ok((offer, refreshed)),
+            (_, OfferState::NotFound) => Err(ModifyOfferError::NotFound(id)),
+            (_, OfferState::Unsubscribed(_)) => Err(ModifyOfferError::AlreadyUnsubscribed(id)),
+            (_, OfferState::Expired(_)) => Err(ModifyOfferError::Expired(id)),
+        }
+    }
+
     /// Local Offers are kept after unsubscribe. Offers from other nodes are removed.
     pub async fn unsubscribe_offer(
         &self,
@@ -232,10 +370,10 @@ impl SubscriptionStore {
         &self,
         id: &Identity,
         demand: &NewDemand,
+        ttl: Option<chrono::Duration>,
     ) -> Result<Demand, DemandError> {
-        let creation_ts = Utc::now().naive_utc();
-        // TODO: requestor agent should set expiration.
-        let expiration_ts = creation_ts + self.config.subscription.default_ttl;
+        let creation_ts = self.now();
+        let expiration_ts = self.expiration_ts(creation_ts, ttl)?;
         let demand = Demand::from_new(demand, &id, creation_ts, expiration_ts)?;
         self.db
             .as_dao::<DemandDao>()
@@ -246,7 +384,7 @@ impl SubscriptionStore {
     }
 
     pub async fn get_demand(&self, id: &SubscriptionId) -> Result<Demand, DemandError> {
-        match self.db.as_dao::<DemandDao>().select(id).await {
+        match self.db.as_dao::<DemandDao>().select(id, self.now()).await {
             Err(e) => Err(DemandError::GetSingle(e, id.clone())),
             Ok(Some(demand)) => Ok(demand),
             Ok(None) => Err(DemandError::NotFound(id.clone())),
@@ -260,7 +398,7 @@ impl SubscriptionStore {
         Ok(self
             .db
             .as_dao::<DemandDao>()
-            .get_demands(node_id, None, Utc::now().naive_utc())
+            .get_demands(node_id, None, self.now())
             .await
             .map_err(QueryDemandsError::from)?
             .into_iter()
@@ -281,11 +419,59 @@ impl SubscriptionStore {
         Ok(self
             .db
             .as_dao::<DemandDao>()
-            .get_demands(None, Some(insertion_ts), Utc::now().naive_utc())
+            .get_demands(None, Some(insertion_ts), self.now())
             .await
             .map_err(|e| DemandError::GetMany(e))?)
     }
 
+    /// Counts up to `count` Proposals delivered for Demand within `limit`
+    /// of all delivered. Returns number of counted Proposals.
+    pub async fn admit_demand_proposals(
+        &self,
+        demand_id: &SubscriptionId,
+        count: usize,
+        limit: usize,
+    ) -> Result<usize, DemandError> {
+        self.db
+            .as_dao::<DemandDao>()
+            .admit_proposals(demand_id, count, limit)
+            .await
+            .map_err(|e| DemandError::Delivered(e, demand_id.clone()))
+    }
+
+    /// Extends expiration of our Demand. Demand already expiring later
+    /// than requested is returned unchanged with `false` flag.
+    pub async fn refresh_demand(
+        &self,
+        demand_id: &SubscriptionId,
+        id: &Identity,
+        ttl: Option<chrono::Duration>,
+    ) -> Result<(Demand, bool), DemandError> {
+        let mut demand = self.get_demand(demand_id).await?;
+        if id.identity != demand.node_id {
+            return Err(DemandError::NotFound(demand_id.clone()));
+        }
+
+        let expiration_ts = self.expiration_ts(self.now(), ttl)?;
+        if expiration_ts <= demand.expiration_ts {
+            return Ok((demand, false));
+        }
+
+        match self
+            .db
+            .as_dao::<DemandDao>()
+            .update_expiration(demand_id, expiration_ts)
+            .await
+            .map_err(|e| DemandError::Refresh(e, demand_id.clone()))?
+        {
+            true => {
+                demand.expiration_ts = expiration_ts;
+                Ok((demand, true))
+            }
+            false => Err(DemandError::NotFound(demand_id.clone())),
+        }
+    }
+
     pub async fn remove_demand(
         &self,
         demand_id: &SubscriptionId,
@@ -307,4 +493,56 @@ impl SubscriptionStore {
             false => Err(DemandError::NotFound(demand_id.clone())),
         }
     }
+
+    /// Refreshes in-memory node policies used by matching from database.
+    pub async fn load_node_policies(&self) -> Result<(), NodePolicyError> {
+        let policies = self
+            .db
+            .as_dao::<NodePolicyDao>()
+            .list(None)
+            .await
+            .map_err(NodePolicyError::Get)?;
+        self.policies.reset(policies);
+        Ok(())
+    }
+
+    pub async fn list_node_policies(
+        &self,
+        owner_id: Option<NodeId>,
+    ) -> Result<Vec<NodePolicy>, NodePolicyError> {
+        self.db
+            .as_dao::<NodePolicyDao>()
+            .list(owner_id)
+            .await
+            .map_err(NodePolicyError::Get)
+    }
+
+    pub async fn set_node_policy(&self, policy: NodePolicy) -> Result<NodePolicy, NodePolicyError> {
+        let node_id = policy.node_id;
+        let policy = self
+            .db
+            .as_dao::<NodePolicyDao>()
+            .set(policy)
+            .await
+            .map_err(|e| NodePolicyError::Save(e, node_id))?;
+        self.load_node_policies().await?;
+        Ok(policy)
+    }
+
+    pub async fn remove_node_policy(
+        &self,
+        owner_id: NodeId,
+        node_id: NodeId,
+    ) -> Result<(), NodePolicyError> {
+        match self
+            .db
+            .as_dao::<NodePolicyDao>()
+            .remove(owner_id, node_id)
+            .await
+            .map_err(|e| NodePolicyError::Remove(e, node_id))?
+        {
+            true => self.load_node_policies().await,
+            false => Err(NodePolicyError::NotFound(node_id)),
+        }
+    }
 }
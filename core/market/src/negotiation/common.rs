@@ -1,4 +1,5 @@
 use chrono::{DateTime, Utc};
+use futures::Stream;
 use metrics::counter;
 use std::str::FromStr;
 use std::sync::Arc;
@@ -6,6 +7,10 @@ use std::time::{Duration, Instant};
 
 use ya_client::model::market::{proposal::Proposal as ClientProposal, reason::Reason, NewProposal};
 use ya_client::model::NodeId;
+use ya_core_model::market::{
+    AgreementAmendment as ClientAmendment, AmendmentEvent as ClientAmendmentEvent,
+    AmendmentProposal, NegotiationStep,
+};
 use ya_market_resolver::{match_demand_offer, Match};
 use ya_persistence::executor::DbExecutor;
 use ya_service_api_web::middleware::Identity;
@@ -14,39 +19,49 @@ use crate::config::Config;
 use crate::db::model::check_transition;
 use crate::db::{
     dao::{
-        AgreementDao, AgreementEventsDao, NegotiationEventsDao, ProposalDao, SaveProposalError,
-        TakeEventsError,
+        AgreementDao, AgreementEventsDao, AmendmentDao, AmendmentDaoError, NegotiationEventsDao,
+        ProposalDao, SaveProposalError, TakeEventsError,
     },
     model::{
-        Agreement, AgreementEvent, AgreementId, AgreementState, AppSessionId, MarketEvent, Owner,
-        Proposal, ProposalId, ProposalState, SubscriptionId,
+        Agreement, AgreementEvent, AgreementId, AgreementState, Amendment, AmendmentState,
+        AppSessionId, MarketEvent, Owner, Proposal, ProposalId, ProposalState, SubscriptionId,
     },
 };
+use crate::identity::IdentityApi;
 use crate::matcher::{store::SubscriptionStore, RawProposal};
 use crate::negotiation::error::RegenerateProposalError;
 use crate::negotiation::error::{NegotiationError, ProposalValidationError};
 use crate::negotiation::{
     error::{
-        AgreementError, AgreementEventsError, GetProposalError, MatchValidationError,
-        ProposalError, QueryEventsError,
+        AgreementError, AgreementEventsError, AmendmentError, GetProposalError,
+        MatchValidationError, ProposalError, QueryEventsError,
     },
-    notifier::NotifierError,
+    notifier::{event_stream, NotifierError},
     EventNotifier,
 };
 use crate::protocol::negotiation::error::{CallerParseError, RejectProposalError};
 use crate::protocol::negotiation::messages::ProposalRejected;
 use crate::protocol::negotiation::{
+    amendment::{self as protocol_amendment, AmendmentApi},
     common as protocol_common,
     error::{
-        CounterProposalError, RemoteAgreementError, RemoteProposalError, TerminateAgreementError,
+        AmendmentProtocolError, CounterProposalError, RemoteAgreementError, RemoteAmendmentError,
+        RemoteProposalError, TerminateAgreementError,
+    },
+    messages::{
+        AgreementTerminated, AmendmentApproved, AmendmentProposed, AmendmentRejected,
+        ProposalReceived,
     },
-    messages::{AgreementTerminated, ProposalReceived},
 };
 use crate::utils::display::EnableDisplay;
 use crate::utils::AgreementLock;
 
 type IsFirst = bool;
 
+/// Placeholder sent instead of Agreement signature by nodes using
+/// previous negotiation protocol version.
+const UNSIGNED_AGREEMENT: &str = "NoSignature";
+
 #[derive(Clone)]
 pub struct CommonBroker {
     pub(super) db: DbExecutor,
@@ -56,6 +71,7 @@ pub struct CommonBroker {
     pub(super) agreement_notifier: EventNotifier<AgreementId>,
     pub(super) config: Arc<Config>,
     pub(super) agreement_lock: AgreementLock,
+    pub(super) identity: Arc<dyn IdentityApi>,
 }
 
 impl CommonBroker {
@@ -64,6 +80,7 @@ impl CommonBroker {
         store: SubscriptionStore,
         session_notifier: EventNotifier<AppSessionId>,
         config: Arc<Config>,
+        identity: Arc<dyn IdentityApi>,
     ) -> CommonBroker {
         CommonBroker {
             store,
@@ -73,6 +90,7 @@ impl CommonBroker {
             agreement_notifier: EventNotifier::new(),
             config,
             agreement_lock: AgreementLock::new(),
+            identity,
         }
     }
 
@@ -189,7 +207,7 @@ impl CommonBroker {
             let events = self
                 .db
                 .as_dao::<NegotiationEventsDao>()
-                .take_events(subscription_id, max_events, owner)
+                .take_events(subscription_id, max_events, owner, self.store.now())
                 .await?;
 
             if events.len() > 0 {
@@ -282,6 +300,123 @@ impl CommonBroker {
         }
     }
 
+    /// Events of subscription are available only to identity, which subscribed it.
+    /// Active subscription of other identity is reported as not found, so that its
+    /// existence isn't revealed. Other subscriptions are validated by querying events.
+    pub async fn validate_events_owner(
+        &self,
+        subscription_id: &SubscriptionId,
+        id: &Identity,
+        owner: Owner,
+    ) -> Result<(), QueryEventsError> {
+        let node_id = match owner {
+            Owner::Provider => self
+                .store
+                .get_offer(subscription_id)
+                .await
+                .ok()
+                .map(|offer| offer.node_id),
+            Owner::Requestor => self
+                .store
+                .get_demand(subscription_id)
+                .await
+                .ok()
+                .map(|demand| demand.node_id),
+        };
+        match node_id {
+            Some(node_id) if node_id != id.identity => {
+                Err(TakeEventsError::NotFound(subscription_id.clone()))?
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Streams negotiation events stored after event `cursor`. Events aren't removed
+    /// from queue while streaming, so reconnecting client can resume from the last event
+    /// it has seen. Resuming acknowledges events up to `cursor`, which are removed then.
+    /// Subscription and its owner are validated before returning stream.
+    pub async fn stream_events(
+        &self,
+        subscription_id: &SubscriptionId,
+        id: &Identity,
+        cursor: Option<i32>,
+        owner: Owner,
+    ) -> Result<impl Stream<Item = Result<Vec<MarketEvent>, QueryEventsError>>, QueryEventsError>
+    {
+        self.validate_events_owner(subscription_id, id, owner)
+            .await?;
+        let listener = self.negotiation_notifier.listen(subscription_id);
+        let db = self.db.clone();
+        let store = self.store.clone();
+        let max_events = self.config.events.max_events_max;
+        let id = subscription_id.clone();
+        let fetch = move |cursor: Option<i32>| {
+            let db = db.clone();
+            let now = store.now();
+            let subscription_id = id.clone();
+            async move {
+                db.as_dao::<NegotiationEventsDao>()
+                    .select_events_after(&subscription_id, cursor, max_events, owner, now)
+                    .await
+                    .map_err(QueryEventsError::from)
+            }
+        };
+
+        let pending = fetch(cursor).await?;
+        if let Some(cursor) = cursor {
+            self.db
+                .as_dao::<NegotiationEventsDao>()
+                .ack_events(subscription_id, cursor)
+                .await
+                .map_err(|e| QueryEventsError::TakeEvents(TakeEventsError::Db(e)))?;
+        }
+        Ok(event_stream(
+            listener,
+            self.config.events.stream_keep_alive_interval,
+            cursor,
+            pending,
+            |event: &MarketEvent| event.id,
+            fetch,
+        ))
+    }
+
+    /// Streams Agreement events of `id` identity stored after event `cursor`.
+    pub async fn stream_agreement_events(
+        &self,
+        session_id: &AppSessionId,
+        cursor: Option<i32>,
+        id: &Identity,
+    ) -> Result<
+        impl Stream<Item = Result<Vec<AgreementEvent>, AgreementEventsError>>,
+        AgreementEventsError,
+    > {
+        let listener = self.session_notifier.listen(session_id);
+        let db = self.db.clone();
+        let max_events = self.config.events.max_events_max;
+        let session_id = session_id.clone();
+        let node_id = id.identity;
+        let fetch = move |cursor: Option<i32>| {
+            let db = db.clone();
+            let session_id = session_id.clone();
+            async move {
+                db.as_dao::<AgreementEventsDao>()
+                    .select_after_id(&node_id, &session_id, max_events, cursor)
+                    .await
+                    .map_err(|e| AgreementEventsError::Internal(e.to_string()))
+            }
+        };
+
+        let pending = fetch(cursor).await?;
+        Ok(event_stream(
+            listener,
+            self.config.events.stream_keep_alive_interval,
+            cursor,
+            pending,
+            |event: &AgreementEvent| event.id,
+            fetch,
+        ))
+    }
+
     pub async fn get_proposal(
         &self,
         subs_id: Option<&SubscriptionId>,
@@ -314,12 +449,28 @@ impl CommonBroker {
             .ok_or(GetProposalError::NotFound(id.clone(), subs_id.cloned()))?)
     }
 
+    /// Returns Proposal only to identity, that owns subscription of its negotiation.
+    pub async fn get_owned_proposal(
+        &self,
+        subs_id: Option<&SubscriptionId>,
+        id: &ProposalId,
+        caller: &Identity,
+    ) -> Result<Proposal, GetProposalError> {
+        let proposal = self.get_proposal(subs_id, id).await?;
+        if proposal.negotiation.owner_id() != caller.identity {
+            // Don't leak information, that Proposal exists.
+            return Err(GetProposalError::NotFound(id.clone(), subs_id.cloned()));
+        }
+        Ok(proposal)
+    }
+
     pub async fn get_client_proposal(
         &self,
         subscription_id: Option<&SubscriptionId>,
         id: &ProposalId,
+        caller: &Identity,
     ) -> Result<ClientProposal, GetProposalError> {
-        self.get_proposal(subscription_id, id)
+        self.get_owned_proposal(subscription_id, id, caller)
             .await
             .and_then(|proposal| {
                 proposal
@@ -328,6 +479,55 @@ impl CommonBroker {
             })
     }
 
+    /// Chain of counter-proposals leading to Proposal, starting from initial one.
+    pub async fn negotiation_history(
+        &self,
+        subs_id: Option<&SubscriptionId>,
+        id: &ProposalId,
+        caller: &Identity,
+    ) -> Result<Vec<NegotiationStep>, GetProposalError> {
+        // Validates, that Proposal belongs to subscription of caller.
+        self.get_owned_proposal(subs_id, id, caller).await?;
+
+        let chain = self
+            .db
+            .as_dao::<ProposalDao>()
+            .get_proposal_chain(id)
+            .await
+            .map_err(|e| GetProposalError::Internal(id.clone(), subs_id.cloned(), e.to_string()))?;
+
+        // Proposals in chain alternate between parties, so each one is compared
+        // with previous Proposal of the same issuer.
+        chain
+            .iter()
+            .enumerate()
+            .map(|(idx, proposal)| {
+                let previous = chain[..idx]
+                    .iter()
+                    .rev()
+                    .find(|previous| previous.body.issuer == proposal.body.issuer);
+                proposal.to_negotiation_step(previous).map_err(|e| {
+                    GetProposalError::Internal(id.clone(), subs_id.cloned(), e.to_string())
+                })
+            })
+            .collect()
+    }
+
+    /// Negotiation, that ended with Agreement. Final Proposal
+    /// of the chain is Offer Proposal promoted to Agreement.
+    pub async fn agreement_negotiation(
+        &self,
+        id: &Identity,
+        client_agreement_id: &str,
+    ) -> Result<Vec<NegotiationStep>, AgreementError> {
+        let agreement = self
+            .select_agreement_by_node(id, client_agreement_id)
+            .await?;
+        self.negotiation_history(None, &agreement.offer_proposal_id, id)
+            .await
+            .map_err(|e| AgreementError::Internal(e.to_string()))
+    }
+
     // Called locally via REST
     pub async fn terminate_agreement(
         &self,
@@ -337,11 +537,7 @@ impl CommonBroker {
     ) -> Result<(), AgreementError> {
         let dao = self.db.as_dao::<AgreementDao>();
         let agreement = match dao
-            .select_by_node(
-                &client_agreement_id,
-                id.identity.clone(),
-                Utc::now().naive_utc(),
-            )
+            .select_by_node(&client_agreement_id, id.identity.clone(), self.store.now())
             .await
             .map_err(|e| AgreementError::Get(client_agreement_id.clone(), e))?
         {
@@ -358,7 +554,7 @@ impl CommonBroker {
 
             validate_transition(&agreement, AgreementState::Terminated)?;
 
-            let timestamp = Utc::now().naive_utc();
+            let timestamp = self.store.now();
             protocol_common::propagate_terminate_agreement(
                 &agreement,
                 reason.clone(),
@@ -438,7 +634,7 @@ impl CommonBroker {
             let _hold = self.agreement_lock.lock(&agreement_id).await;
 
             let agreement = dao
-                .select(&agreement_id, None, Utc::now().naive_utc())
+                .select(&agreement_id, None, self.store.now())
                 .await
                 .map_err(|_e| RemoteAgreementError::NotFound(agreement_id.clone()))?
                 .ok_or(RemoteAgreementError::NotFound(agreement_id.clone()))?;
@@ -459,7 +655,7 @@ impl CommonBroker {
                 &agreement_id,
                 msg.reason.clone(),
                 caller_role,
-                &msg.termination_ts,
+                &self.store.now(),
             )
             .await
             .map_err(|e| {
@@ -685,6 +881,16 @@ impl CommonBroker {
             Err(e)?;
         }
 
+        // Our identity could block the other party, after negotiations started.
+        if !self.store.policies.permits_pair(
+            &proposal.negotiation.provider_id,
+            &proposal.negotiation.requestor_id,
+        ) {
+            let e = ProposalValidationError::Blocked(proposal.body.id.clone());
+            log::info!("{}", e);
+            Err(e)?;
+        }
+
         // check Offer
         self.store.get_offer(&proposal.negotiation.offer_id).await?;
 
@@ -698,6 +904,44 @@ impl CommonBroker {
         Ok(())
     }
 
+    /// Signs canonical Agreement terms with `signer` identity.
+    /// Returns hex encoded signature, that will be stored in Agreement.
+    pub async fn sign_agreement(
+        &self,
+        agreement: &Agreement,
+        signer: NodeId,
+    ) -> Result<String, AgreementError> {
+        let sign_error = |e: String| AgreementError::Sign(agreement.id.clone(), e);
+        let payload = agreement
+            .signed_payload()
+            .map_err(|e| sign_error(e.to_string()))?;
+        let signature = self
+            .identity
+            .sign(signer, payload)
+            .await
+            .map_err(|e| sign_error(e.to_string()))?;
+        Ok(hex::encode(signature))
+    }
+
+    /// Checks, that `signature` of Agreement terms received from the other party
+    /// was made by `signer`. Unsigned Agreements are accepted only in compatibility mode.
+    pub fn verify_agreement_signature(
+        &self,
+        agreement: &Agreement,
+        signature: &str,
+        signer: &NodeId,
+    ) -> Result<(), String> {
+        if signature == UNSIGNED_AGREEMENT && self.config.negotiation.accept_unsigned_agreements {
+            log::warn!(
+                "Accepting unsigned Agreement [{}] from [{}] using previous protocol version.",
+                agreement.id,
+                signer
+            );
+            return Ok(());
+        }
+        agreement.verify_signature(signature, signer)
+    }
+
     pub async fn notify_agreement(&self, agreement: &Agreement) {
         let session_notifier = &self.session_notifier;
 
@@ -754,6 +998,493 @@ impl CommonBroker {
             .await?;
         Ok(())
     }
+
+    /// Protocol API passing amendment messages from the other party to this broker.
+    pub fn amendment_api(&self, owner: Owner) -> AmendmentApi {
+        let broker_proposed = self.clone();
+        let broker_approved = self.clone();
+        let broker_rejected = self.clone();
+
+        AmendmentApi::new(
+            owner,
+            move |caller: String, msg: AmendmentProposed| {
+                broker_proposed.clone().on_amendment_proposed(msg, caller)
+            },
+            move |caller: String, msg: AmendmentApproved| {
+                broker_approved.clone().on_amendment_approved(msg, caller)
+            },
+            move |caller: String, msg: AmendmentRejected| {
+                broker_rejected.clone().on_amendment_rejected(msg, caller)
+            },
+        )
+    }
+
+    async fn select_agreement_by_node(
+        &self,
+        id: &Identity,
+        client_agreement_id: &str,
+    ) -> Result<Agreement, AgreementError> {
+        self.db
+            .as_dao::<AgreementDao>()
+            .select_by_node(client_agreement_id, id.identity.clone(), self.store.now())
+            .await
+            .map_err(|e| AgreementError::Get(client_agreement_id.to_string(), e))?
+            .ok_or_else(|| AgreementError::NotFound(client_agreement_id.to_string()))
+    }
+
+    // Called locally via REST
+    pub async fn propose_amendment(
+        &self,
+        id: Identity,
+        client_agreement_id: String,
+        proposal: AmendmentProposal,
+    ) -> Result<ClientAmendment, AmendmentError> {
+        let agreement_id = self
+            .select_agreement_by_node(&id, &client_agreement_id)
+            .await?
+            .id;
+        let dao = self.db.as_dao::<AmendmentDao>();
+
+        // Proposing consists of storing amendment and sending message to other party.
+        // Other party could propose amendment at the same time and its proposal would
+        // wait for our lock, so the lock isn't held during the remote call. Amendment is
+        // stored first, so that concurrent proposals conflict on both sides and approval
+        // can't come before we know about our own amendment.
+        let (agreement, amendment) = {
+            let _hold = self.agreement_lock.lock(&agreement_id).await;
+            let agreement = self
+                .select_agreement_by_node(&id, &client_agreement_id)
+                .await?;
+
+            if agreement.state != AgreementState::Approved {
+                Err(AmendmentDaoError::InvalidState(
+                    agreement.id.clone(),
+                    agreement.state,
+                ))?
+            }
+            if let Some(pending) = dao.pending(&agreement.id).await? {
+                Err(AmendmentDaoError::Pending(
+                    agreement.id.clone(),
+                    pending.version,
+                ))?
+            }
+
+            let mut amendment = Amendment::new(
+                &agreement,
+                agreement.id.owner(),
+                &proposal.offer_properties,
+                &proposal.demand_properties,
+                self.store.now(),
+            )
+            .map_err(|e| AmendmentError::InvalidProperties(agreement.id.clone(), e))?;
+            amendment.proposed_signature = self
+                .sign_agreement(&amendment.apply(&agreement), id.identity)
+                .await?;
+
+            let amendment = dao.propose(amendment).await?;
+            (agreement, amendment)
+        };
+
+        if let Err(e) = protocol_amendment::propose_amendment(&agreement, &amendment).await {
+            // Other party could have resolved amendment in the meantime (if it received it
+            // despite the error), so it is withdrawn only if it is still pending.
+            let _hold = self.agreement_lock.lock(&agreement.id).await;
+            dao.withdraw(&agreement.id, amendment.version)
+                .await
+                .map_err(|e| {
+                    log::warn!(
+                        "Failed to withdraw amendment {} of Agreement [{}]. Error: {}",
+                        amendment.version,
+                        &agreement.id,
+                        e
+                    )
+                })
+                .ok();
+            Err(e)?
+        }
+
+        log::info!(
+            "{:?} {} proposed amendment {} of Agreement [{}].",
+            agreement.id.owner(),
+            &id.display(),
+            amendment.version,
+            &agreement.id,
+        );
+        amendment
+            .into_client()
+            .map_err(|e| AmendmentError::Internal(agreement.id, e.to_string()))
+    }
+
+    // Called locally via REST
+    pub async fn approve_amendment(
+        &self,
+        id: Identity,
+        client_agreement_id: String,
+        version: u32,
+    ) -> Result<ClientAmendment, AmendmentError> {
+        let agreement = self
+            .select_agreement_by_node(&id, &client_agreement_id)
+            .await?;
+        let dao = self.db.as_dao::<AmendmentDao>();
+        let version = version as i32;
+
+        let amendment = {
+            let _hold = self.agreement_lock.lock(&agreement.id).await;
+
+            let amendment = self.pending_amendment_of_peer(&agreement, version).await?;
+            let signature = self
+                .sign_agreement(&amendment.apply(&agreement), id.identity)
+                .await?;
+            let timestamp = self.store.now();
+
+            protocol_amendment::approve_amendment(
+                &agreement,
+                version,
+                signature.clone(),
+                timestamp,
+            )
+            .await?;
+            dao.approve(&agreement.id, version, &signature, &timestamp)
+                .await?
+                .0
+        };
+
+        log::info!(
+            "{:?} {} approved amendment {} of Agreement [{}].",
+            agreement.id.owner(),
+            &id.display(),
+            version,
+            &agreement.id,
+        );
+        amendment
+            .into_client()
+            .map_err(|e| AmendmentError::Internal(agreement.id, e.to_string()))
+    }
+
+    // Called locally via REST
+    pub async fn reject_amendment(
+        &self,
+        id: Identity,
+        client_agreement_id: String,
+        version: u32,
+        reason: Option<Reason>,
+    ) -> Result<ClientAmendment, AmendmentError> {
+        let agreement = self
+            .select_agreement_by_node(&id, &client_agreement_id)
+            .await?;
+        let dao = self.db.as_dao::<AmendmentDao>();
+        let version = version as i32;
+
+        let amendment = {
+            let _hold = self.agreement_lock.lock(&agreement.id).await;
+
+            self.pending_amendment_of_peer(&agreement, version).await?;
+            let timestamp = self.store.now();
+
+            protocol_amendment::reject_amendment(&agreement, version, reason.clone(), timestamp)
+                .await?;
+            dao.reject(&agreement.id, version, reason.clone(), &timestamp)
+                .await?
+        };
+
+        log::info!(
+            "{:?} {} rejected amendment {} of Agreement [{}]. Reason: {}",
+            agreement.id.owner(),
+            &id.display(),
+            version,
+            &agreement.id,
+            reason.display(),
+        );
+        amendment
+            .into_client()
+            .map_err(|e| AmendmentError::Internal(agreement.id, e.to_string()))
+    }
+
+    // Called locally via REST
+    pub async fn list_amendments(
+        &self,
+        id: Identity,
+        client_agreement_id: String,
+    ) -> Result<Vec<ClientAmendment>, AmendmentError> {
+        let agreement = self
+            .select_agreement_by_node(&id, &client_agreement_id)
+            .await?;
+
+        self.db
+            .as_dao::<AmendmentDao>()
+            .list(&agreement.id)
+            .await?
+            .into_iter()
+            .map(|amendment| {
+                amendment
+                    .into_client()
+                    .map_err(|e| AmendmentError::Internal(agreement.id.clone(), e.to_string()))
+            })
+            .collect()
+    }
+
+    // Called locally via REST
+    pub async fn list_amendment_events(
+        &self,
+        id: Identity,
+        client_agreement_id: String,
+    ) -> Result<Vec<ClientAmendmentEvent>, AmendmentError> {
+        let agreement = self
+            .select_agreement_by_node(&id, &client_agreement_id)
+            .await?;
+
+        Ok(self
+            .db
+            .as_dao::<AgreementEventsDao>()
+            .select_amendments_for_agreement(&agreement.id)
+            .await
+            .map_err(|e| AmendmentError::Internal(agreement.id.clone(), e.to_string()))?
+            .into_iter()
+            .filter_map(|event| event.into_amendment_client())
+            .collect())
+    }
+
+    /// Only the other party can resolve amendment and only as long as it waits for approval.
+    async fn pending_amendment_of_peer(
+        &self,
+        agreement: &Agreement,
+        version: i32,
+    ) -> Result<Amendment, AmendmentError> {
+        let amendment = self
+            .db
+            .as_dao::<AmendmentDao>()
+            .select(&agreement.id, version)
+            .await?
+            .ok_or_else(|| AmendmentError::NotFound(agreement.id.clone(), version))?;
+
+        if amendment.issuer == agreement.id.owner() {
+            Err(AmendmentError::OwnAmendment(agreement.id.clone(), version))?
+        }
+        if amendment.state != AmendmentState::Proposed {
+            Err(AmendmentDaoError::AlreadyResolved(
+                agreement.id.clone(),
+                version,
+                amendment.state,
+            ))?
+        }
+        Ok(amendment)
+    }
+
+    // Called remotely via GSB
+    pub async fn on_amendment_proposed(
+        self,
+        msg: AmendmentProposed,
+        caller: String,
+    ) -> Result<(), AmendmentProtocolError> {
+        let caller_id = CommonBroker::parse_caller(&caller)?;
+        Ok(self.amendment_proposed(msg, caller_id).await?)
+    }
+
+    async fn amendment_proposed(
+        &self,
+        msg: AmendmentProposed,
+        caller_id: NodeId,
+    ) -> Result<(), RemoteAmendmentError> {
+        let agreement_id = msg.agreement_id.clone();
+        {
+            let _hold = self.agreement_lock.lock(&agreement_id).await;
+            let agreement = self.authorize_amendment(&agreement_id, caller_id).await?;
+
+            let amendment = Amendment {
+                agreement_id: agreement_id.clone(),
+                version: msg.version,
+                issuer: agreement.id.owner().swap(),
+                state: AmendmentState::Proposed,
+                offer_properties: msg.offer_properties,
+                demand_properties: msg.demand_properties,
+                creation_ts: msg.creation_ts,
+                resolved_ts: None,
+                reason: None,
+                proposed_signature: msg.signature,
+                approved_signature: None,
+            };
+            amendment
+                .apply(&agreement)
+                .verify_signature(&amendment.proposed_signature, &caller_id)
+                .map_err(|e| RemoteAmendmentError::InvalidSignature(agreement_id.clone(), e))?;
+
+            self.db
+                .as_dao::<AmendmentDao>()
+                .propose(amendment)
+                .await
+                .map_err(|e| remote_amendment_error(&agreement_id, e))?;
+        }
+
+        log::info!(
+            "Received amendment {} of Agreement [{}] from [{}].",
+            msg.version,
+            &agreement_id,
+            &caller_id,
+        );
+        Ok(())
+    }
+
+    // Called remotely via GSB
+    pub async fn on_amendment_approved(
+        self,
+        msg: AmendmentApproved,
+        caller: String,
+    ) -> Result<(), AmendmentProtocolError> {
+        let caller_id = CommonBroker::parse_caller(&caller)?;
+        Ok(self.amendment_approved(msg, caller_id).await?)
+    }
+
+    async fn amendment_approved(
+        &self,
+        msg: AmendmentApproved,
+        caller_id: NodeId,
+    ) -> Result<(), RemoteAmendmentError> {
+        let agreement_id = msg.agreement_id.clone();
+        {
+            let _hold = self.agreement_lock.lock(&agreement_id).await;
+            let agreement = self.authorize_amendment(&agreement_id, caller_id).await?;
+            let amendment = self.own_amendment(&agreement, msg.version).await?;
+
+            amendment
+                .apply(&agreement)
+                .verify_signature(&msg.signature, &caller_id)
+                .map_err(|e| RemoteAmendmentError::InvalidSignature(agreement_id.clone(), e))?;
+
+            self.db
+                .as_dao::<AmendmentDao>()
+                .approve(&agreement_id, msg.version, &msg.signature, &msg.approved_ts)
+                .await
+                .map_err(|e| remote_amendment_error(&agreement_id, e))?;
+        }
+
+        log::info!(
+            "Amendment {} of Agreement [{}] approved by [{}].",
+            msg.version,
+            &agreement_id,
+            &caller_id,
+        );
+        Ok(())
+    }
+
+    // Called remotely via GSB
+    pub async fn on_amendment_rejected(
+        self,
+        msg: AmendmentRejected,
+        caller: String,
+    ) -> Result<(), AmendmentProtocolError> {
+        let caller_id = CommonBroker::parse_caller(&caller)?;
+        Ok(self.amendment_rejected(msg, caller_id).await?)
+    }
+
+    async fn amendment_rejected(
+        &self,
+        msg: AmendmentRejected,
+        caller_id: NodeId,
+    ) -> Result<(), RemoteAmendmentError> {
+        let agreement_id = msg.agreement_id.clone();
+        {
+            let _hold = self.agreement_lock.lock(&agreement_id).await;
+            let agreement = self.authorize_amendment(&agreement_id, caller_id).await?;
+            self.own_amendment(&agreement, msg.version).await?;
+
+            self.db
+                .as_dao::<AmendmentDao>()
+                .reject(
+                    &agreement_id,
+                    msg.version,
+                    msg.reason.clone(),
+                    &msg.rejection_ts,
+                )
+                .await
+                .map_err(|e| remote_amendment_error(&agreement_id, e))?;
+        }
+
+        log::info!(
+            "Amendment {} of Agreement [{}] rejected by [{}]. Reason: {}",
+            msg.version,
+            &agreement_id,
+            &caller_id,
+            msg.reason.display(),
+        );
+        Ok(())
+    }
+
+    /// Returns Agreement, if caller is the other party of it.
+    async fn authorize_amendment(
+        &self,
+        agreement_id: &AgreementId,
+        caller_id: NodeId,
+    ) -> Result<Agreement, RemoteAmendmentError> {
+        let agreement = self
+            .db
+            .as_dao::<AgreementDao>()
+            .select(agreement_id, None, self.store.now())
+            .await
+            .map_err(|_e| RemoteAmendmentError::NotFound(agreement_id.clone()))?
+            .ok_or(RemoteAmendmentError::NotFound(agreement_id.clone()))?;
+
+        let auth_id = match agreement.id.owner() {
+            Owner::Provider => agreement.requestor_id,
+            Owner::Requestor => agreement.provider_id,
+        };
+
+        if auth_id != caller_id {
+            // Don't reveal, that we know this Agreement id.
+            Err(RemoteAmendmentError::NotFound(agreement_id.clone()))?
+        }
+        Ok(agreement)
+    }
+
+    /// Amendment proposed by us, that the other party is resolving.
+    async fn own_amendment(
+        &self,
+        agreement: &Agreement,
+        version: i32,
+    ) -> Result<Amendment, RemoteAmendmentError> {
+        let amendment = self
+            .db
+            .as_dao::<AmendmentDao>()
+            .select(&agreement.id, version)
+            .await
+            .map_err(|e| remote_amendment_error(&agreement.id, e))?
+            .ok_or_else(|| {
+                RemoteAmendmentError::AmendmentNotFound(agreement.id.clone(), version)
+            })?;
+
+        if amendment.issuer != agreement.id.owner() {
+            Err(RemoteAmendmentError::Conflict(
+                agreement.id.clone(),
+                format!("amendment {} wasn't proposed by us", version),
+            ))?
+        }
+        Ok(amendment)
+    }
+}
+
+fn remote_amendment_error(
+    agreement_id: &AgreementId,
+    e: AmendmentDaoError,
+) -> RemoteAmendmentError {
+    match e {
+        AmendmentDaoError::AgreementNotFound(id) => RemoteAmendmentError::NotFound(id),
+        AmendmentDaoError::NotFound(id, version) => {
+            RemoteAmendmentError::AmendmentNotFound(id, version)
+        }
+        AmendmentDaoError::InvalidState(id, state) => RemoteAmendmentError::InvalidState(id, state),
+        AmendmentDaoError::Pending(..)
+        | AmendmentDaoError::InvalidVersion(..)
+        | AmendmentDaoError::AlreadyResolved(..) => {
+            RemoteAmendmentError::Conflict(agreement_id.clone(), e.to_string())
+        }
+        AmendmentDaoError::DbError(_) => {
+            log::warn!(
+                "Amendment of Agreement [{}] failed. Error: {}",
+                agreement_id,
+                e
+            );
+            RemoteAmendmentError::InternalError(agreement_id.clone())
+        }
+    }
 }
 
 pub fn validate_match(
@@ -3,9 +3,10 @@ use thiserror::Error;
 
 use ya_client::model::NodeId;
 
-use crate::db::dao::AgreementDaoError;
+use crate::db::dao::{AgreementDaoError, AmendmentDaoError};
 use crate::db::model::{
-    AgreementId, ProposalId, ProposalIdParseError, SubscriptionId, SubscriptionParseError,
+    AgreementId, AppSessionId, ProposalId, ProposalIdParseError, SubscriptionId,
+    SubscriptionParseError,
 };
 use crate::db::{
     dao::TakeEventsError,
@@ -13,10 +14,11 @@ use crate::db::{
     DbError,
 };
 use crate::matcher::error::{DemandError, QueryOfferError};
+use crate::negotiation::notifier::NotifierError;
 use crate::protocol::negotiation::error::{
-    AgreementProtocolError, CommitAgreementError, CounterProposalError as ProtocolProposalError,
-    GsbAgreementError, NegotiationApiInitError, ProposeAgreementError, RejectProposalError,
-    TerminateAgreementError,
+    AgreementProtocolError, AmendmentProtocolError, CommitAgreementError,
+    CounterProposalError as ProtocolProposalError, GsbAgreementError, NegotiationApiInitError,
+    ProposeAgreementError, RejectProposalError, TerminateAgreementError,
 };
 
 #[derive(Error, Debug)]
@@ -66,6 +68,8 @@ pub enum AgreementError {
     Get(String, AgreementDaoError),
     #[error("Agreement [{0}]. Error: {1}")]
     UpdateState(AgreementId, AgreementDaoError),
+    #[error("Failed to list Agreements. Error: {0}")]
+    List(AgreementDaoError),
     #[error("Invalid Agreement id. {0}")]
     InvalidId(#[from] ProposalIdParseError),
     #[error(transparent)]
@@ -78,10 +82,30 @@ pub enum AgreementError {
     ProtocolTerminate(#[from] TerminateAgreementError),
     #[error("Protocol error while committing: {0}")]
     ProtocolCommit(#[from] CommitAgreementError),
+    #[error("Failed to sign Agreement [{0}]. Error: {1}")]
+    Sign(AgreementId, String),
     #[error("Internal error: {0}")]
     Internal(String),
 }
 
+#[derive(Error, Debug)]
+pub enum AmendmentError {
+    #[error("Amendment {1} of Agreement [{0}] not found.")]
+    NotFound(AgreementId, i32),
+    #[error("Invalid amendment of Agreement [{0}]. {1}")]
+    InvalidProperties(AgreementId, String),
+    #[error("Can't resolve own amendment {1} of Agreement [{0}].")]
+    OwnAmendment(AgreementId, i32),
+    #[error(transparent)]
+    Agreement(#[from] AgreementError),
+    #[error("Amendment failed. {0}")]
+    Dao(#[from] AmendmentDaoError),
+    #[error("Protocol error while amending: {0}")]
+    Protocol(#[from] AmendmentProtocolError),
+    #[error("Failed to convert amendment of Agreement [{0}]. Error: {1}")]
+    Internal(AgreementId, String),
+}
+
 #[derive(Error, Debug)]
 pub enum WaitForApprovalError {
     #[error("Agreement [{0}] not found.")]
@@ -144,6 +168,8 @@ pub enum ProposalValidationError {
     OwnProposal(ProposalId),
     #[error("Unauthorized operation attempt on Proposal [{0}] from [{1}].")]
     Unauthorized(ProposalId, NodeId),
+    #[error("Negotiation of Proposal [{0}] blocked by node policy.")]
+    Blocked(ProposalId),
     #[error("Internal error processing Proposal: {0}.")]
     Internal(String),
 }
@@ -187,6 +213,21 @@ impl AgreementError {
     }
 }
 
+impl From<NotifierError<SubscriptionId>> for QueryEventsError {
+    fn from(e: NotifierError<SubscriptionId>) -> Self {
+        match e {
+            NotifierError::Unsubscribed(id) => TakeEventsError::NotFound(id).into(),
+            _ => QueryEventsError::Internal(e.to_string()),
+        }
+    }
+}
+
+impl From<NotifierError<AppSessionId>> for AgreementEventsError {
+    fn from(e: NotifierError<AppSessionId>) -> Self {
+        AgreementEventsError::Internal(e.to_string())
+    }
+}
+
 impl From<MatchValidationError> for ProposalError {
     fn from(e: MatchValidationError) -> Self {
         ProposalValidationError::NotMatching(e).into()
@@ -1,7 +1,8 @@
+use futures::{Future, Stream};
 use std::fmt::Debug;
 use std::time::{Duration, Instant};
 use thiserror::Error;
-use tokio::sync::broadcast::{channel, Receiver, Sender};
+use tokio::sync::broadcast::{channel, Receiver, RecvError, Sender};
 
 use crate::utils::display::{DisplayEnabler, EnableDisplay};
 
@@ -90,18 +91,22 @@ where
     for<'a> DisplayEnabler<'a, Type>: std::fmt::Display,
 {
     pub async fn wait_for_event(&mut self) -> Result<(), NotifierError<Type>> {
-        while let Ok(value) = self.receiver.recv().await {
-            match value {
-                Notification::<Type>::NewEvent(subscription_id) => {
+        loop {
+            match self.receiver.recv().await {
+                Ok(Notification::<Type>::NewEvent(subscription_id)) => {
                     if subscription_id == self.subscription_id {
                         return Ok(());
                     }
                 }
-                Notification::<Type>::StopEvents(subscription_id) => {
+                Ok(Notification::<Type>::StopEvents(subscription_id)) => {
                     if subscription_id == self.subscription_id {
                         return Err(NotifierError::Unsubscribed(subscription_id));
                     }
                 }
+                // Long living listeners can miss notifications. We don't know if any
+                // of them concerned our subscription, so caller must check for events.
+                Err(RecvError::Lagged(_)) => return Ok(()),
+                Err(RecvError::Closed) => break,
             }
         }
         Err(NotifierError::ChannelClosed(self.subscription_id.clone()))
@@ -130,3 +135,79 @@ where
         self.wait_for_event_with_timeout(timeout).await
     }
 }
+
+struct StreamState<Type, Event, Fetch>
+where
+    Type: Debug + PartialEq + Clone + EnableDisplay<Type> + 'static,
+    for<'a> DisplayEnabler<'a, Type>: std::fmt::Display,
+{
+    listener: EventNotifierListener<Type>,
+    cursor: Option<i32>,
+    pending: Vec<Event>,
+    fetch: Fetch,
+    finished: bool,
+}
+
+/// Turns polling of events stored in database into endless stream of event batches.
+/// `fetch` returns events stored after given cursor, `cursor_of` extracts cursor
+/// from event. Empty batch is yielded every `keep_alive` without new events.
+/// Stream ends after first error or when subscription is unsubscribed.
+///
+/// Listener must be created before `pending` events were fetched, otherwise
+/// we could miss notifications about events stored in the meantime.
+pub fn event_stream<Type, Event, Error, Fetch, Fut>(
+    listener: EventNotifierListener<Type>,
+    keep_alive: Duration,
+    cursor: Option<i32>,
+    pending: Vec<Event>,
+    cursor_of: fn(&Event) -> i32,
+    fetch: Fetch,
+) -> impl Stream<Item = Result<Vec<Event>, Error>>
+where
+    Type: Debug + PartialEq + Clone + EnableDisplay<Type> + 'static,
+    for<'a> DisplayEnabler<'a, Type>: std::fmt::Display,
+    Fetch: Fn(Option<i32>) -> Fut,
+    Fut: Future<Output = Result<Vec<Event>, Error>>,
+    Error: From<NotifierError<Type>>,
+{
+    let state = StreamState {
+        listener,
+        cursor,
+        pending,
+        fetch,
+        finished: false,
+    };
+
+    futures::stream::unfold(state, move |mut state| async move {
+        if state.finished {
+            return None;
+        }
+        loop {
+            let events = match state.pending.is_empty() {
+                false => std::mem::replace(&mut state.pending, vec![]),
+                true => match (state.fetch)(state.cursor).await {
+                    Ok(events) => events,
+                    Err(e) => {
+                        state.finished = true;
+                        return Some((Err(e), state));
+                    }
+                },
+            };
+
+            if let Some(last) = events.last() {
+                state.cursor = Some(cursor_of(last));
+                return Some((Ok(events), state));
+            }
+
+            match state.listener.wait_for_event_with_timeout(keep_alive).await {
+                Ok(()) => continue,
+                Err(NotifierError::Timeout(_)) => return Some((Ok(vec![]), state)),
+                Err(NotifierError::Unsubscribed(_)) => return None,
+                Err(e) => {
+                    state.finished = true;
+                    return Some((Err(e.into()), state));
+                }
+            }
+        }
+    })
+}
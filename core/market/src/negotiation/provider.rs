@@ -1,5 +1,4 @@
-use chrono::Utc;
-use futures::stream::StreamExt;
+use futures::stream::{Stream, StreamExt};
 use metrics::counter;
 use std::sync::Arc;
 
@@ -15,13 +14,16 @@ use crate::db::{
     model::{Issuer, Offer, Owner, Proposal, ProposalId, SubscriptionId},
 };
 use crate::matcher::store::SubscriptionStore;
-use crate::protocol::negotiation::{error::*, messages::*, provider::NegotiationApi};
+use crate::protocol::negotiation::{
+    amendment::AmendmentApi, error::*, messages::*, provider::NegotiationApi,
+};
 
 use super::common::CommonBroker;
 use super::error::*;
 use super::notifier::EventNotifier;
 use crate::config::Config;
 use crate::db::dao::AgreementDaoError;
+use crate::identity::IdentityApi;
 use crate::negotiation::common::validate_transition;
 use crate::negotiation::notifier::NotifierError;
 use crate::utils::display::EnableDisplay;
@@ -40,6 +42,7 @@ pub enum ApprovalResult {
 pub struct ProviderBroker {
     pub(crate) common: CommonBroker,
     api: NegotiationApi,
+    amendment_api: AmendmentApi,
 }
 
 impl ProviderBroker {
@@ -48,8 +51,9 @@ impl ProviderBroker {
         store: SubscriptionStore,
         session_notifier: EventNotifier<AppSessionId>,
         config: Arc<Config>,
+        identity: Arc<dyn IdentityApi>,
     ) -> Result<ProviderBroker, NegotiationInitError> {
-        let broker = CommonBroker::new(db.clone(), store, session_notifier, config);
+        let broker = CommonBroker::new(db.clone(), store, session_notifier, config, identity);
 
         let broker1 = broker.clone();
         let broker2 = broker.clone();
@@ -110,6 +114,7 @@ impl ProviderBroker {
 
         Ok(ProviderBroker {
             api,
+            amendment_api: broker.amendment_api(Owner::Provider),
             common: broker,
         })
     }
@@ -119,7 +124,9 @@ impl ProviderBroker {
         public_prefix: &str,
         local_prefix: &str,
     ) -> Result<(), NegotiationInitError> {
-        Ok(self.api.bind_gsb(public_prefix, local_prefix).await?)
+        self.api.bind_gsb(public_prefix, local_prefix).await?;
+        self.amendment_api.bind_gsb(public_prefix).await?;
+        Ok(())
     }
 
     pub async fn subscribe_offer(&self, _offer: &Offer) -> Result<(), NegotiationError> {
@@ -218,6 +225,42 @@ impl ProviderBroker {
         Ok(events)
     }
 
+    /// Streams events of Offer paired with cursors, that allow to resume the stream.
+    pub async fn stream_events(
+        &self,
+        offer_id: &SubscriptionId,
+        id: &Identity,
+        cursor: Option<i32>,
+    ) -> Result<
+        impl Stream<Item = Result<Vec<(i32, ProviderEvent)>, QueryEventsError>>,
+        QueryEventsError,
+    > {
+        let db = self.common.db.clone();
+        let events = self
+            .common
+            .stream_events(offer_id, id, cursor, Owner::Provider)
+            .await?;
+
+        Ok(events.then(move |batch| {
+            let db = db.clone();
+            async move {
+                let mut client_events = vec![];
+                for event in batch? {
+                    let cursor = event.id;
+                    match event.into_client_provider_event(&db).await {
+                        Ok(event) => client_events.push((cursor, event)),
+                        Err(e) => log::error!("Error converting event to client type: {}", e),
+                    }
+                }
+                counter!(
+                    "market.events.provider.streamed",
+                    client_events.len() as u64
+                );
+                Ok::<_, QueryEventsError>(client_events)
+            }
+        }))
+    }
+
     pub async fn approve_agreement(
         &self,
         id: Identity,
@@ -232,7 +275,7 @@ impl ProviderBroker {
             let _hold = self.common.agreement_lock.lock(&agreement_id).await;
 
             let agreement = dao
-                .select(agreement_id, Some(id.identity), Utc::now().naive_utc())
+                .select(agreement_id, Some(id.identity), self.common.store.now())
                 .await
                 .map_err(|e| AgreementError::Get(agreement_id.to_string(), e))?
                 .ok_or(AgreementError::NotFound(agreement_id.to_string()))?;
@@ -243,9 +286,11 @@ impl ProviderBroker {
 
             validate_transition(&agreement, AgreementState::Approving)?;
 
-            // TODO: Sign Agreement.
-            let signature = "NoSignature".to_string();
-            let timestamp = Utc::now().naive_utc();
+            let signature = self
+                .common
+                .sign_agreement(&agreement, agreement.provider_id)
+                .await?;
+            let timestamp = self.common.store.now();
 
             let agreement = dao
                 .approving(&agreement.id, &app_session_id, &signature, &timestamp)
@@ -325,7 +370,7 @@ impl ProviderBroker {
             let _hold = self.common.agreement_lock.lock(&agreement_id).await;
 
             let agreement = dao
-                .select(agreement_id, None, Utc::now().naive_utc())
+                .select(agreement_id, None, self.common.store.now())
                 .await
                 .map_err(|e| AgreementError::Get(agreement_id.to_string(), e))?
                 .ok_or(AgreementError::Internal(format!(
@@ -356,14 +401,14 @@ impl ProviderBroker {
             let _hold = self.common.agreement_lock.lock(&agreement_id).await;
 
             let agreement = dao
-                .select(agreement_id, Some(id.identity), Utc::now().naive_utc())
+                .select(agreement_id, Some(id.identity), self.common.store.now())
                 .await
                 .map_err(|e| AgreementError::Get(agreement_id.to_string(), e))?
                 .ok_or(AgreementError::NotFound(agreement_id.to_string()))?;
 
             validate_transition(&agreement, AgreementState::Rejected)?;
 
-            let timestamp = Utc::now().naive_utc();
+            let timestamp = self.common.store.now();
             self.api
                 .reject_agreement(&agreement, reason.clone(), timestamp.clone())
                 .await?;
@@ -408,7 +453,7 @@ async fn agreement_committed(
         // Note: we still validate caller here, because we can't be sure, that we were called
         // by the same Requestor.
         let agreement = dao
-            .select(&msg.agreement_id, None, Utc::now().naive_utc())
+            .select(&msg.agreement_id, None, broker.store.now())
             .await
             .map_err(|e| RemoteCommitAgreementError::Unexpected {
                 public_msg: "Internal Error getting Agreement".to_string(),
@@ -442,9 +487,12 @@ async fn agreement_committed(
         }
         .log_err()?;
 
-        // TODO: Validate committed signature from message.
+        broker
+            .verify_agreement_signature(&agreement, &msg.signature, &agreement.requestor_id)
+            .map_err(RemoteCommitAgreementError::InvalidSignature)
+            .log_err()?;
 
-        dao.approve(&msg.agreement_id, &msg.signature)
+        dao.approve(&msg.agreement_id, &msg.signature, &broker.store.now())
             .await
             .map_err(|e| RemoteCommitAgreementError::Unexpected {
                 public_msg: "Failed to update Agreement state to `Approved`.".to_string(),
@@ -498,6 +546,13 @@ async fn initial_proposal(
 
     let proposal = Proposal::new_provider(&msg.demand_id, caller_id, offer);
     let proposal_id = proposal.body.id.clone();
+    if !store
+        .policies
+        .permits(&proposal.negotiation.provider_id, &caller_id)
+    {
+        Err(ProposalValidationError::Blocked(proposal_id.clone()))?;
+    }
+
     let proposal = db
         .as_dao::<ProposalDao>()
         .save_initial_proposal(proposal)
@@ -564,9 +619,7 @@ async fn agreement_received(
         Owner::Provider,
     );
     agreement.state = AgreementState::Pending;
-    agreement.proposed_signature = Some(msg.signature);
-
-    // TODO: Validate signature.
+    agreement.proposed_signature = Some(msg.signature.clone());
 
     // Check if we generated the same id, as Requestor sent us. If not, reject
     // it, because wrong generated ids could be not unique.
@@ -575,6 +628,11 @@ async fn agreement_received(
         Err(RemoteProposeAgreementError::InvalidId(id.clone()))?
     }
 
+    // Requestor must have signed the same terms, that we are going to approve.
+    broker
+        .verify_agreement_signature(&agreement, &msg.signature, &agreement.requestor_id)
+        .map_err(|e| RemoteProposeAgreementError::InvalidSignature(id.clone(), e))?;
+
     // This is creation of Agreement, so lock is not needed yet.
     let agreement = broker
         .db
@@ -636,7 +694,7 @@ async fn agreement_cancelled(
         let _hold = broker.agreement_lock.lock(&msg.agreement_id).await;
 
         let agreement = dao
-            .select(&msg.agreement_id, None, Utc::now().naive_utc())
+            .select(&msg.agreement_id, None, broker.store.now())
             .await
             .log_err()
             .map_err(|_e| RemoteAgreementError::NotFound(msg.agreement_id.clone()))?
@@ -651,7 +709,7 @@ async fn agreement_cancelled(
             RemoteAgreementError::InvalidState(agreement.id.clone(), agreement.state.clone())
         })?;
 
-        dao.cancel(&agreement.id, msg.reason.clone(), &msg.cancellation_ts)
+        dao.cancel(&agreement.id, msg.reason.clone(), &broker.store.now())
             .await
             .log_err()
             .map_err(|e| match e {
@@ -1,5 +1,5 @@
 use chrono::{DateTime, Utc};
-use futures::stream::StreamExt;
+use futures::stream::{Stream, StreamExt};
 use metrics::counter;
 use std::sync::Arc;
 use std::time::Duration;
@@ -17,11 +17,14 @@ use crate::db::{
     model::{Demand, Issuer, Owner, ProposalId, SubscriptionId},
 };
 use crate::matcher::{store::SubscriptionStore, RawProposal};
-use crate::protocol::negotiation::{error::*, messages::*, requestor::NegotiationApi};
+use crate::protocol::negotiation::{
+    amendment::AmendmentApi, error::*, messages::*, requestor::NegotiationApi,
+};
 
 use super::{common::*, error::*, notifier::NotifierError, EventNotifier};
 use crate::config::Config;
 use crate::db::dao::AgreementEventsDao;
+use crate::identity::IdentityApi;
 use crate::utils::display::EnableDisplay;
 
 #[derive(Clone, derive_more::Display, Debug, PartialEq)]
@@ -38,6 +41,7 @@ pub enum ApprovalStatus {
 pub struct RequestorBroker {
     pub(crate) common: CommonBroker,
     api: NegotiationApi,
+    amendment_api: AmendmentApi,
 }
 
 impl RequestorBroker {
@@ -47,8 +51,9 @@ impl RequestorBroker {
         proposal_receiver: UnboundedReceiver<RawProposal>,
         session_notifier: EventNotifier<AppSessionId>,
         config: Arc<Config>,
+        identity: Arc<dyn IdentityApi>,
     ) -> Result<RequestorBroker, NegotiationInitError> {
-        let broker = CommonBroker::new(db.clone(), store, session_notifier, config);
+        let broker = CommonBroker::new(db.clone(), store, session_notifier, config, identity);
 
         let broker1 = broker.clone();
         let broker2 = broker.clone();
@@ -82,6 +87,7 @@ impl RequestorBroker {
 
         let engine = RequestorBroker {
             api,
+            amendment_api: broker.amendment_api(Owner::Requestor),
             common: broker.clone(),
         };
 
@@ -116,6 +122,7 @@ impl RequestorBroker {
         local_prefix: &str,
     ) -> Result<(), NegotiationInitError> {
         self.api.bind_gsb(public_prefix, local_prefix).await?;
+        self.amendment_api.bind_gsb(public_prefix).await?;
         Ok(())
     }
 
@@ -220,6 +227,42 @@ impl RequestorBroker {
         Ok(events)
     }
 
+    /// Streams events of Demand paired with cursors, that allow to resume the stream.
+    pub async fn stream_events(
+        &self,
+        demand_id: &SubscriptionId,
+        id: &Identity,
+        cursor: Option<i32>,
+    ) -> Result<
+        impl Stream<Item = Result<Vec<(i32, RequestorEvent)>, QueryEventsError>>,
+        QueryEventsError,
+    > {
+        let db = self.common.db.clone();
+        let events = self
+            .common
+            .stream_events(demand_id, id, cursor, Owner::Requestor)
+            .await?;
+
+        Ok(events.then(move |batch| {
+            let db = db.clone();
+            async move {
+                let mut client_events = vec![];
+                for event in batch? {
+                    let cursor = event.id;
+                    match event.into_client_requestor_event(&db).await {
+                        Ok(event) => client_events.push((cursor, event)),
+                        Err(e) => log::error!("Error converting event to client type: {}", e),
+                    }
+                }
+                counter!(
+                    "market.events.requestor.streamed",
+                    client_events.len() as u64
+                );
+                Ok::<_, QueryEventsError>(client_events)
+            }
+        }))
+    }
+
     /// Initiates the Agreement handshake phase.
     ///
     /// Formulates an Agreement artifact from the Proposal indicated by the
@@ -302,14 +345,14 @@ impl RequestorBroker {
             let _hold = self.common.agreement_lock.lock(&agreement_id).await;
 
             let agreement = dao
-                .select(agreement_id, Some(id.identity), Utc::now().naive_utc())
+                .select(agreement_id, Some(id.identity), self.common.store.now())
                 .await
                 .map_err(|e| AgreementError::Get(agreement_id.to_string(), e))?
                 .ok_or(AgreementError::NotFound(agreement_id.to_string()))?;
 
             validate_transition(&agreement, AgreementState::Cancelled)?;
 
-            let timestamp = Utc::now().naive_utc();
+            let timestamp = self.common.store.now();
             self.api
                 .cancel_agreement(&agreement, reason.clone(), timestamp.clone())
                 .await?;
@@ -350,7 +393,7 @@ impl RequestorBroker {
                 .common
                 .db
                 .as_dao::<AgreementDao>()
-                .select(id, None, Utc::now().naive_utc())
+                .select(id, None, self.common.store.now())
                 .await
                 .map_err(|e| WaitForApprovalError::Get(id.clone(), e))?
                 .ok_or(WaitForApprovalError::NotFound(id.clone()))?;
@@ -406,7 +449,7 @@ impl RequestorBroker {
                 .select(
                     agreement_id,
                     Some(id.identity.clone()),
-                    Utc::now().naive_utc(),
+                    self.common.store.now(),
                 )
                 .await
                 .map_err(|e| AgreementError::Get(agreement_id.to_string(), e))?
@@ -417,8 +460,10 @@ impl RequestorBroker {
 
             validate_transition(&agreement, AgreementState::Pending)?;
 
-            // TODO: Sign Agreement.
-            let signature = "NoSignature".to_string();
+            let signature = self
+                .common
+                .sign_agreement(&agreement, agreement.requestor_id)
+                .await?;
             agreement.proposed_signature = Some(signature.clone());
 
             self.api.propose_agreement(&agreement).await?;
@@ -486,7 +531,7 @@ async fn agreement_approved(
         let _hold = broker.agreement_lock.lock(&msg.agreement_id).await;
 
         let agreement = dao
-            .select(&msg.agreement_id, None, Utc::now().naive_utc())
+            .select(&msg.agreement_id, None, broker.store.now())
             .await
             .map_err(|_e| RemoteAgreementError::NotFound(msg.agreement_id.clone()))?
             .ok_or(RemoteAgreementError::NotFound(msg.agreement_id.clone()))?;
@@ -504,16 +549,17 @@ async fn agreement_approved(
         //  error from database update, but know we want to escape early, because
         //  otherwise we can't response with this error to Provider.
         let margin = chrono::Duration::milliseconds(30);
-        if agreement.valid_to <= (Utc::now() + margin).naive_utc() {
+        if agreement.valid_to <= broker.store.now() + margin {
             return Err(RemoteAgreementError::Expired(agreement.id.clone()));
         }
 
-        // TODO: Validate agreement signature.
-        let signature = "NoSignature".to_string();
+        broker
+            .verify_agreement_signature(&agreement, &msg.signature, &agreement.provider_id)
+            .map_err(|e| RemoteAgreementError::InvalidSignature(agreement.id.clone(), e))?;
 
         // Note: session must be None, because either we already set this value in ConfirmAgreement,
         // or we purposely left it None.
-        dao.approving(&agreement.id, &None, &signature, &msg.approved_ts)
+        dao.approving(&agreement.id, &None, &msg.signature, &msg.approved_ts)
             .await
             .map_err(|err| match err {
                 AgreementDaoError::InvalidTransition { from, .. } => {
@@ -561,13 +607,14 @@ async fn commit_agreement(broker: CommonBroker, agreement_id: AgreementId) {
 
         let dao = broker.db.as_dao::<AgreementDao>();
         let mut agreement = dao
-            .select(&agreement_id, None, Utc::now().naive_utc())
+            .select(&agreement_id, None, broker.store.now())
             .await
             .map_err(|_e| AgreementError::NotFound(agreement_id.to_string()))?
             .ok_or(AgreementError::NotFound(agreement_id.to_string()))?;
 
-        // TODO: Sign Agreement.
-        let signature = "NoSignature".to_string();
+        let signature = broker
+            .sign_agreement(&agreement, agreement.requestor_id)
+            .await?;
         agreement.committed_signature = Some(signature.clone());
 
         // Note: This GSB call is racing with potential `cancel_agreement` call.
@@ -642,7 +689,7 @@ async fn agreement_rejected(
         let _hold = broker.agreement_lock.lock(&msg.agreement_id).await;
 
         let agreement = dao
-            .select(&msg.agreement_id, None, Utc::now().naive_utc())
+            .select(&msg.agreement_id, None, broker.store.now())
             .await
             .map_err(|_e| RemoteAgreementError::NotFound(msg.agreement_id.clone()))?
             .ok_or(RemoteAgreementError::NotFound(msg.agreement_id.clone()))?;
@@ -656,7 +703,7 @@ async fn agreement_rejected(
             RemoteAgreementError::InvalidState(agreement.id.clone(), agreement.state.clone())
         })?;
 
-        dao.reject(&agreement.id, msg.reason.clone(), &msg.rejection_ts)
+        dao.reject(&agreement.id, msg.reason.clone(), &broker.store.now())
             .await
             .log_err()
             .map_err(|e| match e {
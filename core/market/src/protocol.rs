@@ -7,6 +7,15 @@ macro_rules! PROTOCOL_VERSION {
     };
 }
 
+/// Version of discovery protocol. Bumped separately from negotiation protocol,
+/// since `mk2` carries Offers signed by issuing identity.
+#[macro_export]
+macro_rules! DISCOVERY_PROTOCOL_VERSION {
+    () => {
+        "mk2"
+    };
+}
+
 pub mod callback;
 pub mod discovery;
 pub mod negotiation;
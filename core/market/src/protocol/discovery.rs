@@ -1,4 +1,5 @@
 //! Discovery protocol interface
+use metrics::counter;
 use std::str::FromStr;
 use std::sync::Arc;
 use tokio::sync::Mutex;
@@ -18,10 +19,12 @@ use crate::identity::{IdentityApi, IdentityError};
 pub mod builder;
 pub mod error;
 pub mod message;
+pub(crate) mod quota;
 
-use crate::PROTOCOL_VERSION;
+use crate::DISCOVERY_PROTOCOL_VERSION;
 use error::*;
 use message::*;
+use quota::{BcastQuotas, Quota};
 
 /// Responsible for communication with markets on other nodes
 /// during discovery phase.
@@ -37,10 +40,15 @@ pub(super) struct OfferHandlers {
 
 pub struct DiscoveryImpl {
     identity: Arc<dyn IdentityApi>,
+    /// Exchange broadcasts also using previous protocol version, which doesn't sign Offers.
+    legacy_protocol: bool,
+    /// Limits broadcasts accepted from single node. No limits if not set.
+    quotas: Option<BcastQuotas>,
 
     offer_handlers: Mutex<OfferHandlers>,
     get_local_offers_handler: HandlerSlot<RetrieveOffers>,
     offer_unsubscribe_handler: HandlerSlot<UnsubscribedOffersBcast>,
+    offer_refresh_handler: HandlerSlot<RefreshedOffersBcast>,
 }
 
 impl Discovery {
@@ -48,8 +56,16 @@ impl Discovery {
     /// get call to function bound at `OfferBcast`.
     pub async fn bcast_offers(&self, offer_ids: Vec<SubscriptionId>) -> Result<(), DiscoveryError> {
         let default_id = self.default_identity().await?;
-        let bcast_msg = SendBroadcastMessage::new(OffersBcast { offer_ids });
+        let offers = OffersBcast { offer_ids };
 
+        if self.inner.legacy_protocol {
+            let bcast_msg = SendBroadcastMessage::new(legacy::OffersBcast(offers.clone()));
+            let _ = bus::service(local_net::BUS_ID)
+                .send_as(default_id, bcast_msg)
+                .await?;
+        }
+
+        let bcast_msg = SendBroadcastMessage::new(offers);
         // TODO: We shouldn't use send_as. Put identity inside broadcasted message instead.
         let _ = bus::service(local_net::BUS_ID)
             .send_as(default_id, bcast_msg) // TODO: should we send as our (default) identity?
@@ -57,18 +73,25 @@ impl Discovery {
         Ok(())
     }
 
-    /// Ask remote Node for specified Offers.
+    /// Ask remote Node for specified Offers. Node using previous
+    /// protocol version is asked at `legacy` address.
     pub async fn get_remote_offers(
         &self,
         target_node_id: String,
         offer_ids: Vec<SubscriptionId>,
+        legacy: bool,
     ) -> Result<Vec<ModelOffer>, DiscoveryError> {
         let target_node = NodeId::from_str(&target_node_id)
             .map_err(|e| DiscoveryError::InternalError(e.to_string()))?;
+        let addr = if legacy {
+            legacy::get_offers_addr(BUS_ID)
+        } else {
+            get_offers_addr(BUS_ID)
+        };
 
         Ok(net::from(self.default_identity().await?)
             .to(target_node)
-            .service(&get_offers_addr(BUS_ID))
+            .service(&addr)
             .send(RetrieveOffers { offer_ids })
             .await??)
     }
@@ -78,8 +101,17 @@ impl Discovery {
         offer_ids: Vec<SubscriptionId>,
     ) -> Result<(), DiscoveryError> {
         let default_id = self.default_identity().await?;
+        let unsubscribes = UnsubscribedOffersBcast { offer_ids };
+
+        if self.inner.legacy_protocol {
+            let bcast_msg =
+                SendBroadcastMessage::new(legacy::UnsubscribedOffersBcast(unsubscribes.clone()));
+            let _ = bus::service(local_net::BUS_ID)
+                .send_as(default_id, bcast_msg)
+                .await?;
+        }
 
-        let bcast_msg = SendBroadcastMessage::new(UnsubscribedOffersBcast { offer_ids });
+        let bcast_msg = SendBroadcastMessage::new(unsubscribes);
 
         // TODO: We shouldn't use send_as. Put identity inside broadcasted message instead.
         let _ = bus::service(local_net::BUS_ID)
@@ -88,12 +120,30 @@ impl Discovery {
         Ok(())
     }
 
+    /// Broadcasts extended expiration of Offers. Only nodes signing Offers
+    /// can verify refreshes, so they aren't sent using legacy protocol.
+    pub async fn bcast_refreshes(
+        &self,
+        refreshes: Vec<OfferRefresh>,
+    ) -> Result<(), DiscoveryError> {
+        let default_id = self.default_identity().await?;
+        let bcast_msg = SendBroadcastMessage::new(RefreshedOffersBcast { refreshes });
+
+        let _ = bus::service(local_net::BUS_ID)
+            .send_as(default_id, bcast_msg)
+            .await?;
+        Ok(())
+    }
+
     pub async fn bind_gsb(
         &self,
         public_prefix: &str,
         local_prefix: &str,
     ) -> Result<(), DiscoveryInitError> {
-        log::info!("Discovery protocol version: {}", PROTOCOL_VERSION!());
+        log::info!(
+            "Discovery protocol version: {}",
+            DISCOVERY_PROTOCOL_VERSION!()
+        );
 
         let myself = self.clone();
         // /local/market/market-protocol-mk1-offer
@@ -102,7 +152,7 @@ impl Discovery {
             &bcast_address,
             move |caller, msg: SendBroadcastMessage<OffersBcast>| {
                 let myself = myself.clone();
-                myself.on_bcast_offers(caller, msg.body().to_owned())
+                myself.on_bcast_offers(caller, msg.body().to_owned(), false)
             },
         )
         .await
@@ -121,6 +171,18 @@ impl Discovery {
         .await
         .map_err(|e| DiscoveryInitError::from_pair(bcast_address, e))?;
 
+        let myself = self.clone();
+        let bcast_address = format!("{}/{}", local_prefix, RefreshedOffersBcast::TOPIC);
+        ya_net::bind_broadcast_with_caller(
+            &bcast_address,
+            move |caller, msg: SendBroadcastMessage<RefreshedOffersBcast>| {
+                let myself = myself.clone();
+                myself.on_bcast_refreshes(caller, msg.body().to_owned())
+            },
+        )
+        .await
+        .map_err(|e| DiscoveryInitError::from_pair(bcast_address, e))?;
+
         ServiceBinder::new(&get_offers_addr(public_prefix), &(), self.clone()).bind_with_processor(
             move |_, myself, caller: String, msg: RetrieveOffers| {
                 let myself = myself.clone();
@@ -128,15 +190,72 @@ impl Discovery {
             },
         );
 
+        if self.inner.legacy_protocol {
+            self.bind_legacy_gsb(public_prefix, local_prefix).await?;
+        }
+        Ok(())
+    }
+
+    /// Binds endpoints of previous protocol version to the same handlers.
+    async fn bind_legacy_gsb(
+        &self,
+        public_prefix: &str,
+        local_prefix: &str,
+    ) -> Result<(), DiscoveryInitError> {
+        log::info!("Discovery protocol version mk1 enabled. Unsigned Offers will be accepted.");
+
+        let myself = self.clone();
+        let bcast_address = format!("{}/{}", local_prefix, legacy::OffersBcast::TOPIC);
+        ya_net::bind_broadcast_with_caller(
+            &bcast_address,
+            move |caller, msg: SendBroadcastMessage<legacy::OffersBcast>| {
+                let myself = myself.clone();
+                myself.on_bcast_offers(caller, msg.body().0.to_owned(), true)
+            },
+        )
+        .await
+        .map_err(|e| DiscoveryInitError::from_pair(bcast_address, e))?;
+
+        let myself = self.clone();
+        let bcast_address = format!(
+            "{}/{}",
+            local_prefix,
+            legacy::UnsubscribedOffersBcast::TOPIC
+        );
+        ya_net::bind_broadcast_with_caller(
+            &bcast_address,
+            move |caller, msg: SendBroadcastMessage<legacy::UnsubscribedOffersBcast>| {
+                let myself = myself.clone();
+                myself.on_bcast_unsubscribes(caller, msg.body().0.to_owned())
+            },
+        )
+        .await
+        .map_err(|e| DiscoveryInitError::from_pair(bcast_address, e))?;
+
+        ServiceBinder::new(&legacy::get_offers_addr(public_prefix), &(), self.clone())
+            .bind_with_processor(move |_, myself, caller: String, msg: RetrieveOffers| {
+                let myself = myself.clone();
+                myself.on_get_remote_offers(caller, msg)
+            });
         Ok(())
     }
 
-    async fn on_bcast_offers(self, caller: String, msg: OffersBcast) -> Result<(), ()> {
+    async fn on_bcast_offers(
+        self,
+        caller: String,
+        msg: OffersBcast,
+        legacy: bool,
+    ) -> Result<(), ()> {
         let num_ids_received = msg.offer_ids.len();
         if !msg.offer_ids.is_empty() {
             log::trace!("Received {} Offers from [{}].", num_ids_received, &caller);
         }
 
+        let msg = match self.admit_bcast(&caller, Quota::Offers, msg.offer_ids) {
+            Some(offer_ids) => OffersBcast { offer_ids },
+            None => return Ok(()),
+        };
+
         // We should do filtering and getting Offers in single transaction. Otherwise multiple
         // broadcasts can overlap and we will ask other nodes for the same Offers more than once.
         // Note that it wouldn't cause incorrect behavior, because we will add Offers only once.
@@ -152,7 +271,7 @@ impl Discovery {
 
             if !unknown_offer_ids.is_empty() {
                 let offers = self
-                    .get_remote_offers(caller.clone(), unknown_offer_ids)
+                    .get_remote_offers(caller.clone(), unknown_offer_ids, legacy)
                     .await
                     .map_err(|e| {
                         log::debug!("Can't get Offers from [{}]. Error: {}", &caller, e)
@@ -210,6 +329,11 @@ impl Discovery {
             );
         }
 
+        let msg = match self.admit_bcast(&caller, Quota::Unsubscribes, msg.offer_ids) {
+            Some(offer_ids) => UnsubscribedOffersBcast { offer_ids },
+            None => return Ok(()),
+        };
+
         let offer_unsubscribe_handler = self.inner.offer_unsubscribe_handler.clone();
         let unsubscribed_offer_ids = offer_unsubscribe_handler.call(caller.clone(), msg).await?;
 
@@ -229,6 +353,79 @@ impl Discovery {
         Ok(())
     }
 
+    async fn on_bcast_refreshes(self, caller: String, msg: RefreshedOffersBcast) -> Result<(), ()> {
+        let num_received = msg.refreshes.len();
+        if !msg.refreshes.is_empty() {
+            log::trace!(
+                "Received {} refreshed Offers from [{}].",
+                num_received,
+                &caller,
+            );
+        }
+
+        // Refreshes count to the same quota as new Offers.
+        let msg = match self.admit_bcast(&caller, Quota::Offers, msg.refreshes) {
+            Some(refreshes) => RefreshedOffersBcast { refreshes },
+            None => return Ok(()),
+        };
+
+        let offer_refresh_handler = self.inner.offer_refresh_handler.clone();
+        let refreshes = offer_refresh_handler.call(caller.clone(), msg).await?;
+
+        if !refreshes.is_empty() {
+            log::debug!(
+                "Propagating {}/{} refreshed Offers received from [{}].",
+                refreshes.len(),
+                num_received,
+                &caller,
+            );
+
+            // Refresh applied only if it extends expiration, so propagation stops
+            // at nodes, which already know it.
+            if let Err(error) = self.bcast_refreshes(refreshes).await {
+                log::error!("Error propagating refreshed Offers further: {}", error);
+            }
+        }
+        Ok(())
+    }
+
+    /// Truncates broadcasted elements to quota of node, that sent them to us.
+    /// Broadcasts are relayed, so we can't account them to Offer issuers: single
+    /// node could flood us with ids of Offers issued by many identities.
+    /// Checked before we ask for anything, so ignored elements cost us no network
+    /// traffic. Returns `None`, if nothing should be processed.
+    fn admit_bcast<T>(&self, caller: &str, quota: Quota, mut elements: Vec<T>) -> Option<Vec<T>> {
+        let quotas = match &self.inner.quotas {
+            Some(quotas) => quotas,
+            None => return Some(elements),
+        };
+        let admitted = match NodeId::from_str(caller) {
+            Ok(caller) => quotas.admit(&caller, quota, elements.len()),
+            Err(_) => 0,
+        };
+
+        if admitted < elements.len() {
+            let ignored = (elements.len() - admitted) as u64;
+            match quota {
+                Quota::Offers => counter!("market.offers.incoming.ignored", ignored),
+                Quota::Unsubscribes => counter!("market.offers.unsubscribes.ignored", ignored),
+            };
+            log::trace!(
+                "Ignoring {}/{} broadcasted elements from [{}], which exceeded {:?} quota.",
+                elements.len() - admitted,
+                elements.len(),
+                caller,
+                quota,
+            );
+            elements.truncate(admitted);
+        }
+
+        match elements.is_empty() {
+            true => None,
+            false => Some(elements),
+        }
+    }
+
     async fn default_identity(&self) -> Result<NodeId, IdentityError> {
         Ok(self.inner.identity.default_identity().await?)
     }
@@ -6,6 +6,7 @@ use tokio::sync::Mutex;
 use crate::protocol::callback::{CallbackFuture, OutputFuture};
 use crate::protocol::callback::{CallbackHandler, CallbackMessage, HandlerSlot};
 
+use super::quota::BcastQuotas;
 use super::{Discovery, DiscoveryImpl};
 use crate::protocol::discovery::OfferHandlers;
 
@@ -13,9 +14,23 @@ use crate::protocol::discovery::OfferHandlers;
 pub struct DiscoveryBuilder {
     data: HashMap<TypeId, Box<dyn Any>>,
     handlers: HashMap<TypeId, Box<dyn Any>>,
+    legacy_protocol: bool,
+    quotas: Option<BcastQuotas>,
 }
 
 impl DiscoveryBuilder {
+    /// Enables exchanging broadcasts using previous protocol version, which doesn't sign Offers.
+    pub fn with_legacy_protocol(mut self, enabled: bool) -> Self {
+        self.legacy_protocol = enabled;
+        self
+    }
+
+    /// Limits broadcasts accepted from single node.
+    pub fn with_quotas(mut self, quotas: BcastQuotas) -> Self {
+        self.quotas = Some(quotas);
+        self
+    }
+
     pub fn add_data<T: Clone + Send + Sync + 'static>(mut self, data: T) -> Self {
         self.data.insert(TypeId::of::<T>(), Box::new(data));
         self
@@ -65,9 +80,12 @@ impl DiscoveryBuilder {
         Discovery {
             inner: Arc::new(DiscoveryImpl {
                 identity: self.get_data(),
+                legacy_protocol: self.legacy_protocol,
+                quotas: self.quotas.take(),
                 offer_handlers,
                 get_local_offers_handler: self.get_handler(),
                 offer_unsubscribe_handler: self.get_handler(),
+                offer_refresh_handler: self.get_handler(),
             }),
         }
     }
@@ -134,11 +152,12 @@ mod test {
     }
 
     #[test]
-    fn build_from_with_four_handlers_should_pass() {
+    fn build_from_with_five_handlers_should_pass() {
         DiscoveryBuilder::default()
             .add_data(MockIdentity::new("test") as Arc<dyn IdentityApi>)
             .add_handler(|_, _: OffersRetrieved| async { Ok(vec![]) })
             .add_handler(|_, _: UnsubscribedOffersBcast| async { Ok(vec![]) })
+            .add_handler(|_, _: RefreshedOffersBcast| async { Ok(vec![]) })
             .add_handler(|_, _: OffersBcast| async { Ok(vec![]) })
             .add_handler(|_, _: RetrieveOffers| async { Ok(vec![]) })
             .build();
@@ -151,6 +170,7 @@ mod test {
             .add_data("mock data")
             .add_handler(|_, _: OffersRetrieved| async { Ok(vec![]) })
             .add_data_handler(|_: &str, _, _: UnsubscribedOffersBcast| async { Ok(vec![]) })
+            .add_data_handler(|_: &str, _, _: RefreshedOffersBcast| async { Ok(vec![]) })
             .add_handler(|_, _: OffersBcast| async { Ok(vec![]) })
             .add_data_handler(|_: &str, _, _: RetrieveOffers| async { Ok(vec![]) })
             .build();
@@ -170,6 +190,7 @@ mod test {
             .add_handler(|_, _: OffersRetrieved| async { Ok(vec![]) })
             .add_handler(|_, _: RetrieveOffers| async { panic!("should not be invoked") })
             .add_data_handler(|_: &str, _, _: UnsubscribedOffersBcast| async { Ok(vec![]) })
+            .add_handler(|_, _: RefreshedOffersBcast| async { Ok(vec![]) })
             .add_data_handler(move |data: usize, _, _: RetrieveOffers| {
                 let cnt = cnt.clone();
                 async move {
@@ -1,3 +1,4 @@
+use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
 
 use ya_core_model::net::local::BroadcastMessage;
@@ -22,15 +23,18 @@ impl CallbackMessage for OffersBcast {
 }
 
 impl BroadcastMessage for OffersBcast {
-    const TOPIC: &'static str =
-        concat!("market-protocol-discovery-", PROTOCOL_VERSION!(), "-offers");
+    const TOPIC: &'static str = concat!(
+        "market-protocol-discovery-",
+        DISCOVERY_PROTOCOL_VERSION!(),
+        "-offers"
+    );
 }
 
 pub(super) fn get_offers_addr(prefix: &str) -> String {
     format!(
         "{}/protocol/{}/discovery/offers",
         prefix,
-        PROTOCOL_VERSION!()
+        DISCOVERY_PROTOCOL_VERSION!()
     )
 }
 
@@ -75,7 +79,82 @@ impl CallbackMessage for UnsubscribedOffersBcast {
 impl BroadcastMessage for UnsubscribedOffersBcast {
     const TOPIC: &'static str = concat!(
         "market-protocol-discovery-",
-        PROTOCOL_VERSION!(),
-        "market-protocol-discovery-mk1-offers-unsubscribe"
+        DISCOVERY_PROTOCOL_VERSION!(),
+        "-offers-unsubscribe"
+    );
+}
+
+/// Offer expiration extended by its issuer, who signed `Offer::signed_payload`
+/// of refreshed Offer.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OfferRefresh {
+    pub offer_id: SubscriptionId,
+    pub expiration_ts: NaiveDateTime,
+    pub signature: String,
+}
+
+impl OfferRefresh {
+    pub fn new(offer: &ModelOffer) -> OfferRefresh {
+        OfferRefresh {
+            offer_id: offer.id.clone(),
+            expiration_ts: offer.expiration_ts,
+            signature: offer.signature.clone().unwrap_or_default(),
+        }
+    }
+}
+
+/// Refreshes are exchanged only by nodes signing Offers,
+/// so there is no legacy version of this message.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RefreshedOffersBcast {
+    pub refreshes: Vec<OfferRefresh>,
+}
+
+/// Local handler will return only refreshes, that extended stored Offers.
+/// Those will be bcasted further to the network.
+impl CallbackMessage for RefreshedOffersBcast {
+    type Ok = Vec<OfferRefresh>;
+    type Error = ();
+}
+
+impl BroadcastMessage for RefreshedOffersBcast {
+    const TOPIC: &'static str = concat!(
+        "market-protocol-discovery-",
+        DISCOVERY_PROTOCOL_VERSION!(),
+        "-offers-refresh"
     );
 }
+
+/// Messages of previous discovery protocol version `mk1`, which doesn't sign Offers.
+/// They have the same content as current ones, but are exchanged using different
+/// topics and addresses. Used only, if compatibility with unsigned peers is enabled.
+pub mod legacy {
+    use serde::{Deserialize, Serialize};
+
+    use ya_core_model::net::local::BroadcastMessage;
+
+    const PROTOCOL_VERSION: &str = "mk1";
+
+    #[derive(Clone, Serialize, Deserialize)]
+    #[serde(transparent)]
+    pub struct OffersBcast(pub super::OffersBcast);
+
+    impl BroadcastMessage for OffersBcast {
+        const TOPIC: &'static str = "market-protocol-discovery-mk1-offers";
+    }
+
+    #[derive(Clone, Serialize, Deserialize)]
+    #[serde(transparent)]
+    pub struct UnsubscribedOffersBcast(pub super::UnsubscribedOffersBcast);
+
+    impl BroadcastMessage for UnsubscribedOffersBcast {
+        const TOPIC: &'static str =
+            "market-protocol-discovery-mk1market-protocol-discovery-mk1-offers-unsubscribe";
+    }
+
+    pub(in super::super) fn get_offers_addr(prefix: &str) -> String {
+        format!("{}/protocol/{}/discovery/offers", prefix, PROTOCOL_VERSION)
+    }
+}
@@ -0,0 +1,205 @@
+use chrono::{DateTime, Utc};
+use metrics::counter;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use ya_client::model::NodeId;
+use ya_utils_actix::clock::Clock;
+
+use crate::config::DiscoveryConfig;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Quota {
+    /// New Offers and Offer refreshes sent by node.
+    Offers,
+    /// Unsubscribes of Offers sent by node.
+    Unsubscribes,
+}
+
+struct NodeQuota {
+    period_start: DateTime<Utc>,
+    offers: u32,
+    unsubscribes: u32,
+    banned_since: Option<DateTime<Utc>>,
+}
+
+/// Time passed between `since` and `now`. Zero if clock moved backwards.
+fn elapsed(since: DateTime<Utc>, now: DateTime<Utc>) -> Duration {
+    (now - since).to_std().unwrap_or_default()
+}
+
+impl NodeQuota {
+    fn new(now: DateTime<Utc>) -> NodeQuota {
+        NodeQuota {
+            period_start: now,
+            offers: 0,
+            unsubscribes: 0,
+            banned_since: None,
+        }
+    }
+
+    fn is_banned(&self, now: DateTime<Utc>, ban_period: Duration) -> bool {
+        self.banned_since
+            .map(|since| elapsed(since, now) < ban_period)
+            .unwrap_or(false)
+    }
+
+    fn is_stale(&self, now: DateTime<Utc>, period: Duration, ban_period: Duration) -> bool {
+        !self.is_banned(now, ban_period) && elapsed(self.period_start, now) >= period
+    }
+}
+
+/// Counts broadcasted elements received from each node. Quotas are kept for
+/// nodes sending us broadcasts, so single node can't flood us with ids of Offers
+/// issued by many (possibly generated) identities.
+/// Nodes exceeding quotas of `DiscoveryConfig` are ignored for `offender_ban_period`.
+#[derive(Clone)]
+pub struct BcastQuotas {
+    nodes: Arc<Mutex<HashMap<NodeId, NodeQuota>>>,
+    config: DiscoveryConfig,
+    clock: Clock,
+}
+
+impl BcastQuotas {
+    pub fn new(config: DiscoveryConfig, clock: Clock) -> BcastQuotas {
+        BcastQuotas {
+            nodes: Arc::new(Mutex::new(HashMap::new())),
+            config,
+            clock,
+        }
+    }
+
+    /// Returns how many of `count` elements sent by `caller` can be accepted.
+    /// Node exceeding quota is banned, so nothing of it will be accepted
+    /// until ban expires.
+    pub fn admit(&self, caller: &NodeId, quota: Quota, count: usize) -> usize {
+        let now = self.clock.now();
+        let period = self.config.quota_period;
+        let ban_period = self.config.offender_ban_period;
+        let limit = match quota {
+            Quota::Offers => self.config.max_received_offers_per_node,
+            Quota::Unsubscribes => self.config.max_received_unsubscribes_per_node,
+        } as usize;
+        if limit == 0 {
+            return count;
+        }
+
+        let mut nodes = self.nodes.lock().unwrap();
+        if !nodes.contains_key(caller) {
+            // Forget nodes, that didn't send anything recently.
+            nodes.retain(|_, node| !node.is_stale(now, period, ban_period));
+        }
+
+        let node = nodes.entry(*caller).or_insert_with(|| NodeQuota::new(now));
+        if node.is_banned(now, ban_period) {
+            return 0;
+        }
+        // Quota is renewed also after ban expires.
+        if node.banned_since.is_some() || elapsed(node.period_start, now) >= period {
+            *node = NodeQuota::new(now);
+        }
+
+        let used = match quota {
+            Quota::Offers => &mut node.offers,
+            Quota::Unsubscribes => &mut node.unsubscribes,
+        };
+        let remaining = limit.saturating_sub(*used as usize);
+        if count <= remaining {
+            *used += count as u32;
+            return count;
+        }
+
+        *used = limit as u32;
+        node.banned_since = Some(now);
+        counter!("market.discovery.offenders.banned", 1);
+        log::info!(
+            "Node [{}] exceeded {:?} quota ({} in {:?}). Ignoring it for {:?}.",
+            caller,
+            quota,
+            limit,
+            period,
+            ban_period
+        );
+        remaining
+    }
+
+    #[cfg(test)]
+    fn is_banned(&self, caller: &NodeId) -> bool {
+        let now = self.clock.now();
+        self.nodes
+            .lock()
+            .unwrap()
+            .get(caller)
+            .map(|node| node.is_banned(now, self.config.offender_ban_period))
+            .unwrap_or(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration as ChronoDuration, Utc};
+    use std::time::Duration;
+
+    use ya_client::model::NodeId;
+    use ya_utils_actix::clock::Clock;
+
+    use super::{BcastQuotas, Quota};
+    use crate::config::DiscoveryConfig;
+
+    fn node_id(n: u8) -> NodeId {
+        NodeId::from(&[n; 20][..])
+    }
+
+    fn quotas(clock: Clock) -> BcastQuotas {
+        let mut config = DiscoveryConfig::default();
+        config.quota_period = Duration::from_secs(60);
+        config.max_received_offers_per_node = 10;
+        config.max_received_unsubscribes_per_node = 0;
+        config.offender_ban_period = Duration::from_secs(600);
+        BcastQuotas::new(config, clock)
+    }
+
+    #[test]
+    fn admit_up_to_quota_and_ban_offender() {
+        let clock = Clock::manual(Utc::now());
+        let quotas = quotas(clock.clone());
+
+        assert_eq!(quotas.admit(&node_id(1), Quota::Offers, 6), 6);
+        assert_eq!(quotas.admit(&node_id(1), Quota::Offers, 6), 4);
+        assert!(quotas.is_banned(&node_id(1)));
+        // Other nodes have their own quotas.
+        assert_eq!(quotas.admit(&node_id(2), Quota::Offers, 10), 10);
+        assert!(!quotas.is_banned(&node_id(2)));
+
+        // Ban lasts longer than quota period.
+        clock.advance(ChronoDuration::seconds(120));
+        assert_eq!(quotas.admit(&node_id(1), Quota::Offers, 1), 0);
+
+        clock.advance(ChronoDuration::seconds(481));
+        assert!(!quotas.is_banned(&node_id(1)));
+        assert_eq!(quotas.admit(&node_id(1), Quota::Offers, 10), 10);
+    }
+
+    #[test]
+    fn quota_renews_every_period() {
+        let clock = Clock::manual(Utc::now());
+        let quotas = quotas(clock.clone());
+
+        assert_eq!(quotas.admit(&node_id(1), Quota::Offers, 10), 10);
+        clock.advance(ChronoDuration::seconds(60));
+        assert_eq!(quotas.admit(&node_id(1), Quota::Offers, 10), 10);
+        assert!(!quotas.is_banned(&node_id(1)));
+    }
+
+    #[test]
+    fn zero_disables_quota() {
+        let quotas = quotas(Clock::manual(Utc::now()));
+
+        assert_eq!(
+            quotas.admit(&node_id(1), Quota::Unsubscribes, 100_000),
+            100_000
+        );
+        assert!(!quotas.is_banned(&node_id(1)));
+    }
+}
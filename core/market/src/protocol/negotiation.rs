@@ -1,4 +1,5 @@
 #![allow(dead_code)]
+pub mod amendment;
 pub mod error;
 pub mod messages;
 pub mod provider;
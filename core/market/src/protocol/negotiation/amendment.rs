@@ -0,0 +1,192 @@
+use std::sync::Arc;
+
+use chrono::NaiveDateTime;
+
+use ya_client::model::market::Reason;
+use ya_core_model::market::BUS_ID;
+use ya_net::{self as net, RemoteEndpoint};
+use ya_service_bus::{typed::ServiceBinder, RpcEndpoint, RpcMessage};
+
+use crate::db::model::{Agreement, Amendment, Owner};
+
+use super::super::callback::{CallbackHandler, HandlerSlot};
+use super::error::{AmendmentProtocolError, GsbAgreementError, NegotiationApiInitError};
+use super::messages::{
+    provider, requestor, AmendmentApproved, AmendmentProposed, AmendmentRejected,
+};
+
+/// Responsible for amending approved Agreements with the other party.
+/// Each side binds it on its own Agreement address.
+#[derive(Clone)]
+pub struct AmendmentApi {
+    inner: Arc<AmendmentImpl>,
+}
+
+struct AmendmentImpl {
+    owner: Owner,
+    amendment_proposed: HandlerSlot<AmendmentProposed>,
+    amendment_approved: HandlerSlot<AmendmentApproved>,
+    amendment_rejected: HandlerSlot<AmendmentRejected>,
+}
+
+impl AmendmentApi {
+    pub fn new(
+        owner: Owner,
+        amendment_proposed: impl CallbackHandler<AmendmentProposed>,
+        amendment_approved: impl CallbackHandler<AmendmentApproved>,
+        amendment_rejected: impl CallbackHandler<AmendmentRejected>,
+    ) -> AmendmentApi {
+        AmendmentApi {
+            inner: Arc::new(AmendmentImpl {
+                owner,
+                amendment_proposed: HandlerSlot::new(amendment_proposed),
+                amendment_approved: HandlerSlot::new(amendment_approved),
+                amendment_rejected: HandlerSlot::new(amendment_rejected),
+            }),
+        }
+    }
+
+    async fn on_amendment_proposed(
+        self,
+        caller: String,
+        msg: AmendmentProposed,
+    ) -> Result<(), AmendmentProtocolError> {
+        log::debug!(
+            "Amendment API: Amendment {} of Agreement [{}] proposed by [{}].",
+            msg.version,
+            &msg.agreement_id,
+            &caller
+        );
+        self.inner
+            .amendment_proposed
+            .call(caller, msg.translate(self.inner.owner))
+            .await
+    }
+
+    async fn on_amendment_approved(
+        self,
+        caller: String,
+        msg: AmendmentApproved,
+    ) -> Result<(), AmendmentProtocolError> {
+        log::debug!(
+            "Amendment API: Amendment {} of Agreement [{}] approved by [{}].",
+            msg.version,
+            &msg.agreement_id,
+            &caller
+        );
+        self.inner
+            .amendment_approved
+            .call(caller, msg.translate(self.inner.owner))
+            .await
+    }
+
+    async fn on_amendment_rejected(
+        self,
+        caller: String,
+        msg: AmendmentRejected,
+    ) -> Result<(), AmendmentProtocolError> {
+        log::debug!(
+            "Amendment API: Amendment {} of Agreement [{}] rejected by [{}].",
+            msg.version,
+            &msg.agreement_id,
+            &caller
+        );
+        self.inner
+            .amendment_rejected
+            .call(caller, msg.translate(self.inner.owner))
+            .await
+    }
+
+    pub async fn bind_gsb(&self, public_prefix: &str) -> Result<(), NegotiationApiInitError> {
+        let addr = match self.inner.owner {
+            Owner::Provider => provider::agreement_addr(public_prefix),
+            Owner::Requestor => requestor::agreement_addr(public_prefix),
+        };
+
+        ServiceBinder::new(&addr, &(), self.clone())
+            .bind_with_processor(move |_, myself, caller: String, msg: AmendmentProposed| {
+                let myself = myself.clone();
+                myself.on_amendment_proposed(caller, msg)
+            })
+            .bind_with_processor(move |_, myself, caller: String, msg: AmendmentApproved| {
+                let myself = myself.clone();
+                myself.on_amendment_approved(caller, msg)
+            })
+            .bind_with_processor(move |_, myself, caller: String, msg: AmendmentRejected| {
+                let myself = myself.clone();
+                myself.on_amendment_rejected(caller, msg)
+            });
+        Ok(())
+    }
+}
+
+/// Sends amendment proposal to the other party. Amendment must be already signed.
+pub async fn propose_amendment(
+    agreement: &Agreement,
+    amendment: &Amendment,
+) -> Result<(), AmendmentProtocolError> {
+    let msg = AmendmentProposed {
+        agreement_id: agreement.id.clone().swap_owner(),
+        version: amendment.version,
+        offer_properties: amendment.offer_properties.clone(),
+        demand_properties: amendment.demand_properties.clone(),
+        creation_ts: amendment.creation_ts,
+        signature: amendment.proposed_signature.clone(),
+    };
+    send_to_peer(agreement, msg).await
+}
+
+pub async fn approve_amendment(
+    agreement: &Agreement,
+    version: i32,
+    signature: String,
+    timestamp: NaiveDateTime,
+) -> Result<(), AmendmentProtocolError> {
+    let msg = AmendmentApproved {
+        agreement_id: agreement.id.clone().swap_owner(),
+        version,
+        signature,
+        approved_ts: timestamp,
+    };
+    send_to_peer(agreement, msg).await
+}
+
+pub async fn reject_amendment(
+    agreement: &Agreement,
+    version: i32,
+    reason: Option<Reason>,
+    timestamp: NaiveDateTime,
+) -> Result<(), AmendmentProtocolError> {
+    let msg = AmendmentRejected {
+        agreement_id: agreement.id.clone().swap_owner(),
+        version,
+        reason,
+        rejection_ts: timestamp,
+    };
+    send_to_peer(agreement, msg).await
+}
+
+async fn send_to_peer<Msg>(agreement: &Agreement, msg: Msg) -> Result<(), AmendmentProtocolError>
+where
+    Msg: RpcMessage<Item = (), Error = AmendmentProtocolError> + Unpin,
+{
+    let (service, sender, receiver) = match agreement.id.owner() {
+        Owner::Requestor => (
+            provider::agreement_addr(BUS_ID),
+            agreement.requestor_id,
+            agreement.provider_id,
+        ),
+        Owner::Provider => (
+            requestor::agreement_addr(BUS_ID),
+            agreement.provider_id,
+            agreement.requestor_id,
+        ),
+    };
+    net::from(sender)
+        .to(receiver)
+        .service(&service)
+        .send(msg)
+        .await
+        .map_err(|e| GsbAgreementError(e.to_string(), agreement.id.clone()))??;
+    Ok(())
+}
@@ -90,6 +90,8 @@ pub enum RemoteProposeAgreementError {
     AlreadyCountered(ProposalId),
     #[error("Agreement id [{0}] is invalid.")]
     InvalidId(AgreementId),
+    #[error("Agreement [{0}] has invalid Requestor signature: {1}")]
+    InvalidSignature(AgreementId, String),
     /// We should hide `original_msg`, since we don't want to reveal our details to
     /// other Nodes. On the other side we should log whole message on local Node.
     /// Use `RemoteSensitiveError::hide_sensitive_info` for this.
@@ -144,6 +146,8 @@ pub enum RemoteAgreementError {
     InvalidState(AgreementId, AgreementState),
     #[error("Can't finish operation on Agreement [{0}] due to internal error.")]
     InternalError(AgreementId),
+    #[error("Agreement [{0}] has invalid Provider signature: {1}")]
+    InvalidSignature(AgreementId, String),
 }
 
 #[derive(Error, Debug, Serialize, Deserialize)]
@@ -169,6 +173,8 @@ pub enum RemoteCommitAgreementError {
     NotFound,
     #[error("Agreement in state {0}, can't be committed.")]
     InvalidState(AgreementState),
+    #[error("Invalid Requestor signature: {0}")]
+    InvalidSignature(String),
     #[error("Unexpected error: {public_msg} {original_msg}.")]
     Unexpected {
         public_msg: String,
@@ -176,6 +182,33 @@ pub enum RemoteCommitAgreementError {
     },
 }
 
+#[derive(Error, Debug, Serialize, Deserialize)]
+pub enum AmendmentProtocolError {
+    #[error("Amendment {0}.")]
+    Gsb(#[from] GsbAgreementError),
+    #[error("Remote amendment error: {0}")]
+    Remote(#[from] RemoteAmendmentError),
+    #[error(transparent)]
+    CallerParse(#[from] CallerParseError),
+}
+
+#[derive(Error, Debug, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum RemoteAmendmentError {
+    #[error("Agreement [{0}] not found.")]
+    NotFound(AgreementId),
+    #[error("Amendment {1} of Agreement [{0}] not found.")]
+    AmendmentNotFound(AgreementId, i32),
+    #[error("Agreement [{0}] in state {1}, can't be amended.")]
+    InvalidState(AgreementId, AgreementState),
+    #[error("Amendment of Agreement [{0}] conflicts with local state: {1}")]
+    Conflict(AgreementId, String),
+    #[error("Amendment of Agreement [{0}] has invalid signature: {1}")]
+    InvalidSignature(AgreementId, String),
+    #[error("Can't finish amendment of Agreement [{0}] due to internal error.")]
+    InternalError(AgreementId),
+}
+
 impl RemoteSensitiveError for RemoteProposeAgreementError {
     fn hide_sensitive_info(self) -> RemoteProposeAgreementError {
         match self {
@@ -10,7 +10,9 @@ use crate::protocol::negotiation::error::{
 };
 
 use super::super::callback::CallbackMessage;
-use super::error::{AgreementProtocolError, CounterProposalError, TerminateAgreementError};
+use super::error::{
+    AgreementProtocolError, AmendmentProtocolError, CounterProposalError, TerminateAgreementError,
+};
 
 pub mod provider {
     pub fn proposal_addr(prefix: &str) -> String {
@@ -205,6 +207,58 @@ impl RpcMessage for AgreementCommitted {
     type Error = CommitAgreementError;
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AmendmentProposed {
+    pub agreement_id: AgreementId,
+    /// Agreement version after applying amendment.
+    pub version: i32,
+    /// Full Offer properties after amendment.
+    pub offer_properties: String,
+    /// Full Demand properties after amendment.
+    pub demand_properties: String,
+    pub creation_ts: NaiveDateTime,
+    /// Issuer signature of amended Agreement terms.
+    pub signature: String,
+}
+
+impl RpcMessage for AmendmentProposed {
+    const ID: &'static str = "AmendmentProposed";
+    type Item = ();
+    type Error = AmendmentProtocolError;
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AmendmentApproved {
+    pub agreement_id: AgreementId,
+    pub version: i32,
+    /// Approver signature of amended Agreement terms.
+    pub signature: String,
+    pub approved_ts: NaiveDateTime,
+}
+
+impl RpcMessage for AmendmentApproved {
+    const ID: &'static str = "AmendmentApproved";
+    type Item = ();
+    type Error = AmendmentProtocolError;
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AmendmentRejected {
+    pub agreement_id: AgreementId,
+    pub version: i32,
+    pub reason: Option<Reason>,
+    pub rejection_ts: NaiveDateTime,
+}
+
+impl RpcMessage for AmendmentRejected {
+    const ID: &'static str = "AmendmentRejected";
+    type Item = ();
+    type Error = AmendmentProtocolError;
+}
+
 /// The same messaged will be used on GSB and as messages in callbacks.
 impl<Message: RpcMessage> CallbackMessage for Message {
     type Ok = <Message as RpcMessage>::Item;
@@ -287,3 +341,24 @@ impl AgreementCommitted {
         self
     }
 }
+
+impl AmendmentProposed {
+    pub fn translate(mut self, owner: Owner) -> Self {
+        self.agreement_id = self.agreement_id.translate(owner);
+        self
+    }
+}
+
+impl AmendmentApproved {
+    pub fn translate(mut self, owner: Owner) -> Self {
+        self.agreement_id = self.agreement_id.translate(owner);
+        self
+    }
+}
+
+impl AmendmentRejected {
+    pub fn translate(mut self, owner: Owner) -> Self {
+        self.agreement_id = self.agreement_id.translate(owner);
+        self
+    }
+}
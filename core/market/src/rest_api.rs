@@ -4,12 +4,18 @@
 //! within market modules and mapping return values to http responses.
 //! No market logic is allowed here.
 
-use actix_web::web::JsonConfig;
-use actix_web::{error::InternalError, http::StatusCode, web::PathConfig};
+use actix_web::http::header::{HeaderValue, WARNING};
+use actix_web::web::{Bytes, JsonConfig};
+use actix_web::{
+    error::InternalError, http::StatusCode, web::PathConfig, HttpRequest, HttpResponse,
+};
 use chrono::{DateTime, Utc};
-use serde::Deserialize;
+use futures::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
 
-use ya_client::model::ErrorMessage;
+use ya_client::model::{ErrorMessage, NodeId};
+use ya_market_resolver::LintWarning;
 
 use crate::db::model::{
     AgreementId, AppSessionId, Owner, ProposalId, ProposalIdParseError, SubscriptionId,
@@ -21,6 +27,8 @@ pub(crate) mod provider;
 pub(crate) mod requestor;
 
 const DEFAULT_EVENT_TIMEOUT: f32 = 5.0; // seconds
+/// Header sent by reconnecting Server-Sent Events clients.
+const LAST_EVENT_ID: &str = "Last-Event-ID";
 const DEFAULT_QUERY_TIMEOUT: f32 = 5.0;
 
 pub fn path_config() -> PathConfig {
@@ -43,11 +51,51 @@ pub fn json_config() -> JsonConfig {
     })
 }
 
+/// Response to Offer/Demand subscription. Constraints lint warnings are returned
+/// in `Warning` headers, so by default the body stays plain subscription id.
+/// With `lint` requested, the body carries both the id and the warnings.
+pub(crate) fn subscribed_response(
+    subscription_id: SubscriptionId,
+    warnings: Vec<LintWarning>,
+    query: &QueryLint,
+) -> HttpResponse {
+    let mut response = HttpResponse::Created();
+    warnings
+        .iter()
+        .filter_map(|warning| {
+            let text = warning.to_string().replace('"', "\\\"");
+            HeaderValue::from_str(&format!("199 yagna \"{}\"", text)).ok()
+        })
+        .for_each(|value| {
+            response.header(WARNING, value);
+        });
+    if query.lint {
+        response.json(SubscriptionCreated {
+            subscription_id,
+            warnings,
+        })
+    } else {
+        response.json(subscription_id)
+    }
+}
+
 #[derive(Deserialize, Clone)]
 pub struct PathAgreement {
     pub agreement_id: String,
 }
 
+#[derive(Deserialize, Clone)]
+pub struct PathAmendment {
+    pub agreement_id: String,
+    pub version: u32,
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PathNode {
+    pub node_id: NodeId,
+}
+
 #[derive(Deserialize)]
 pub struct PathSubscription {
     pub subscription_id: SubscriptionId,
@@ -59,6 +107,12 @@ pub struct PathSubscriptionProposal {
     pub proposal_id: ProposalId,
 }
 
+#[derive(Deserialize)]
+pub struct PathSubscriptionOffer {
+    pub subscription_id: SubscriptionId,
+    pub offer_id: SubscriptionId,
+}
+
 #[derive(Deserialize)]
 pub struct QueryAppSessionId {
     #[serde(rename = "appSessionId")]
@@ -111,11 +165,109 @@ pub struct QueryAgreementEvents {
     pub after_timestamp: Option<DateTime<Utc>>,
 }
 
+#[derive(Deserialize, Debug)]
+pub struct QueryEventsCursor {
+    /// resume stream after event with this id
+    #[serde(rename = "cursor")]
+    pub cursor: Option<i32>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct QueryAgreementEventsCursor {
+    #[serde(rename = "appSessionId")]
+    pub app_session_id: AppSessionId,
+    /// resume stream after event with this id
+    #[serde(rename = "cursor")]
+    pub cursor: Option<i32>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct QuerySubscriptionTtl {
+    /// Requested subscription lifetime in seconds.
+    #[serde(rename = "ttl")]
+    pub ttl: Option<u32>,
+}
+
+impl QuerySubscriptionTtl {
+    pub fn to_duration(&self) -> Option<chrono::Duration> {
+        self.ttl.map(|ttl| chrono::Duration::seconds(ttl as i64))
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct QueryLint {
+    /// Return constraints lint warnings in response body.
+    #[serde(rename = "lint", default)]
+    pub lint: bool,
+}
+
+/// Body of response to Offer/Demand subscription with `lint` requested.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SubscriptionCreated {
+    pub subscription_id: SubscriptionId,
+    pub warnings: Vec<LintWarning>,
+}
+
+/// Body of response to Offer/Demand refresh.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SubscriptionRefreshed {
+    pub expiration_ts: DateTime<Utc>,
+}
+
 #[derive(Deserialize, Debug)]
 pub struct QueryTerminateAgreement {
     pub reason: Option<String>,
 }
 
+/// Cursor from query takes precedence over `Last-Event-ID` header,
+/// which is sent automatically by reconnecting browsers.
+pub(crate) fn event_cursor(req: &HttpRequest, cursor: Option<i32>) -> Option<i32> {
+    cursor.or_else(|| {
+        req.headers()
+            .get(LAST_EVENT_ID)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse().ok())
+    })
+}
+
+/// Streams event batches as Server-Sent Events. Each event carries its cursor
+/// in `id` field. Empty batches are sent as keep-alive comments.
+/// Error ends the stream with `error` event.
+pub(crate) fn event_stream_response<S, T, E>(events: S) -> HttpResponse
+where
+    S: Stream<Item = Result<Vec<(i32, T)>, E>> + 'static,
+    T: Serialize,
+    E: Display,
+{
+    let body = events.map(|batch| {
+        let chunk = match batch {
+            Ok(events) if events.is_empty() => ":keep-alive\n\n".to_string(),
+            Ok(events) => events
+                .into_iter()
+                .filter_map(|(cursor, event)| match serde_json::to_string(&event) {
+                    Ok(data) => Some(format!("id: {}\ndata: {}\n\n", cursor, data)),
+                    Err(e) => {
+                        log::error!("Failed to serialize event [{}]. Error: {}", cursor, e);
+                        None
+                    }
+                })
+                .collect(),
+            Err(e) => format!(
+                "event: error\ndata: {}\n\n",
+                serde_json::to_string(&ErrorMessage::new(e.to_string())).unwrap()
+            ),
+        };
+        Ok::<_, actix_web::Error>(Bytes::from(chunk))
+    });
+
+    HttpResponse::Ok()
+        .keep_alive()
+        .content_type("text/event-stream")
+        .streaming(Box::pin(body))
+}
+
 #[inline(always)]
 pub(crate) fn default_query_timeout() -> f32 {
     DEFAULT_QUERY_TIMEOUT
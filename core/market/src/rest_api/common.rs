@@ -1,13 +1,18 @@
 use actix_web::web::{Data, Json, Path, Query};
-use actix_web::{HttpResponse, Responder, Scope};
+use actix_web::{HttpRequest, HttpResponse, Responder, Scope};
 use chrono::{TimeZone, Utc};
+use serde::Deserialize;
 use std::sync::Arc;
 
 use ya_client::model::market::Reason;
+use ya_core_model::market::{AmendmentProposal, ListAgreements, NodePolicy};
 use ya_service_api_web::middleware::Identity;
 use ya_std_utils::LogErr;
 
-use super::PathAgreement;
+use super::{
+    event_cursor, event_stream_response, PathAgreement, PathAmendment, PathNode,
+    QueryAgreementEventsCursor,
+};
 use crate::db::model::Owner;
 use crate::market::MarketService;
 use crate::negotiation::error::AgreementError;
@@ -16,8 +21,33 @@ use crate::rest_api::QueryAgreementEvents;
 pub fn register_endpoints(scope: Scope) -> Scope {
     scope
         .service(collect_agreement_events)
+        .service(stream_agreement_events)
+        .service(list_agreements)
         .service(get_agreement)
+        .service(get_agreement_negotiation)
         .service(terminate_agreement)
+        .service(propose_amendment)
+        .service(list_amendments)
+        .service(list_amendment_events)
+        .service(approve_amendment)
+        .service(reject_amendment)
+        .service(list_node_policies)
+        .service(set_node_policy)
+        .service(remove_node_policy)
+        .service(get_market_stats)
+}
+
+#[actix_web::get("/agreements")]
+async fn list_agreements(
+    market: Data<Arc<MarketService>>,
+    query: Query<ListAgreements>,
+    id: Identity,
+) -> impl Responder {
+    market
+        .list_agreements(query.into_inner(), &id)
+        .await
+        .log_err()
+        .map(|agreements| HttpResponse::Ok().json(agreements))
 }
 
 #[actix_web::get("/agreements/{agreement_id}")]
@@ -48,6 +78,20 @@ async fn get_agreement(
     }
 }
 
+#[actix_web::get("/agreements/{agreement_id}/negotiation")]
+async fn get_agreement_negotiation(
+    market: Data<Arc<MarketService>>,
+    path: Path<PathAgreement>,
+    id: Identity,
+) -> impl Responder {
+    let client_agreement_id = path.into_inner().agreement_id;
+    market
+        .agreement_negotiation(id, client_agreement_id)
+        .await
+        .log_err()
+        .map(|steps| HttpResponse::Ok().json(steps))
+}
+
 #[actix_web::get("/agreementEvents")]
 async fn collect_agreement_events(
     market: Data<Arc<MarketService>>,
@@ -72,6 +116,21 @@ async fn collect_agreement_events(
         .map(|events| HttpResponse::Ok().json(events))
 }
 
+#[actix_web::get("/agreementEvents/stream")]
+async fn stream_agreement_events(
+    market: Data<Arc<MarketService>>,
+    query: Query<QueryAgreementEventsCursor>,
+    req: HttpRequest,
+    id: Identity,
+) -> impl Responder {
+    let cursor = event_cursor(&req, query.cursor);
+    market
+        .stream_agreement_events(&query.app_session_id, cursor, &id)
+        .await
+        .log_err()
+        .map(event_stream_response)
+}
+
 #[actix_web::post("/agreements/{agreement_id}/terminate")]
 async fn terminate_agreement(
     market: Data<Arc<MarketService>>,
@@ -86,3 +145,128 @@ async fn terminate_agreement(
         .log_err()
         .map(|_| HttpResponse::Ok().finish())
 }
+
+#[actix_web::post("/agreements/{agreement_id}/amendments")]
+async fn propose_amendment(
+    market: Data<Arc<MarketService>>,
+    path: Path<PathAgreement>,
+    id: Identity,
+    body: Json<AmendmentProposal>,
+) -> impl Responder {
+    let client_agreement_id = path.into_inner().agreement_id;
+    market
+        .propose_amendment(id, client_agreement_id, body.into_inner())
+        .await
+        .log_err()
+        .map(|amendment| HttpResponse::Created().json(amendment))
+}
+
+#[actix_web::get("/agreements/{agreement_id}/amendments")]
+async fn list_amendments(
+    market: Data<Arc<MarketService>>,
+    path: Path<PathAgreement>,
+    id: Identity,
+) -> impl Responder {
+    let client_agreement_id = path.into_inner().agreement_id;
+    market
+        .list_amendments(id, client_agreement_id)
+        .await
+        .log_err()
+        .map(|amendments| HttpResponse::Ok().json(amendments))
+}
+
+#[actix_web::get("/agreements/{agreement_id}/amendmentEvents")]
+async fn list_amendment_events(
+    market: Data<Arc<MarketService>>,
+    path: Path<PathAgreement>,
+    id: Identity,
+) -> impl Responder {
+    let client_agreement_id = path.into_inner().agreement_id;
+    market
+        .list_amendment_events(id, client_agreement_id)
+        .await
+        .log_err()
+        .map(|events| HttpResponse::Ok().json(events))
+}
+
+#[actix_web::post("/agreements/{agreement_id}/amendments/{version}/approve")]
+async fn approve_amendment(
+    market: Data<Arc<MarketService>>,
+    path: Path<PathAmendment>,
+    id: Identity,
+) -> impl Responder {
+    let path = path.into_inner();
+    market
+        .approve_amendment(id, path.agreement_id, path.version)
+        .await
+        .log_err()
+        .map(|amendment| HttpResponse::Ok().json(amendment))
+}
+
+#[actix_web::post("/agreements/{agreement_id}/amendments/{version}/reject")]
+async fn reject_amendment(
+    market: Data<Arc<MarketService>>,
+    path: Path<PathAmendment>,
+    id: Identity,
+    body: Json<Option<Reason>>,
+) -> impl Responder {
+    let path = path.into_inner();
+    market
+        .reject_amendment(id, path.agreement_id, path.version, body.into_inner())
+        .await
+        .log_err()
+        .map(|amendment| HttpResponse::Ok().json(amendment))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct NodePolicyBody {
+    policy: NodePolicy,
+    description: Option<String>,
+}
+
+#[actix_web::get("/nodePolicies")]
+async fn list_node_policies(market: Data<Arc<MarketService>>, id: Identity) -> impl Responder {
+    market
+        .list_node_policies(id)
+        .await
+        .log_err()
+        .map(|policies| HttpResponse::Ok().json(policies))
+}
+
+#[actix_web::put("/nodePolicies/{node_id}")]
+async fn set_node_policy(
+    market: Data<Arc<MarketService>>,
+    path: Path<PathNode>,
+    id: Identity,
+    body: Json<NodePolicyBody>,
+) -> impl Responder {
+    let body = body.into_inner();
+    market
+        .set_node_policy(id, path.into_inner().node_id, body.policy, body.description)
+        .await
+        .log_err()
+        .map(|policy| HttpResponse::Ok().json(policy))
+}
+
+#[actix_web::delete("/nodePolicies/{node_id}")]
+async fn remove_node_policy(
+    market: Data<Arc<MarketService>>,
+    path: Path<PathNode>,
+    id: Identity,
+) -> impl Responder {
+    market
+        .remove_node_policy(id, path.into_inner().node_id)
+        .await
+        .log_err()
+        .map(|_| HttpResponse::Ok().finish())
+}
+
+#[actix_web::get("/stats")]
+async fn get_market_stats(market: Data<Arc<MarketService>>, id: Identity) -> impl Responder {
+    market
+        .market_stats(&id)
+        .await
+        .log_err()
+        .map(|stats| HttpResponse::Ok().json(stats))
+}
@@ -2,20 +2,21 @@ use actix_web::{HttpResponse, ResponseError};
 
 use ya_client::model::ErrorMessage;
 
-use crate::db::dao::{AgreementDaoError, SaveProposalError};
+use crate::db::dao::{AgreementDaoError, AmendmentDaoError, SaveProposalError};
 use crate::db::model::AgreementState;
 use crate::negotiation::error::{AgreementEventsError, ProposalValidationError};
 use crate::protocol::negotiation::error::RejectProposalError;
 use crate::{
     db::dao::TakeEventsError,
-    market::MarketError,
+    market::{stats::StatsError, MarketError},
     matcher::error::{
-        DemandError, MatcherError, ModifyOfferError, QueryDemandsError, QueryOfferError,
-        QueryOffersError, ResolverError, SaveOfferError,
+        DemandError, ExplainMatchError, MatcherError, ModifyOfferError, NodePolicyError,
+        QueryDemandsError, QueryOfferError, QueryOffersError, ResolverError, SaveOfferError,
+        ScanOffersError,
     },
     negotiation::error::{
-        AgreementError, GetProposalError, NegotiationError, ProposalError, QueryEventsError,
-        WaitForApprovalError,
+        AgreementError, AmendmentError, GetProposalError, NegotiationError, ProposalError,
+        QueryEventsError, WaitForApprovalError,
     },
 };
 
@@ -66,6 +67,9 @@ impl ResponseError for DemandError {
             DemandError::NotFound(_) => {
                 HttpResponse::NotFound().json(ErrorMessage::new(self.to_string()))
             }
+            DemandError::Ttl(_) => {
+                HttpResponse::BadRequest().json(ErrorMessage::new(self.to_string()))
+            }
             _ => HttpResponse::InternalServerError().json(ErrorMessage::new(self.to_string())),
         }
     }
@@ -83,6 +87,17 @@ impl ResponseError for QueryOffersError {
     }
 }
 
+impl ResponseError for ScanOffersError {
+    fn error_response(&self) -> HttpResponse {
+        match self {
+            ScanOffersError::InvalidConstraints(_) => {
+                HttpResponse::BadRequest().json(ErrorMessage::new(self.to_string()))
+            }
+            ScanOffersError::QueryOffers(e) => e.error_response(),
+        }
+    }
+}
+
 impl ResponseError for QueryOfferError {
     fn error_response(&self) -> HttpResponse {
         let msg = ErrorMessage::new(self.to_string());
@@ -100,6 +115,7 @@ impl ResponseError for SaveOfferError {
             SaveOfferError::Unsubscribed(_) | SaveOfferError::Expired(_) => {
                 HttpResponse::Gone().json(msg)
             }
+            SaveOfferError::Ttl(_) => HttpResponse::BadRequest().json(msg),
             _ => HttpResponse::InternalServerError().json(msg),
         }
     }
@@ -113,11 +129,24 @@ impl ResponseError for ModifyOfferError {
             ModifyOfferError::AlreadyUnsubscribed(_) | ModifyOfferError::Expired(_) => {
                 HttpResponse::Gone().json(msg)
             }
+            ModifyOfferError::Ttl(_) => HttpResponse::BadRequest().json(msg),
             _ => HttpResponse::InternalServerError().json(msg),
         }
     }
 }
 
+impl ResponseError for ExplainMatchError {
+    fn error_response(&self) -> HttpResponse {
+        match self {
+            ExplainMatchError::Demand(e) => e.error_response(),
+            ExplainMatchError::QueryOffer(e) => e.error_response(),
+            ExplainMatchError::Resolve { .. } => {
+                HttpResponse::InternalServerError().json(ErrorMessage::new(self.to_string()))
+            }
+        }
+    }
+}
+
 impl ResponseError for QueryEventsError {
     fn error_response(&self) -> HttpResponse {
         let msg = ErrorMessage::new(self.to_string());
@@ -173,6 +202,7 @@ impl ResponseError for ProposalValidationError {
             | ProposalValidationError::OwnProposal(_) => HttpResponse::BadRequest().json(msg),
             ProposalValidationError::SubscriptionExpired(_) => HttpResponse::Gone().json(msg),
             ProposalValidationError::Unauthorized(_, _) => HttpResponse::Unauthorized().json(msg),
+            ProposalValidationError::Blocked(_) => HttpResponse::Forbidden().json(msg),
             ProposalValidationError::Internal(_) => HttpResponse::InternalServerError().json(msg),
         }
     }
@@ -204,11 +234,13 @@ impl ResponseError for AgreementError {
             AgreementError::GetProposal(..)
             | AgreementError::Save(..)
             | AgreementError::Get(..)
+            | AgreementError::List(_)
             | AgreementError::Gsb(_)
             | AgreementError::ProtocolCreate(_)
             | AgreementError::Protocol(_)
             | AgreementError::ProtocolTerminate(_)
             | AgreementError::ProtocolCommit(_)
+            | AgreementError::Sign(..)
             | AgreementError::Internal(_) => HttpResponse::InternalServerError().json(msg),
         }
     }
@@ -236,6 +268,39 @@ impl ResponseError for AgreementDaoError {
     }
 }
 
+impl ResponseError for AmendmentError {
+    fn error_response(&self) -> HttpResponse {
+        let msg = ErrorMessage::new(self.to_string());
+        match self {
+            AmendmentError::NotFound(..) => HttpResponse::NotFound().json(msg),
+            AmendmentError::InvalidProperties(..) | AmendmentError::OwnAmendment(..) => {
+                HttpResponse::BadRequest().json(msg)
+            }
+            AmendmentError::Agreement(e) => e.error_response(),
+            AmendmentError::Dao(e) => e.error_response(),
+            AmendmentError::Protocol(_) | AmendmentError::Internal(..) => {
+                HttpResponse::InternalServerError().json(msg)
+            }
+        }
+    }
+}
+
+impl ResponseError for AmendmentDaoError {
+    fn error_response(&self) -> HttpResponse {
+        let msg = ErrorMessage::new(self.to_string());
+        match self {
+            AmendmentDaoError::AgreementNotFound(_) | AmendmentDaoError::NotFound(..) => {
+                HttpResponse::NotFound().json(msg)
+            }
+            AmendmentDaoError::InvalidState(..)
+            | AmendmentDaoError::Pending(..)
+            | AmendmentDaoError::InvalidVersion(..) => HttpResponse::Conflict().json(msg),
+            AmendmentDaoError::AlreadyResolved(..) => HttpResponse::Gone().json(msg),
+            AmendmentDaoError::DbError(_) => HttpResponse::InternalServerError().json(msg),
+        }
+    }
+}
+
 impl ResponseError for WaitForApprovalError {
     fn error_response(&self) -> HttpResponse {
         let msg = ErrorMessage::new(self.to_string());
@@ -262,3 +327,19 @@ impl ResponseError for AgreementEventsError {
         }
     }
 }
+
+impl ResponseError for NodePolicyError {
+    fn error_response(&self) -> HttpResponse {
+        let msg = ErrorMessage::new(self.to_string());
+        match self {
+            NodePolicyError::NotFound(_) => HttpResponse::NotFound().json(msg),
+            _ => HttpResponse::InternalServerError().json(msg),
+        }
+    }
+}
+
+impl ResponseError for StatsError {
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::InternalServerError().json(ErrorMessage::new(self.to_string()))
+    }
+}
@@ -1,5 +1,6 @@
 use actix_web::web::{Data, Json, Path, Query};
-use actix_web::{HttpResponse, Responder, Scope};
+use actix_web::{HttpRequest, HttpResponse, Responder, Scope};
+use futures::TryFutureExt;
 use std::sync::Arc;
 
 use ya_client::model::market::{NewOffer, NewProposal, Reason};
@@ -9,7 +10,11 @@ use ya_std_utils::LogErr;
 use crate::db::model::Owner;
 use crate::market::MarketService;
 
-use super::{PathAgreement, PathSubscription, PathSubscriptionProposal, QueryTimeoutMaxEvents};
+use super::{
+    event_cursor, event_stream_response, subscribed_response, PathAgreement, PathSubscription,
+    PathSubscriptionProposal, QueryEventsCursor, QueryLint, QuerySubscriptionTtl,
+    QueryTimeoutMaxEvents, SubscriptionRefreshed,
+};
 use crate::negotiation::ApprovalResult;
 use crate::rest_api::QueryTimeoutAppSessionId;
 use ya_client::model::ErrorMessage;
@@ -18,10 +23,13 @@ pub fn register_endpoints(scope: Scope) -> Scope {
     scope
         .service(subscribe)
         .service(get_offers)
+        .service(refresh)
         .service(unsubscribe)
         .service(collect)
+        .service(stream_events)
         .service(counter_proposal)
         .service(get_proposal)
+        .service(get_proposal_negotiation)
         .service(reject_proposal)
         .service(approve_agreement)
         .service(reject_agreement)
@@ -31,13 +39,18 @@ pub fn register_endpoints(scope: Scope) -> Scope {
 async fn subscribe(
     market: Data<Arc<MarketService>>,
     body: Json<NewOffer>,
+    query: Query<QuerySubscriptionTtl>,
+    lint: Query<QueryLint>,
     id: Identity,
 ) -> impl Responder {
+    let offer = body.into_inner();
+    let warnings = market.matcher.lint_constraints(&offer.constraints);
+
     market
-        .subscribe_offer(&body.into_inner(), &id)
+        .subscribe_offer_with_ttl(&offer, &id, query.to_duration())
         .await
         .log_err()
-        .map(|id| HttpResponse::Created().json(id))
+        .map(|id| subscribed_response(id, warnings, &lint))
 }
 
 #[actix_web::get("/offers")]
@@ -49,6 +62,21 @@ async fn get_offers(market: Data<Arc<MarketService>>, id: Identity) -> impl Resp
         .map(|offers| HttpResponse::Ok().json(offers))
 }
 
+/// Extends expiration of Offer without changing its subscription id.
+#[actix_web::post("/offers/{subscription_id}/refresh")]
+async fn refresh(
+    market: Data<Arc<MarketService>>,
+    path: Path<PathSubscription>,
+    query: Query<QuerySubscriptionTtl>,
+    id: Identity,
+) -> impl Responder {
+    market
+        .refresh_offer(&path.into_inner().subscription_id, &id, query.to_duration())
+        .await
+        .log_err()
+        .map(|expiration_ts| HttpResponse::Ok().json(SubscriptionRefreshed { expiration_ts }))
+}
+
 #[actix_web::delete("/offers/{subscription_id}")]
 async fn unsubscribe(
     market: Data<Arc<MarketService>>,
@@ -67,17 +95,37 @@ async fn collect(
     market: Data<Arc<MarketService>>,
     path: Path<PathSubscription>,
     query: Query<QueryTimeoutMaxEvents>,
-    _id: Identity,
+    id: Identity,
 ) -> impl Responder {
     let subscription_id = path.into_inner().subscription_id;
     let timeout = query.timeout;
     let max_events = query.max_events;
+    let engine = &market.provider_engine;
+    engine
+        .common
+        .validate_events_owner(&subscription_id, &id, Owner::Provider)
+        .and_then(|_| engine.query_events(&subscription_id, timeout, max_events))
+        .await
+        .log_err()
+        .map(|events| HttpResponse::Ok().json(events))
+}
+
+#[actix_web::get("/offers/{subscription_id}/events/stream")]
+async fn stream_events(
+    market: Data<Arc<MarketService>>,
+    path: Path<PathSubscription>,
+    query: Query<QueryEventsCursor>,
+    req: HttpRequest,
+    id: Identity,
+) -> impl Responder {
+    let subscription_id = path.into_inner().subscription_id;
+    let cursor = event_cursor(&req, query.cursor);
     market
         .provider_engine
-        .query_events(&subscription_id, timeout, max_events)
+        .stream_events(&subscription_id, &id, cursor)
         .await
         .log_err()
-        .map(|events| HttpResponse::Ok().json(events))
+        .map(event_stream_response)
 }
 
 #[actix_web::post("/offers/{subscription_id}/proposals/{proposal_id}")]
@@ -104,9 +152,8 @@ async fn counter_proposal(
 async fn get_proposal(
     market: Data<Arc<MarketService>>,
     path: Path<PathSubscriptionProposal>,
-    _id: Identity,
+    id: Identity,
 ) -> impl Responder {
-    // TODO: Authorization
     let PathSubscriptionProposal {
         subscription_id,
         proposal_id,
@@ -115,11 +162,30 @@ async fn get_proposal(
     market
         .provider_engine
         .common
-        .get_client_proposal(Some(&subscription_id), &proposal_id)
+        .get_client_proposal(Some(&subscription_id), &proposal_id, &id)
         .await
         .map(|proposal| HttpResponse::Ok().json(proposal))
 }
 
+#[actix_web::get("/offers/{subscription_id}/proposals/{proposal_id}/negotiation")]
+async fn get_proposal_negotiation(
+    market: Data<Arc<MarketService>>,
+    path: Path<PathSubscriptionProposal>,
+    id: Identity,
+) -> impl Responder {
+    let PathSubscriptionProposal {
+        subscription_id,
+        proposal_id,
+    } = path.into_inner();
+
+    market
+        .provider_engine
+        .common
+        .negotiation_history(Some(&subscription_id), &proposal_id, &id)
+        .await
+        .map(|steps| HttpResponse::Ok().json(steps))
+}
+
 #[actix_web::post("/offers/{subscription_id}/proposals/{proposal_id}/reject")]
 async fn reject_proposal(
     market: Data<Arc<MarketService>>,
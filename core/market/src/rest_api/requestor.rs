@@ -1,10 +1,12 @@
 use actix_web::web::{Data, Json, Path, Query};
-use actix_web::{HttpResponse, Responder, Scope};
+use actix_web::{HttpRequest, HttpResponse, Responder, Scope};
+use futures::TryFutureExt;
 use std::str::FromStr;
 use std::sync::Arc;
 
 use ya_client::model::market::{AgreementProposal, NewDemand, NewProposal, Reason};
 use ya_client::model::ErrorMessage;
+use ya_core_model::market::ScanOffers;
 use ya_service_api_web::middleware::Identity;
 use ya_std_utils::LogErr;
 
@@ -12,8 +14,9 @@ use crate::db::model::Owner;
 use crate::market::MarketService;
 
 use super::{
-    PathAgreement, PathSubscription, PathSubscriptionProposal, ProposalId, QueryTimeout,
-    QueryTimeoutMaxEvents,
+    event_cursor, event_stream_response, subscribed_response, PathAgreement, PathSubscription,
+    PathSubscriptionOffer, PathSubscriptionProposal, ProposalId, QueryEventsCursor, QueryLint,
+    QuerySubscriptionTtl, QueryTimeout, QueryTimeoutMaxEvents, SubscriptionRefreshed,
 };
 use crate::negotiation::ApprovalStatus;
 use crate::rest_api::QueryAppSessionId;
@@ -22,11 +25,16 @@ pub fn register_endpoints(scope: Scope) -> Scope {
     scope
         .service(subscribe)
         .service(get_demands)
+        .service(refresh)
         .service(unsubscribe)
         .service(collect)
+        .service(stream_events)
         .service(counter_proposal)
         .service(get_proposal)
+        .service(get_proposal_negotiation)
         .service(reject_proposal)
+        .service(explain_match)
+        .service(scan_offers)
         .service(create_agreement)
         .service(confirm_agreement)
         .service(wait_for_approval)
@@ -37,13 +45,18 @@ pub fn register_endpoints(scope: Scope) -> Scope {
 async fn subscribe(
     market: Data<Arc<MarketService>>,
     body: Json<NewDemand>,
+    query: Query<QuerySubscriptionTtl>,
+    lint: Query<QueryLint>,
     id: Identity,
 ) -> impl Responder {
+    let demand = body.into_inner();
+    let warnings = market.matcher.lint_constraints(&demand.constraints);
+
     market
-        .subscribe_demand(&body.into_inner(), &id)
+        .subscribe_demand_with_ttl(&demand, &id, query.to_duration())
         .await
         .log_err()
-        .map(|id| HttpResponse::Created().json(id))
+        .map(|id| subscribed_response(id, warnings, &lint))
 }
 
 #[actix_web::get("/demands")]
@@ -54,6 +67,21 @@ async fn get_demands(market: Data<Arc<MarketService>>, id: Identity) -> impl Res
         .map(|demands| HttpResponse::Ok().json(demands))
 }
 
+/// Extends expiration of Demand without changing its subscription id.
+#[actix_web::post("/demands/{subscription_id}/refresh")]
+async fn refresh(
+    market: Data<Arc<MarketService>>,
+    path: Path<PathSubscription>,
+    query: Query<QuerySubscriptionTtl>,
+    id: Identity,
+) -> impl Responder {
+    market
+        .refresh_demand(&path.into_inner().subscription_id, &id, query.to_duration())
+        .await
+        .log_err()
+        .map(|expiration_ts| HttpResponse::Ok().json(SubscriptionRefreshed { expiration_ts }))
+}
+
 #[actix_web::delete("/demands/{subscription_id}")]
 async fn unsubscribe(
     market: Data<Arc<MarketService>>,
@@ -73,17 +101,37 @@ async fn collect(
     market: Data<Arc<MarketService>>,
     path: Path<PathSubscription>,
     query: Query<QueryTimeoutMaxEvents>,
-    _id: Identity, // TODO: use it
+    id: Identity,
 ) -> impl Responder {
     let subscription_id = path.into_inner().subscription_id;
     let timeout = query.timeout;
     let max_events = query.max_events;
+    let engine = &market.requestor_engine;
+    engine
+        .common
+        .validate_events_owner(&subscription_id, &id, Owner::Requestor)
+        .and_then(|_| engine.query_events(&subscription_id, timeout, max_events))
+        .await
+        .log_err()
+        .map(|events| HttpResponse::Ok().json(events))
+}
+
+#[actix_web::get("/demands/{subscription_id}/events/stream")]
+async fn stream_events(
+    market: Data<Arc<MarketService>>,
+    path: Path<PathSubscription>,
+    query: Query<QueryEventsCursor>,
+    req: HttpRequest,
+    id: Identity,
+) -> impl Responder {
+    let subscription_id = path.into_inner().subscription_id;
+    let cursor = event_cursor(&req, query.cursor);
     market
         .requestor_engine
-        .query_events(&subscription_id, timeout, max_events)
+        .stream_events(&subscription_id, &id, cursor)
         .await
         .log_err()
-        .map(|events| HttpResponse::Ok().json(events))
+        .map(event_stream_response)
 }
 
 #[actix_web::post("/demands/{subscription_id}/proposals/{proposal_id}")]
@@ -110,9 +158,8 @@ async fn counter_proposal(
 async fn get_proposal(
     market: Data<Arc<MarketService>>,
     path: Path<PathSubscriptionProposal>,
-    _id: Identity,
+    id: Identity,
 ) -> impl Responder {
-    // TODO: Authorization
     let PathSubscriptionProposal {
         subscription_id,
         proposal_id,
@@ -121,11 +168,30 @@ async fn get_proposal(
     market
         .requestor_engine
         .common
-        .get_client_proposal(Some(&subscription_id), &proposal_id)
+        .get_client_proposal(Some(&subscription_id), &proposal_id, &id)
         .await
         .map(|proposal| HttpResponse::Ok().json(proposal))
 }
 
+#[actix_web::get("/demands/{subscription_id}/proposals/{proposal_id}/negotiation")]
+async fn get_proposal_negotiation(
+    market: Data<Arc<MarketService>>,
+    path: Path<PathSubscriptionProposal>,
+    id: Identity,
+) -> impl Responder {
+    let PathSubscriptionProposal {
+        subscription_id,
+        proposal_id,
+    } = path.into_inner();
+
+    market
+        .requestor_engine
+        .common
+        .negotiation_history(Some(&subscription_id), &proposal_id, &id)
+        .await
+        .map(|steps| HttpResponse::Ok().json(steps))
+}
+
 #[actix_web::post("/demands/{subscription_id}/proposals/{proposal_id}/reject")]
 async fn reject_proposal(
     market: Data<Arc<MarketService>>,
@@ -146,6 +212,39 @@ async fn reject_proposal(
         .map(|_| HttpResponse::NoContent().finish())
 }
 
+#[actix_web::get("/demands/{subscription_id}/offers/{offer_id}/explain")]
+async fn explain_match(
+    market: Data<Arc<MarketService>>,
+    path: Path<PathSubscriptionOffer>,
+    id: Identity,
+) -> impl Responder {
+    let PathSubscriptionOffer {
+        subscription_id,
+        offer_id,
+    } = path.into_inner();
+
+    market
+        .matcher
+        .explain_match(&subscription_id, &offer_id, &id)
+        .await
+        .log_err()
+        .map(|explanation| HttpResponse::Ok().json(explanation))
+}
+
+#[actix_web::post("/scan")]
+async fn scan_offers(
+    market: Data<Arc<MarketService>>,
+    body: Json<ScanOffers>,
+    _id: Identity,
+) -> impl Responder {
+    market
+        .matcher
+        .scan_offers(&body.into_inner())
+        .await
+        .log_err()
+        .map(|offers| HttpResponse::Ok().json(offers))
+}
+
 #[actix_web::post("/agreements")]
 async fn create_agreement(
     market: Data<Arc<MarketService>>,
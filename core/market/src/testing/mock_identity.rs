@@ -1,4 +1,5 @@
-use rand::distributions::Alphanumeric;
+use ethsign::SecretKey;
+use lazy_static::lazy_static;
 use rand::{thread_rng, Rng};
 use std::sync::{Arc, Mutex};
 
@@ -30,6 +31,13 @@ impl IdentityApi for MockIdentity {
             .map(|(_, id)| id.identity)
             .collect())
     }
+
+    async fn sign(&self, node_id: NodeId, payload: Vec<u8>) -> Result<Vec<u8>, IdentityError> {
+        sign(node_id, &payload).ok_or(IdentityError::SignError(format!(
+            "Unknown identity [{}]",
+            node_id
+        )))
+    }
 }
 
 impl MockIdentity {
@@ -69,12 +77,33 @@ impl MockIdentity {
     }
 }
 
+lazy_static! {
+    /// Secret keys of all generated identities, so that anyone could sign in their name.
+    static ref KEYS: Mutex<HashMap<NodeId, SecretKey>> = Mutex::new(HashMap::new());
+}
+
 pub fn generate_identity(name: &str) -> Identity {
-    let random_node_id: String = thread_rng().sample_iter(&Alphanumeric).take(20).collect();
+    let secret = loop {
+        if let Ok(secret) = SecretKey::from_raw(&thread_rng().gen::<[u8; 32]>()) {
+            break secret;
+        }
+    };
+    let node_id = NodeId::from(&secret.public().address()[..]);
+    KEYS.lock().unwrap().insert(node_id, secret);
 
     Identity {
         name: name.to_string(),
         role: "manager".to_string(),
-        identity: NodeId::from(random_node_id.as_bytes()),
+        identity: node_id,
     }
 }
+
+/// Signs payload the same way as identity service does.
+pub fn sign(node_id: NodeId, payload: &[u8]) -> Option<Vec<u8>> {
+    let signature = KEYS.lock().unwrap().get(&node_id)?.sign(payload).ok()?;
+    let mut bytes = Vec::with_capacity(65);
+    bytes.push(signature.v);
+    bytes.extend_from_slice(&signature.r[..]);
+    bytes.extend_from_slice(&signature.s[..]);
+    Some(bytes)
+}
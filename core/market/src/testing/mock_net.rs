@@ -26,6 +26,14 @@ pub struct MockNet {
 struct MockNetInner {
     /// Maps NodeIds to gsb prefixes of market nodes.
     pub nodes: HashMap<NodeId, String>,
+    /// Nodes in different partitions can't reach each other.
+    /// Nodes not listed here belong to partition 0.
+    pub partitions: HashMap<NodeId, usize>,
+    pub next_partition: usize,
+    /// Number of messages to be lost on link from first to second node.
+    pub scripted_losses: HashMap<(NodeId, NodeId), usize>,
+    /// Probability of losing any message, that wasn't lost otherwise.
+    pub loss_probability: f64,
 }
 
 lazy_static::lazy_static! {
@@ -61,6 +69,77 @@ impl MockNet {
         }
     }
 
+    /// Separates `nodes` from all other nodes. Nodes from consecutive
+    /// calls form separate partitions.
+    pub fn partition(&self, nodes: &[NodeId]) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.next_partition += 1;
+        let partition = inner.next_partition;
+        for node_id in nodes {
+            inner.partitions.insert(node_id.clone(), partition);
+        }
+    }
+
+    pub fn heal_partitions(&self) {
+        self.inner.lock().unwrap().partitions.clear();
+    }
+
+    /// Next `count` messages, both broadcasts and direct ones,
+    /// sent from `from` node to `to` node will be lost.
+    pub fn drop_next_messages(&self, from: &NodeId, to: &NodeId, count: usize) {
+        if count == 0 {
+            return;
+        }
+        let mut inner = self.inner.lock().unwrap();
+        *inner
+            .scripted_losses
+            .entry((from.clone(), to.clone()))
+            .or_default() += count;
+    }
+
+    /// Each message will be lost with given probability.
+    pub fn set_message_loss(&self, probability: f64) {
+        self.inner.lock().unwrap().loss_probability = probability;
+    }
+
+    /// Removes partitions and stops losing messages.
+    pub fn reset_faults(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.partitions.clear();
+        inner.scripted_losses.clear();
+        inner.loss_probability = 0.0;
+    }
+
+    /// Decides if message from `from` node reaches `to` node.
+    pub fn is_delivered(&self, from: &NodeId, to: &NodeId) -> bool {
+        let mut inner = self.inner.lock().unwrap();
+        let partition_of = |id: &NodeId| inner.partitions.get(id).copied().unwrap_or(0);
+        if partition_of(from) != partition_of(to) {
+            log::debug!("[MockNet] Nodes [{}] and [{}] are partitioned.", from, to);
+            return false;
+        }
+
+        let link = (from.clone(), to.clone());
+        if let Some(count) = inner.scripted_losses.get_mut(&link) {
+            *count -= 1;
+            if *count == 0 {
+                inner.scripted_losses.remove(&link);
+            }
+            log::debug!("[MockNet] Message from [{}] to [{}] lost.", from, to);
+            return false;
+        }
+
+        if inner.loss_probability > 0.0 && rand::random::<f64>() < inner.loss_probability {
+            log::debug!(
+                "[MockNet] Message from [{}] to [{}] lost randomly.",
+                from,
+                to
+            );
+            return false;
+        }
+        true
+    }
+
     pub fn unregister_node(&self, node_id: &NodeId) -> Result<()> {
         let mut inner = self.inner.lock().unwrap();
         inner
@@ -70,7 +149,7 @@ impl MockNet {
             .ok_or(anyhow::anyhow!("node not registered: {}", node_id))
     }
 
-    async fn translate_address(&self, address: String) -> Result<(NodeId, String)> {
+    async fn translate_address(&self, address: String) -> Result<(NodeId, NodeId, String)> {
         let (from_node, to_addr) = match parse_from_addr(&address) {
             Ok(v) => v,
             Err(e) => Err(Error::GsbBadRequest(e.to_string()))?,
@@ -88,7 +167,11 @@ impl MockNet {
 
         if let Some(local_prefix) = local_prefix {
             let net_prefix = format!("/net/{}", dst_id);
-            Ok((from_node, to_addr.replacen(&net_prefix, &local_prefix, 1)))
+            Ok((
+                from_node,
+                dest_node_id,
+                to_addr.replacen(&net_prefix, &local_prefix, 1),
+            ))
         } else {
             Err(Error::GsbFailure(format!(
                 "[MockNet] Can't find destination address for endpoint [{}].",
@@ -164,6 +247,12 @@ impl MockNetInner {
                         }
                     };
 
+                    if let Ok(caller_id) = NodeId::from_str(&caller) {
+                        if !mock_net.is_delivered(&caller_id, &node_id) {
+                            continue;
+                        }
+                    }
+
                     log::debug!(
                         "BCasting on {} to address: {}, node: [{}]",
                         topic,
@@ -190,11 +279,18 @@ impl MockNetInner {
                 let addr = addr.to_string();
 
                 async move {
-                    let (from, local_addr) = mock_net
+                    let (from, to, local_addr) = mock_net
                         .translate_address(addr)
                         .await
                         .map_err(|e| Error::GsbBadRequest(e.to_string()))?;
 
+                    if !mock_net.is_delivered(&from, &to) {
+                        Err(Error::GsbFailure(format!(
+                            "[MockNet] Node [{}] unreachable from [{}].",
+                            to, from
+                        )))?
+                    }
+
                     log::debug!(
                         "[MockNet] Sending message from [{}], to address [{}].",
                         &caller,
@@ -7,8 +7,10 @@ use std::collections::HashMap;
 use std::{fs, path::PathBuf, sync::Arc, time::Duration};
 
 use ya_client::model::market::RequestorEvent;
+use ya_core_model::market::NegotiationStep;
 use ya_persistence::executor::DbExecutor;
 use ya_service_api_web::middleware::{auth::dummy::DummyAuth, Identity};
+use ya_utils_actix::clock::Clock;
 
 use crate::MarketService;
 
@@ -39,6 +41,7 @@ pub struct MarketsNetwork {
     test_dir: PathBuf,
     test_name: String,
     config: Arc<Config>,
+    clock: Clock,
 }
 
 pub struct MockNode {
@@ -128,6 +131,7 @@ impl MarketsNetwork {
         let test_dir = prepare_test_dir(&tn).unwrap();
 
         MockNet::default().bind_gsb();
+        MockNet::default().reset_faults();
 
         // Disable cyclic broadcasts by default.
         let mut config = Config::default();
@@ -139,6 +143,7 @@ impl MarketsNetwork {
             test_dir,
             test_name: tn.to_string(),
             config: Arc::new(config),
+            clock: Clock::system(),
         }
     }
 
@@ -148,6 +153,17 @@ impl MarketsNetwork {
         self
     }
 
+    /// Clock will be used by all consecutive Nodes. Manual clock lets
+    /// tests expire Offers and Demands without waiting.
+    pub fn with_clock(mut self, clock: Clock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn clock(&self) -> Clock {
+        self.clock.clone()
+    }
+
     async fn add_node(
         mut self,
         name: &str,
@@ -178,6 +194,20 @@ impl MarketsNetwork {
         Ok(())
     }
 
+    /// Nodes with given names can communicate only with each other.
+    pub fn partition(&self, node_names: &[&str]) {
+        let ids = node_names
+            .iter()
+            .flat_map(|name| self.list_ids(name).into_iter())
+            .map(|(_, id)| id.identity)
+            .collect::<Vec<_>>();
+        MockNet::default().partition(&ids);
+    }
+
+    pub fn heal_partitions(&self) {
+        MockNet::default().heal_partitions();
+    }
+
     pub fn enable_networking_for(&self, node_name: &str) -> Result<()> {
         for (_, id) in self.list_ids(node_name) {
             let (public_gsb_prefix, _) = gsb_prefixes(&self.test_name, node_name);
@@ -190,10 +220,11 @@ impl MarketsNetwork {
         let db = self.create_database(name);
         let identity_api = MockIdentity::new(name);
         let market = Arc::new(
-            MarketService::new(
+            MarketService::with_clock(
                 &db,
                 identity_api.clone() as Arc<dyn IdentityApi>,
                 self.config.clone(),
+                self.clock.clone(),
             )
             .unwrap(),
         );
@@ -204,7 +235,7 @@ impl MarketsNetwork {
     pub async fn add_matcher_instance(self, name: &str) -> Self {
         let db = self.init_database(name);
 
-        let store = SubscriptionStore::new(db.clone(), self.config.clone());
+        let store = SubscriptionStore::new(db.clone(), self.config.clone(), self.clock.clone());
         let identity_api = MockIdentity::new(name);
 
         let (matcher, listeners) =
@@ -231,6 +262,7 @@ impl MarketsNetwork {
             .add_handler(empty_on_offers_retrieved)
             .add_handler(empty_on_offers_bcast)
             .add_handler(empty_on_offer_unsubscribed_bcast)
+            .add_handler(empty_on_offer_refreshed_bcast)
             .add_handler(empty_on_retrieve_offers)
     }
 
@@ -510,21 +542,24 @@ pub fn prepare_test_dir(dir_name: &str) -> Result<PathBuf> {
 }
 
 /// Facilitates waiting for broadcast propagation.
+/// Returns false, if Offer wasn't (or was, depending on `stop_is_ok`)
+/// found in `market` within grace period.
 pub async fn wait_for_bcast(
     grace_millis: u64,
     market: &MarketService,
     subscription_id: &SubscriptionId,
     stop_is_ok: bool,
-) {
+) -> bool {
     let steps = 20;
     let wait_step = Duration::from_millis(grace_millis / steps);
     let store = market.matcher.store.clone();
     for _ in 0..steps {
         tokio::time::delay_for(wait_step).await;
         if store.get_offer(&subscription_id).await.is_ok() == stop_is_ok {
-            break;
+            return true;
         }
     }
+    false
 }
 
 #[macro_export]
@@ -539,6 +574,12 @@ pub trait MarketServiceExt {
     async fn get_offer(&self, id: &SubscriptionId) -> Result<Offer, QueryOfferError>;
     async fn get_demand(&self, id: &SubscriptionId) -> Result<Demand, DemandError>;
     async fn get_proposal(&self, id: &ProposalId) -> Result<Proposal, GetProposalError>;
+    async fn negotiation_history(
+        &self,
+        subscription_id: &SubscriptionId,
+        id: &ProposalId,
+        caller: &Identity,
+    ) -> Result<Vec<NegotiationStep>, GetProposalError>;
     async fn get_proposal_from_db(
         &self,
         proposal_id: &ProposalId,
@@ -565,6 +606,18 @@ impl MarketServiceExt for MarketService {
         self.provider_engine.common.get_proposal(None, id).await
     }
 
+    async fn negotiation_history(
+        &self,
+        subscription_id: &SubscriptionId,
+        id: &ProposalId,
+        caller: &Identity,
+    ) -> Result<Vec<NegotiationStep>, GetProposalError> {
+        self.requestor_engine
+            .common
+            .negotiation_history(Some(subscription_id), id, caller)
+            .await
+    }
+
     async fn get_proposal_from_db(
         &self,
         proposal_id: &ProposalId,
@@ -625,6 +678,13 @@ pub mod default {
         Ok(vec![])
     }
 
+    pub async fn empty_on_offer_refreshed_bcast(
+        _caller: String,
+        _msg: RefreshedOffersBcast,
+    ) -> Result<Vec<OfferRefresh>, ()> {
+        Ok(vec![])
+    }
+
     pub async fn empty_on_initial_proposal(
         _caller: String,
         _msg: InitialProposalReceived,
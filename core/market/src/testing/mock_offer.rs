@@ -7,7 +7,7 @@ use ya_client::model::NodeId;
 
 use crate::db::model::{Demand, Offer};
 use crate::protocol::discovery::message::RetrieveOffers;
-use crate::testing::mock_identity::generate_identity;
+use crate::testing::mock_identity::{generate_identity, sign};
 use crate::testing::SubscriptionId;
 
 pub fn flatten_json(json: &Value) -> Value {
@@ -25,24 +25,34 @@ pub fn sample_retrieve_offers() -> RetrieveOffers {
 pub fn sample_offer() -> Offer {
     let creation_ts = Utc::now().naive_utc();
     let expiration_ts = creation_ts + Duration::hours(1);
-    Offer::from_new(
-        &client::sample_offer(),
-        &generate_identity(""),
-        creation_ts,
-        expiration_ts,
+    sign_offer(
+        Offer::from_new(
+            &client::sample_offer(),
+            &generate_identity(""),
+            creation_ts,
+            expiration_ts,
+        )
+        .unwrap(),
     )
-    .unwrap()
 }
 
 pub fn sample_offer_with_expiration(expiration_ts: NaiveDateTime) -> Offer {
     let creation_ts = Utc::now().naive_utc();
-    Offer::from_new(
-        &client::sample_offer(),
-        &generate_identity(""),
-        creation_ts,
-        expiration_ts,
+    sign_offer(
+        Offer::from_new(
+            &client::sample_offer(),
+            &generate_identity(""),
+            creation_ts,
+            expiration_ts,
+        )
+        .unwrap(),
     )
-    .unwrap()
+}
+
+/// Signs Offer with key of identity generated by `generate_identity`.
+pub fn sign_offer(mut offer: Offer) -> Offer {
+    offer.signature = sign(offer.node_id, &offer.signed_payload()).map(hex::encode);
+    offer
 }
 
 pub fn generate_offer(id: &str, expiration_ts: NaiveDateTime) -> Offer {
@@ -54,6 +64,8 @@ pub fn generate_offer(id: &str, expiration_ts: NaiveDateTime) -> Offer {
         creation_ts: Utc::now().naive_utc(),
         insertion_ts: None,
         expiration_ts,
+        signature: None,
+        initial_expiration_ts: None,
     }
 }
 
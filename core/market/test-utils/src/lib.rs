@@ -0,0 +1,18 @@
+//! Simulation of many market nodes inside single process, for integration
+//! tests of market and services built on top of it.
+//!
+//! Nodes communicate through [`MockNet`], which can partition the network
+//! and lose messages. Expiration of subscriptions follows [`Clock`] given
+//! to the network, so tests can use manual clock instead of waiting.
+//!
+//! All nodes share GSB router, so tests must be run sequentially.
+pub mod scenario;
+
+pub use ya_market::testing::mock_net::MockNet;
+pub use ya_market::testing::{
+    client, events_helper, mock_identity, wait_for_bcast, MarketServiceExt, MarketsNetwork,
+};
+pub use ya_market::MarketService;
+pub use ya_utils_actix::clock::Clock;
+
+pub use scenario::{Participant, Scenario, ScenarioBuilder};
@@ -0,0 +1,172 @@
+use anyhow::{bail, Result};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use ya_client::model::market::{NewDemand, NewOffer};
+use ya_market::testing::{Config, MarketServiceExt, MarketsNetwork, SubscriptionId};
+use ya_market::MarketService;
+use ya_service_api_web::middleware::Identity;
+use ya_utils_actix::clock::Clock;
+
+/// Builds network of Provider and Requestor market nodes,
+/// each with single subscription.
+pub struct ScenarioBuilder {
+    test_name: String,
+    config: Option<Arc<Config>>,
+    clock: Clock,
+    offers: Vec<NewOffer>,
+    demands: Vec<NewDemand>,
+}
+
+/// Market node with its subscription.
+#[derive(Clone)]
+pub struct Participant {
+    pub name: String,
+    pub identity: Identity,
+    pub market: Arc<MarketService>,
+    pub subscription_id: SubscriptionId,
+}
+
+pub struct Scenario {
+    pub network: MarketsNetwork,
+    pub providers: Vec<Participant>,
+    pub requestors: Vec<Participant>,
+}
+
+impl ScenarioBuilder {
+    /// Test name must be unique between all tests, because it is used
+    /// for test directories and GSB addresses.
+    pub fn new(test_name: &str) -> ScenarioBuilder {
+        ScenarioBuilder {
+            test_name: test_name.to_string(),
+            config: None,
+            clock: Clock::system(),
+            offers: vec![],
+            demands: vec![],
+        }
+    }
+
+    /// Replaces default test config, which disables cyclic broadcasts.
+    pub fn with_config(mut self, config: Arc<Config>) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    pub fn with_clock(mut self, clock: Clock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Adds `count` Provider nodes named `Provider-1`, `Provider-2`, ...
+    /// Each of them subscribes `offer`.
+    pub fn providers(mut self, count: usize, offer: NewOffer) -> Self {
+        self.offers.extend(std::iter::repeat(offer).take(count));
+        self
+    }
+
+    /// Adds `count` Requestor nodes named `Requestor-1`, `Requestor-2`, ...
+    /// Each of them subscribes `demand`.
+    pub fn requestors(mut self, count: usize, demand: NewDemand) -> Self {
+        self.demands.extend(std::iter::repeat(demand).take(count));
+        self
+    }
+
+    pub async fn build(self) -> Result<Scenario> {
+        let mut network = MarketsNetwork::new(Some(&self.test_name))
+            .await
+            .with_clock(self.clock);
+        if let Some(config) = self.config {
+            network = network.with_config(config);
+        }
+
+        let provider_names = node_names("Provider", self.offers.len());
+        let requestor_names = node_names("Requestor", self.demands.len());
+        for name in provider_names.iter().chain(requestor_names.iter()) {
+            network = network.add_market_instance(name).await;
+        }
+
+        let mut providers = vec![];
+        for (name, offer) in provider_names.into_iter().zip(self.offers.iter()) {
+            let (market, identity) = (network.get_market(&name), network.get_default_id(&name));
+            let subscription_id = market.subscribe_offer(offer, &identity).await?;
+            providers.push(Participant {
+                name,
+                identity,
+                market,
+                subscription_id,
+            });
+        }
+
+        let mut requestors = vec![];
+        for (name, demand) in requestor_names.into_iter().zip(self.demands.iter()) {
+            let (market, identity) = (network.get_market(&name), network.get_default_id(&name));
+            let subscription_id = market.subscribe_demand(demand, &identity).await?;
+            requestors.push(Participant {
+                name,
+                identity,
+                market,
+                subscription_id,
+            });
+        }
+
+        Ok(Scenario {
+            network,
+            providers,
+            requestors,
+        })
+    }
+}
+
+impl Scenario {
+    pub fn clock(&self) -> Clock {
+        self.network.clock()
+    }
+
+    pub fn participant(&self, name: &str) -> Option<&Participant> {
+        self.providers
+            .iter()
+            .chain(self.requestors.iter())
+            .find(|participant| participant.name == name)
+    }
+
+    /// Waits until Offers of all Providers are known to all Requestor nodes.
+    pub async fn wait_for_offers(&self, timeout: Duration) -> Result<()> {
+        let deadline = Instant::now() + timeout;
+        for requestor in &self.requestors {
+            for provider in &self.providers {
+                while requestor
+                    .market
+                    .get_offer(&provider.subscription_id)
+                    .await
+                    .is_err()
+                {
+                    if Instant::now() >= deadline {
+                        bail!(
+                            "Offer [{}] of {} didn't reach {}.",
+                            provider.subscription_id,
+                            provider.name,
+                            requestor.name
+                        );
+                    }
+                    tokio::time::delay_for(Duration::from_millis(50)).await;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Nodes with given names can communicate only with each other.
+    pub fn partition(&self, node_names: &[&str]) {
+        self.network.partition(node_names)
+    }
+
+    pub fn heal_partitions(&self) {
+        self.network.heal_partitions()
+    }
+}
+
+fn node_names(prefix: &str, count: usize) -> Vec<String> {
+    (1..=count)
+        .map(|idx| format!("{}-{}", prefix, idx))
+        .collect()
+}
@@ -0,0 +1,158 @@
+use chrono::Utc;
+use std::time::Duration;
+
+use ya_market::testing::{Config, QueryEventsError, QueryOfferError, TakeEventsError};
+use ya_market_test_utils::{
+    client, wait_for_bcast, Clock, MarketServiceExt, MockNet, ScenarioBuilder,
+};
+
+#[cfg_attr(not(feature = "test-suite"), ignore)]
+#[serial_test::serial]
+async fn test_scenario_offers_reach_all_requestors() {
+    let scenario = ScenarioBuilder::new("test_scenario_offers_reach_all_requestors")
+        .providers(4, client::sample_offer())
+        .requestors(2, client::sample_demand())
+        .build()
+        .await
+        .unwrap();
+
+    assert_eq!(scenario.providers.len(), 4);
+    assert_eq!(scenario.requestors.len(), 2);
+    assert!(scenario.participant("Provider-4").is_some());
+    scenario
+        .wait_for_offers(Duration::from_secs(2))
+        .await
+        .unwrap();
+}
+
+/// Subscriptions expire, when manual clock passes their expiration.
+#[cfg_attr(not(feature = "test-suite"), ignore)]
+#[serial_test::serial]
+async fn test_scenario_manual_clock_expires_subscriptions() {
+    let clock = Clock::manual(Utc::now());
+    let scenario = ScenarioBuilder::new("test_scenario_manual_clock_expires_subscriptions")
+        .with_clock(clock.clone())
+        .providers(1, client::sample_offer())
+        .requestors(1, client::sample_demand())
+        .build()
+        .await
+        .unwrap();
+    scenario
+        .wait_for_offers(Duration::from_secs(1))
+        .await
+        .unwrap();
+
+    let provider = &scenario.providers[0];
+    let requestor = &scenario.requestors[0];
+    clock.advance(Config::default().subscription.default_ttl - chrono::Duration::seconds(1));
+    assert!(provider
+        .market
+        .get_offer(&provider.subscription_id)
+        .await
+        .is_ok());
+    assert!(requestor
+        .market
+        .get_demand(&requestor.subscription_id)
+        .await
+        .is_ok());
+    assert!(provider
+        .market
+        .provider_engine
+        .query_events(&provider.subscription_id, 0.0, None)
+        .await
+        .is_ok());
+
+    clock.advance(chrono::Duration::seconds(2));
+    match provider.market.get_offer(&provider.subscription_id).await {
+        Err(QueryOfferError::Expired(id)) => assert_eq!(id, provider.subscription_id),
+        result => panic!("Expected expired Offer, got: {:?}", result),
+    }
+    match requestor.market.get_offer(&provider.subscription_id).await {
+        Err(QueryOfferError::Expired(_)) => (),
+        result => panic!("Expected expired Offer, got: {:?}", result),
+    }
+    assert!(requestor
+        .market
+        .get_demand(&requestor.subscription_id)
+        .await
+        .is_err());
+    // Negotiation events are validated against the same clock.
+    match provider
+        .market
+        .provider_engine
+        .query_events(&provider.subscription_id, 0.0, None)
+        .await
+    {
+        Err(QueryEventsError::TakeEvents(TakeEventsError::Expired(id))) => {
+            assert_eq!(id, provider.subscription_id)
+        }
+        result => panic!("Expected expired subscription, got: {:?}", result),
+    }
+    match requestor
+        .market
+        .requestor_engine
+        .query_events(&requestor.subscription_id, 0.0, None)
+        .await
+    {
+        Err(QueryEventsError::TakeEvents(TakeEventsError::Expired(id))) => {
+            assert_eq!(id, requestor.subscription_id)
+        }
+        result => panic!("Expected expired subscription, got: {:?}", result),
+    }
+}
+
+/// Broadcasts don't cross partitions and scripted losses drop
+/// exactly requested number of messages.
+#[cfg_attr(not(feature = "test-suite"), ignore)]
+#[serial_test::serial]
+async fn test_scenario_partitions_and_message_loss() {
+    let scenario = ScenarioBuilder::new("test_scenario_partitions_and_message_loss")
+        .providers(2, client::sample_offer())
+        .requestors(1, client::sample_demand())
+        .build()
+        .await
+        .unwrap();
+    scenario
+        .wait_for_offers(Duration::from_secs(1))
+        .await
+        .unwrap();
+
+    let prov1 = scenario.participant("Provider-1").unwrap();
+    let prov2 = scenario.participant("Provider-2").unwrap();
+    let req = &scenario.requestors[0];
+
+    scenario.partition(&["Provider-1"]);
+    let offer_id = prov1
+        .market
+        .subscribe_offer(&client::sample_offer(), &prov1.identity)
+        .await
+        .unwrap();
+    wait_for_bcast(500, &req.market, &offer_id, true).await;
+    assert!(req.market.get_offer(&offer_id).await.is_err());
+
+    scenario.heal_partitions();
+    let offer_id = prov1
+        .market
+        .subscribe_offer(&client::sample_offer(), &prov1.identity)
+        .await
+        .unwrap();
+    wait_for_bcast(1000, &req.market, &offer_id, true).await;
+    assert!(req.market.get_offer(&offer_id).await.is_ok());
+
+    MockNet::default().drop_next_messages(&prov2.identity.identity, &req.identity.identity, 1);
+    let lost_id = prov2
+        .market
+        .subscribe_offer(&client::sample_offer(), &prov2.identity)
+        .await
+        .unwrap();
+    wait_for_bcast(500, &req.market, &lost_id, true).await;
+    assert!(req.market.get_offer(&lost_id).await.is_err());
+
+    let offer_id = prov2
+        .market
+        .subscribe_offer(&client::sample_offer(), &prov2.identity)
+        .await
+        .unwrap();
+    wait_for_bcast(1000, &req.market, &offer_id, true).await;
+    assert!(req.market.get_offer(&offer_id).await.is_ok());
+}
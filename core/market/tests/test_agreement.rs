@@ -1,6 +1,10 @@
 use actix_web::{http::StatusCode, test, web::Bytes};
 use chrono::{Duration, Utc};
 
+use serde_json::json;
+
+use ya_agreement_utils::agreement::flatten;
+use ya_agreement_utils::verify_agreement;
 use ya_core_model::{market, Role};
 use ya_market::assert_err_eq;
 use ya_market::testing::{
@@ -10,8 +14,8 @@ use ya_market::testing::{
     mock_agreement::generate_agreement,
     mock_node::MarketServiceExt,
     proposal_util::{exchange_draft_proposals, NegotiationHelper},
-    AgreementDao, AgreementDaoError, AgreementError, AgreementState, ApprovalStatus,
-    MarketsNetwork, Owner, ProposalState, WaitForApprovalError,
+    AgreementDao, AgreementDaoError, AgreementError, AgreementFilter, AgreementState,
+    AmendmentError, ApprovalStatus, MarketsNetwork, Owner, ProposalState, WaitForApprovalError,
 };
 use ya_service_bus::{typed as bus, RpcEndpoint};
 
@@ -100,6 +104,301 @@ async fn test_get_agreement() {
     assert_eq!(agreement.offer.provider_id, prov_id.identity);
 }
 
+#[cfg_attr(not(feature = "test-suite"), ignore)]
+#[serial_test::serial]
+async fn test_agreement_signatures() {
+    let network = MarketsNetwork::new(None)
+        .await
+        .add_market_instance(REQ_NAME)
+        .await
+        .add_market_instance(PROV_NAME)
+        .await;
+
+    let req_market = network.get_market(REQ_NAME);
+    let prov_market = network.get_market(PROV_NAME);
+    let req_id = network.get_default_id(REQ_NAME);
+    let prov_id = network.get_default_id(PROV_NAME);
+
+    let negotiation = negotiate_agreement(
+        &network,
+        REQ_NAME,
+        PROV_NAME,
+        "negotiation",
+        "r-session",
+        "p-session",
+    )
+    .await
+    .unwrap();
+
+    let r_agreement = req_market
+        .get_agreement(&negotiation.r_agreement, &req_id)
+        .await
+        .unwrap();
+    let p_agreement = prov_market
+        .get_agreement(&negotiation.p_agreement, &prov_id)
+        .await
+        .unwrap();
+
+    // Both sides should store the same signatures and be able to prove,
+    // that the other party agreed on exactly the same terms.
+    verify_agreement(&r_agreement, 0).unwrap();
+    verify_agreement(&p_agreement, 0).unwrap();
+    assert!(r_agreement.committed_signature.is_some());
+    assert_eq!(
+        r_agreement.proposed_signature,
+        p_agreement.proposed_signature
+    );
+    assert_eq!(
+        r_agreement.approved_signature,
+        p_agreement.approved_signature
+    );
+    assert_eq!(
+        r_agreement.committed_signature,
+        p_agreement.committed_signature
+    );
+
+    // Changing any of agreed terms invalidates signatures.
+    let mut forged = p_agreement.clone();
+    forged.valid_to = forged.valid_to + Duration::hours(1);
+    assert!(verify_agreement(&forged, 0).is_err());
+}
+
+#[cfg_attr(not(feature = "test-suite"), ignore)]
+#[serial_test::serial]
+async fn test_agreement_amendment() {
+    let network = MarketsNetwork::new(None)
+        .await
+        .add_market_instance(REQ_NAME)
+        .await
+        .add_market_instance(PROV_NAME)
+        .await;
+
+    let req_market = network.get_market(REQ_NAME);
+    let prov_market = network.get_market(PROV_NAME);
+    let req_id = network.get_default_id(REQ_NAME);
+    let prov_id = network.get_default_id(PROV_NAME);
+
+    let negotiation = negotiate_agreement(
+        &network,
+        REQ_NAME,
+        PROV_NAME,
+        "negotiation",
+        "r-session",
+        "p-session",
+    )
+    .await
+    .unwrap();
+    let client_agreement_id = negotiation.r_agreement.into_client();
+
+    let amendment = req_market
+        .propose_amendment(
+            req_id.clone(),
+            client_agreement_id.clone(),
+            market::AmendmentProposal {
+                offer_properties: json!({}),
+                demand_properties: json!({"golem.srv.amended": "yes"}),
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(amendment.version, 1);
+    assert_eq!(amendment.issuer, Role::Requestor);
+    assert_eq!(amendment.state, market::AmendmentState::Proposed);
+
+    // Only the other party can resolve amendment.
+    match req_market
+        .approve_amendment(req_id.clone(), client_agreement_id.clone(), 1)
+        .await
+    {
+        Err(AmendmentError::OwnAmendment(..)) => (),
+        e => panic!("Expected OwnAmendment error, got: {:?}", e),
+    }
+
+    let amendments = prov_market
+        .list_amendments(prov_id.clone(), client_agreement_id.clone())
+        .await
+        .unwrap();
+    assert_eq!(amendments.len(), 1);
+    assert_eq!(amendments[0].state, market::AmendmentState::Proposed);
+    assert_eq!(amendments[0].demand_properties, amendment.demand_properties);
+
+    let approved = prov_market
+        .approve_amendment(prov_id.clone(), client_agreement_id.clone(), 1)
+        .await
+        .unwrap();
+    assert_eq!(approved.state, market::AmendmentState::Approved);
+
+    // Both sides should see amended terms signed by both parties.
+    let r_agreement = req_market
+        .get_agreement(&negotiation.r_agreement, &req_id)
+        .await
+        .unwrap();
+    let p_agreement = prov_market
+        .get_agreement(&negotiation.p_agreement, &prov_id)
+        .await
+        .unwrap();
+    for agreement in &[&r_agreement, &p_agreement] {
+        verify_agreement(agreement, 1).unwrap();
+        // Signatures are bound to Agreement version.
+        assert!(verify_agreement(agreement, 0).is_err());
+        assert_eq!(
+            flatten(agreement.demand.properties.clone())["golem.srv.amended"],
+            json!("yes")
+        );
+    }
+    assert_eq!(
+        r_agreement.approved_signature,
+        p_agreement.approved_signature
+    );
+
+    // Rejected amendment doesn't change Agreement.
+    prov_market
+        .propose_amendment(
+            prov_id.clone(),
+            client_agreement_id.clone(),
+            market::AmendmentProposal {
+                offer_properties: json!({"golem.srv.amended": null}),
+                demand_properties: json!({}),
+            },
+        )
+        .await
+        .unwrap();
+    let rejected = req_market
+        .reject_amendment(
+            req_id.clone(),
+            client_agreement_id.clone(),
+            2,
+            Some(gen_reason("Not now")),
+        )
+        .await
+        .unwrap();
+    assert_eq!(rejected.state, market::AmendmentState::Rejected);
+    assert_eq!(rejected.issuer, Role::Provider);
+
+    let amendments = prov_market
+        .list_amendments(prov_id.clone(), client_agreement_id.clone())
+        .await
+        .unwrap();
+    assert_eq!(amendments.len(), 2);
+    assert_eq!(amendments[1].state, market::AmendmentState::Rejected);
+
+    let unchanged = prov_market
+        .get_agreement(&negotiation.p_agreement, &prov_id)
+        .await
+        .unwrap();
+    assert_eq!(unchanged.offer.properties, p_agreement.offer.properties);
+    assert_eq!(unchanged.approved_signature, p_agreement.approved_signature);
+}
+
+#[cfg_attr(not(feature = "test-suite"), ignore)]
+#[serial_test::serial]
+async fn test_concurrent_amendment_proposals() {
+    let network = MarketsNetwork::new(None)
+        .await
+        .add_market_instance(REQ_NAME)
+        .await
+        .add_market_instance(PROV_NAME)
+        .await;
+
+    let req_market = network.get_market(REQ_NAME);
+    let prov_market = network.get_market(PROV_NAME);
+    let req_id = network.get_default_id(REQ_NAME);
+    let prov_id = network.get_default_id(PROV_NAME);
+
+    let negotiation = negotiate_agreement(
+        &network,
+        REQ_NAME,
+        PROV_NAME,
+        "negotiation",
+        "r-session",
+        "p-session",
+    )
+    .await
+    .unwrap();
+    let client_agreement_id = negotiation.r_agreement.into_client();
+    let proposal = || market::AmendmentProposal {
+        offer_properties: json!({}),
+        demand_properties: json!({"golem.srv.amended": "yes"}),
+    };
+
+    // Both parties propose at the same time. Neither of them can wait
+    // for the other one, so both calls return and at most one succeeds.
+    let (r_result, p_result) = futures::join!(
+        req_market.propose_amendment(req_id.clone(), client_agreement_id.clone(), proposal()),
+        prov_market.propose_amendment(prov_id.clone(), client_agreement_id.clone(), proposal()),
+    );
+    assert!(r_result.is_err() || p_result.is_err());
+
+    // Both parties see the same amendments.
+    let r_amendments = req_market
+        .list_amendments(req_id.clone(), client_agreement_id.clone())
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|amendment| (amendment.version, amendment.issuer))
+        .collect::<Vec<_>>();
+    let p_amendments = prov_market
+        .list_amendments(prov_id.clone(), client_agreement_id.clone())
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|amendment| (amendment.version, amendment.issuer))
+        .collect::<Vec<_>>();
+    assert_eq!(r_amendments, p_amendments);
+}
+
+#[cfg_attr(not(feature = "test-suite"), ignore)]
+#[serial_test::serial]
+async fn test_undelivered_amendment_is_withdrawn() {
+    let network = MarketsNetwork::new(None)
+        .await
+        .add_market_instance(REQ_NAME)
+        .await
+        .add_market_instance(PROV_NAME)
+        .await;
+
+    let req_market = network.get_market(REQ_NAME);
+    let req_id = network.get_default_id(REQ_NAME);
+
+    let negotiation = negotiate_agreement(
+        &network,
+        REQ_NAME,
+        PROV_NAME,
+        "negotiation",
+        "r-session",
+        "p-session",
+    )
+    .await
+    .unwrap();
+    let client_agreement_id = negotiation.r_agreement.into_client();
+    let proposal = || market::AmendmentProposal {
+        offer_properties: json!({}),
+        demand_properties: json!({"golem.srv.amended": "yes"}),
+    };
+
+    network.break_networking_for(PROV_NAME).unwrap();
+    match req_market
+        .propose_amendment(req_id.clone(), client_agreement_id.clone(), proposal())
+        .await
+    {
+        Err(AmendmentError::Protocol(_)) => (),
+        e => panic!("Expected protocol error, got: {:?}", e),
+    }
+    assert!(req_market
+        .list_amendments(req_id.clone(), client_agreement_id.clone())
+        .await
+        .unwrap()
+        .is_empty());
+
+    // Withdrawn amendment doesn't block the next one.
+    network.enable_networking_for(PROV_NAME).unwrap();
+    let amendment = req_market
+        .propose_amendment(req_id.clone(), client_agreement_id.clone(), proposal())
+        .await
+        .unwrap();
+    assert_eq!(amendment.version, 1);
+}
+
 #[cfg_attr(not(feature = "test-suite"), ignore)]
 #[serial_test::serial]
 async fn test_rest_get_not_existing_agreement() {
@@ -1217,3 +1516,58 @@ async fn test_terminate_invalid_reason() {
     let resp = test::call_service(&mut app, req).await;
     assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
 }
+
+#[cfg_attr(not(feature = "test-suite"), ignore)]
+#[serial_test::serial]
+async fn test_list_agreements_doesnt_expire_stored_state() {
+    let network = MarketsNetwork::new(None)
+        .await
+        .add_market_instance(REQ_NAME)
+        .await;
+    let agreement_dao = network.get_market(REQ_NAME).db.as_dao::<AgreementDao>();
+    let valid_to = Utc::now().naive_utc();
+    let agreement = generate_agreement(1, valid_to);
+    agreement_dao.save(agreement.clone()).await.unwrap();
+
+    let in_states = |states: Vec<AgreementState>| AgreementFilter {
+        states: Some(states),
+        ..Default::default()
+    };
+    let after_validity = valid_to + Duration::seconds(1);
+
+    // Agreement, which wasn't finalized within validity period, is listed and counted as expired.
+    let listed = agreement_dao
+        .list(in_states(vec![AgreementState::Expired]), after_validity)
+        .await
+        .unwrap();
+    assert_eq!(listed.len(), 1);
+    assert_eq!(listed[0].state, AgreementState::Expired);
+    assert!(agreement_dao
+        .list(in_states(vec![AgreementState::Proposal]), after_validity)
+        .await
+        .unwrap()
+        .is_empty());
+    assert_eq!(
+        agreement_dao
+            .count_by_state(None, after_validity)
+            .await
+            .unwrap(),
+        vec![(AgreementState::Expired, 1)]
+    );
+
+    // Reading doesn't change state stored in database.
+    let before_validity = valid_to - Duration::seconds(1);
+    let listed = agreement_dao
+        .list(in_states(vec![AgreementState::Proposal]), before_validity)
+        .await
+        .unwrap();
+    assert_eq!(listed.len(), 1);
+    assert_eq!(listed[0].state, AgreementState::Proposal);
+    assert_eq!(
+        agreement_dao
+            .count_by_state(None, before_validity)
+            .await
+            .unwrap(),
+        vec![(AgreementState::Proposal, 1)]
+    );
+}
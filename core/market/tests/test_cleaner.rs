@@ -67,6 +67,62 @@ async fn test_demand() {
     );
 }
 
+#[cfg_attr(not(feature = "test-suite"), ignore)]
+#[serial_test::serial]
+async fn test_demand_delivered_proposals() {
+    let valid_demand = generate_demand(
+        "c76161077d0343ab85ac986eb5f6ea38-edb0016d9f8bafb54540da34f05a8d510de8114488f23916276bdead05509a53",
+        future(),
+        );
+    let expired_demand = generate_demand(
+        "c76161077d0343ab85ac986eb5f6ea38-edb0016d9f8bafb54540da34f05a8d510de8114488f23916276bdead05509a54",
+        past(),
+        );
+    let db = MarketsNetwork::new(None).await.init_database("testnode");
+    let demand_dao = db.as_dao::<DemandDao>();
+    demand_dao.insert(&valid_demand).await.unwrap();
+    demand_dao.insert(&expired_demand).await.unwrap();
+
+    assert_eq!(
+        demand_dao
+            .admit_proposals(&valid_demand.id, 3, 2)
+            .await
+            .unwrap(),
+        2
+    );
+    assert_eq!(
+        demand_dao
+            .admit_proposals(&valid_demand.id, 1, 2)
+            .await
+            .unwrap(),
+        0
+    );
+    assert_eq!(
+        demand_dao
+            .admit_proposals(&expired_demand.id, 1, 2)
+            .await
+            .unwrap(),
+        1
+    );
+
+    // Count of expired Demand is cleaned together with Demand.
+    clean(db.clone()).await;
+    assert_eq!(
+        demand_dao
+            .admit_proposals(&valid_demand.id, 1, 2)
+            .await
+            .unwrap(),
+        0
+    );
+    assert_eq!(
+        demand_dao
+            .admit_proposals(&expired_demand.id, 3, 2)
+            .await
+            .unwrap(),
+        2
+    );
+}
+
 #[cfg_attr(not(feature = "test-suite"), ignore)]
 #[serial_test::serial]
 async fn test_offer() {
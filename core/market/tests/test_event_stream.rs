@@ -0,0 +1,193 @@
+use futures::StreamExt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use ya_client::model::market::event::RequestorEvent;
+use ya_market::assert_err_eq;
+use ya_market::testing::mock_offer::client::{sample_demand, sample_offer};
+use ya_market::testing::{Config, MarketsNetwork, QueryEventsError, TakeEventsError};
+
+const REQ_NAME: &str = "Node-1";
+
+/// Streamed events aren't removed until client resumes after them, so client
+/// can resume stream after any event it has seen without getting duplicates.
+#[cfg_attr(not(feature = "test-suite"), ignore)]
+#[serial_test::serial]
+async fn test_resume_event_stream_from_cursor() {
+    let mut config = Config::default();
+    config.events.stream_keep_alive_interval = Duration::from_millis(100);
+
+    let network = MarketsNetwork::new(None)
+        .await
+        .with_config(Arc::new(config))
+        .add_market_instance(REQ_NAME)
+        .await;
+
+    let market = network.get_market(REQ_NAME);
+    let req_id = network.get_default_id(REQ_NAME);
+    let prov_id = network.create_identity(REQ_NAME, "Provider");
+
+    let demand_id = market
+        .subscribe_demand(&sample_demand(), &req_id)
+        .await
+        .unwrap();
+    market
+        .subscribe_offer(&sample_offer(), &prov_id)
+        .await
+        .unwrap();
+
+    let mut stream = Box::pin(
+        market
+            .requestor_engine
+            .stream_events(&demand_id, &req_id, None)
+            .await
+            .unwrap(),
+    );
+    let events = stream.next().await.unwrap().unwrap();
+    assert_eq!(events.len(), 1);
+    let (cursor, event) = events[0].clone();
+    match event {
+        RequestorEvent::ProposalEvent { .. } => (),
+        e => panic!("Invalid event Type. ProposalEvent expected, got: {:?}", e),
+    };
+
+    // No new events, so we should get keep-alive.
+    let events = stream.next().await.unwrap().unwrap();
+    assert!(events.is_empty());
+
+    // Reconnecting without cursor replays the same event.
+    let mut stream = Box::pin(
+        market
+            .requestor_engine
+            .stream_events(&demand_id, &req_id, None)
+            .await
+            .unwrap(),
+    );
+    let events = stream.next().await.unwrap().unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].0, cursor);
+
+    // Resuming from cursor skips events already seen.
+    let mut stream = Box::pin(
+        market
+            .requestor_engine
+            .stream_events(&demand_id, &req_id, Some(cursor))
+            .await
+            .unwrap(),
+    );
+    let events = stream.next().await.unwrap().unwrap();
+    assert!(events.is_empty());
+
+    // Resuming acknowledged events up to cursor, so they aren't replayed anymore.
+    let mut replayed = Box::pin(
+        market
+            .requestor_engine
+            .stream_events(&demand_id, &req_id, None)
+            .await
+            .unwrap(),
+    );
+    let events = replayed.next().await.unwrap().unwrap();
+    assert!(events.is_empty());
+
+    // Event added to subscription is pushed to resumed stream.
+    market
+        .subscribe_offer(&sample_offer(), &prov_id)
+        .await
+        .unwrap();
+    let events = tokio::time::timeout(Duration::from_secs(1), async {
+        loop {
+            let events = stream.next().await.unwrap().unwrap();
+            if !events.is_empty() {
+                return events;
+            }
+        }
+    })
+    .await
+    .unwrap();
+    assert_eq!(events.len(), 1);
+    assert!(events[0].0 > cursor);
+}
+
+/// Stream can't be opened for not existing subscription.
+#[cfg_attr(not(feature = "test-suite"), ignore)]
+#[serial_test::serial]
+async fn test_event_stream_non_existent_subscription() {
+    let network = MarketsNetwork::new(None)
+        .await
+        .add_market_instance(REQ_NAME)
+        .await;
+
+    let market = network.get_market(REQ_NAME);
+    let req_id = network.get_default_id(REQ_NAME);
+    let non_existent_id = "80da375cb604426fb6cddd64f4ccc715-85fdde1924371f4a3a412748f61e5b941c500ea69a55a5135b886a2bffcb8e55".parse().unwrap();
+
+    let result = market
+        .requestor_engine
+        .stream_events(&non_existent_id, &req_id, None)
+        .await
+        .map(|_| ());
+    assert_err_eq!(
+        QueryEventsError::TakeEvents(TakeEventsError::NotFound(non_existent_id)),
+        result
+    );
+}
+
+/// Identity, which doesn't own subscription, can neither stream its events
+/// nor acknowledge them by resuming from cursor.
+#[cfg_attr(not(feature = "test-suite"), ignore)]
+#[serial_test::serial]
+async fn test_event_stream_of_other_identity() {
+    let network = MarketsNetwork::new(None)
+        .await
+        .add_market_instance(REQ_NAME)
+        .await;
+
+    let market = network.get_market(REQ_NAME);
+    let req_id = network.get_default_id(REQ_NAME);
+    let prov_id = network.create_identity(REQ_NAME, "Provider");
+    let other_id = network.create_identity(REQ_NAME, "Other");
+
+    let demand_id = market
+        .subscribe_demand(&sample_demand(), &req_id)
+        .await
+        .unwrap();
+    market
+        .subscribe_offer(&sample_offer(), &prov_id)
+        .await
+        .unwrap();
+
+    let mut stream = Box::pin(
+        market
+            .requestor_engine
+            .stream_events(&demand_id, &req_id, None)
+            .await
+            .unwrap(),
+    );
+    let events = stream.next().await.unwrap().unwrap();
+    assert_eq!(events.len(), 1);
+    let cursor = events[0].0;
+
+    for cursor in vec![None, Some(cursor)] {
+        let result = market
+            .requestor_engine
+            .stream_events(&demand_id, &other_id, cursor)
+            .await
+            .map(|_| ());
+        assert_err_eq!(
+            QueryEventsError::TakeEvents(TakeEventsError::NotFound(demand_id.clone())),
+            result
+        );
+    }
+
+    // Event wasn't acknowledged by other identity, so owner still gets it.
+    let mut replayed = Box::pin(
+        market
+            .requestor_engine
+            .stream_events(&demand_id, &req_id, None)
+            .await
+            .unwrap(),
+    );
+    let events = replayed.next().await.unwrap().unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].0, cursor);
+}
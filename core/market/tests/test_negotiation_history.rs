@@ -0,0 +1,160 @@
+use serde_json::json;
+
+use ya_client::model::market::proposal::State;
+use ya_market::assert_err_eq;
+use ya_market::testing::{
+    agreement_utils::negotiate_agreement, proposal_util::exchange_draft_proposals, AgreementError,
+    GetProposalError, MarketServiceExt, MarketsNetwork, ProposalId,
+};
+
+const REQ_NAME: &str = "Node-1";
+const PROV_NAME: &str = "Node-2";
+
+/// Negotiation history should contain all Proposals from initial one
+/// to the one promoted to Agreement, on both sides.
+#[cfg_attr(not(feature = "test-suite"), ignore)]
+#[serial_test::serial]
+async fn test_agreement_negotiation_history() {
+    let network = MarketsNetwork::new(None)
+        .await
+        .add_market_instance(REQ_NAME)
+        .await
+        .add_market_instance(PROV_NAME)
+        .await;
+
+    let req_market = network.get_market(REQ_NAME);
+    let prov_market = network.get_market(PROV_NAME);
+    let req_id = network.get_default_id(REQ_NAME);
+    let prov_id = network.get_default_id(PROV_NAME);
+
+    let negotiation = negotiate_agreement(
+        &network,
+        REQ_NAME,
+        PROV_NAME,
+        "negotiation-history",
+        "r-session",
+        "p-session",
+    )
+    .await
+    .unwrap();
+
+    let steps = req_market
+        .agreement_negotiation(req_id.clone(), negotiation.r_agreement.into_client())
+        .await
+        .unwrap();
+    assert_eq!(steps.len(), 3);
+    assert_eq!(steps[0].prev_proposal_id, None);
+    assert_eq!(
+        steps[1].prev_proposal_id.as_ref(),
+        Some(&steps[0].proposal_id)
+    );
+    assert_eq!(
+        steps[2].prev_proposal_id.as_ref(),
+        Some(&steps[1].proposal_id)
+    );
+    assert_eq!(steps[0].issuer_id, prov_id.identity);
+    assert_eq!(steps[1].issuer_id, req_id.identity);
+    assert_eq!(steps[2].issuer_id, prov_id.identity);
+    assert_eq!(
+        steps[2].proposal_id,
+        negotiation.negotiation.proposal_id.to_string()
+    );
+    assert_eq!(steps[2].state, State::Accepted);
+
+    // First Proposal of each party lists all properties.
+    assert_eq!(steps[0].issuer_prev_proposal_id, None);
+    assert_eq!(steps[1].issuer_prev_proposal_id, None);
+    assert!(steps[0].issuer_constraints.is_some());
+    assert!(steps[1].issuer_constraints.is_some());
+    assert!(steps[0]
+        .issuer_changes
+        .iter()
+        .any(|change| change.name == "golem.node.id.name"
+            && change.previous.is_none()
+            && change.current == Some(json!("its-test-provider"))));
+    // Provider countered with the same Offer, which is compared
+    // with its previous Offer Proposal, not with Requestor's Proposal.
+    assert_eq!(
+        steps[2].issuer_prev_proposal_id,
+        Some(steps[0].proposal_id.clone())
+    );
+    assert_eq!(
+        steps[2].prev_proposal_id,
+        Some(steps[1].proposal_id.clone())
+    );
+    assert!(steps[2].issuer_changes.is_empty());
+    assert!(steps[2].issuer_constraints.is_none());
+
+    // Provider sees the same chain.
+    let p_steps = prov_market
+        .agreement_negotiation(prov_id.clone(), negotiation.p_agreement.into_client())
+        .await
+        .unwrap();
+    assert_eq!(p_steps.len(), 3);
+    assert_eq!(p_steps[2].issuer_id, prov_id.identity);
+    assert_eq!(p_steps[2].state, State::Accepted);
+
+    // Agreement isn't visible for other identities.
+    let other_id = network.create_identity(REQ_NAME, "Other");
+    let client_agreement_id = negotiation.r_agreement.into_client();
+    assert_err_eq!(
+        AgreementError::NotFound(client_agreement_id.clone()),
+        req_market
+            .agreement_negotiation(other_id, client_agreement_id)
+            .await,
+    );
+}
+
+/// History of Proposal can be queried only with subscription it belongs to
+/// and only by identity owning this subscription.
+#[cfg_attr(not(feature = "test-suite"), ignore)]
+#[serial_test::serial]
+async fn test_proposal_negotiation_history() {
+    let network = MarketsNetwork::new(None)
+        .await
+        .add_market_instance(REQ_NAME)
+        .await
+        .add_market_instance(PROV_NAME)
+        .await;
+
+    let negotiation = exchange_draft_proposals(&network, REQ_NAME, PROV_NAME)
+        .await
+        .unwrap();
+    let req_market = network.get_market(REQ_NAME);
+    let req_id = network.get_default_id(REQ_NAME);
+
+    let steps = req_market
+        .negotiation_history(&negotiation.demand_id, &negotiation.proposal_id, &req_id)
+        .await
+        .unwrap();
+    assert_eq!(steps.len(), 3);
+    assert_eq!(steps[2].state, State::Draft);
+
+    let proposal_id: ProposalId = steps[1].proposal_id.parse().unwrap();
+    let steps = req_market
+        .negotiation_history(&negotiation.demand_id, &proposal_id, &req_id)
+        .await
+        .unwrap();
+    assert_eq!(steps.len(), 2);
+
+    assert_err_eq!(
+        GetProposalError::NotFound(
+            negotiation.proposal_id.clone(),
+            Some(negotiation.offer_id.clone())
+        ),
+        req_market
+            .negotiation_history(&negotiation.offer_id, &negotiation.proposal_id, &req_id)
+            .await,
+    );
+
+    let other_id = network.create_identity(REQ_NAME, "Other");
+    assert_err_eq!(
+        GetProposalError::NotFound(
+            negotiation.proposal_id.clone(),
+            Some(negotiation.demand_id.clone())
+        ),
+        req_market
+            .negotiation_history(&negotiation.demand_id, &negotiation.proposal_id, &other_id)
+            .await,
+    );
+}
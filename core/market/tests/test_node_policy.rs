@@ -0,0 +1,80 @@
+use ya_client::model::market::event::RequestorEvent;
+use ya_core_model::market::NodePolicy;
+use ya_market::testing::mock_offer::client::{sample_demand, sample_offer};
+use ya_market::testing::{MarketServiceExt, MarketsNetwork, NodePolicyError};
+
+const REQ_NAME: &str = "Node-1";
+
+/// Offers of blocked node shouldn't generate Proposals. After removing policy
+/// new Offers of this node are matched again.
+#[cfg_attr(not(feature = "test-suite"), ignore)]
+#[serial_test::serial]
+async fn test_blocked_node_offers_dont_match() {
+    let network = MarketsNetwork::new(None)
+        .await
+        .add_market_instance(REQ_NAME)
+        .await;
+
+    let market = network.get_market(REQ_NAME);
+    let req_id = network.get_default_id(REQ_NAME);
+    let prov_id = network.create_identity(REQ_NAME, "Provider");
+
+    let entry = market
+        .set_node_policy(
+            req_id.clone(),
+            prov_id.identity,
+            NodePolicy::Block,
+            Some("spammer".to_string()),
+        )
+        .await
+        .unwrap();
+    assert_eq!(entry.owner_id, req_id.identity);
+    assert_eq!(entry.policy, NodePolicy::Block);
+
+    let policies = market.list_node_policies(req_id.clone()).await.unwrap();
+    assert_eq!(policies.len(), 1);
+    assert_eq!(policies[0].node_id, prov_id.identity);
+
+    let demand_id = market
+        .subscribe_demand(&sample_demand(), &req_id)
+        .await
+        .unwrap();
+    market
+        .subscribe_offer(&sample_offer(), &prov_id)
+        .await
+        .unwrap();
+
+    let events = market.query_events(&demand_id, 0.5, Some(5)).await.unwrap();
+    assert_eq!(events.len(), 0);
+
+    market
+        .remove_node_policy(req_id.clone(), prov_id.identity)
+        .await
+        .unwrap();
+    assert!(market
+        .list_node_policies(req_id.clone())
+        .await
+        .unwrap()
+        .is_empty());
+
+    market
+        .subscribe_offer(&sample_offer(), &prov_id)
+        .await
+        .unwrap();
+
+    let events = market.query_events(&demand_id, 1.0, Some(5)).await.unwrap();
+    assert_eq!(events.len(), 1);
+    match &events[0] {
+        RequestorEvent::ProposalEvent { .. } => (),
+        e => panic!("Invalid event Type. ProposalEvent expected, got: {:?}", e),
+    };
+
+    // Removing not existing policy should fail.
+    match market
+        .remove_node_policy(req_id.clone(), prov_id.identity)
+        .await
+    {
+        Err(NodePolicyError::NotFound(node_id)) => assert_eq!(node_id, prov_id.identity),
+        result => panic!("NotFound error expected, got: {:?}", result),
+    };
+}
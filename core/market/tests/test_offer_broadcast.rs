@@ -9,7 +9,7 @@ use ya_market::assert_err_eq;
 use ya_market::testing::discovery::{message::*, Discovery};
 use ya_market::testing::mock_offer::{client, sample_offer, sample_offer_with_expiration};
 use ya_market::testing::{wait_for_bcast, MarketServiceExt, MarketsNetwork};
-use ya_market::testing::{QueryOfferError, SubscriptionId};
+use ya_market::testing::{Config, QueryOfferError, SubscriptionId};
 
 /// Test adds offer. It should be broadcasted to other nodes in the network.
 /// Than sending unsubscribe should remove Offer from other nodes.
@@ -144,6 +144,96 @@ async fn test_broadcast_offer_id_validation() {
     );
 }
 
+/// Offer should be signed by identity, that it claims to be issued by.
+/// Market should reject Offers with invalid signature or without signature,
+/// since anyone could publish Offers in the name of other Node.
+#[cfg_attr(not(feature = "test-suite"), ignore)]
+#[serial_test::serial]
+async fn test_broadcast_offer_signature_validation() {
+    let _ = env_logger::builder().try_init();
+    let network = MarketsNetwork::new(None)
+        .await
+        .add_market_instance("Node-1")
+        .await;
+
+    let market1 = network.get_market("Node-1");
+
+    // Signature made by other identity for other Offer.
+    let mut forged = sample_offer();
+    forged.signature = sample_offer().signature;
+    let mut unsigned = sample_offer();
+    unsigned.signature = None;
+
+    let offers = vec![forged.clone(), unsigned.clone()];
+    let offer_ids = vec![forged.id.clone(), unsigned.id.clone()];
+
+    let network = network
+        .add_discovery_instance(
+            "Node-2",
+            MarketsNetwork::discovery_builder().add_handler(move |_: String, _: RetrieveOffers| {
+                let offers = offers.clone();
+                async move { Ok(offers) }
+            }),
+        )
+        .await;
+    let discovery2: Discovery = network.get_discovery("Node-2");
+
+    // Offers should be propagated to market1, but he should reject them.
+    discovery2.bcast_offers(offer_ids.clone()).await.unwrap();
+
+    for offer_id in offer_ids.iter() {
+        wait_for_bcast(100, &market1, offer_id, false).await;
+        assert_err_eq!(
+            QueryOfferError::NotFound(offer_id.clone()),
+            market1.get_offer(offer_id).await,
+        );
+    }
+}
+
+/// Nodes using previous discovery protocol version don't sign Offers.
+/// Market should accept their Offers, if compatibility is enabled in config.
+#[cfg_attr(not(feature = "test-suite"), ignore)]
+#[serial_test::serial]
+async fn test_broadcast_unsigned_offer_accepted_in_compatibility_mode() {
+    let _ = env_logger::builder().try_init();
+    let mut config = Config::default();
+    config.discovery.max_bcasted_offers = 0;
+    config.discovery.max_bcasted_unsubscribes = 0;
+    config.discovery.accept_unsigned_offers = true;
+
+    let network = MarketsNetwork::new(None)
+        .await
+        .with_config(Arc::new(config))
+        .add_market_instance("Node-1")
+        .await;
+
+    let market1 = network.get_market("Node-1");
+
+    let mut offer = sample_offer();
+    offer.signature = None;
+    let offer_clone = offer.clone();
+    let offer_id = offer.id.clone();
+
+    let network = network
+        .add_discovery_instance(
+            "Node-2",
+            MarketsNetwork::discovery_builder().add_handler(move |_: String, _: RetrieveOffers| {
+                let offer = offer.clone();
+                async move { Ok(vec![offer]) }
+            }),
+        )
+        .await;
+    let discovery2: Discovery = network.get_discovery("Node-2");
+
+    discovery2
+        .bcast_offers(vec![offer_id.clone()])
+        .await
+        .unwrap();
+
+    wait_for_bcast(1000, &market1, &offer_id, true).await;
+    assert_eq!(offer_clone, market1.get_offer(&offer_id).await.unwrap());
+}
+
 /// Node should reject Offer, that already expired.
 #[cfg_attr(not(feature = "test-suite"), ignore)]
 #[serial_test::serial]
@@ -188,6 +278,136 @@ async fn test_broadcast_expired_offer() {
     );
 }
 
+/// Node shouldn't store more active Offers of single node, than configured.
+#[cfg_attr(not(feature = "test-suite"), ignore)]
+#[serial_test::serial]
+async fn test_broadcast_stored_offers_limit() {
+    let _ = env_logger::builder().try_init();
+    let mut config = Config::default();
+    config.discovery.max_stored_offers_per_node = 2;
+
+    let network = MarketsNetwork::new(None)
+        .await
+        .with_config(Arc::new(config))
+        .add_market_instance("Node-1")
+        .await
+        .add_market_instance("Node-2")
+        .await;
+
+    let market1 = network.get_market("Node-1");
+    let market2 = network.get_market("Node-2");
+    let id1 = network.get_default_id("Node-1");
+
+    // Local Offers aren't limited.
+    let mut offer_ids = vec![];
+    for _ in 0..3 {
+        let subscription_id = market1
+            .subscribe_offer(&client::sample_offer(), &id1)
+            .await
+            .unwrap();
+        let stored = wait_for_bcast(500, &market2, &subscription_id, true).await;
+        assert_eq!(stored, offer_ids.len() < 2);
+        offer_ids.push(subscription_id);
+    }
+    assert_eq!(
+        market1
+            .matcher
+            .store
+            .get_offers(offer_ids.clone())
+            .await
+            .unwrap()
+            .len(),
+        3
+    );
+    assert_eq!(
+        market2
+            .matcher
+            .store
+            .get_offers(offer_ids.clone())
+            .await
+            .unwrap()
+            .len(),
+        2
+    );
+
+    market2.get_offer(&offer_ids[0]).await.unwrap();
+    market2.get_offer(&offer_ids[1]).await.unwrap();
+    assert_err_eq!(
+        QueryOfferError::NotFound(offer_ids[2].clone()),
+        market2.get_offer(&offer_ids[2]).await,
+    );
+}
+
+/// Broadcast quotas are counted for node sending broadcasts, not for Offer issuers.
+/// Otherwise single node could flood us with Offers of many generated identities.
+/// Ids exceeding quota shouldn't be even requested from sender.
+#[cfg_attr(not(feature = "test-suite"), ignore)]
+#[serial_test::serial]
+async fn test_broadcast_quota_of_flooding_node() {
+    let _ = env_logger::builder().try_init();
+    let mut config = Config::default();
+    config.discovery.max_received_offers_per_node = 2;
+    config.discovery.quota_period = std::time::Duration::from_secs(3600);
+    config.discovery.offender_ban_period = std::time::Duration::from_secs(3600);
+
+    let network = MarketsNetwork::new(None)
+        .await
+        .with_config(Arc::new(config))
+        .add_market_instance("Node-1")
+        .await;
+
+    let market1 = network.get_market("Node-1");
+
+    // Each Offer is issued by different identity.
+    let offers = (0..5).map(|_| sample_offer()).collect::<Vec<_>>();
+    let offer_ids = offers.iter().map(|o| o.id.clone()).collect::<Vec<_>>();
+    let requested = Arc::new(AtomicUsize::new(0));
+
+    let requested_clone = requested.clone();
+    let network = network
+        .add_discovery_instance(
+            "Node-2",
+            MarketsNetwork::discovery_builder().add_handler(
+                move |_: String, msg: RetrieveOffers| {
+                    let offers = offers.clone();
+                    requested_clone.fetch_add(msg.offer_ids.len(), Ordering::SeqCst);
+                    async move {
+                        Ok(offers
+                            .into_iter()
+                            .filter(|o| msg.offer_ids.contains(&o.id))
+                            .collect())
+                    }
+                },
+            ),
+        )
+        .await;
+    let discovery2: Discovery = network.get_discovery("Node-2");
+
+    discovery2
+        .bcast_offers(offer_ids[..4].to_vec())
+        .await
+        .unwrap();
+    wait_for_bcast(1000, &market1, &offer_ids[1], true).await;
+
+    market1.get_offer(&offer_ids[0]).await.unwrap();
+    market1.get_offer(&offer_ids[1]).await.unwrap();
+    for offer_id in offer_ids[2..4].iter() {
+        assert_err_eq!(
+            QueryOfferError::NotFound(offer_id.clone()),
+            market1.get_offer(offer_id).await,
+        );
+    }
+    assert_eq!(requested.load(Ordering::SeqCst), 2);
+
+    // Node exceeding quota is banned, even for Offers of new issuers.
+    discovery2
+        .bcast_offers(vec![offer_ids[4].clone()])
+        .await
+        .unwrap();
+    assert!(!wait_for_bcast(500, &market1, &offer_ids[4], true).await);
+    assert_eq!(requested.load(Ordering::SeqCst), 2);
+}
+
 /// Nodes shouldn't broadcast unsubscribed Offers.
 /// This test broadcasts unsubscribed Offer and checks how other market Nodes
 /// behave. We expect that market nodes will stop broadcast and Discovery interface will
@@ -311,10 +531,12 @@ async fn test_discovery_get_offers() {
         .get_remote_offers(
             id1.identity.to_string(),
             vec![subscription_id.clone(), invalid_subscription],
+            false,
         )
         .await
         .unwrap();
 
     assert_eq!(offers.len(), 1);
     assert_eq!(offers[0].id, subscription_id);
+    offers[0].verify_signature().unwrap();
 }
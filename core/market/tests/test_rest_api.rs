@@ -1,5 +1,5 @@
 use actix_web::{
-    body::MessageBody, dev::ServiceResponse, error::PathError, http::StatusCode, test,
+    body::MessageBody, dev::ServiceResponse, error::PathError, http::header, http::StatusCode, test,
 };
 use chrono::Utc;
 use serde::de::DeserializeOwned;
@@ -12,16 +12,19 @@ use ya_client::model::market::{
 };
 use ya_client::model::ErrorMessage;
 use ya_client::web::QueryParamsBuilder;
+use ya_core_model::market::{AmendmentEvent, AmendmentEventType, AmendmentProposal};
+use ya_core_model::Role;
 use ya_market::testing::agreement_utils::negotiate_agreement;
 use ya_market::testing::events_helper::requestor::expect_approve;
 use ya_market::testing::{
     agreement_utils::gen_reason,
-    client::{sample_demand, sample_offer},
+    client::{not_matching_offer, sample_demand, sample_offer},
     mock_node::{wait_for_bcast, MarketServiceExt},
     mock_offer::flatten_json,
     proposal_util::exchange_draft_proposals,
     DemandError, MarketsNetwork, ModifyOfferError, Owner, SubscriptionId, SubscriptionParseError,
 };
+use ya_market_resolver::{ExplainResult, MatchExplanation};
 
 const REQ_NAME: &str = "Node-1";
 const PROV_NAME: &str = "Node-2";
@@ -270,6 +273,198 @@ async fn test_rest_subscribe_unsubscribe_demand() {
     );
 }
 
+#[cfg_attr(not(feature = "test-suite"), ignore)]
+#[serial_test::serial]
+async fn test_rest_subscribe_returns_lint_warnings() {
+    let network = MarketsNetwork::new(None)
+        .await
+        .add_market_instance("Node-1")
+        .await;
+    let mut app = network.get_rest_app("Node-1").await;
+
+    let req = test::TestRequest::post()
+        .uri("/market-api/v1/demands")
+        .set_json(&sample_demand())
+        .to_request();
+    let resp = test::call_service(&mut app, req).await;
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    assert!(resp.headers().get(header::WARNING).is_none());
+
+    let demand = NewDemand::new(
+        json!({}),
+        "(&(golem.inf.mem.gib>8)(golem.inf.mem.gib<4)(golem.inf.cpu.coers>2))".to_string(),
+    );
+    let req = test::TestRequest::post()
+        .uri("/market-api/v1/demands")
+        .set_json(&demand)
+        .to_request();
+    let resp = test::call_service(&mut app, req).await;
+    assert_eq!(resp.status(), StatusCode::CREATED);
+
+    let warnings: Vec<String> = resp
+        .headers()
+        .get_all(header::WARNING)
+        .map(|value| value.to_str().unwrap().to_string())
+        .collect();
+    assert_eq!(warnings.len(), 2);
+    assert!(warnings[0].starts_with("199 yagna \"unknown property"));
+    assert!(warnings[1].starts_with("199 yagna \"contradiction"));
+
+    // warnings don't change response body
+    let _subscription_id: SubscriptionId = read_response_json(resp).await;
+
+    let offer = NewOffer::new(json!({}), "(golem.com.usage.vector>1)".to_string());
+    let req = test::TestRequest::post()
+        .uri("/market-api/v1/offers")
+        .set_json(&offer)
+        .to_request();
+    let resp = test::call_service(&mut app, req).await;
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    assert_eq!(resp.headers().get_all(header::WARNING).count(), 1);
+
+    // warnings requested in response body
+    let req = test::TestRequest::post()
+        .uri("/market-api/v1/offers?lint=true")
+        .set_json(&offer)
+        .to_request();
+    let resp = test::call_service(&mut app, req).await;
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let body: serde_json::Value = read_response_json(resp).await;
+    assert!(body["subscriptionId"].is_string());
+    assert_eq!(body["warnings"].as_array().unwrap().len(), 1);
+    assert_eq!(body["warnings"][0]["kind"], "invalidOperator");
+}
+
+#[cfg_attr(not(feature = "test-suite"), ignore)]
+#[serial_test::serial]
+async fn test_rest_explain_match() {
+    let network = MarketsNetwork::new(None)
+        .await
+        .add_market_instance("Node-1")
+        .await;
+
+    let market = network.get_market("Node-1");
+    let identity = network.get_default_id("Node-1");
+    let demand_id = market
+        .subscribe_demand(&sample_demand(), &identity)
+        .await
+        .unwrap();
+    let offer_id = market
+        .subscribe_offer(&sample_offer(), &identity)
+        .await
+        .unwrap();
+    let not_matching_id = market
+        .subscribe_offer(&not_matching_offer(), &identity)
+        .await
+        .unwrap();
+
+    let mut app = network.get_rest_app("Node-1").await;
+
+    let req = test::TestRequest::get()
+        .uri(&format!(
+            "/market-api/v1/demands/{}/offers/{}/explain",
+            demand_id, offer_id
+        ))
+        .to_request();
+    let resp = test::call_service(&mut app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let explanation: MatchExplanation = read_response_json(resp).await;
+    assert_eq!(explanation.result, ExplainResult::True);
+
+    let req = test::TestRequest::get()
+        .uri(&format!(
+            "/market-api/v1/demands/{}/offers/{}/explain",
+            demand_id, not_matching_id
+        ))
+        .to_request();
+    let resp = test::call_service(&mut app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let explanation: MatchExplanation = read_response_json(resp).await;
+    assert_eq!(explanation.result, ExplainResult::Undefined);
+    assert_eq!(explanation.demand_constraints.result, ExplainResult::True);
+
+    let failed = explanation
+        .offer_constraints
+        .operands
+        .iter()
+        .find(|clause| clause.result != ExplainResult::True)
+        .unwrap();
+    assert_eq!(failed.property, Some("custom.dontmatch".to_string()));
+    assert_eq!(failed.result, ExplainResult::Undefined);
+    assert_eq!(failed.value, None);
+}
+
+#[cfg_attr(not(feature = "test-suite"), ignore)]
+#[serial_test::serial]
+async fn test_rest_scan_offers() {
+    let network = MarketsNetwork::new(None)
+        .await
+        .add_market_instance("Node-1")
+        .await;
+
+    let market = network.get_market("Node-1");
+    let identity = network.get_default_id("Node-1");
+    let offer_id = market
+        .subscribe_offer(&sample_offer(), &identity)
+        .await
+        .unwrap();
+    let other_offer = NewOffer::new(
+        json!({ "golem": { "node.debug.subnet": "other" } }),
+        "()".to_string(),
+    );
+    market
+        .subscribe_offer(&other_offer, &identity)
+        .await
+        .unwrap();
+
+    let mut app = network.get_rest_app("Node-1").await;
+
+    let req = test::TestRequest::post()
+        .uri("/market-api/v1/scan")
+        .set_json(&json!({ "constraints": "(golem.node.debug.subnet=blaa)" }))
+        .to_request();
+    let resp = test::call_service(&mut app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let offers: Vec<Offer> = read_response_json(resp).await;
+    assert_eq!(offers.len(), 1);
+    assert_eq!(offers[0].offer_id, offer_id.to_string());
+
+    let req = test::TestRequest::post()
+        .uri("/market-api/v1/scan")
+        .set_json(&json!({ "constraints": "(golem.node.debug.subnet=*)", "limit": 1 }))
+        .to_request();
+    let resp = test::call_service(&mut app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let first_page: Vec<Offer> = read_response_json(resp).await;
+    assert_eq!(first_page.len(), 1);
+
+    let req = test::TestRequest::post()
+        .uri("/market-api/v1/scan")
+        .set_json(&json!({ "constraints": "(golem.node.debug.subnet=*)", "offset": 1 }))
+        .to_request();
+    let resp = test::call_service(&mut app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let second_page: Vec<Offer> = read_response_json(resp).await;
+    assert_eq!(second_page.len(), 1);
+    assert_ne!(first_page[0].offer_id, second_page[0].offer_id);
+
+    let req = test::TestRequest::post()
+        .uri("/market-api/v1/scan")
+        .set_json(&json!({ "constraints": "(golem.node.debug.subnet=*)", "limit": u32::MAX }))
+        .to_request();
+    let resp = test::call_service(&mut app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let all: Vec<Offer> = read_response_json(resp).await;
+    assert_eq!(all.len(), 2);
+
+    let req = test::TestRequest::post()
+        .uri("/market-api/v1/scan")
+        .set_json(&json!({ "constraints": "(golem.node.debug.subnet=blaa" }))
+        .to_request();
+    let resp = test::call_service(&mut app, req).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
 #[cfg_attr(not(feature = "test-suite"), ignore)]
 #[serial_test::serial]
 async fn test_rest_get_proposal() {
@@ -422,6 +617,217 @@ async fn test_rest_query_agreement_events() {
     expect_approve(events, "After agreementEvents").unwrap();
 }
 
+#[cfg_attr(not(feature = "test-suite"), ignore)]
+#[serial_test::serial]
+async fn test_rest_list_agreements() {
+    let network = MarketsNetwork::new(None)
+        .await
+        .add_market_instance("Node-1")
+        .await
+        .add_market_instance("Node-2")
+        .await;
+
+    let first = negotiate_agreement(
+        &network,
+        "Node-1",
+        "Node-2",
+        "first",
+        "r-session-1",
+        "p-session-1",
+    )
+    .await
+    .unwrap();
+    let second = negotiate_agreement(
+        &network,
+        "Node-1",
+        "Node-2",
+        "second",
+        "r-session-2",
+        "p-session-2",
+    )
+    .await
+    .unwrap();
+    let req_id = network.get_default_id("Node-1");
+    let prov_id = network.get_default_id("Node-2");
+
+    let list = |node_name: &'static str, query: String| {
+        let network = &network;
+        async move {
+            let mut app = network.get_rest_app(node_name).await;
+            let req = test::TestRequest::get()
+                .uri(&format!("/market-api/v1/agreements?{}", query))
+                .to_request();
+            let resp = test::call_service(&mut app, req).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+            read_response_json::<_, Vec<Agreement>>(resp)
+                .await
+                .into_iter()
+                .map(|agreement| agreement.agreement_id)
+                .collect::<Vec<_>>()
+        }
+    };
+
+    // Requestor sees only its own side of Agreements, ordered by creation time.
+    assert_eq!(
+        list("Node-1", QueryParamsBuilder::new().build()).await,
+        vec![
+            first.r_agreement.into_client(),
+            second.r_agreement.into_client()
+        ]
+    );
+    assert_eq!(
+        list(
+            "Node-1",
+            QueryParamsBuilder::new()
+                .put("state", Some("Approved"))
+                .put("appSessionId", Some("r-session-2"))
+                .build()
+        )
+        .await,
+        vec![second.r_agreement.into_client()]
+    );
+    assert_eq!(
+        list(
+            "Node-1",
+            QueryParamsBuilder::new()
+                .put("state", Some("Pending"))
+                .build()
+        )
+        .await,
+        Vec::<String>::new()
+    );
+    assert_eq!(
+        list(
+            "Node-1",
+            QueryParamsBuilder::new()
+                .put("offset", Some(1))
+                .put("limit", Some(1))
+                .build()
+        )
+        .await,
+        vec![second.r_agreement.into_client()]
+    );
+    assert_eq!(
+        list(
+            "Node-2",
+            QueryParamsBuilder::new()
+                .put("peerId", Some(req_id.identity))
+                .put("beforeDate", Some(Utc::now()))
+                .build()
+        )
+        .await,
+        vec![
+            first.p_agreement.into_client(),
+            second.p_agreement.into_client()
+        ]
+    );
+    // Peer is the other side of Agreement, not the owner.
+    assert_eq!(
+        list(
+            "Node-1",
+            QueryParamsBuilder::new()
+                .put("peerId", Some(prov_id.identity))
+                .build()
+        )
+        .await,
+        vec![
+            first.r_agreement.into_client(),
+            second.r_agreement.into_client()
+        ]
+    );
+    assert_eq!(
+        list(
+            "Node-1",
+            QueryParamsBuilder::new()
+                .put("peerId", Some(req_id.identity))
+                .build()
+        )
+        .await,
+        Vec::<String>::new()
+    );
+}
+
+#[cfg_attr(not(feature = "test-suite"), ignore)]
+#[serial_test::serial]
+async fn test_rest_amendment_events() {
+    let network = MarketsNetwork::new(None)
+        .await
+        .add_market_instance(REQ_NAME)
+        .await
+        .add_market_instance(PROV_NAME)
+        .await;
+    let req_id = network.get_default_id(REQ_NAME);
+    let prov_id = network.get_default_id(PROV_NAME);
+
+    let negotiation = negotiate_agreement(
+        &network,
+        REQ_NAME,
+        PROV_NAME,
+        "negotiation",
+        "r-session",
+        "p-session",
+    )
+    .await
+    .unwrap();
+    let agreement_id = negotiation.r_agreement.into_client();
+
+    network
+        .get_market(REQ_NAME)
+        .propose_amendment(
+            req_id,
+            agreement_id.clone(),
+            AmendmentProposal {
+                offer_properties: json!({}),
+                demand_properties: json!({"golem.srv.amended": "yes"}),
+            },
+        )
+        .await
+        .unwrap();
+    network
+        .get_market(PROV_NAME)
+        .reject_amendment(
+            prov_id,
+            agreement_id.clone(),
+            1,
+            Some(gen_reason("Not now")),
+        )
+        .await
+        .unwrap();
+
+    for node_name in &[REQ_NAME, PROV_NAME] {
+        let mut app = network.get_rest_app(node_name).await;
+        let req = test::TestRequest::get()
+            .uri(&format!(
+                "/market-api/v1/agreements/{}/amendmentEvents",
+                agreement_id
+            ))
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let events: Vec<AmendmentEvent> = read_response_json(resp).await;
+        let events = events
+            .into_iter()
+            .map(|event| {
+                assert_eq!(event.agreement_id, agreement_id);
+                (event.issuer, event.event_type)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            events,
+            vec![
+                (Role::Requestor, AmendmentEventType::AmendmentProposedEvent),
+                (
+                    Role::Provider,
+                    AmendmentEventType::AmendmentRejectedEvent {
+                        reason: Some(gen_reason("Not now"))
+                    }
+                ),
+            ]
+        );
+    }
+}
+
 #[cfg_attr(not(feature = "test-suite"), ignore)]
 #[serial_test::serial]
 async fn test_terminate_agreement() {
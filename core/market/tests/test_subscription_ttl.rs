@@ -0,0 +1,181 @@
+use actix_web::{http::StatusCode, test};
+use chrono::{DateTime, Duration, Utc};
+
+use ya_market::assert_err_eq;
+use ya_market::testing::mock_offer::client;
+use ya_market::testing::{
+    wait_for_bcast, MarketServiceExt, MarketsNetwork, ModifyOfferError, TtlError,
+};
+
+/// Refreshed Offer keeps its subscription id and its new expiration
+/// is propagated to other nodes.
+#[cfg_attr(not(feature = "test-suite"), ignore)]
+#[serial_test::serial]
+async fn test_refresh_offer() {
+    let _ = env_logger::builder().try_init();
+    let network = MarketsNetwork::new(None)
+        .await
+        .add_market_instance("Node-1")
+        .await
+        .add_market_instance("Node-2")
+        .await;
+
+    let market1 = network.get_market("Node-1");
+    let market2 = network.get_market("Node-2");
+    let id1 = network.get_default_id("Node-1");
+
+    let offer_id = market1
+        .subscribe_offer_with_ttl(&client::sample_offer(), &id1, Some(Duration::minutes(1)))
+        .await
+        .unwrap();
+    let offer = market1.get_offer(&offer_id).await.unwrap();
+    wait_for_bcast(1000, &market2, &offer_id, true).await;
+
+    let expiration_ts = market1
+        .refresh_offer(&offer_id, &id1, Some(Duration::hours(1)))
+        .await
+        .unwrap();
+    let refreshed = market1.get_offer(&offer_id).await.unwrap();
+    assert_eq!(refreshed.id, offer_id);
+    assert_eq!(
+        DateTime::<Utc>::from_utc(refreshed.expiration_ts, Utc),
+        expiration_ts
+    );
+    assert!(refreshed.expiration_ts > offer.expiration_ts);
+    assert_eq!(refreshed.initial_expiration_ts, Some(offer.expiration_ts));
+    refreshed.validate().unwrap();
+    refreshed.verify_signature().unwrap();
+
+    // Wait for refresh broadcast.
+    for _ in 0..10 {
+        let remote = market2.get_offer(&offer_id).await.unwrap();
+        if remote.expiration_ts == refreshed.expiration_ts {
+            break;
+        }
+        tokio::time::delay_for(std::time::Duration::from_millis(100)).await;
+    }
+    assert_eq!(refreshed, market2.get_offer(&offer_id).await.unwrap());
+
+    // Refresh never shortens expiration.
+    assert_eq!(
+        expiration_ts,
+        market1
+            .refresh_offer(&offer_id, &id1, Some(Duration::minutes(1)))
+            .await
+            .unwrap()
+    );
+
+    // Only Offer owner can refresh it.
+    let other_id = network.create_identity("Node-1", "Other");
+    assert_err_eq!(
+        ModifyOfferError::NotFound(offer_id.clone()),
+        market1.refresh_offer(&offer_id, &other_id, None).await,
+    );
+
+    market1.unsubscribe_offer(&offer_id, &id1).await.unwrap();
+    assert_err_eq!(
+        ModifyOfferError::AlreadyUnsubscribed(offer_id.clone()),
+        market1.refresh_offer(&offer_id, &id1, None).await,
+    );
+}
+
+/// Requested TTL must be within configured bounds.
+#[cfg_attr(not(feature = "test-suite"), ignore)]
+#[serial_test::serial]
+async fn test_demand_ttl_bounds() {
+    let network = MarketsNetwork::new(None)
+        .await
+        .add_market_instance("Node-1")
+        .await;
+
+    let market = network.get_market("Node-1");
+    let id = network.get_default_id("Node-1");
+
+    assert_err_eq!(
+        TtlError {
+            requested: 1,
+            min: 10,
+            max: 86400
+        },
+        market
+            .subscribe_demand_with_ttl(&client::sample_demand(), &id, Some(Duration::seconds(1)))
+            .await,
+    );
+    assert_err_eq!(
+        TtlError {
+            requested: 172800,
+            min: 10,
+            max: 86400
+        },
+        market
+            .subscribe_demand_with_ttl(&client::sample_demand(), &id, Some(Duration::days(2)))
+            .await,
+    );
+
+    let before = Utc::now().naive_utc();
+    let demand_id = market
+        .subscribe_demand_with_ttl(&client::sample_demand(), &id, Some(Duration::minutes(5)))
+        .await
+        .unwrap();
+    let demand = market.get_demand(&demand_id).await.unwrap();
+    assert!(demand.expiration_ts >= before + Duration::minutes(5));
+    assert!(demand.expiration_ts < before + Duration::minutes(6));
+
+    let expiration_ts = market
+        .refresh_demand(&demand_id, &id, Some(Duration::hours(2)))
+        .await
+        .unwrap();
+    let demand = market.get_demand(&demand_id).await.unwrap();
+    assert_eq!(
+        DateTime::<Utc>::from_utc(demand.expiration_ts, Utc),
+        expiration_ts
+    );
+    assert!(demand.expiration_ts >= before + Duration::hours(2));
+
+    assert!(market
+        .refresh_demand(&demand_id, &id, Some(Duration::days(2)))
+        .await
+        .is_err());
+}
+
+#[cfg_attr(not(feature = "test-suite"), ignore)]
+#[serial_test::serial]
+async fn test_rest_subscription_ttl() {
+    let network = MarketsNetwork::new(None)
+        .await
+        .add_market_instance("Node-1")
+        .await;
+    let mut app = network.get_rest_app("Node-1").await;
+
+    let req = test::TestRequest::post()
+        .uri("/market-api/v1/offers?ttl=1")
+        .set_json(&client::sample_offer())
+        .to_request();
+    let resp = test::call_service(&mut app, req).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    let req = test::TestRequest::post()
+        .uri("/market-api/v1/offers?ttl=600")
+        .set_json(&client::sample_offer())
+        .to_request();
+    let resp = test::call_service(&mut app, req).await;
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let offer_id: String = serde_json::from_slice(&test::read_body(resp).await).unwrap();
+
+    let req = test::TestRequest::post()
+        .uri(&format!(
+            "/market-api/v1/offers/{}/refresh?ttl=3600",
+            offer_id
+        ))
+        .to_request();
+    let resp = test::call_service(&mut app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body: serde_json::Value = serde_json::from_slice(&test::read_body(resp).await).unwrap();
+    assert!(body["expirationTs"].is_string());
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/market-api/v1/demands/{}/refresh", offer_id))
+        .to_request();
+    let resp = test::call_service(&mut app, req).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
@@ -1,8 +1,13 @@
 //! Market service bus API.
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 use crate::Role;
-pub use ya_client_model::market::Agreement;
+pub use ya_client_model::market::agreement::State as AgreementState;
+pub use ya_client_model::market::proposal::State as ProposalState;
+pub use ya_client_model::market::{Agreement, Offer, Reason};
+use ya_client_model::NodeId;
 use ya_service_bus::RpcMessage;
 
 /// Public Market bus address.
@@ -41,6 +46,285 @@ impl RpcMessage for GetAgreement {
     type Error = RpcMessageError;
 }
 
+/// Returns Offers currently known to the market, which properties satisfy given constraints.
+/// Read-only query: doesn't create Proposals nor negotiation events.
+/// Bound on local Market bus address.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScanOffers {
+    pub constraints: String,
+    /// Number of matching Offers to skip.
+    #[serde(default)]
+    pub offset: u32,
+    /// Maximum number of Offers to return. It is capped by the market.
+    pub limit: Option<u32>,
+}
+
+impl RpcMessage for ScanOffers {
+    const ID: &'static str = "ScanOffers";
+    type Item = Vec<Offer>;
+    type Error = RpcMessageError;
+}
+
+/// Lists Agreements of given identity matching all specified criteria,
+/// ordered by creation time. Bound on local Market bus address.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListAgreements {
+    /// Identity owning Agreements; all local identities if not specified.
+    pub node_id: Option<NodeId>,
+    pub state: Option<AgreementState>,
+    /// Identity of the other side of Agreement.
+    pub peer_id: Option<NodeId>,
+    pub app_session_id: Option<String>,
+    /// Created at or after this date.
+    pub after_date: Option<DateTime<Utc>>,
+    /// Created before this date.
+    pub before_date: Option<DateTime<Utc>>,
+    /// Valid to at or after this date.
+    pub valid_after: Option<DateTime<Utc>>,
+    /// Valid to before this date.
+    pub valid_before: Option<DateTime<Utc>>,
+    /// Number of Agreements to skip.
+    pub offset: Option<u32>,
+    /// Maximum number of Agreements to return.
+    pub limit: Option<u32>,
+}
+
+impl RpcMessage for ListAgreements {
+    const ID: &'static str = "ListAgreements";
+    type Item = Vec<Agreement>;
+    type Error = RpcMessageError;
+}
+
+/// Returns effective Market configuration as `[market]` section of config file
+/// in TOML format. Bound on local Market bus address.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct GetConfig {}
+
+impl RpcMessage for GetConfig {
+    const ID: &'static str = "GetConfig";
+    type Item = String;
+    type Error = RpcMessageError;
+}
+
+/// Returns statistics of active Offers known to the market and numbers
+/// of Agreements in each state. Bound on local Market bus address.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetMarketStats {
+    /// Identity owning counted Agreements; all local identities if not specified.
+    pub node_id: Option<NodeId>,
+}
+
+impl RpcMessage for GetMarketStats {
+    const ID: &'static str = "GetMarketStats";
+    type Item = MarketStats;
+    type Error = RpcMessageError;
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MarketStats {
+    pub offers: OfferStats,
+    /// Number of Agreements in each state.
+    pub agreements: BTreeMap<String, u64>,
+}
+
+/// Summary of active Offers. Offers without runtime, subnet or payment
+/// platform are counted as `unknown`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OfferStats {
+    pub total: u64,
+    pub unique_providers: u64,
+    /// Offers by `golem.runtime.name`.
+    pub runtimes: BTreeMap<String, u64>,
+    /// Offers by `golem.node.debug.subnet`.
+    pub subnets: BTreeMap<String, u64>,
+    /// Offer accepting many payment platforms is counted for each of them.
+    pub payment_platforms: BTreeMap<String, u64>,
+    /// Offers by `golem.inf.cpu.threads`, keyed by lower bound
+    /// of power of two range, i.e. `4` counts Offers with 4 to 7 threads.
+    pub cpu_threads: BTreeMap<u64, u64>,
+    /// Offers by `golem.inf.mem.gib`, bucketed like `cpu_threads`.
+    pub memory_gib: BTreeMap<u64, u64>,
+    /// Linear pricing coefficients by usage counter from `golem.com.usage.vector`.
+    /// Coefficient following all usage counters is listed as `fixed`.
+    pub linear_pricing: BTreeMap<String, PriceStats>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PriceStats {
+    pub offers: u64,
+    pub min: f64,
+    pub median: f64,
+    pub max: f64,
+}
+
+/// Changes of approved Agreement terms proposed by one of the parties.
+/// Given properties are merged into current Agreement properties;
+/// property with `null` value is removed. Constraints can't be amended.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AmendmentProposal {
+    #[serde(default)]
+    pub offer_properties: serde_json::Value,
+    #[serde(default)]
+    pub demand_properties: serde_json::Value,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum AmendmentState {
+    /// Waiting for approval of the other party
+    Proposed,
+    /// Approved by the other party; Agreement terms were updated
+    Approved,
+    /// Rejected by the other party; Agreement terms remain unchanged
+    Rejected,
+}
+
+/// Amendment of Agreement terms. Approved amendment becomes new `version`
+/// of the Agreement, with properties listed here.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgreementAmendment {
+    pub agreement_id: String,
+    pub version: u32,
+    /// Party, that proposed the amendment.
+    pub issuer: Role,
+    pub state: AmendmentState,
+    pub offer_properties: serde_json::Value,
+    pub demand_properties: serde_json::Value,
+    pub proposed_date: DateTime<Utc>,
+    /// Date of approval or rejection.
+    pub resolved_date: Option<DateTime<Utc>>,
+    pub reason: Option<Reason>,
+}
+
+/// Event of Agreement amendment flow. Amendment events have no representation
+/// in client `AgreementOperationEvent`, so they are listed by separate endpoint.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AmendmentEvent {
+    pub agreement_id: String,
+    pub event_date: DateTime<Utc>,
+    /// Party, that proposed, approved or rejected the amendment.
+    pub issuer: Role,
+    #[serde(flatten)]
+    pub event_type: AmendmentEventType,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "eventType")]
+pub enum AmendmentEventType {
+    AmendmentProposedEvent,
+    AmendmentApprovedEvent,
+    AmendmentRejectedEvent { reason: Option<Reason> },
+}
+
+/// Change of single property between Proposals issued by the same party.
+/// Property names are flattened, like `golem.inf.mem.gib`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PropertyChange {
+    pub name: String,
+    /// Value in previous step; `None` if property was added.
+    pub previous: Option<serde_json::Value>,
+    /// Value in this step; `None` if property was removed.
+    pub current: Option<serde_json::Value>,
+}
+
+/// Single Proposal in the chain of counter-proposals leading
+/// from initial Proposal to the Agreement.
+/// Proposals in the chain alternate between parties and describe different sides
+/// (Offer or Demand), so each step is compared with the previous Proposal of the
+/// same issuer (`issuer_prev_proposal_id`), not with the immediately preceding
+/// one (`prev_proposal_id`), which was issued by the other party.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NegotiationStep {
+    pub proposal_id: String,
+    pub prev_proposal_id: Option<String>,
+    /// Previous Proposal of the same issuer; `None` for first Proposal of each party.
+    pub issuer_prev_proposal_id: Option<String>,
+    pub issuer_id: NodeId,
+    pub timestamp: DateTime<Utc>,
+    pub state: ProposalState,
+    /// Properties changed comparing to previous Proposal of the same issuer.
+    /// First Proposal of each party lists all its properties as added.
+    pub issuer_changes: Vec<PropertyChange>,
+    /// Constraints, if they differ from previous Proposal of the same issuer.
+    pub issuer_constraints: Option<String>,
+}
+
+/// Decides, if local identity matches and negotiates with given node.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NodePolicy {
+    /// Identity with any allowed node ignores all nodes, which aren't allowed.
+    Allow,
+    /// Offers and Proposals of blocked node are ignored.
+    Block,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NodePolicyEntry {
+    /// Local identity, that the policy applies to.
+    pub owner_id: NodeId,
+    pub node_id: NodeId,
+    pub policy: NodePolicy,
+    pub description: Option<String>,
+    pub insertion_date: DateTime<Utc>,
+}
+
+/// Lists node policies of given identity; all local identities if not specified.
+/// Bound on local Market bus address.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListNodePolicies {
+    pub owner_id: Option<NodeId>,
+}
+
+impl RpcMessage for ListNodePolicies {
+    const ID: &'static str = "ListNodePolicies";
+    type Item = Vec<NodePolicyEntry>;
+    type Error = RpcMessageError;
+}
+
+/// Allows or blocks node for given identity; default identity if not specified.
+/// Replaces previous policy of this node. Bound on local Market bus address.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetNodePolicy {
+    pub owner_id: Option<NodeId>,
+    pub node_id: NodeId,
+    pub policy: NodePolicy,
+    pub description: Option<String>,
+}
+
+impl RpcMessage for SetNodePolicy {
+    const ID: &'static str = "SetNodePolicy";
+    type Item = NodePolicyEntry;
+    type Error = RpcMessageError;
+}
+
+/// Removes policy of node for given identity; default identity if not specified.
+/// Bound on local Market bus address.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoveNodePolicy {
+    pub owner_id: Option<NodeId>,
+    pub node_id: NodeId,
+}
+
+impl RpcMessage for RemoveNodePolicy {
+    const ID: &'static str = "RemoveNodePolicy";
+    type Item = ();
+    type Error = RpcMessageError;
+}
+
 /// Error message for market service bus API.
 #[derive(thiserror::Error, Clone, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
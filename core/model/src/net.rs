@@ -10,8 +10,10 @@ pub const PUBLIC_PREFIX: &str = "/public";
 ///
 ///
 pub mod local {
+    use chrono::{DateTime, Utc};
     use serde::de::DeserializeOwned;
     use serde::{Deserialize, Serialize};
+    use ya_client_model::NodeId;
     use ya_service_bus::RpcMessage;
 
     pub const BUS_ID: &str = "/local/net";
@@ -20,16 +22,39 @@ pub mod local {
         const TOPIC: &'static str;
     }
 
+    /// Audience of a broadcast. Subnet of a node is set with `NET_SUBNET`.
+    #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+    #[serde(rename_all = "camelCase")]
+    pub enum BroadcastScope {
+        /// Nodes of sender's subnet, or nodes without subnet, if sender has none.
+        Network,
+        /// Nodes of given subnet.
+        Subnet(String),
+        /// Given nodes, if they are in sender's subnet. Hub delivers it only to them.
+        /// In peer-to-peer mode other peers relay it, but don't deliver it locally.
+        Nodes(Vec<NodeId>),
+    }
+
+    impl Default for BroadcastScope {
+        fn default() -> Self {
+            BroadcastScope::Network
+        }
+    }
+
     #[derive(Serialize, Deserialize)]
     pub struct SendBroadcastStub {
         pub id: Option<String>,
         pub topic: String,
+        #[serde(default)]
+        pub scope: BroadcastScope,
     }
 
     #[derive(Serialize, Deserialize)]
     pub struct SendBroadcastMessage<M> {
         id: Option<String>,
         topic: String,
+        #[serde(default)]
+        scope: BroadcastScope,
         body: M,
     }
 
@@ -37,7 +62,18 @@ pub mod local {
         pub fn new(body: M) -> Self {
             let id = None;
             let topic = M::TOPIC.to_owned();
-            Self { id, topic, body }
+            let scope = BroadcastScope::default();
+            Self {
+                id,
+                topic,
+                scope,
+                body,
+            }
+        }
+
+        pub fn with_scope(mut self, scope: BroadcastScope) -> Self {
+            self.scope = scope;
+            self
         }
 
         pub fn body(&self) -> &M {
@@ -50,6 +86,10 @@ pub mod local {
             self.topic.as_ref()
         }
 
+        pub fn scope(&self) -> &BroadcastScope {
+            &self.scope
+        }
+
         pub fn set_id(&mut self, id: String) {
             self.id = Some(id)
         }
@@ -80,8 +120,28 @@ pub mod local {
         }
     }
 
+    /// Stops forwarding broadcasts of the topic to the endpoint.
+    /// Replies false, if endpoint was not subscribed to the topic.
+    #[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq, Hash)]
+    #[serde(rename_all = "camelCase")]
+    pub struct Unsubscribe {
+        topic: String,
+        endpoint: String,
+    }
+
+    impl Unsubscribe {
+        pub fn topic(&self) -> &str {
+            self.topic.as_ref()
+        }
+
+        pub fn endpoint(&self) -> &str {
+            self.endpoint.as_ref()
+        }
+    }
+
     pub trait ToEndpoint<M: BroadcastMessage> {
         fn into_subscribe_msg(endpoint: impl Into<String>) -> Subscribe;
+        fn into_unsubscribe_msg(endpoint: impl Into<String>) -> Unsubscribe;
     }
 
     impl<M: BroadcastMessage> ToEndpoint<M> for M {
@@ -90,6 +150,12 @@ pub mod local {
             let endpoint = endpoint.into();
             Subscribe { topic, endpoint }
         }
+
+        fn into_unsubscribe_msg(endpoint: impl Into<String>) -> Unsubscribe {
+            let topic = M::TOPIC.to_owned();
+            let endpoint = endpoint.into();
+            Unsubscribe { topic, endpoint }
+        }
     }
 
     impl RpcMessage for Subscribe {
@@ -98,6 +164,12 @@ pub mod local {
         type Error = SubscribeError;
     }
 
+    impl RpcMessage for Unsubscribe {
+        const ID: &'static str = "Unsubscribe";
+        type Item = bool;
+        type Error = SubscribeError;
+    }
+
     #[derive(thiserror::Error, Clone, Debug, Serialize, Deserialize)]
     #[serde(rename_all = "camelCase")]
     pub enum SubscribeError {
@@ -105,6 +177,42 @@ pub mod local {
         RuntimeException(String),
     }
 
+    /// Health of connection with the network.
+    #[derive(Clone, Debug, Default, Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct Status {}
+
+    impl RpcMessage for Status {
+        const ID: &'static str = "Status";
+        type Item = StatusInfo;
+        type Error = StatusError;
+    }
+
+    /// Messages are calls and broadcasts. Bytes count payloads
+    /// of messages and of replies to calls.
+    #[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+    #[serde(rename_all = "camelCase")]
+    pub struct StatusInfo {
+        /// Hub, node is connected to at the moment.
+        pub hub: Option<String>,
+        /// Hubs in order they are tried.
+        pub hubs: Vec<String>,
+        pub connected_since: Option<DateTime<Utc>>,
+        /// Number of times connection was established again after it broke.
+        pub reconnects: u64,
+        pub bytes_sent: u64,
+        pub bytes_received: u64,
+        pub messages_sent: u64,
+        pub messages_received: u64,
+    }
+
+    #[derive(thiserror::Error, Clone, Debug, Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub enum StatusError {
+        #[error("{0}")]
+        RuntimeException(String),
+    }
+
     #[derive(thiserror::Error, Debug)]
     pub enum BindBroadcastError {
         #[error(transparent)]
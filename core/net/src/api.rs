@@ -9,6 +9,8 @@ pub use ya_core_model::net::{
 };
 #[cfg(any(feature = "service", test))]
 use ya_core_model::NodeId;
+#[cfg(any(feature = "service", test))]
+use ya_service_bus::Error;
 use ya_service_bus::{typed as bus, Handle, RpcEndpoint, RpcMessage};
 
 pub async fn bind_broadcast_with_caller<MsgType, Output, F>(
@@ -51,6 +53,30 @@ where
     Ok(bus::bind_with_caller(broadcast_address, handler))
 }
 
+/// Stops delivery of broadcasts to handler bound with [`bind_broadcast_with_caller`].
+pub async fn unbind_broadcast<MsgType>(broadcast_address: &str) -> Result<(), BindBroadcastError>
+where
+    MsgType: BroadcastMessage + Send + Sync + 'static,
+{
+    log::debug!(
+        "Removing broadcast topic {} endpoint '{}'.",
+        MsgType::TOPIC,
+        broadcast_address
+    );
+
+    // Removed subscription won't be restored after reconnection to hub.
+    {
+        let mut subscriptions = SUBSCRIPTIONS.lock().unwrap();
+        subscriptions.remove(&MsgType::into_subscribe_msg(broadcast_address));
+    }
+
+    bus::service(net::local::BUS_ID)
+        .send(MsgType::into_unsubscribe_msg(broadcast_address))
+        .await??;
+    bus::unbind(broadcast_address).await?;
+    Ok(())
+}
+
 #[cfg(any(feature = "service", test))]
 pub(crate) fn parse_from_addr(from_addr: &str) -> anyhow::Result<(NodeId, String)> {
     let mut it = from_addr.split("/").fuse();
@@ -67,6 +93,21 @@ pub(crate) fn parse_from_addr(from_addr: &str) -> anyhow::Result<(NodeId, String
     anyhow::bail!("invalid net-from destination: {}", from_addr)
 }
 
+/// Extracts destination node from `/net/<node_id>/...` address.
+#[cfg(any(feature = "service", test))]
+pub(crate) fn dst_node(addr: &str) -> Result<NodeId, Error> {
+    let mut it = addr.split("/").fuse();
+    match (it.next(), it.next(), it.next()) {
+        (Some(""), Some("net"), Some(node_id)) => node_id
+            .parse()
+            .map_err(|e| Error::GsbBadRequest(format!("invalid destination {}: {}", addr, e))),
+        _ => Err(Error::GsbBadRequest(format!(
+            "invalid destination: {}",
+            addr
+        ))),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -102,4 +143,13 @@ mod tests {
         let out = parse_from_addr("/from/0xe93ab94a2095729ad0b7cfa5bfd7d33e1b44d6df/to/0x99402605903da83901151b0871ebeae9296ef66b/x");
         assert!(out.is_ok())
     }
+
+    #[test]
+    fn dst_node_of_net_address() {
+        let node_id = "0x99402605903da83901151b0871ebeae9296ef66b";
+        let addr = format!("/net/{}/market/discovery", node_id);
+        assert_eq!(dst_node(&addr).unwrap(), node_id.parse::<NodeId>().unwrap());
+        assert!(dst_node("/public/market").is_err());
+        assert!(dst_node("/net/0x123/market").is_err());
+    }
 }
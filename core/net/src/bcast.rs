@@ -3,7 +3,20 @@
 use std::cell::RefCell;
 use std::collections::BTreeMap;
 use std::rc::Rc;
-use ya_core_model::net::local as local_net;
+use ya_core_model::net::local::{self as local_net, BroadcastScope, SendBroadcastStub};
+use ya_core_model::NodeId;
+use ya_service_bus::serialization;
+
+/// Subnet of this node. It receives only broadcasts sent within that subnet.
+pub const NET_SUBNET_ENV_VAR: &str = "NET_SUBNET";
+
+/// Subnet from `NET_SUBNET`, if it is set.
+pub fn subnet_from_env() -> Option<String> {
+    std::env::var(NET_SUBNET_ENV_VAR)
+        .ok()
+        .map(|subnet| subnet.trim().to_string())
+        .filter(|subnet| !subnet.is_empty())
+}
 
 #[derive(Clone, Default)]
 pub struct BCastService {
@@ -14,9 +27,21 @@ pub struct BCastService {
 struct BCastServiceInner {
     last_id: u64,
     topics: BTreeMap<String, Vec<(u64, Rc<str>)>>,
+    subnet: Option<String>,
+    node_ids: Vec<NodeId>,
 }
 
 impl BCastService {
+    pub fn new(subnet: Option<String>, node_ids: Vec<NodeId>) -> Self {
+        BCastService {
+            inner: Rc::new(RefCell::new(BCastServiceInner {
+                subnet,
+                node_ids,
+                ..Default::default()
+            })),
+        }
+    }
+
     pub fn add(&self, subscribe: local_net::Subscribe) -> (bool, u64) {
         let mut me = self.inner.borrow_mut();
         let id = me.last_id;
@@ -31,6 +56,23 @@ impl BCastService {
         (is_new, id)
     }
 
+    /// Returns false, if endpoint was not subscribed to the topic.
+    pub fn remove(&self, unsubscribe: &local_net::Unsubscribe) -> bool {
+        let mut me = self.inner.borrow_mut();
+        let receivers = match me.topics.get_mut(unsubscribe.topic()) {
+            Some(receivers) => receivers,
+            None => return false,
+        };
+
+        let count = receivers.len();
+        receivers.retain(|(_, endpoint)| endpoint.as_ref() != unsubscribe.endpoint());
+        let removed = receivers.len() < count;
+        if receivers.is_empty() {
+            me.topics.remove(unsubscribe.topic());
+        }
+        removed
+    }
+
     pub fn resolve(&self, topic: &str) -> Vec<Rc<str>> {
         let me = self.inner.borrow();
         me.topics
@@ -43,4 +85,166 @@ impl BCastService {
             })
             .unwrap_or_default()
     }
+
+    /// Topic, under which broadcast with `scope` is sent over the network.
+    /// Broadcasts within subnet use separate topics, so they reach
+    /// only nodes of that subnet.
+    pub fn network_topic(&self, topic: &str, scope: &BroadcastScope) -> String {
+        let me = self.inner.borrow();
+        let subnet = match scope {
+            BroadcastScope::Subnet(subnet) => Some(subnet),
+            BroadcastScope::Network | BroadcastScope::Nodes(_) => me.subnet.as_ref(),
+        };
+        match subnet {
+            Some(subnet) => format!("{}@{}", topic, subnet),
+            None => topic.to_string(),
+        }
+    }
+
+    /// Topics, under which broadcast with `scope` is sent over the network.
+    /// Broadcast to given nodes is sent separately to topic of each of them,
+    /// so hub delivers it only to them.
+    pub fn network_topics(&self, topic: &str, scope: &BroadcastScope) -> Vec<String> {
+        let topic = self.network_topic(topic, scope);
+        match scope {
+            BroadcastScope::Nodes(nodes) => {
+                let mut topics = Vec::with_capacity(nodes.len());
+                for node_id in nodes {
+                    let node_topic = node_topic(&topic, node_id);
+                    if !topics.contains(&node_topic) {
+                        topics.push(node_topic);
+                    }
+                }
+                topics
+            }
+            BroadcastScope::Network | BroadcastScope::Subnet(_) => vec![topic],
+        }
+    }
+
+    /// Topic, under which this node receives broadcasts of `topic`.
+    pub fn subscribed_topic(&self, topic: &str) -> String {
+        self.network_topic(topic, &BroadcastScope::Network)
+    }
+
+    /// All topics, under which this node receives broadcasts of `topic`:
+    /// the one of its subnet and one for each own node.
+    pub fn subscribed_topics(&self, topic: &str) -> Vec<String> {
+        let topic = self.subscribed_topic(topic);
+        let node_topics = self
+            .inner
+            .borrow()
+            .node_ids
+            .iter()
+            .map(|node_id| node_topic(&topic, node_id))
+            .collect::<Vec<_>>();
+        std::iter::once(topic).chain(node_topics).collect()
+    }
+
+    /// Endpoints of broadcast received over the network. There are none,
+    /// if broadcast is not addressed to this node.
+    pub fn resolve_received(&self, network_topic: &str, msg: &[u8]) -> Vec<Rc<str>> {
+        let stub: SendBroadcastStub = match serialization::from_slice(msg) {
+            Ok(stub) => stub,
+            Err(e) => {
+                log::debug!("Invalid broadcast to topic {}: {}", network_topic, e);
+                return Vec::new();
+            }
+        };
+        let topic = self.subscribed_topic(&stub.topic);
+        let addressed = match &stub.scope {
+            BroadcastScope::Nodes(nodes) => {
+                let me = self.inner.borrow();
+                me.node_ids.iter().any(|node_id| {
+                    nodes.contains(node_id) && node_topic(&topic, node_id) == network_topic
+                })
+            }
+            BroadcastScope::Network | BroadcastScope::Subnet(_) => topic == network_topic,
+        };
+        match addressed {
+            true => self.resolve(&stub.topic),
+            false => Vec::new(),
+        }
+    }
+}
+
+fn node_topic(topic: &str, node_id: &NodeId) -> String {
+    format!("{}/{}", topic, node_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::{Deserialize, Serialize};
+    use ya_core_model::net::local::{BroadcastMessage, SendBroadcastMessage, ToEndpoint};
+
+    #[derive(Serialize, Deserialize)]
+    struct Ping;
+
+    impl BroadcastMessage for Ping {
+        const TOPIC: &'static str = "ping";
+    }
+
+    fn node_id(n: u8) -> NodeId {
+        NodeId::from(&[n; 20][..])
+    }
+
+    fn received(bcast: &BCastService, network_topic: &str, scope: BroadcastScope) -> usize {
+        let msg =
+            serialization::to_vec(&SendBroadcastMessage::new(Ping).with_scope(scope)).unwrap();
+        bcast.resolve_received(network_topic, &msg).len()
+    }
+
+    #[test]
+    fn unsubscribed_endpoint_is_not_resolved() {
+        let bcast = BCastService::default();
+        bcast.add(Ping::into_subscribe_msg("/local/a"));
+        bcast.add(Ping::into_subscribe_msg("/local/b"));
+
+        assert!(bcast.remove(&Ping::into_unsubscribe_msg("/local/a")));
+        assert!(!bcast.remove(&Ping::into_unsubscribe_msg("/local/a")));
+        assert_eq!(bcast.resolve("ping"), vec![Rc::from("/local/b")]);
+
+        assert!(bcast.remove(&Ping::into_unsubscribe_msg("/local/b")));
+        assert!(bcast.resolve("ping").is_empty());
+        // Next subscription is the first one again.
+        assert!(bcast.add(Ping::into_subscribe_msg("/local/a")).0);
+    }
+
+    #[test]
+    fn broadcast_scopes() {
+        let bcast = BCastService::new(Some("test".to_string()), vec![node_id(1)]);
+        bcast.add(Ping::into_subscribe_msg("/local/a"));
+        let subnet = |s: &str| BroadcastScope::Subnet(s.to_string());
+
+        assert_eq!(bcast.subscribed_topic("ping"), "ping@test");
+        assert_eq!(bcast.network_topic("ping", &subnet("prod")), "ping@prod");
+        assert_eq!(
+            BCastService::default().network_topic("ping", &BroadcastScope::Network),
+            "ping"
+        );
+
+        assert_eq!(received(&bcast, "ping@test", BroadcastScope::Network), 1);
+        assert_eq!(received(&bcast, "ping@test", subnet("test")), 1);
+        assert_eq!(received(&bcast, "ping", BroadcastScope::Network), 0);
+        assert_eq!(received(&bcast, "ping@prod", subnet("prod")), 0);
+
+        let nodes = BroadcastScope::Nodes(vec![node_id(2), node_id(1), node_id(2)]);
+        let topics = bcast.network_topics("ping", &nodes);
+        assert_eq!(
+            topics,
+            vec![
+                format!("ping@test/{}", node_id(2)),
+                format!("ping@test/{}", node_id(1)),
+            ]
+        );
+        assert_eq!(
+            bcast.subscribed_topics("ping"),
+            vec!["ping@test".to_string(), topics[1].clone()]
+        );
+        assert_eq!(received(&bcast, &topics[1], nodes.clone()), 1);
+        assert_eq!(received(&bcast, &topics[0], nodes.clone()), 0);
+        assert_eq!(received(&bcast, "ping@test", nodes), 0);
+        let nodes = BroadcastScope::Nodes(vec![node_id(2)]);
+        assert_eq!(received(&bcast, &topics[1], nodes), 0);
+    }
 }
@@ -0,0 +1,25 @@
+use structopt::StructOpt;
+
+use ya_core_model::net::local as local_net;
+use ya_service_api::{CliCtx, CommandOutput};
+use ya_service_bus::{typed as bus, RpcEndpoint};
+
+/// Network management.
+#[derive(StructOpt, Debug)]
+pub enum NetCommand {
+    /// Show connection with hub: current hub, reconnects and traffic
+    Status {},
+}
+
+impl NetCommand {
+    pub async fn run_command(self, _ctx: &CliCtx) -> anyhow::Result<CommandOutput> {
+        match self {
+            NetCommand::Status {} => {
+                let status = bus::service(local_net::BUS_ID)
+                    .send(local_net::Status {})
+                    .await??;
+                CommandOutput::object(status)
+            }
+        }
+    }
+}
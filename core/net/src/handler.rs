@@ -89,7 +89,6 @@ async fn rebind<B, U, Fb, Fu, Fr, E>(
         match bind().await {
             Ok(dc_rx) => {
                 reconnect.replace(Default::default());
-                resubscribe().await;
 
                 Arbiter::spawn(async move {
                     if let Ok(_) = dc_rx.await {
@@ -114,7 +113,7 @@ async fn rebind<B, U, Fb, Fu, Fr, E>(
     Arbiter::spawn(rx.then(move |_| rebind(reconnect, bind, unbind).then(|_| future::ready(()))));
 }
 
-async fn resubscribe() {
+pub(crate) async fn resubscribe() {
     futures::stream::iter({ SUBSCRIPTIONS.lock().unwrap().clone() }.into_iter())
         .for_each(|msg| {
             let topic = msg.topic().to_owned();
@@ -0,0 +1,106 @@
+//! Health of connection with hub, reported by `/local/net` Status message.
+use chrono::Utc;
+use futures::future;
+use std::cell::RefCell;
+use std::net::SocketAddr;
+use std::rc::Rc;
+
+use ya_core_model::net::local::{self as local_net, Status, StatusInfo};
+use ya_service_bus::typed as bus;
+
+#[derive(Clone, Default)]
+pub(crate) struct Health {
+    inner: Rc<RefCell<HealthInner>>,
+}
+
+#[derive(Default)]
+struct HealthInner {
+    status: StatusInfo,
+    binds: u64,
+}
+
+impl Health {
+    pub fn bind_gsb(&self) {
+        let health = self.clone();
+        let _ = bus::bind(local_net::BUS_ID, move |_: Status| {
+            future::ok(health.status())
+        });
+    }
+
+    pub fn status(&self) -> StatusInfo {
+        self.inner.borrow().status.clone()
+    }
+
+    pub fn set_hubs(&self, hubs: &[SocketAddr]) {
+        self.inner.borrow_mut().status.hubs = hubs.iter().map(ToString::to_string).collect();
+    }
+
+    pub fn connected(&self, hub: SocketAddr) {
+        let mut inner = self.inner.borrow_mut();
+        if inner.binds > 0 {
+            inner.status.reconnects += 1;
+        }
+        inner.binds += 1;
+        inner.status.hub = Some(hub.to_string());
+        inner.status.connected_since = Some(Utc::now());
+    }
+
+    pub fn disconnected(&self) {
+        let mut inner = self.inner.borrow_mut();
+        inner.status.hub = None;
+        inner.status.connected_since = None;
+    }
+
+    pub fn message_sent(&self, bytes: usize) {
+        let mut inner = self.inner.borrow_mut();
+        inner.status.messages_sent += 1;
+        inner.status.bytes_sent += bytes as u64;
+    }
+
+    pub fn message_received(&self, bytes: usize) {
+        let mut inner = self.inner.borrow_mut();
+        inner.status.messages_received += 1;
+        inner.status.bytes_received += bytes as u64;
+    }
+
+    pub fn reply_sent(&self, bytes: usize) {
+        self.inner.borrow_mut().status.bytes_sent += bytes as u64;
+    }
+
+    pub fn reply_received(&self, bytes: usize) {
+        self.inner.borrow_mut().status.bytes_received += bytes as u64;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reconnects_are_counted_after_first_connection() {
+        let health = Health::default();
+        let hub1: SocketAddr = "127.0.0.1:7464".parse().unwrap();
+        let hub2: SocketAddr = "127.0.0.2:7464".parse().unwrap();
+        health.set_hubs(&[hub1, hub2]);
+
+        health.connected(hub1);
+        health.message_sent(10);
+        health.reply_received(5);
+        assert_eq!(health.status().reconnects, 0);
+
+        health.disconnected();
+        assert_eq!(health.status().hub, None);
+        assert_eq!(health.status().connected_since, None);
+
+        health.connected(hub2);
+        health.message_received(3);
+        health.reply_sent(2);
+
+        let status = health.status();
+        assert_eq!(status.hub, Some(hub2.to_string()));
+        assert_eq!(status.hubs, vec![hub1.to_string(), hub2.to_string()]);
+        assert_eq!(status.reconnects, 1);
+        assert_eq!((status.messages_sent, status.bytes_sent), (1, 12));
+        assert_eq!((status.messages_received, status.bytes_received), (1, 8));
+    }
+}
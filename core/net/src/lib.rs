@@ -1,10 +1,32 @@
 #[cfg(any(feature = "service", test))]
 mod bcast;
 #[cfg(any(feature = "service", test))]
+mod cli;
+#[cfg(any(feature = "service", test))]
 mod handler;
 #[cfg(any(feature = "service", test))]
+mod health;
+#[cfg(any(feature = "service", test))]
+mod p2p;
+#[cfg(any(feature = "service", test))]
+mod secure;
+#[cfg(any(feature = "service", test))]
 mod service;
+#[cfg(feature = "testing")]
+pub mod testing;
 
+#[cfg(feature = "service")]
+pub use bcast::NET_SUBNET_ENV_VAR;
+#[cfg(feature = "service")]
+pub use p2p::{
+    P2pConfig, DEFAULT_P2P_LISTEN_ADDR, NET_MODE_ENV_VAR, P2P_LAN_DISCOVERY_ENV_VAR,
+    P2P_LISTEN_ENV_VAR, P2P_PEERS_ENV_VAR,
+};
+#[cfg(feature = "service")]
+pub use secure::{
+    EnvelopeConfig, EnvelopeError, EnvelopeMode, NET_ENVELOPE_ENV_VAR,
+    NET_ENVELOPE_REQUIRED_ENV_VAR,
+};
 #[cfg(feature = "service")]
 pub use service::*;
 
@@ -0,0 +1,318 @@
+//! Network transport without central hub. Nodes connect directly to each
+//! other over TCP, using static list of peers or discovery in local network.
+//! Peers prove they own node ids they announce and exchange routes to other
+//! nodes, so calls are relayed along the shortest known route and not every
+//! pair of nodes has to be connected. Broadcasts are flooded through all connections.
+use actix_rt::Arbiter;
+use anyhow::bail;
+use futures::prelude::*;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::rc::Rc;
+use std::time::Duration;
+use tokio::net::TcpListener;
+
+use ya_core_model::net;
+use ya_core_model::net::local::{self as local_net, SendBroadcastMessage, SendBroadcastStub};
+use ya_core_model::NodeId;
+use ya_service_bus::{serialization, typed as bus, untyped as local_bus, Error, RpcMessage};
+
+use crate::api::parse_from_addr;
+use crate::bcast::BCastService;
+
+mod lan;
+mod peers;
+pub(crate) mod protocol;
+
+use peers::{connect, serve, Peers};
+
+/// Network transport: `hub` (default) or `p2p`.
+pub const NET_MODE_ENV_VAR: &str = "NET_MODE";
+/// Address, on which node accepts connections from peers.
+pub const P2P_LISTEN_ENV_VAR: &str = "NET_P2P_LISTEN";
+/// Comma separated `host:port` addresses of peers, we keep connection with.
+pub const P2P_PEERS_ENV_VAR: &str = "NET_P2P_PEERS";
+/// UDP port used to discover peers in local network. Discovery is disabled if not set.
+pub const P2P_LAN_DISCOVERY_ENV_VAR: &str = "NET_P2P_LAN_DISCOVERY_PORT";
+
+pub const DEFAULT_P2P_LISTEN_ADDR: &str = "0.0.0.0:7477";
+
+/// Delay before reconnecting to static peer.
+const PEER_RECONNECT_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct P2pConfig {
+    pub listen_addr: SocketAddr,
+    pub peers: Vec<String>,
+    pub lan_discovery_port: Option<u16>,
+}
+
+impl P2pConfig {
+    /// Returns None, if hub transport should be used.
+    pub fn from_env() -> anyhow::Result<Option<P2pConfig>> {
+        let var = |name| std::env::var(name).ok();
+        P2pConfig::from_vars(
+            var(NET_MODE_ENV_VAR),
+            var(P2P_LISTEN_ENV_VAR),
+            var(P2P_PEERS_ENV_VAR),
+            var(P2P_LAN_DISCOVERY_ENV_VAR),
+        )
+    }
+
+    fn from_vars(
+        mode: Option<String>,
+        listen_addr: Option<String>,
+        peers: Option<String>,
+        lan_discovery_port: Option<String>,
+    ) -> anyhow::Result<Option<P2pConfig>> {
+        match mode.as_deref() {
+            None | Some("hub") => return Ok(None),
+            Some("p2p") => (),
+            Some(mode) => bail!("invalid {}: {}", NET_MODE_ENV_VAR, mode),
+        }
+
+        let listen_addr = listen_addr.unwrap_or_else(|| DEFAULT_P2P_LISTEN_ADDR.to_string());
+        let lan_discovery_port = match lan_discovery_port {
+            Some(port) => Some(
+                port.parse()
+                    .map_err(|e| anyhow::anyhow!("invalid {}: {}", P2P_LAN_DISCOVERY_ENV_VAR, e))?,
+            ),
+            None => None,
+        };
+        Ok(Some(P2pConfig {
+            listen_addr: listen_addr
+                .parse()
+                .map_err(|e| anyhow::anyhow!("invalid {}: {}", P2P_LISTEN_ENV_VAR, e))?,
+            peers: peers
+                .unwrap_or_default()
+                .split(',')
+                .map(str::trim)
+                .filter(|peer| !peer.is_empty())
+                .map(str::to_string)
+                .collect(),
+            lan_discovery_port,
+        }))
+    }
+}
+
+/// Initialize net module without hub.
+pub async fn bind_p2p(
+    config: P2pConfig,
+    default_node_id: NodeId,
+    nodes: Vec<NodeId>,
+    subnet: Option<String>,
+) -> std::io::Result<()> {
+    let bcast = BCastService::new(subnet, nodes.clone());
+    let peers = Peers::new(default_node_id, nodes.clone(), bcast.clone());
+
+    let mut listener = TcpListener::bind(config.listen_addr).await?;
+    let listen_port = listener.local_addr()?.port();
+    log::info!(
+        "network service listening for peers on: {}",
+        config.listen_addr
+    );
+    {
+        let peers = peers.clone();
+        Arbiter::spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok((stream, addr)) => {
+                        Arbiter::spawn(serve(peers.clone(), stream, addr).map(move |result| {
+                            if let Err(e) = result {
+                                log::warn!("Connection with peer {} failed: {}", addr, e);
+                            }
+                        }))
+                    }
+                    Err(e) => log::warn!("Failed to accept peer connection: {}", e),
+                }
+            }
+        });
+    }
+
+    for peer in config.peers {
+        Arbiter::spawn(keep_connected(peers.clone(), peer));
+    }
+    if let Some(port) = config.lan_discovery_port {
+        lan::start(peers.clone(), port, listen_port).await?;
+    }
+
+    // bind /net on my local bus and route all calls to peers
+    {
+        // `caller` is usually "local", so we replace it with our default node id
+        let peers_rpc = peers.clone();
+        let default_caller_rpc = default_node_id.to_string();
+        let rpc = move |_caller: &str, addr: &str, msg: &[u8]| {
+            peers_rpc.call_rpc(default_caller_rpc.clone(), addr.to_string(), Vec::from(msg))
+        };
+
+        let peers_stream = peers.clone();
+        let default_caller_stream = default_node_id.to_string();
+        let stream = move |_caller: &str, addr: &str, msg: &[u8]| {
+            peers_stream.call(
+                default_caller_stream.clone(),
+                addr.to_string(),
+                Vec::from(msg),
+            )
+        };
+
+        local_bus::subscribe(net::BUS_ID, rpc, stream);
+    }
+
+    // bind /from/<caller>/to/<addr> on my local bus and route all calls to peers
+    {
+        let nodes_rpc = nodes.clone();
+        let peers_rpc = peers.clone();
+        let rpc = move |_caller: &str, addr: &str, msg: &[u8]| {
+            let (from_node, to_addr) = match parse_from_addr(addr) {
+                Ok(v) => v,
+                Err(e) => return future::err(Error::GsbBadRequest(e.to_string())).boxed_local(),
+            };
+            if !nodes_rpc.contains(&from_node) {
+                return future::err(Error::GsbBadRequest(format!(
+                    "caller: {:?} is not on src list: {:?}",
+                    from_node, nodes_rpc,
+                )))
+                .boxed_local();
+            }
+            peers_rpc.call_rpc(from_node.to_string(), to_addr, Vec::from(msg))
+        };
+
+        let nodes_stream = nodes.clone();
+        let peers_stream = peers.clone();
+        let stream = move |_caller: &str, addr: &str, msg: &[u8]| {
+            let (from_node, to_addr) = match parse_from_addr(addr) {
+                Ok(v) => v,
+                Err(e) => {
+                    let err = Error::GsbBadRequest(e.to_string());
+                    return stream::once(future::err(err)).boxed_local();
+                }
+            };
+            if !nodes_stream.contains(&from_node) {
+                let err = Error::GsbBadRequest(format!(
+                    "caller: {:?} is not on src list: {:?}",
+                    from_node, nodes_stream,
+                ));
+                return stream::once(future::err(err)).boxed_local();
+            }
+            peers_stream.call(from_node.to_string(), to_addr, Vec::from(msg))
+        };
+
+        local_bus::subscribe("/from", rpc, stream);
+    }
+
+    // Subscribe broadcast locally; there is no hub to notify.
+    {
+        let bcast = bcast.clone();
+        let _ = bus::bind(local_net::BUS_ID, move |subscribe: local_net::Subscribe| {
+            let topic = subscribe.topic().to_owned();
+            let (_, id) = bcast.add(subscribe);
+            log::debug!("Subscribed topic {} for peer broadcasts.", topic);
+            future::ok(id)
+        });
+    }
+
+    {
+        let bcast = bcast.clone();
+        let _ = bus::bind(
+            local_net::BUS_ID,
+            move |unsubscribe: local_net::Unsubscribe| {
+                log::debug!("Unsubscribed topic {}.", unsubscribe.topic());
+                future::ok(bcast.remove(&unsubscribe))
+            },
+        );
+    }
+
+    // There is no hub to report connection with
+    {
+        let _ = bus::bind(local_net::BUS_ID, |_: local_net::Status| {
+            future::err::<local_net::StatusInfo, _>(local_net::StatusError::RuntimeException(
+                "network runs in peer-to-peer mode, without hub".to_string(),
+            ))
+        });
+    }
+
+    // Flood broadcast to peers
+    {
+        let bcast_service_id = <SendBroadcastMessage<()> as RpcMessage>::ID;
+        let addr = format!("{}/{}", local_net::BUS_ID, bcast_service_id);
+        let resp: Rc<[u8]> = serialization::to_vec(&Ok::<(), ()>(())).unwrap().into();
+        let _ = local_bus::subscribe(
+            &addr,
+            move |caller: &str, _addr: &str, msg: &[u8]| {
+                let stub: SendBroadcastStub = match serialization::from_slice(msg) {
+                    Ok(m) => m,
+                    Err(e) => {
+                        let err = Error::GsbFailure(format!("invalid bcast message: {}", e));
+                        return future::err(err);
+                    }
+                };
+
+                // Peers drop broadcasts with ids seen before, so given id
+                // can be used only for a single topic.
+                let topics = bcast.network_topics(&stub.topic, &stub.scope);
+                let id = match topics.len() {
+                    1 => stub.id,
+                    _ => None,
+                };
+                for topic in topics {
+                    peers.broadcast(caller.to_owned(), topic, id.clone(), Vec::from(msg));
+                }
+                future::ok(Vec::from(resp.as_ref()))
+            },
+            (),
+        );
+    }
+
+    Ok(())
+}
+
+/// Keeps connection with peer from static list, reconnecting when it breaks.
+async fn keep_connected(peers: Peers, peer: String) {
+    loop {
+        let addr = peer.to_socket_addrs().map(|mut addrs| addrs.next());
+        match addr {
+            Ok(Some(addr)) => match connect(peers.clone(), addr).await {
+                Ok(_) => log::info!("Connection with peer {} closed.", peer),
+                Err(e) => log::debug!("Connection with peer {} failed: {}", peer, e),
+            },
+            Ok(None) => log::warn!("Peer address {} not resolved.", peer),
+            Err(e) => log::warn!("Failed to resolve peer address {}: {}", peer, e),
+        }
+        tokio::time::delay_for(PEER_RECONNECT_INTERVAL).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn some(value: &str) -> Option<String> {
+        Some(value.to_string())
+    }
+
+    #[test]
+    fn hub_mode_by_default() {
+        assert_eq!(P2pConfig::from_vars(None, None, None, None).unwrap(), None);
+        assert_eq!(
+            P2pConfig::from_vars(some("hub"), some("0.0.0.0:1"), None, None).unwrap(),
+            None
+        );
+        assert!(P2pConfig::from_vars(some("mesh"), None, None, None).is_err());
+    }
+
+    #[test]
+    fn p2p_config_from_vars() {
+        let config = P2pConfig::from_vars(
+            some("p2p"),
+            None,
+            some("10.0.0.2:7477, node-3:7000,"),
+            some("7466"),
+        )
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(config.listen_addr, DEFAULT_P2P_LISTEN_ADDR.parse().unwrap());
+        assert_eq!(config.peers, vec!["10.0.0.2:7477", "node-3:7000"]);
+        assert_eq!(config.lan_discovery_port, Some(7466));
+        assert!(P2pConfig::from_vars(some("p2p"), some("localhost"), None, None).is_err());
+    }
+}
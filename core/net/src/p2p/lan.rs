@@ -0,0 +1,87 @@
+//! Discovery of peers in local network. Nodes announce their ids and
+//! listening port with UDP broadcasts and connect to announced nodes,
+//! they aren't connected to yet.
+use actix_rt::Arbiter;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::rc::Rc;
+use std::time::Duration;
+use tokio::net::UdpSocket;
+
+use ya_core_model::NodeId;
+use ya_service_bus::serialization;
+
+use super::peers::{connect, Peers};
+
+const ANNOUNCE_INTERVAL: Duration = Duration::from_secs(10);
+
+#[derive(Serialize, Deserialize)]
+struct Announcement {
+    node_ids: Vec<NodeId>,
+    listen_port: u16,
+}
+
+pub async fn start(peers: Peers, port: u16, listen_port: u16) -> std::io::Result<()> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, port)).await?;
+    socket.set_broadcast(true)?;
+    let (mut receiver, mut sender) = socket.split();
+
+    let announcement = Announcement {
+        node_ids: peers.own_ids(),
+        listen_port,
+    };
+    let announcement = serialization::to_vec(&announcement)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))?;
+    let target = SocketAddr::from((Ipv4Addr::BROADCAST, port));
+    log::info!("Announcing node in local network on UDP port {}.", port);
+
+    Arbiter::spawn(async move {
+        loop {
+            if let Err(e) = sender.send_to(&announcement, &target).await {
+                log::debug!("Failed to announce node in local network: {}", e);
+            }
+            tokio::time::delay_for(ANNOUNCE_INTERVAL).await;
+        }
+    });
+
+    Arbiter::spawn(async move {
+        let outgoing = Rc::new(RefCell::new(HashSet::new()));
+        let mut buf = vec![0u8; 64 * 1024];
+        loop {
+            let (len, from) = match receiver.recv_from(&mut buf).await {
+                Ok(received) => received,
+                Err(e) => {
+                    log::debug!("Failed to receive announcement: {}", e);
+                    continue;
+                }
+            };
+            let announcement: Announcement = match serialization::from_slice(&buf[..len]) {
+                Ok(announcement) => announcement,
+                Err(e) => {
+                    log::debug!("Invalid announcement from {}: {}", from, e);
+                    continue;
+                }
+            };
+            let known = announcement
+                .node_ids
+                .iter()
+                .any(|node_id| peers.is_own(node_id) || peers.is_connected(node_id));
+            let addr = SocketAddr::new(from.ip(), announcement.listen_port);
+            if known || !outgoing.borrow_mut().insert(addr) {
+                continue;
+            }
+
+            log::debug!("Discovered peer {} in local network.", addr);
+            let (peers, outgoing) = (peers.clone(), outgoing.clone());
+            Arbiter::spawn(async move {
+                if let Err(e) = connect(peers, addr).await {
+                    log::debug!("Connection with discovered peer {} failed: {}", addr, e);
+                }
+                outgoing.borrow_mut().remove(&addr);
+            });
+        }
+    });
+    Ok(())
+}
@@ -0,0 +1,1323 @@
+//! Connections to other nodes and routing of messages between them.
+use actix_rt::Arbiter;
+use anyhow::{anyhow, bail};
+use futures::channel::mpsc::{self, unbounded, UnboundedSender};
+use futures::prelude::*;
+use futures::{future::LocalBoxFuture, stream::LocalBoxStream};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::rc::Rc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::net::TcpStream;
+use tokio::time::{delay_until, timeout, Instant};
+use tokio_util::codec::{Framed, LengthDelimitedCodec};
+
+use ya_agreement_utils::signature::verify_raw_signature;
+use ya_core_model::net;
+use ya_core_model::net::local::SendBroadcastMessage;
+use ya_core_model::NodeId;
+use ya_service_bus::{untyped as local_bus, Error, ResponseChunk, RpcMessage};
+
+use super::protocol::{
+    broadcast_digest, call_digest, challenge_digest, generate_challenge, Chunk, Frame, Origin,
+    SeenIds, MAX_BROADCAST_HOPS, MAX_ROUTE_HOPS, SEEN_BROADCASTS_CAPACITY, SEEN_CALLS_CAPACITY,
+};
+use crate::api::{dst_node, net_service};
+use crate::bcast::BCastService;
+use crate::secure::{identity_signer, Signer};
+
+/// Calls without any reply for that long fail.
+pub const CALL_TIMEOUT: Duration = Duration::from_secs(60);
+/// Peer has to send its Hello and Proof within this time.
+pub const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+/// Frames waiting for sending to single peer. Frames to peer,
+/// which doesn't keep up with receiving them, are dropped.
+const CONNECTION_QUEUE_SIZE: usize = 1024;
+
+/// Connections to other nodes. Nodes of connected peers are reachable directly,
+/// the other ones through the peer, which announced the shortest route to them.
+/// Calls are relayed along these routes, so not every pair of nodes has to be
+/// connected. Broadcasts are flooded to all connections.
+#[derive(Clone)]
+pub struct Peers {
+    inner: Rc<RefCell<PeersInner>>,
+    bcast: BCastService,
+    signer: Signer,
+}
+
+struct PeersInner {
+    default_node_id: NodeId,
+    own_ids: Vec<NodeId>,
+    connections: HashMap<u64, Connection>,
+    routes: HashMap<NodeId, Route>,
+    pending: HashMap<u64, PendingCall>,
+    seen: SeenIds,
+    /// Nonces of verified relayed calls.
+    seen_calls: SeenIds,
+    call_timeout: Duration,
+    handshake_timeout: Duration,
+    bcast_id_prefix: String,
+    last_conn_id: u64,
+    last_request_id: u64,
+    last_bcast_id: u64,
+}
+
+struct Connection {
+    addr: SocketAddr,
+    /// Nodes, which peer proved to own in Hello.
+    node_ids: Vec<NodeId>,
+    /// Nodes reachable through peer with number of hops from it.
+    announced: HashMap<NodeId, u8>,
+    sender: mpsc::Sender<Frame>,
+}
+
+impl Connection {
+    /// Queues frame for sending. Returns false, if frame was dropped.
+    fn send(&mut self, frame: Frame) -> bool {
+        match self.sender.try_send(frame) {
+            Ok(()) => true,
+            Err(e) => {
+                if e.is_full() {
+                    log::debug!("Dropping frame to {}, which doesn't keep up.", self.addr);
+                }
+                false
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Route {
+    conn_id: u64,
+    /// 1 for nodes of directly connected peer.
+    hops: u8,
+}
+
+struct PendingCall {
+    conn_id: u64,
+    addr: String,
+    /// Call fails, if there is no reply until then.
+    deadline: Instant,
+    sender: UnboundedSender<Result<ResponseChunk, Error>>,
+}
+
+impl Peers {
+    pub fn new(default_node_id: NodeId, own_ids: Vec<NodeId>, bcast: BCastService) -> Self {
+        Peers::with_signer(
+            default_node_id,
+            own_ids,
+            bcast,
+            identity_signer(),
+            CALL_TIMEOUT,
+            HANDSHAKE_TIMEOUT,
+        )
+    }
+
+    fn with_signer(
+        default_node_id: NodeId,
+        own_ids: Vec<NodeId>,
+        bcast: BCastService,
+        signer: Signer,
+        call_timeout: Duration,
+        handshake_timeout: Duration,
+    ) -> Self {
+        let started = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_nanos())
+            .unwrap_or_default();
+        let inner = PeersInner {
+            default_node_id,
+            own_ids,
+            connections: Default::default(),
+            routes: Default::default(),
+            pending: Default::default(),
+            seen: SeenIds::new(SEEN_BROADCASTS_CAPACITY),
+            seen_calls: SeenIds::new(SEEN_CALLS_CAPACITY),
+            call_timeout,
+            handshake_timeout,
+            bcast_id_prefix: format!("{}-{}", default_node_id, started),
+            last_conn_id: 0,
+            last_request_id: 0,
+            last_bcast_id: 0,
+        };
+        Peers {
+            inner: Rc::new(RefCell::new(inner)),
+            bcast,
+            signer,
+        }
+    }
+
+    pub fn own_ids(&self) -> Vec<NodeId> {
+        self.inner.borrow().own_ids.clone()
+    }
+
+    pub fn is_own(&self, node_id: &NodeId) -> bool {
+        self.inner.borrow().own_ids.contains(node_id)
+    }
+
+    /// Whether node is connected directly, not through other peers.
+    pub fn is_connected(&self, node_id: &NodeId) -> bool {
+        match self.inner.borrow().routes.get(node_id) {
+            Some(route) => route.hops == 1,
+            None => false,
+        }
+    }
+
+    /// Registers connection with peer, which proved it owns `node_ids`.
+    /// Live connection with any of these nodes is never replaced,
+    /// so the new one is refused then.
+    fn register(
+        &self,
+        addr: SocketAddr,
+        node_ids: Vec<NodeId>,
+        sender: mpsc::Sender<Frame>,
+    ) -> anyhow::Result<u64> {
+        let conn_id = {
+            let mut inner = self.inner.borrow_mut();
+            for node_id in &node_ids {
+                let live = inner
+                    .connections
+                    .values()
+                    .find(|connection| connection.node_ids.contains(node_id));
+                if let Some(live) = live {
+                    bail!(
+                        "node [{}] is already connected through {}",
+                        node_id,
+                        live.addr
+                    );
+                }
+            }
+
+            inner.last_conn_id += 1;
+            let conn_id = inner.last_conn_id;
+            inner.connections.insert(
+                conn_id,
+                Connection {
+                    addr,
+                    node_ids,
+                    announced: Default::default(),
+                    sender,
+                },
+            );
+            conn_id
+        };
+        // New peer has to learn routes, even if they didn't change.
+        self.update_routes();
+        self.announce_routes();
+        Ok(conn_id)
+    }
+
+    fn unregister(&self, conn_id: u64) {
+        {
+            let mut inner = self.inner.borrow_mut();
+            let connection = match inner.connections.remove(&conn_id) {
+                Some(connection) => connection,
+                None => return,
+            };
+
+            let broken = inner
+                .pending
+                .iter()
+                .filter(|(_, call)| call.conn_id == conn_id)
+                .map(|(request_id, _)| *request_id)
+                .collect::<Vec<_>>();
+            for request_id in broken {
+                if let Some(call) = inner.pending.remove(&request_id) {
+                    let error = format!("peer {} disconnected", connection.addr);
+                    let _ = call
+                        .sender
+                        .unbounded_send(Err(Error::RemoteError(call.addr, error)));
+                }
+            }
+        }
+        if self.update_routes() {
+            self.announce_routes();
+        }
+    }
+
+    /// Chooses the shortest route to every node reachable through connections.
+    /// Current route is kept, unless other one is shorter.
+    /// Returns true, if any route changed.
+    fn update_routes(&self) -> bool {
+        let mut inner = self.inner.borrow_mut();
+        let mut routes: HashMap<NodeId, Route> = HashMap::new();
+        for (conn_id, connection) in inner.connections.iter() {
+            let direct = connection.node_ids.iter().map(|node_id| (*node_id, 1));
+            // Nodes of peer are 1 hop from it, so announced routes have at least
+            // 2 hops and never win with direct connection. Announced hops aren't
+            // proven, so routes through peer can be at most as bad, as it claims.
+            let announced = connection
+                .announced
+                .iter()
+                .filter(|(_, hops)| **hops >= 1 && **hops < MAX_ROUTE_HOPS)
+                .map(|(node_id, hops)| (*node_id, hops + 1));
+
+            for (node_id, hops) in direct.chain(announced) {
+                if inner.own_ids.contains(&node_id) {
+                    continue;
+                }
+                let current = inner.routes.get(&node_id).map(|route| route.conn_id);
+                let shorter = match routes.get(&node_id) {
+                    Some(best) => {
+                        hops < best.hops || (hops == best.hops && current == Some(*conn_id))
+                    }
+                    None => true,
+                };
+                if shorter {
+                    let conn_id = *conn_id;
+                    routes.insert(node_id, Route { conn_id, hops });
+                }
+            }
+        }
+
+        let changed = routes != inner.routes;
+        inner.routes = routes;
+        changed
+    }
+
+    /// Sends each peer all routes, which don't lead through that peer.
+    fn announce_routes(&self) {
+        let mut inner = self.inner.borrow_mut();
+        let inner = &mut *inner;
+        let routes = &inner.routes;
+        for (conn_id, connection) in inner.connections.iter_mut() {
+            let routes = routes
+                .iter()
+                .filter(|(_, route)| route.conn_id != *conn_id)
+                .map(|(node_id, route)| (*node_id, route.hops))
+                .collect();
+            connection.send(Frame::Routes { routes });
+        }
+    }
+
+    /// Queues frame for sending to peer `conn_id`.
+    fn send(&self, conn_id: u64, frame: Frame) -> bool {
+        match self.inner.borrow_mut().connections.get_mut(&conn_id) {
+            Some(connection) => connection.send(frame),
+            None => false,
+        }
+    }
+
+    /// Signs Hello challenge of peer with each of own node ids.
+    async fn sign_challenge(&self, challenge: &[u8]) -> anyhow::Result<Vec<Vec<u8>>> {
+        let mut signatures = vec![];
+        for node_id in self.own_ids() {
+            let digest = challenge_digest(challenge, &node_id);
+            let signature = (self.signer)(node_id, digest)
+                .await
+                .map_err(|e| anyhow!("failed to sign Hello as [{}]: {}", node_id, e))?;
+            signatures.push(signature);
+        }
+        Ok(signatures)
+    }
+
+    /// Routes call to `/net/<node_id>/...` address. Calls to nodes, which
+    /// aren't connected directly, are signed by caller.
+    pub fn call(
+        &self,
+        caller: String,
+        addr: String,
+        data: Vec<u8>,
+    ) -> LocalBoxStream<'static, Result<ResponseChunk, Error>> {
+        let dst = match dst_node(&addr) {
+            Ok(dst) => dst,
+            Err(e) => return stream::once(future::err(e)).boxed_local(),
+        };
+        if self.is_own(&dst) || self.is_connected(&dst) {
+            return self.route_call(caller, addr, data, MAX_ROUTE_HOPS, None);
+        }
+
+        let caller_id = match caller.parse::<NodeId>() {
+            Ok(caller_id) if self.is_own(&caller_id) => caller_id,
+            _ => {
+                let error = Error::GsbBadRequest(format!("can't sign call as {}", caller));
+                return stream::once(future::err(error)).boxed_local();
+            }
+        };
+        let peers = self.clone();
+        async move {
+            let nonce = generate_challenge();
+            let digest = call_digest(&caller_id, &nonce, &addr, &data);
+            match (peers.signer)(caller_id, digest).await {
+                Ok(signature) => {
+                    let origin = Origin { nonce, signature };
+                    peers.route_call(caller, addr, data, MAX_ROUTE_HOPS, Some(origin))
+                }
+                Err(e) => {
+                    let error = format!("failed to sign call as [{}]: {}", caller_id, e);
+                    stream::once(future::err(Error::GsbFailure(error))).boxed_local()
+                }
+            }
+        }
+        .flatten_stream()
+        .boxed_local()
+    }
+
+    fn route_call(
+        &self,
+        caller: String,
+        addr: String,
+        data: Vec<u8>,
+        hops: u8,
+        origin: Option<Origin>,
+    ) -> LocalBoxStream<'static, Result<ResponseChunk, Error>> {
+        let dst = match dst_node(&addr) {
+            Ok(dst) => dst,
+            Err(e) => return stream::once(future::err(e)).boxed_local(),
+        };
+
+        let mut inner = self.inner.borrow_mut();
+        if inner.own_ids.contains(&dst) {
+            let local_addr = addr.replacen(&net_service(&dst), net::PUBLIC_PREFIX, 1);
+            return local_bus::call_stream(&local_addr, &caller, &data).boxed_local();
+        }
+
+        let conn_id = match inner.routes.get(&dst) {
+            Some(route) => route.conn_id,
+            None => {
+                let error = Error::GsbFailure(format!("node [{}] is not reachable", dst));
+                return stream::once(future::err(error)).boxed_local();
+            }
+        };
+        inner.last_request_id += 1;
+        let request_id = inner.last_request_id;
+        let (sender, receiver) = unbounded();
+        let deadline = Instant::now() + inner.call_timeout;
+        inner.pending.insert(
+            request_id,
+            PendingCall {
+                conn_id,
+                addr: addr.clone(),
+                deadline,
+                sender,
+            },
+        );
+
+        log::trace!("{} is calling {} through peer connection.", caller, addr);
+        let frame = Frame::Call {
+            request_id,
+            caller,
+            addr: addr.clone(),
+            data,
+            hops,
+            origin,
+        };
+        let sent = match inner.connections.get_mut(&conn_id) {
+            Some(connection) => connection.send(frame),
+            None => false,
+        };
+        if !sent {
+            inner.pending.remove(&request_id);
+            let error = Error::RemoteError(addr, "can't send call to peer".to_string());
+            return stream::once(future::err(error)).boxed_local();
+        }
+        drop(inner);
+        Arbiter::spawn(self.clone().expire_call(request_id));
+        receiver.boxed_local()
+    }
+
+    /// Fails pending call, when there is no reply until its deadline.
+    async fn expire_call(self, request_id: u64) {
+        loop {
+            let deadline = match self.inner.borrow().pending.get(&request_id) {
+                Some(call) => call.deadline,
+                None => return,
+            };
+            delay_until(deadline).await;
+
+            let mut inner = self.inner.borrow_mut();
+            match inner.pending.get(&request_id) {
+                Some(call) if call.deadline <= Instant::now() => (),
+                Some(_) => continue,
+                None => return,
+            }
+            let timeout = inner.call_timeout;
+            if let Some(call) = inner.pending.remove(&request_id) {
+                log::debug!("Call to {} timed out.", call.addr);
+                let error = format!("no reply within {:?}", timeout);
+                let _ = call
+                    .sender
+                    .unbounded_send(Err(Error::RemoteError(call.addr, error)));
+            }
+            return;
+        }
+    }
+
+    /// Routes call and waits for its full response.
+    pub fn call_rpc(
+        &self,
+        caller: String,
+        addr: String,
+        data: Vec<u8>,
+    ) -> LocalBoxFuture<'static, Result<Vec<u8>, Error>> {
+        let mut replies = self.call(caller, addr.clone(), data);
+        async move {
+            while let Some(chunk) = replies.next().await {
+                if let ResponseChunk::Full(data) = chunk? {
+                    return Ok(data);
+                }
+            }
+            Err(Error::RemoteError(addr, "no reply".to_string()))
+        }
+        .boxed_local()
+    }
+
+    /// Signs and floods broadcast originating from this node. Broadcasts
+    /// of callers, which aren't own nodes, are sent as default node.
+    pub fn broadcast(&self, caller: String, topic: String, id: Option<String>, data: Vec<u8>) {
+        let (id, caller_id) = {
+            let mut inner = self.inner.borrow_mut();
+            let id = id.unwrap_or_else(|| {
+                inner.last_bcast_id += 1;
+                format!("{}-{}", inner.bcast_id_prefix, inner.last_bcast_id)
+            });
+            inner.seen.insert(&id);
+
+            let caller_id = match caller.parse::<NodeId>() {
+                Ok(caller_id) if inner.own_ids.contains(&caller_id) => caller_id,
+                _ => inner.default_node_id,
+            };
+            (id, caller_id)
+        };
+
+        let peers = self.clone();
+        Arbiter::spawn(async move {
+            let digest = broadcast_digest(&caller_id, &id, &topic, &data);
+            let signature = match (peers.signer)(caller_id, digest).await {
+                Ok(signature) => signature,
+                Err(e) => {
+                    return log::warn!("Failed to sign broadcast as [{}]: {}", caller_id, e);
+                }
+            };
+
+            log::trace!(
+                "Broadcasting {} on topic {} from [{}].",
+                id,
+                topic,
+                caller_id
+            );
+            peers.forward(
+                None,
+                Frame::Broadcast {
+                    id,
+                    caller: caller_id.to_string(),
+                    topic,
+                    data,
+                    hops: MAX_BROADCAST_HOPS,
+                    signature,
+                },
+            );
+        });
+    }
+
+    fn forward(&self, except: Option<u64>, frame: Frame) {
+        let mut inner = self.inner.borrow_mut();
+        for (conn_id, connection) in inner.connections.iter_mut() {
+            if Some(*conn_id) != except {
+                connection.send(frame.clone());
+            }
+        }
+    }
+
+    fn handle(&self, conn_id: u64, frame: Frame) {
+        match frame {
+            Frame::Hello { .. } | Frame::Proof { .. } => {
+                log::warn!("Unexpected handshake frame from peer connection.")
+            }
+            Frame::Routes { routes } => self.handle_routes(conn_id, routes),
+            Frame::Call {
+                request_id,
+                caller,
+                addr,
+                data,
+                hops,
+                origin,
+            } => self.handle_call(conn_id, request_id, caller, addr, data, hops, origin),
+            Frame::Reply { request_id, reply } => self.handle_reply(conn_id, request_id, reply),
+            Frame::Broadcast {
+                id,
+                caller,
+                topic,
+                data,
+                hops,
+                signature,
+            } => self.handle_broadcast(conn_id, id, caller, topic, data, hops, signature),
+        }
+    }
+
+    fn handle_routes(&self, conn_id: u64, routes: Vec<(NodeId, u8)>) {
+        match self.inner.borrow_mut().connections.get_mut(&conn_id) {
+            Some(connection) => connection.announced = routes.into_iter().collect(),
+            None => return,
+        }
+        if self.update_routes() {
+            self.announce_routes();
+        }
+    }
+
+    /// Accepts call made by peer itself or relayed call signed by its caller.
+    #[allow(clippy::too_many_arguments)]
+    fn handle_call(
+        &self,
+        conn_id: u64,
+        request_id: u64,
+        caller: String,
+        addr: String,
+        data: Vec<u8>,
+        hops: u8,
+        origin: Option<Origin>,
+    ) {
+        let peers = self.clone();
+        let reply = move |reply: Result<Chunk, String>| {
+            peers.send(conn_id, Frame::Reply { request_id, reply });
+        };
+
+        let verified = {
+            let mut inner = self.inner.borrow_mut();
+            let inner = &mut *inner;
+            let connection = match inner.connections.get(&conn_id) {
+                Some(connection) => connection,
+                None => return,
+            };
+            match caller.parse::<NodeId>() {
+                Ok(caller_id) if connection.node_ids.contains(&caller_id) => Ok(()),
+                Ok(caller_id) => verify_origin(
+                    &mut inner.seen_calls,
+                    &caller_id,
+                    &addr,
+                    &data,
+                    origin.as_ref(),
+                ),
+                Err(_) => Err(format!("invalid caller {}", caller)),
+            }
+        };
+        if let Err(e) = verified {
+            log::debug!("Refusing call to {}: {}", addr, e);
+            return reply(Err(e));
+        }
+
+        let prefix = self
+            .own_ids()
+            .iter()
+            .map(|node_id| net_service(node_id))
+            .find(|prefix| addr.starts_with(prefix));
+        let mut replies = match prefix {
+            Some(prefix) => {
+                // replaces  /net/<dest_node_id>/test/1 --> /public/test/1
+                let local_addr = addr.replacen(&prefix, net::PUBLIC_PREFIX, 1);
+                log::trace!(
+                    "Incoming msg from = {}, to = {}, fwd to local addr = {}, request_id: {}",
+                    caller,
+                    addr,
+                    local_addr,
+                    request_id
+                );
+                local_bus::call_stream(&local_addr, &caller, &data).boxed_local()
+            }
+            None => match self.relay(conn_id, caller, addr, data, hops, origin) {
+                Ok(replies) => replies,
+                Err(e) => return reply(Err(e)),
+            },
+        };
+
+        Arbiter::spawn(async move {
+            while let Some(chunk) = replies.next().await {
+                let last = match &chunk {
+                    Ok(ResponseChunk::Part(_)) => false,
+                    _ => true,
+                };
+                reply(chunk.map(Chunk::from).map_err(|e| e.to_string()));
+                if last {
+                    break;
+                }
+            }
+        });
+    }
+
+    /// Forwards call received from peer `conn_id` towards its destination.
+    fn relay(
+        &self,
+        conn_id: u64,
+        caller: String,
+        addr: String,
+        data: Vec<u8>,
+        hops: u8,
+        origin: Option<Origin>,
+    ) -> Result<LocalBoxStream<'static, Result<ResponseChunk, Error>>, String> {
+        let dst = dst_node(&addr).map_err(|e| e.to_string())?;
+        if hops <= 1 {
+            return Err(format!("too many hops on the way to [{}]", dst));
+        }
+        match self.inner.borrow().routes.get(&dst) {
+            Some(route) if route.conn_id != conn_id => (),
+            _ => return Err(format!("node [{}] is not reachable", dst)),
+        }
+        // Next peer can't verify caller, which isn't its direct peer, without signature.
+        if origin.is_none() {
+            return Err(format!("call from {} to [{}] isn't signed", caller, dst));
+        }
+
+        log::trace!("Relaying call from {} to {}.", caller, addr);
+        Ok(self.route_call(caller, addr, data, hops - 1, origin))
+    }
+
+    fn handle_reply(&self, conn_id: u64, request_id: u64, reply: Result<Chunk, String>) {
+        let mut inner = self.inner.borrow_mut();
+        let deadline = Instant::now() + inner.call_timeout;
+        match inner.pending.get_mut(&request_id) {
+            Some(call) if call.conn_id == conn_id => call.deadline = deadline,
+            _ => return log::debug!("Dropping reply to unknown request {}.", request_id),
+        }
+
+        let last = match &reply {
+            Ok(Chunk::Part(_)) => false,
+            _ => true,
+        };
+        let call = &inner.pending[&request_id];
+        let reply = reply
+            .map(ResponseChunk::from)
+            .map_err(|e| Error::RemoteError(call.addr.clone(), e));
+        let _ = call.sender.unbounded_send(reply);
+        if last {
+            inner.pending.remove(&request_id);
+        }
+    }
+
+    /// Delivers broadcast signed by its caller locally and forwards it further.
+    #[allow(clippy::too_many_arguments)]
+    fn handle_broadcast(
+        &self,
+        conn_id: u64,
+        id: String,
+        caller: String,
+        topic: String,
+        data: Vec<u8>,
+        hops: u8,
+        signature: Vec<u8>,
+    ) {
+        if self.inner.borrow().seen.contains(&id) {
+            return;
+        }
+        // Id is marked as seen only after verification, so forged broadcast
+        // can't suppress genuine one with the same id.
+        if let Err(e) = verify_broadcast(&id, &caller, &topic, &data, &signature) {
+            return log::debug!("Dropping broadcast {}: {}", id, e);
+        }
+        self.inner.borrow_mut().seen.insert(&id);
+
+        let endpoints = self.bcast.resolve_received(&topic, &data);
+        let bcast_service_id = <SendBroadcastMessage<()> as RpcMessage>::ID;
+        let msg: Rc<[u8]> = data.clone().into();
+        let (local_caller, local_topic) = (caller.clone(), topic.clone());
+        Arbiter::spawn(async move {
+            log::trace!(
+                "Received broadcast to topic {} from [{}].",
+                local_topic,
+                local_caller
+            );
+            for endpoint in endpoints {
+                let addr = format!("{}/{}", endpoint, bcast_service_id);
+                let _ = local_bus::send(addr.as_ref(), &local_caller, msg.as_ref()).await;
+            }
+        });
+
+        if hops > 1 {
+            self.forward(
+                Some(conn_id),
+                Frame::Broadcast {
+                    id,
+                    caller,
+                    topic,
+                    data,
+                    hops: hops - 1,
+                    signature,
+                },
+            );
+        }
+    }
+
+    fn handshake_timeout(&self) -> Duration {
+        self.inner.borrow().handshake_timeout
+    }
+}
+
+/// Connects to peer and serves connection until it is closed.
+pub async fn connect(peers: Peers, addr: SocketAddr) -> anyhow::Result<()> {
+    let stream = TcpStream::connect(addr).await?;
+    serve(peers, stream, addr).await
+}
+
+/// Exchanges Hello and Proof frames, so both sides prove they own announced
+/// node ids, and handles incoming frames until connection is closed.
+pub async fn serve(peers: Peers, stream: TcpStream, addr: SocketAddr) -> anyhow::Result<()> {
+    let mut framed = Framed::new(stream, LengthDelimitedCodec::new());
+    let node_ids = timeout(
+        peers.handshake_timeout(),
+        handshake(&peers, &mut framed, addr),
+    )
+    .await
+    .map_err(|_| anyhow!("{} didn't finish handshake in time", addr))??;
+
+    let (sink, mut frames) = framed.split();
+    let (sender, receiver) = mpsc::channel::<Frame>(CONNECTION_QUEUE_SIZE);
+    let conn_id = peers.register(addr, node_ids.clone(), sender)?;
+    log::info!("Connected to peer {} with nodes {:?}.", addr, node_ids);
+
+    Arbiter::spawn(
+        receiver
+            .filter_map(|frame| {
+                future::ready(
+                    frame
+                        .encode()
+                        .map_err(|e| log::error!("Failed to encode frame: {}", e))
+                        .ok(),
+                )
+            })
+            .map(Ok)
+            .forward(sink)
+            .map(move |result| {
+                if let Err(e) = result {
+                    log::debug!("Failed to send frame to {}: {}", addr, e);
+                }
+            }),
+    );
+
+    let result: anyhow::Result<()> = async {
+        while let Some(data) = frames.next().await {
+            peers.handle(conn_id, Frame::decode(&data?)?);
+        }
+        Ok(())
+    }
+    .await;
+
+    peers.unregister(conn_id);
+    log::info!("Disconnected from peer {}.", addr);
+    result
+}
+
+/// Returns node ids, which peer proved to own.
+async fn handshake(
+    peers: &Peers,
+    framed: &mut Framed<TcpStream, LengthDelimitedCodec>,
+    addr: SocketAddr,
+) -> anyhow::Result<Vec<NodeId>> {
+    let challenge = generate_challenge();
+    let hello = Frame::Hello {
+        node_ids: peers.own_ids(),
+        challenge: challenge.clone(),
+    };
+    framed.send(hello.encode()?).await?;
+
+    let (node_ids, peer_challenge) = match next_frame(framed, addr).await? {
+        Frame::Hello {
+            node_ids,
+            challenge,
+        } => (node_ids, challenge),
+        frame => bail!("expected Hello from {}, got: {:?}", addr, frame),
+    };
+    if node_ids.is_empty() {
+        bail!("{} announced no nodes", addr);
+    }
+    if node_ids.iter().any(|node_id| peers.is_own(node_id)) {
+        bail!("{} is our own address", addr);
+    }
+
+    let proof = Frame::Proof {
+        signatures: peers.sign_challenge(&peer_challenge).await?,
+    };
+    framed.send(proof.encode()?).await?;
+    match next_frame(framed, addr).await? {
+        Frame::Proof { signatures } => verify_proof(&challenge, &node_ids, &signatures)
+            .map_err(|e| anyhow!("{} failed to prove its nodes: {}", addr, e))?,
+        frame => bail!("expected Proof from {}, got: {:?}", addr, frame),
+    }
+    Ok(node_ids)
+}
+
+async fn next_frame(
+    framed: &mut Framed<TcpStream, LengthDelimitedCodec>,
+    addr: SocketAddr,
+) -> anyhow::Result<Frame> {
+    match framed.next().await {
+        Some(data) => Ok(Frame::decode(&data?)?),
+        None => bail!("{} closed connection during handshake", addr),
+    }
+}
+
+/// Checks, that peer signed our challenge with each node id it announced.
+fn verify_proof(
+    challenge: &[u8],
+    node_ids: &[NodeId],
+    signatures: &[Vec<u8>],
+) -> Result<(), String> {
+    if signatures.len() != node_ids.len() {
+        return Err(format!(
+            "{} signatures for {} nodes",
+            signatures.len(),
+            node_ids.len()
+        ));
+    }
+    for (node_id, signature) in node_ids.iter().zip(signatures) {
+        verify_raw_signature(&challenge_digest(challenge, node_id), signature, node_id)
+            .map_err(|e| format!("[{}]: {}", node_id, e))?;
+    }
+    Ok(())
+}
+
+/// Checks, that relayed call was signed by its caller. Nonces of verified
+/// calls are remembered, so peer can't replay call it relayed before.
+fn verify_origin(
+    seen_calls: &mut SeenIds,
+    caller: &NodeId,
+    addr: &str,
+    data: &[u8],
+    origin: Option<&Origin>,
+) -> Result<(), String> {
+    let origin = match origin {
+        Some(origin) => origin,
+        None => return Err(format!("call from [{}] isn't signed", caller)),
+    };
+    let digest = call_digest(caller, &origin.nonce, addr, data);
+    verify_raw_signature(&digest, &origin.signature, caller)
+        .map_err(|e| format!("invalid signature of [{}]: {}", caller, e))?;
+    if !seen_calls.insert(&hex::encode(&origin.nonce)) {
+        return Err(format!("call from [{}] was already received", caller));
+    }
+    Ok(())
+}
+
+/// Checks, that broadcast was signed by its caller.
+fn verify_broadcast(
+    id: &str,
+    caller: &str,
+    topic: &str,
+    data: &[u8],
+    signature: &[u8],
+) -> Result<(), String> {
+    let caller = caller
+        .parse::<NodeId>()
+        .map_err(|_| format!("invalid caller {}", caller))?;
+    verify_raw_signature(
+        &broadcast_digest(&caller, id, topic, data),
+        signature,
+        &caller,
+    )
+    .map_err(|e| format!("invalid signature of [{}]: {}", caller, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::{Deserialize, Serialize};
+    use tokio::io::AsyncReadExt;
+    use tokio::net::TcpListener;
+    use ya_core_model::net::local::{BroadcastMessage, BroadcastScope, ToEndpoint};
+    use ya_service_bus::serialization;
+
+    use crate::secure::{generate_identity, sign};
+
+    const ECHO_ADDR: &str = "/public/p2p-test/echo";
+    const SILENT_ADDR: &str = "/public/p2p-test/silent";
+
+    #[derive(Serialize, Deserialize)]
+    struct Ping;
+
+    impl BroadcastMessage for Ping {
+        const TOPIC: &'static str = "p2p-test-ping";
+    }
+
+    struct TestPeer {
+        node_id: NodeId,
+        peers: Peers,
+        addr: SocketAddr,
+    }
+
+    impl TestPeer {
+        fn hops_to(&self, other: &TestPeer) -> Option<u8> {
+            self.route_to(&other.node_id).map(|route| route.hops)
+        }
+
+        fn route_to(&self, node_id: &NodeId) -> Option<Route> {
+            self.peers.inner.borrow().routes.get(node_id).cloned()
+        }
+
+        /// Id of connection, when peer has only one.
+        fn conn_id(&self) -> u64 {
+            let inner = self.peers.inner.borrow();
+            assert_eq!(inner.connections.len(), 1);
+            *inner.connections.keys().next().unwrap()
+        }
+
+        fn addr_of(&self, service: &str) -> String {
+            service.replacen(net::PUBLIC_PREFIX, &net_service(&self.node_id), 1)
+        }
+    }
+
+    fn signer(secret: ethsign::SecretKey) -> Signer {
+        let secret = Rc::new(secret);
+        Rc::new(move |_: NodeId, digest: Vec<u8>| future::ok(sign(&secret, &digest)).boxed_local())
+    }
+
+    fn peers(node_id: NodeId, signer: Signer, call_timeout: Duration) -> Peers {
+        let bcast = BCastService::new(None, vec![node_id]);
+        Peers::with_signer(
+            node_id,
+            vec![node_id],
+            bcast,
+            signer,
+            call_timeout,
+            HANDSHAKE_TIMEOUT,
+        )
+    }
+
+    async fn start_peer(call_timeout: Duration) -> TestPeer {
+        start_peer_with(call_timeout, HANDSHAKE_TIMEOUT).await
+    }
+
+    async fn start_peer_with(call_timeout: Duration, handshake_timeout: Duration) -> TestPeer {
+        let (node_id, secret) = generate_identity();
+        let bcast = BCastService::new(None, vec![node_id]);
+        let peers = Peers::with_signer(
+            node_id,
+            vec![node_id],
+            bcast,
+            signer(secret),
+            call_timeout,
+            handshake_timeout,
+        );
+        let mut listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = peers.clone();
+        Arbiter::spawn(async move {
+            while let Ok((stream, addr)) = listener.accept().await {
+                Arbiter::spawn(serve(server.clone(), stream, addr).map(|_| ()));
+            }
+        });
+        TestPeer {
+            node_id,
+            peers,
+            addr,
+        }
+    }
+
+    fn link(from: &TestPeer, to: &TestPeer) {
+        Arbiter::spawn(connect(from.peers.clone(), to.addr).map(|_| ()));
+    }
+
+    async fn wait_for(condition: impl Fn() -> bool) -> bool {
+        for _ in 0..100 {
+            if condition() {
+                return true;
+            }
+            tokio::time::delay_for(Duration::from_millis(20)).await;
+        }
+        false
+    }
+
+    #[actix_rt::test]
+    async fn call_is_relayed_through_intermediate_peer() {
+        let _ = local_bus::subscribe(
+            ECHO_ADDR,
+            |caller: &str, _addr: &str, msg: &[u8]| {
+                let mut reply = caller.as_bytes().to_vec();
+                reply.extend_from_slice(msg);
+                future::ok(reply)
+            },
+            (),
+        );
+        let a = start_peer(CALL_TIMEOUT).await;
+        let b = start_peer(CALL_TIMEOUT).await;
+        let c = start_peer(CALL_TIMEOUT).await;
+        link(&a, &b);
+        link(&c, &b);
+
+        assert!(wait_for(|| a.hops_to(&c) == Some(2) && c.hops_to(&a) == Some(2)).await);
+        assert!(a.peers.is_connected(&b.node_id));
+        assert!(!a.peers.is_connected(&c.node_id));
+
+        let reply = a
+            .peers
+            .call_rpc(
+                a.node_id.to_string(),
+                c.addr_of(ECHO_ADDR),
+                b"ping".to_vec(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(reply, format!("{}ping", a.node_id).into_bytes());
+
+        // Caller has to be reachable through peer relaying the call.
+        let (other_id, _) = generate_identity();
+        let reply = a
+            .peers
+            .call_rpc(other_id.to_string(), c.addr_of(ECHO_ADDR), b"ping".to_vec())
+            .await;
+        assert!(reply.is_err());
+
+        let (unknown_id, _) = generate_identity();
+        let addr = ECHO_ADDR.replacen(net::PUBLIC_PREFIX, &net_service(&unknown_id), 1);
+        let reply = a
+            .peers
+            .call_rpc(a.node_id.to_string(), addr, b"ping".to_vec())
+            .await;
+        assert!(reply.is_err());
+        assert!(a.peers.inner.borrow().pending.is_empty());
+    }
+
+    #[actix_rt::test]
+    async fn broadcast_is_flooded_once_to_every_node() {
+        let received = Rc::new(RefCell::new(HashMap::<String, usize>::new()));
+        let mut nodes = vec![];
+        for name in &["a", "b", "c", "d"] {
+            let node = start_peer(CALL_TIMEOUT).await;
+            let endpoint = format!("/local/p2p-test/{}", name);
+            node.peers.bcast.add(Ping::into_subscribe_msg(&endpoint));
+
+            let bcast_service_id = <SendBroadcastMessage<()> as RpcMessage>::ID;
+            let (received, name) = (received.clone(), name.to_string());
+            let _ = local_bus::subscribe(
+                &format!("{}/{}", endpoint, bcast_service_id),
+                move |_caller: &str, _addr: &str, _msg: &[u8]| {
+                    *received.borrow_mut().entry(name.clone()).or_insert(0) += 1;
+                    future::ok(vec![])
+                },
+                (),
+            );
+            nodes.push(node);
+        }
+        // Triangle of a, b and c, with d connected to c only.
+        link(&nodes[0], &nodes[1]);
+        link(&nodes[1], &nodes[2]);
+        link(&nodes[2], &nodes[0]);
+        link(&nodes[3], &nodes[2]);
+        assert!(
+            wait_for(|| {
+                nodes[1].peers.is_connected(&nodes[2].node_id)
+                    && nodes[0].hops_to(&nodes[3]) == Some(2)
+            })
+            .await
+        );
+
+        let sender = &nodes[0];
+        let msg = serialization::to_vec(&SendBroadcastMessage::new(Ping)).unwrap();
+        let topic = sender
+            .peers
+            .bcast
+            .network_topic(Ping::TOPIC, &BroadcastScope::Network);
+        sender
+            .peers
+            .broadcast(sender.node_id.to_string(), topic, None, msg);
+
+        assert!(wait_for(|| received.borrow().len() == 3).await);
+        tokio::time::delay_for(Duration::from_millis(200)).await;
+        let received = received.borrow();
+        assert_eq!(received.get("a"), None);
+        for name in &["b", "c", "d"] {
+            assert_eq!(received.get(*name), Some(&1), "node {}", name);
+        }
+    }
+
+    #[actix_rt::test]
+    async fn pending_call_times_out() {
+        let _ = local_bus::subscribe(
+            SILENT_ADDR,
+            |_caller: &str, _addr: &str, _msg: &[u8]| future::pending::<Result<Vec<u8>, Error>>(),
+            (),
+        );
+        let a = start_peer(Duration::from_millis(200)).await;
+        let b = start_peer(CALL_TIMEOUT).await;
+        link(&a, &b);
+        assert!(wait_for(|| a.peers.is_connected(&b.node_id)).await);
+
+        let reply = a
+            .peers
+            .call_rpc(a.node_id.to_string(), b.addr_of(SILENT_ADDR), vec![])
+            .await;
+        match reply {
+            Err(Error::RemoteError(_, msg)) => assert!(msg.starts_with("no reply"), "{}", msg),
+            reply => panic!("expected timeout, got: {:?}", reply),
+        }
+        assert!(a.peers.inner.borrow().pending.is_empty());
+    }
+
+    #[actix_rt::test]
+    async fn peer_has_to_prove_its_nodes() {
+        let a = start_peer(CALL_TIMEOUT).await;
+
+        // Impostor claims node id, it has no key of.
+        let (victim_id, _) = generate_identity();
+        let (_, impostor_secret) = generate_identity();
+        let impostor = peers(victim_id, signer(impostor_secret), CALL_TIMEOUT);
+        let _ = connect(impostor, a.addr).await;
+        assert!(!a.peers.is_connected(&victim_id));
+        assert!(a.peers.inner.borrow().connections.is_empty());
+
+        // Second connection of the same node doesn't replace live one.
+        let (node_id, secret) = generate_identity();
+        let signer = signer(secret);
+        Arbiter::spawn(connect(peers(node_id, signer.clone(), CALL_TIMEOUT), a.addr).map(|_| ()));
+        assert!(wait_for(|| a.peers.is_connected(&node_id)).await);
+        let route = a.peers.inner.borrow().routes[&node_id];
+
+        let _ = connect(peers(node_id, signer, CALL_TIMEOUT), a.addr).await;
+        assert_eq!(a.peers.inner.borrow().routes[&node_id], route);
+        assert_eq!(a.peers.inner.borrow().connections.len(), 1);
+    }
+
+    #[actix_rt::test]
+    async fn announced_routes_dont_prove_nodes() {
+        let a = start_peer(CALL_TIMEOUT).await;
+        let b = start_peer(CALL_TIMEOUT).await;
+        let m = start_peer(CALL_TIMEOUT).await;
+        link(&a, &b);
+        link(&m, &a);
+        assert!(wait_for(|| a.peers.is_connected(&b.node_id) && a.hops_to(&m) == Some(1)).await);
+        let route_to_b = a.route_to(&b.node_id).unwrap();
+
+        // Announced routes are never as short as direct connection.
+        let (victim_id, _) = generate_identity();
+        let m_conn = m.conn_id();
+        let routes = vec![(b.node_id, 0), (victim_id, 1)];
+        m.peers.send(m_conn, Frame::Routes { routes });
+        assert!(wait_for(|| a.route_to(&victim_id).map(|route| route.hops) == Some(2)).await);
+        assert!(!a.peers.is_connected(&victim_id));
+        assert_eq!(a.route_to(&b.node_id), Some(route_to_b));
+
+        // Peer can't call as node, which it only announced route to,
+        // neither without signature, nor with its own one.
+        let addr = a.addr_of(ECHO_ADDR);
+        let nonce = generate_challenge();
+        let digest = call_digest(&victim_id, &nonce, &addr, b"ping");
+        let signature = (m.peers.signer)(m.node_id, digest).await.unwrap();
+        let forged = vec![None, Some(Origin { nonce, signature })];
+        for (request_id, origin) in forged.into_iter().enumerate() {
+            let request_id = 1000 + request_id as u64;
+            let (sender, mut replies) = unbounded();
+            m.peers.inner.borrow_mut().pending.insert(
+                request_id,
+                PendingCall {
+                    conn_id: m_conn,
+                    addr: addr.clone(),
+                    deadline: Instant::now() + CALL_TIMEOUT,
+                    sender,
+                },
+            );
+            let frame = Frame::Call {
+                request_id,
+                caller: victim_id.to_string(),
+                addr: addr.clone(),
+                data: b"ping".to_vec(),
+                hops: MAX_ROUTE_HOPS,
+                origin,
+            };
+            m.peers.send(m_conn, frame);
+            match replies.next().await {
+                Some(Err(Error::RemoteError(_, msg))) => assert!(
+                    msg.contains("signature") || msg.contains("signed"),
+                    "{}",
+                    msg
+                ),
+                reply => panic!("expected refusal, got: {:?}", reply),
+            }
+        }
+    }
+
+    #[actix_rt::test]
+    async fn forged_broadcast_is_dropped() {
+        let received = Rc::new(RefCell::new(vec![]));
+        let a = start_peer(CALL_TIMEOUT).await;
+        let m = start_peer(CALL_TIMEOUT).await;
+        let endpoint = "/local/p2p-test/forged";
+        a.peers.bcast.add(Ping::into_subscribe_msg(endpoint));
+        let bcast_service_id = <SendBroadcastMessage<()> as RpcMessage>::ID;
+        let callers = received.clone();
+        let _ = local_bus::subscribe(
+            &format!("{}/{}", endpoint, bcast_service_id),
+            move |caller: &str, _addr: &str, _msg: &[u8]| {
+                callers.borrow_mut().push(caller.to_string());
+                future::ok(vec![])
+            },
+            (),
+        );
+        link(&m, &a);
+        assert!(wait_for(|| a.hops_to(&m) == Some(1)).await);
+
+        let msg = serialization::to_vec(&SendBroadcastMessage::new(Ping)).unwrap();
+        let topic = a
+            .peers
+            .bcast
+            .network_topic(Ping::TOPIC, &BroadcastScope::Network);
+        let id = "p2p-test-forged".to_string();
+        let (victim_id, _) = generate_identity();
+        let digest = broadcast_digest(&victim_id, &id, &topic, &msg);
+        let frame = Frame::Broadcast {
+            id: id.clone(),
+            caller: victim_id.to_string(),
+            topic: topic.clone(),
+            data: msg.clone(),
+            hops: MAX_BROADCAST_HOPS,
+            signature: (m.peers.signer)(m.node_id, digest).await.unwrap(),
+        };
+        m.peers.send(m.conn_id(), frame);
+
+        // Forged broadcast doesn't suppress genuine one with the same id.
+        m.peers
+            .broadcast(m.node_id.to_string(), topic, Some(id), msg);
+        assert!(wait_for(|| !received.borrow().is_empty()).await);
+        tokio::time::delay_for(Duration::from_millis(200)).await;
+        assert_eq!(*received.borrow(), vec![m.node_id.to_string()]);
+    }
+
+    #[actix_rt::test]
+    async fn silent_peer_is_disconnected_after_handshake_timeout() {
+        let a = start_peer_with(CALL_TIMEOUT, Duration::from_millis(200)).await;
+
+        let mut stream = TcpStream::connect(a.addr).await.unwrap();
+        let mut hello = vec![];
+        let closed = timeout(Duration::from_secs(5), stream.read_to_end(&mut hello)).await;
+        assert!(matches!(closed, Ok(Ok(_))), "{:?}", closed);
+        assert!(!hello.is_empty());
+        assert!(a.peers.inner.borrow().connections.is_empty());
+    }
+
+    #[test]
+    fn relayed_call_needs_signature_of_caller() {
+        let (caller, secret) = generate_identity();
+        let (other_id, _) = generate_identity();
+        let mut seen = SeenIds::new(SEEN_CALLS_CAPACITY);
+        let nonce = generate_challenge();
+        let signature = sign(
+            &secret,
+            &call_digest(&caller, &nonce, "/net/0x1/a", b"data"),
+        );
+        let origin = Origin { nonce, signature };
+
+        assert!(verify_origin(&mut seen, &caller, "/net/0x1/a", b"data", None).is_err());
+        assert!(verify_origin(&mut seen, &other_id, "/net/0x1/a", b"data", Some(&origin)).is_err());
+        assert!(verify_origin(&mut seen, &caller, "/net/0x1/b", b"data", Some(&origin)).is_err());
+        assert!(verify_origin(&mut seen, &caller, "/net/0x1/a", b"data", Some(&origin)).is_ok());
+        // Call can't be replayed.
+        assert!(verify_origin(&mut seen, &caller, "/net/0x1/a", b"data", Some(&origin)).is_err());
+    }
+
+    #[test]
+    fn proof_needs_signature_of_every_node() {
+        let (node_id, secret) = generate_identity();
+        let (other_id, other_secret) = generate_identity();
+        let challenge = generate_challenge();
+        let signature = sign(&secret, &challenge_digest(&challenge, &node_id));
+        let other_signature = sign(&other_secret, &challenge_digest(&challenge, &other_id));
+
+        assert!(verify_proof(&challenge, &[node_id], &[signature.clone()]).is_ok());
+        assert!(verify_proof(
+            &challenge,
+            &[node_id, other_id],
+            &[signature.clone(), other_signature.clone()]
+        )
+        .is_ok());
+        assert!(verify_proof(&challenge, &[node_id, other_id], &[signature.clone()]).is_err());
+        assert!(verify_proof(&challenge, &[other_id], &[signature.clone()]).is_err());
+        assert!(verify_proof(&generate_challenge(), &[node_id], &[signature]).is_err());
+    }
+}
@@ -0,0 +1,250 @@
+//! Frames exchanged between directly connected nodes.
+use bytes::Bytes;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Sha3_256};
+use std::collections::{HashSet, VecDeque};
+
+use ya_core_model::NodeId;
+use ya_service_bus::{serialization, Error, ResponseChunk};
+
+/// Broadcasts are forwarded at most this many times.
+pub const MAX_BROADCAST_HOPS: u8 = 8;
+/// Calls are relayed at most this many times and routes longer
+/// than that are not announced.
+pub const MAX_ROUTE_HOPS: u8 = 8;
+/// Number of remembered broadcast ids.
+pub const SEEN_BROADCASTS_CAPACITY: usize = 4096;
+/// Number of remembered nonces of relayed calls.
+pub const SEEN_CALLS_CAPACITY: usize = 4096;
+
+const CHALLENGE_DOMAIN: &[u8] = b"yagna-net-p2p-hello";
+const CALL_DOMAIN: &[u8] = b"yagna-net-p2p-call";
+const BROADCAST_DOMAIN: &[u8] = b"yagna-net-p2p-broadcast";
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub enum Frame {
+    /// First frame sent by both sides of connection. The other side
+    /// has to sign `challenge` with each of its `node_ids`.
+    Hello {
+        node_ids: Vec<NodeId>,
+        challenge: Vec<u8>,
+    },
+    /// Second frame sent by both sides of connection: signatures of peer's
+    /// challenge (see `challenge_digest`) in order of own `node_ids` from Hello.
+    Proof { signatures: Vec<Vec<u8>> },
+    /// Nodes reachable through sender with number of hops to them.
+    /// Replaces routes announced before.
+    Routes { routes: Vec<(NodeId, u8)> },
+    Call {
+        request_id: u64,
+        caller: String,
+        addr: String,
+        data: Vec<u8>,
+        /// Remaining number of relays.
+        hops: u8,
+        /// Proof, that `caller` made this call. Set for calls, which have to be
+        /// relayed, since relaying peer can't prove it.
+        origin: Option<Origin>,
+    },
+    Reply {
+        request_id: u64,
+        reply: Result<Chunk, String>,
+    },
+    Broadcast {
+        id: String,
+        caller: String,
+        topic: String,
+        data: Vec<u8>,
+        hops: u8,
+        /// Signature of `broadcast_digest` made by `caller`.
+        signature: Vec<u8>,
+    },
+}
+
+/// Signature of `call_digest` made by caller. Random `nonce` makes
+/// every call unique, so it can't be replayed.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct Origin {
+    pub nonce: Vec<u8>,
+    pub signature: Vec<u8>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub enum Chunk {
+    Part(Vec<u8>),
+    Full(Vec<u8>),
+}
+
+impl From<ResponseChunk> for Chunk {
+    fn from(chunk: ResponseChunk) -> Self {
+        match chunk {
+            ResponseChunk::Part(data) => Chunk::Part(data),
+            ResponseChunk::Full(data) => Chunk::Full(data),
+        }
+    }
+}
+
+impl From<Chunk> for ResponseChunk {
+    fn from(chunk: Chunk) -> Self {
+        match chunk {
+            Chunk::Part(data) => ResponseChunk::Part(data),
+            Chunk::Full(data) => ResponseChunk::Full(data),
+        }
+    }
+}
+
+impl Frame {
+    pub fn encode(&self) -> Result<Bytes, Error> {
+        serialization::to_vec(self)
+            .map(Bytes::from)
+            .map_err(|e| Error::GsbFailure(format!("invalid frame: {}", e)))
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Frame, Error> {
+        serialization::from_slice(data)
+            .map_err(|e| Error::GsbBadRequest(format!("invalid frame: {}", e)))
+    }
+}
+
+pub fn generate_challenge() -> Vec<u8> {
+    rand::thread_rng().gen::<[u8; 32]>().to_vec()
+}
+
+/// Hash of Hello challenge, which node signs to prove it owns `node_id`.
+pub fn challenge_digest(challenge: &[u8], node_id: &NodeId) -> Vec<u8> {
+    let mut hasher = Sha3_256::new();
+    hasher.input(CHALLENGE_DOMAIN);
+    hasher.input(challenge);
+    hasher.input(&node_id.into_array());
+    hasher.result().to_vec()
+}
+
+/// Hash of call, which caller signs, when call has to be relayed.
+pub fn call_digest(caller: &NodeId, nonce: &[u8], addr: &str, data: &[u8]) -> Vec<u8> {
+    let mut hasher = Sha3_256::new();
+    hasher.input(CALL_DOMAIN);
+    hasher.input(&caller.into_array());
+    input_field(&mut hasher, nonce);
+    input_field(&mut hasher, addr.as_bytes());
+    input_field(&mut hasher, data);
+    hasher.result().to_vec()
+}
+
+/// Hash of broadcast, which its caller signs.
+pub fn broadcast_digest(caller: &NodeId, id: &str, topic: &str, data: &[u8]) -> Vec<u8> {
+    let mut hasher = Sha3_256::new();
+    hasher.input(BROADCAST_DOMAIN);
+    hasher.input(&caller.into_array());
+    input_field(&mut hasher, id.as_bytes());
+    input_field(&mut hasher, topic.as_bytes());
+    input_field(&mut hasher, data);
+    hasher.result().to_vec()
+}
+
+/// Length prefix keeps boundaries of variable length fields.
+fn input_field(hasher: &mut Sha3_256, field: &[u8]) {
+    hasher.input(&(field.len() as u64).to_be_bytes());
+    hasher.input(field);
+}
+
+/// Ids of recently seen broadcasts. Oldest ids are forgotten first.
+pub struct SeenIds {
+    ids: HashSet<String>,
+    order: VecDeque<String>,
+    capacity: usize,
+}
+
+impl SeenIds {
+    pub fn new(capacity: usize) -> Self {
+        SeenIds {
+            ids: HashSet::new(),
+            order: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.ids.contains(id)
+    }
+
+    /// Returns false, if id was already seen.
+    pub fn insert(&mut self, id: &str) -> bool {
+        if self.ids.contains(id) {
+            return false;
+        }
+        if self.order.len() >= self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.ids.remove(&oldest);
+            }
+        }
+        self.ids.insert(id.to_string());
+        self.order.push_back(id.to_string());
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seen_ids_forget_oldest() {
+        let mut seen = SeenIds::new(2);
+        assert!(seen.insert("a"));
+        assert!(!seen.insert("a"));
+        assert!(seen.insert("b"));
+        assert!(seen.insert("c"));
+        assert!(seen.insert("a"));
+        assert!(!seen.insert("c"));
+    }
+
+    #[test]
+    fn challenge_digest_depends_on_challenge_and_node() {
+        let node_id = NodeId::from(&[1u8; 20][..]);
+        let other_id = NodeId::from(&[2u8; 20][..]);
+        let challenge = generate_challenge();
+        let digest = challenge_digest(&challenge, &node_id);
+
+        assert_eq!(digest, challenge_digest(&challenge, &node_id));
+        assert_ne!(digest, challenge_digest(&challenge, &other_id));
+        assert_ne!(digest, challenge_digest(&generate_challenge(), &node_id));
+    }
+
+    #[test]
+    fn call_digest_keeps_field_boundaries() {
+        let caller = NodeId::from(&[1u8; 20][..]);
+        let nonce = generate_challenge();
+        let digest = call_digest(&caller, &nonce, "/net/0x1/a", b"bc");
+
+        assert_eq!(digest, call_digest(&caller, &nonce, "/net/0x1/a", b"bc"));
+        assert_ne!(digest, call_digest(&caller, &nonce, "/net/0x1/ab", b"c"));
+        assert_ne!(
+            digest,
+            call_digest(&caller, &generate_challenge(), "/net/0x1/a", b"bc")
+        );
+        assert_ne!(
+            broadcast_digest(&caller, "1", "market", b""),
+            broadcast_digest(&caller, "1m", "arket", b"")
+        );
+    }
+
+    #[test]
+    fn frame_roundtrip() {
+        let frame = Frame::Broadcast {
+            id: "1".to_string(),
+            caller: "0x1".to_string(),
+            topic: "market".to_string(),
+            data: vec![1, 2, 3],
+            hops: MAX_BROADCAST_HOPS,
+            signature: vec![4; 65],
+        };
+        match Frame::decode(&frame.encode().unwrap()).unwrap() {
+            Frame::Broadcast { data, hops, .. } => {
+                assert_eq!(data, vec![1, 2, 3]);
+                assert_eq!(hops, MAX_BROADCAST_HOPS);
+            }
+            frame => panic!("unexpected frame {:?}", frame),
+        }
+    }
+}
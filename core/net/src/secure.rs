@@ -0,0 +1,790 @@
+//! Optional envelopes for messages relayed by hub, so the hub can't forge,
+//! redirect or replay them. Each outgoing call and broadcast is signed with
+//! the caller's identity key. Replies to signed calls are signed by the callee
+//! and bound to the call. In `encrypted` mode calls are also encrypted
+//! with a key shared with the session key of destination node, which that
+//! node publishes signed by its identity. Replies are encrypted with the same
+//! key. Broadcasts have no single recipient, so they are only signed.
+//!
+//! Envelopes are always opened, whatever the mode is, so nodes can switch
+//! modes one by one. Peer-to-peer transport doesn't use envelopes.
+use anyhow::bail;
+use chrono::Utc;
+use futures::future::LocalBoxFuture;
+use futures::prelude::*;
+use secp256k1::{PublicKey, SecretKey};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashMap};
+use std::rc::Rc;
+use std::str::FromStr;
+
+use ya_core_model::identity;
+use ya_core_model::NodeId;
+use ya_service_bus::{typed as bus, Error, ResponseChunk, RpcEndpoint, RpcMessage};
+
+use crate::api::{dst_node, net_service};
+
+mod envelope;
+
+#[cfg(any(test, feature = "testing"))]
+pub use envelope::sign;
+#[cfg(test)]
+pub(crate) use envelope::tests::generate_identity;
+pub use envelope::EnvelopeError;
+use envelope::{generate_keypair, session_key_digest, verify_signature, Body, Envelope, ReplyKey};
+
+/// Envelope of outgoing messages: `plain` (default), `signed` or `encrypted`.
+pub const NET_ENVELOPE_ENV_VAR: &str = "NET_ENVELOPE";
+/// When `true`, incoming messages without envelope are rejected.
+pub const NET_ENVELOPE_REQUIRED_ENV_VAR: &str = "NET_ENVELOPE_REQUIRED";
+
+/// Envelopes with timestamp further from local time are rejected,
+/// so they have to be remembered only for that long.
+const MAX_CLOCK_SKEW_MS: i64 = 5 * 60 * 1000;
+/// Local service publishing session key, exempt from envelopes.
+const SESSION_KEY_SERVICE: &str = "/public/net";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EnvelopeMode {
+    Plain,
+    Signed,
+    Encrypted,
+}
+
+impl FromStr for EnvelopeMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "plain" => EnvelopeMode::Plain,
+            "signed" => EnvelopeMode::Signed,
+            "encrypted" => EnvelopeMode::Encrypted,
+            _ => bail!("invalid {}: {}", NET_ENVELOPE_ENV_VAR, s),
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnvelopeConfig {
+    pub mode: EnvelopeMode,
+    pub required: bool,
+}
+
+impl Default for EnvelopeConfig {
+    fn default() -> Self {
+        EnvelopeConfig {
+            mode: EnvelopeMode::Plain,
+            required: false,
+        }
+    }
+}
+
+impl EnvelopeConfig {
+    pub fn from_env() -> anyhow::Result<EnvelopeConfig> {
+        let var = |name| std::env::var(name).ok();
+        EnvelopeConfig::from_vars(
+            var(NET_ENVELOPE_ENV_VAR),
+            var(NET_ENVELOPE_REQUIRED_ENV_VAR),
+        )
+    }
+
+    fn from_vars(mode: Option<String>, required: Option<String>) -> anyhow::Result<EnvelopeConfig> {
+        Ok(EnvelopeConfig {
+            mode: match mode {
+                Some(mode) => mode.parse()?,
+                None => EnvelopeMode::Plain,
+            },
+            required: match required {
+                Some(required) => required.parse().map_err(|e| {
+                    anyhow::anyhow!("invalid {}: {}", NET_ENVELOPE_REQUIRED_ENV_VAR, e)
+                })?,
+                None => false,
+            },
+        })
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetSessionKey {
+    pub node_id: NodeId,
+}
+
+impl RpcMessage for GetSessionKey {
+    const ID: &'static str = "GetSessionKey";
+    type Item = SessionKeyInfo;
+    type Error = String;
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionKeyInfo {
+    pub public_key: Vec<u8>,
+    /// Signature of `node_id` over the key, see `session_key_digest`.
+    pub signature: Vec<u8>,
+}
+
+/// Signs digest with identity key, returning signature in `[v, r, s]` form.
+pub(crate) type Signer =
+    Rc<dyn Fn(NodeId, Vec<u8>) -> LocalBoxFuture<'static, Result<Vec<u8>, String>>>;
+
+pub(crate) fn identity_signer() -> Signer {
+    Rc::new(|node_id: NodeId, payload: Vec<u8>| {
+        async move {
+            bus::service(identity::BUS_ID)
+                .send(identity::Sign { node_id, payload })
+                .await
+                .map_err(|e| e.to_string())?
+                .map_err(|e| e.to_string())
+        }
+        .boxed_local()
+    })
+}
+
+#[derive(Clone)]
+pub(crate) struct Secure {
+    inner: Rc<SecureInner>,
+}
+
+struct SecureInner {
+    config: EnvelopeConfig,
+    signer: Signer,
+    /// Generated on start, never leaves this process.
+    session_secret: SecretKey,
+    session_public: PublicKey,
+    own_key_signatures: RefCell<HashMap<NodeId, Vec<u8>>>,
+    peer_keys: RefCell<HashMap<NodeId, PublicKey>>,
+    seen: RefCell<SeenEnvelopes>,
+}
+
+/// Protection of replies to single call. Replies to encrypted call are encrypted
+/// with the call key, replies to signed call are signed by callee.
+#[derive(Clone)]
+pub(crate) enum ReplySeal {
+    None,
+    Signed {
+        callee: NodeId,
+        /// Digest of call envelope, which replies are bound to.
+        call_digest: Vec<u8>,
+    },
+    Encrypted(ReplyKey),
+}
+
+/// Digests of envelopes received within clock skew window, ordered by timestamp.
+/// Older envelopes are rejected as expired, so their digests are forgotten.
+#[derive(Default)]
+struct SeenEnvelopes {
+    digests: BTreeSet<(i64, Vec<u8>)>,
+}
+
+impl SeenEnvelopes {
+    /// Returns false, if envelope was already seen.
+    fn insert(&mut self, now: i64, timestamp: i64, digest: Vec<u8>) -> bool {
+        self.digests = self.digests.split_off(&(now - MAX_CLOCK_SKEW_MS, vec![]));
+        self.digests.insert((timestamp, digest))
+    }
+}
+
+impl Secure {
+    pub fn new(config: EnvelopeConfig) -> Self {
+        Secure::with_signer(config, identity_signer())
+    }
+
+    fn with_signer(config: EnvelopeConfig, signer: Signer) -> Self {
+        let (session_secret, session_public) = generate_keypair();
+        Secure {
+            inner: Rc::new(SecureInner {
+                config,
+                signer,
+                session_secret,
+                session_public,
+                own_key_signatures: Default::default(),
+                peer_keys: Default::default(),
+                seen: Default::default(),
+            }),
+        }
+    }
+
+    /// Publishes session key of this node to other nodes.
+    pub fn bind_gsb(&self) {
+        let secure = self.clone();
+        let _ = bus::bind(SESSION_KEY_SERVICE, move |msg: GetSessionKey| {
+            let secure = secure.clone();
+            async move {
+                secure
+                    .session_key_info(msg.node_id)
+                    .await
+                    .map_err(|e| e.to_string())
+            }
+        });
+    }
+
+    /// Whether outgoing messages are signed, so they have to be sent
+    /// as one of own identities.
+    pub fn signs(&self) -> bool {
+        self.inner.config.mode != EnvelopeMode::Plain
+    }
+
+    /// Wraps request to `addr` in envelope. Returned seal opens replies.
+    pub async fn seal_call(
+        &self,
+        caller: NodeId,
+        addr: &str,
+        data: Vec<u8>,
+    ) -> Result<(Vec<u8>, ReplySeal), EnvelopeError> {
+        if !self.signs() || is_session_key_addr(addr) {
+            return Ok((data, ReplySeal::None));
+        }
+
+        let dst = dst_node(addr).map_err(|e| EnvelopeError::Malformed(e.to_string()))?;
+        let (body, reply_key) = match self.inner.config.mode {
+            EnvelopeMode::Encrypted => {
+                let session_key = self.session_key_of(dst).await?;
+                let (ephemeral_secret, ephemeral_public) = generate_keypair();
+                let key = ReplyKey::new(dst, session_key, ephemeral_secret);
+                let body = Body::Encrypted {
+                    ephemeral_key: ephemeral_public.serialize().to_vec(),
+                    data: key.encrypt(&data)?,
+                };
+                (body, Some(key))
+            }
+            _ => (Body::Plain(data), None),
+        };
+        let (data, call_digest) = self.sign(caller, addr, body).await?;
+        let seal = match reply_key {
+            Some(key) => ReplySeal::Encrypted(key),
+            None => ReplySeal::Signed {
+                callee: dst,
+                call_digest,
+            },
+        };
+        Ok((data, seal))
+    }
+
+    pub async fn seal_broadcast(
+        &self,
+        caller: NodeId,
+        topic: &str,
+        data: Vec<u8>,
+    ) -> Result<Vec<u8>, EnvelopeError> {
+        if !self.signs() {
+            return Ok(data);
+        }
+        Ok(self.sign(caller, topic, Body::Plain(data)).await?.0)
+    }
+
+    /// Unwraps request received from hub. Returned seal protects
+    /// replies the same way as request was protected.
+    pub fn open_call(
+        &self,
+        caller: &str,
+        addr: &str,
+        data: Vec<u8>,
+    ) -> Result<(Vec<u8>, ReplySeal), EnvelopeError> {
+        let (data, opened) = self.open(caller, addr, data, is_session_key_addr(addr))?;
+        let seal = match opened {
+            None => ReplySeal::None,
+            Some((_, Some(key))) => ReplySeal::Encrypted(key),
+            Some((call_digest, None)) => ReplySeal::Signed {
+                callee: dst_node(addr).map_err(|e| EnvelopeError::Malformed(e.to_string()))?,
+                call_digest,
+            },
+        };
+        Ok((data, seal))
+    }
+
+    pub fn open_broadcast(
+        &self,
+        caller: &str,
+        topic: &str,
+        data: Vec<u8>,
+    ) -> Result<Vec<u8>, EnvelopeError> {
+        match self.open(caller, topic, data, false)? {
+            (data, None) | (data, Some((_, None))) => Ok(data),
+            (_, Some((_, Some(_)))) => Err(EnvelopeError::Malformed(
+                "broadcast can't be encrypted".to_string(),
+            )),
+        }
+    }
+
+    /// Seals reply chunk the way call was sealed.
+    pub async fn seal_reply(
+        &self,
+        seal: &ReplySeal,
+        chunk: ResponseChunk,
+    ) -> Result<ResponseChunk, Error> {
+        let (callee, call_digest) = match seal {
+            ReplySeal::None => return Ok(chunk),
+            ReplySeal::Encrypted(key) => return map_chunk(chunk, |data| key.encrypt(&data)),
+            ReplySeal::Signed {
+                callee,
+                call_digest,
+            } => (*callee, call_digest),
+        };
+
+        let (data, into_chunk): (Vec<u8>, fn(Vec<u8>) -> ResponseChunk) = match chunk {
+            ResponseChunk::Part(data) => (data, ResponseChunk::Part),
+            ResponseChunk::Full(data) => (data, ResponseChunk::Full),
+        };
+        let (data, _) = self
+            .sign(callee, &reply_target(call_digest), Body::Plain(data))
+            .await
+            .map_err(|e| Error::GsbFailure(e.to_string()))?;
+        Ok(into_chunk(data))
+    }
+
+    /// Opens reply to call sealed with `seal`.
+    pub fn open_reply(
+        &self,
+        seal: &ReplySeal,
+        reply: Result<Vec<u8>, Error>,
+    ) -> Result<Vec<u8>, Error> {
+        match reply {
+            Ok(data) => self
+                .open_reply_data(seal, data)
+                .map_err(|e| Error::GsbFailure(e.to_string())),
+            Err(e) => {
+                self.call_failed(seal);
+                Err(e)
+            }
+        }
+    }
+
+    pub fn open_reply_chunk(
+        &self,
+        seal: &ReplySeal,
+        chunk: Result<ResponseChunk, Error>,
+    ) -> Result<ResponseChunk, Error> {
+        match chunk {
+            Ok(chunk) => map_chunk(chunk, |data| self.open_reply_data(seal, data)),
+            Err(e) => {
+                self.call_failed(seal);
+                Err(e)
+            }
+        }
+    }
+
+    fn open_reply_data(&self, seal: &ReplySeal, data: Vec<u8>) -> Result<Vec<u8>, EnvelopeError> {
+        let (callee, call_digest) = match seal {
+            ReplySeal::None => return Ok(data),
+            ReplySeal::Encrypted(key) => return key.decrypt(&data),
+            ReplySeal::Signed {
+                callee,
+                call_digest,
+            } => (*callee, call_digest),
+        };
+
+        let envelope =
+            Envelope::decode(&data)?.ok_or_else(|| EnvelopeError::Missing(callee.to_string()))?;
+        if envelope.caller != callee {
+            return Err(EnvelopeError::CalleeMismatch {
+                callee,
+                signer: envelope.caller,
+            });
+        }
+        let target = reply_target(call_digest);
+        if envelope.target != target {
+            return Err(EnvelopeError::TargetMismatch {
+                signed: envelope.target,
+                delivered: target,
+            });
+        }
+        self.verify(&envelope)?;
+        match envelope.body {
+            Body::Plain(data) => Ok(data),
+            Body::Encrypted { .. } => Err(EnvelopeError::Malformed(
+                "reply to signed call can't be encrypted".to_string(),
+            )),
+        }
+    }
+
+    /// Peer might have restarted with new session key, so it is fetched again
+    /// after call failure.
+    fn call_failed(&self, seal: &ReplySeal) {
+        if let ReplySeal::Encrypted(key) = seal {
+            self.inner.peer_keys.borrow_mut().remove(&key.node_id);
+        }
+    }
+
+    /// Returns payload and, for messages in envelope, digest of the envelope
+    /// and key encrypting replies, if payload was encrypted.
+    fn open(
+        &self,
+        caller: &str,
+        target: &str,
+        data: Vec<u8>,
+        exempt: bool,
+    ) -> Result<(Vec<u8>, Option<(Vec<u8>, Option<ReplyKey>)>), EnvelopeError> {
+        let envelope = match Envelope::decode(&data)? {
+            Some(envelope) => envelope,
+            None if self.inner.config.required && !exempt => {
+                return Err(EnvelopeError::Missing(caller.to_string()))
+            }
+            None => return Ok((data, None)),
+        };
+
+        if caller.parse::<NodeId>().ok() != Some(envelope.caller) {
+            return Err(EnvelopeError::CallerMismatch {
+                caller: caller.to_string(),
+                signer: envelope.caller,
+            });
+        }
+        if envelope.target != target {
+            return Err(EnvelopeError::TargetMismatch {
+                signed: envelope.target,
+                delivered: target.to_string(),
+            });
+        }
+        let digest = self.verify(&envelope)?;
+
+        match envelope.body {
+            Body::Plain(data) => Ok((data, Some((digest, None)))),
+            Body::Encrypted {
+                ephemeral_key,
+                data,
+            } => {
+                let ephemeral_key = PublicKey::from_slice(&ephemeral_key)
+                    .map_err(|e| EnvelopeError::Decryption(e.to_string()))?;
+                let key = ReplyKey::new(envelope.caller, ephemeral_key, self.inner.session_secret);
+                Ok((key.decrypt(&data)?, Some((digest, Some(key)))))
+            }
+        }
+    }
+
+    /// Checks timestamp and signature of envelope and that it wasn't
+    /// received before. Returns digest of the envelope.
+    fn verify(&self, envelope: &Envelope) -> Result<Vec<u8>, EnvelopeError> {
+        let now = Utc::now().timestamp_millis();
+        if (now - envelope.timestamp).abs() > MAX_CLOCK_SKEW_MS {
+            return Err(EnvelopeError::Expired(envelope.caller));
+        }
+        let digest = envelope.digest()?;
+        verify_signature(&digest, &envelope.signature, envelope.caller)?;
+        if !self
+            .inner
+            .seen
+            .borrow_mut()
+            .insert(now, envelope.timestamp, digest.clone())
+        {
+            return Err(EnvelopeError::Replayed(envelope.caller));
+        }
+        Ok(digest)
+    }
+
+    /// Returns encoded envelope and its digest.
+    async fn sign(
+        &self,
+        caller: NodeId,
+        target: &str,
+        body: Body,
+    ) -> Result<(Vec<u8>, Vec<u8>), EnvelopeError> {
+        let mut envelope = Envelope::new(caller, target, Utc::now().timestamp_millis(), body);
+        let digest = envelope.digest()?;
+        envelope.signature = (self.inner.signer)(caller, digest.clone())
+            .await
+            .map_err(|e| EnvelopeError::Sign(caller, e))?;
+        Ok((envelope.encode()?, digest))
+    }
+
+    async fn session_key_info(&self, node_id: NodeId) -> Result<SessionKeyInfo, EnvelopeError> {
+        let public_key = self.inner.session_public.serialize().to_vec();
+        let cached = self
+            .inner
+            .own_key_signatures
+            .borrow()
+            .get(&node_id)
+            .cloned();
+        let signature = match cached {
+            Some(signature) => signature,
+            None => {
+                let digest = session_key_digest(node_id, &public_key);
+                let signature = (self.inner.signer)(node_id, digest)
+                    .await
+                    .map_err(|e| EnvelopeError::Sign(node_id, e))?;
+                self.inner
+                    .own_key_signatures
+                    .borrow_mut()
+                    .insert(node_id, signature.clone());
+                signature
+            }
+        };
+        Ok(SessionKeyInfo {
+            public_key,
+            signature,
+        })
+    }
+
+    /// Session key of remote node. Request passes through the hub without
+    /// envelope, but the key is signed by the node itself.
+    async fn session_key_of(&self, node_id: NodeId) -> Result<PublicKey, EnvelopeError> {
+        if let Some(key) = self.inner.peer_keys.borrow().get(&node_id) {
+            return Ok(*key);
+        }
+
+        let error = |e: String| EnvelopeError::SessionKey(node_id, e);
+        let info = bus::service(&session_key_service(node_id))
+            .send(GetSessionKey { node_id })
+            .await
+            .map_err(|e| error(e.to_string()))?
+            .map_err(error)?;
+        verify_signature(
+            &session_key_digest(node_id, &info.public_key),
+            &info.signature,
+            node_id,
+        )?;
+        let key = PublicKey::from_slice(&info.public_key).map_err(|e| error(e.to_string()))?;
+
+        self.inner.peer_keys.borrow_mut().insert(node_id, key);
+        Ok(key)
+    }
+}
+
+fn map_chunk(
+    chunk: ResponseChunk,
+    f: impl FnOnce(Vec<u8>) -> Result<Vec<u8>, EnvelopeError>,
+) -> Result<ResponseChunk, Error> {
+    let error = |e: EnvelopeError| Error::GsbFailure(e.to_string());
+    match chunk {
+        ResponseChunk::Part(data) => f(data).map(ResponseChunk::Part).map_err(error),
+        ResponseChunk::Full(data) => f(data).map(ResponseChunk::Full).map_err(error),
+    }
+}
+
+/// Wraps `data` sent to `target` in envelope signed with `secret`,
+/// the way node with that identity does.
+#[cfg(feature = "testing")]
+pub fn signed_envelope(secret: &ethsign::SecretKey, target: &str, data: Vec<u8>) -> Vec<u8> {
+    let caller = NodeId::from(&secret.public().address()[..]);
+    let mut envelope = Envelope::new(
+        caller,
+        target,
+        Utc::now().timestamp_millis(),
+        Body::Plain(data),
+    );
+    envelope.signature = sign(secret, &envelope.digest().unwrap());
+    envelope.encode().unwrap()
+}
+
+/// Target of replies to call with envelope digest `call_digest`.
+fn reply_target(call_digest: &[u8]) -> String {
+    format!("reply/{}", hex::encode(call_digest))
+}
+
+fn session_key_service(node_id: NodeId) -> String {
+    format!("{}/net", net_service(node_id))
+}
+
+fn is_session_key_addr(addr: &str) -> bool {
+    match dst_node(addr) {
+        Ok(node_id) => addr == format!("{}/{}", session_key_service(node_id), GetSessionKey::ID),
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::envelope::{sign, tests::generate_identity};
+    use super::*;
+
+    fn secure(mode: EnvelopeMode, required: bool, secret: ethsign::SecretKey) -> Secure {
+        let secret = Rc::new(secret);
+        let signer: Signer = Rc::new(move |_: NodeId, digest: Vec<u8>| {
+            future::ok(sign(&secret, &digest)).boxed_local()
+        });
+        Secure::with_signer(EnvelopeConfig { mode, required }, signer)
+    }
+
+    fn addr(node_id: NodeId) -> String {
+        format!("{}/test/Echo", net_service(node_id))
+    }
+
+    #[test]
+    fn config_from_vars() {
+        let config = |mode: Option<&str>, required: Option<&str>| {
+            EnvelopeConfig::from_vars(mode.map(str::to_string), required.map(str::to_string))
+        };
+        assert_eq!(config(None, None).unwrap(), EnvelopeConfig::default());
+        assert_eq!(
+            config(Some("encrypted"), Some("true")).unwrap(),
+            EnvelopeConfig {
+                mode: EnvelopeMode::Encrypted,
+                required: true
+            }
+        );
+        assert!(config(Some("secret"), None).is_err());
+        assert!(config(None, Some("yes")).is_err());
+    }
+
+    #[test]
+    fn seen_envelopes_are_kept_within_clock_skew() {
+        let mut seen = SeenEnvelopes::default();
+        assert!(seen.insert(0, 0, vec![1]));
+        assert!(seen.insert(0, 10, vec![2]));
+        assert!(!seen.insert(MAX_CLOCK_SKEW_MS, 0, vec![1]));
+        assert!(!seen.insert(MAX_CLOCK_SKEW_MS, 10, vec![2]));
+
+        // Envelope that old is rejected as expired, so it is forgotten.
+        assert!(seen.insert(MAX_CLOCK_SKEW_MS + 1, 20, vec![3]));
+        assert_eq!(seen.digests.len(), 2);
+        assert!(seen.insert(MAX_CLOCK_SKEW_MS + 1, 0, vec![1]));
+    }
+
+    #[actix_rt::test]
+    async fn signed_call_is_verified() {
+        let (caller_id, caller_secret) = generate_identity();
+        let (callee_id, callee_secret) = generate_identity();
+        let caller = secure(EnvelopeMode::Signed, false, caller_secret);
+        let callee = secure(EnvelopeMode::Plain, true, callee_secret);
+        let addr = addr(callee_id);
+
+        let (data, caller_seal) = caller
+            .seal_call(caller_id, &addr, vec![1, 2])
+            .await
+            .unwrap();
+        let (opened, callee_seal) = callee
+            .open_call(&caller_id.to_string(), &addr, data.clone())
+            .unwrap();
+        assert_eq!(opened, vec![1, 2]);
+
+        // Reply is signed by callee and bound to the call.
+        let reply = callee
+            .seal_reply(&callee_seal, ResponseChunk::Full(vec![3]))
+            .await
+            .unwrap();
+        let reply_data = match reply {
+            ResponseChunk::Full(data) => data,
+            ResponseChunk::Part(_) => panic!("unexpected partial reply"),
+        };
+        assert_eq!(
+            caller
+                .open_reply(&caller_seal, Ok(reply_data.clone()))
+                .unwrap(),
+            vec![3]
+        );
+        assert!(caller.open_reply(&caller_seal, Ok(reply_data)).is_err());
+        assert!(caller.open_reply(&caller_seal, Ok(vec![3])).is_err());
+
+        // Hub can't replay, redirect or attribute message to someone else.
+        let caller_str = caller_id.to_string();
+        assert!(callee.open_call(&caller_str, &addr, data.clone()).is_err());
+        let (data, _) = caller
+            .seal_call(caller_id, &addr, vec![1, 2])
+            .await
+            .unwrap();
+        let other_addr = format!("{}/test/Other", net_service(callee_id));
+        assert!(callee
+            .open_call(&caller_str, &other_addr, data.clone())
+            .is_err());
+        let (other_id, _) = generate_identity();
+        assert!(callee
+            .open_call(&other_id.to_string(), &addr, data)
+            .is_err());
+
+        // Plain messages are rejected, when envelope is required.
+        assert!(callee.open_call(&caller_str, &addr, vec![1, 2]).is_err());
+        let session_key_addr = format!("{}/GetSessionKey", session_key_service(callee_id));
+        assert!(callee
+            .open_call(&caller_str, &session_key_addr, vec![1, 2])
+            .is_ok());
+    }
+
+    #[actix_rt::test]
+    async fn reply_signed_by_other_node_is_rejected() {
+        let (caller_id, caller_secret) = generate_identity();
+        let (callee_id, _) = generate_identity();
+        let (other_id, other_secret) = generate_identity();
+        let caller = secure(EnvelopeMode::Signed, false, caller_secret);
+        let other = secure(EnvelopeMode::Signed, false, other_secret);
+
+        let (_, caller_seal) = caller
+            .seal_call(caller_id, &addr(callee_id), vec![1, 2])
+            .await
+            .unwrap();
+        let call_digest = match &caller_seal {
+            ReplySeal::Signed { call_digest, .. } => call_digest.clone(),
+            _ => panic!("signed call expected"),
+        };
+        let forged_seal = ReplySeal::Signed {
+            callee: other_id,
+            call_digest,
+        };
+        let reply = other
+            .seal_reply(&forged_seal, ResponseChunk::Full(vec![3]))
+            .await
+            .unwrap();
+        assert!(caller.open_reply_chunk(&caller_seal, Ok(reply)).is_err());
+    }
+
+    #[actix_rt::test]
+    async fn encrypted_call_and_reply() {
+        let (caller_id, caller_secret) = generate_identity();
+        let (callee_id, callee_secret) = generate_identity();
+        let caller = secure(EnvelopeMode::Encrypted, false, caller_secret);
+        let callee = secure(EnvelopeMode::Plain, false, callee_secret);
+        let addr = addr(callee_id);
+
+        // Session key is verified and cached, as if fetched through the hub.
+        let info = callee.session_key_info(callee_id).await.unwrap();
+        verify_signature(
+            &session_key_digest(callee_id, &info.public_key),
+            &info.signature,
+            callee_id,
+        )
+        .unwrap();
+        caller
+            .inner
+            .peer_keys
+            .borrow_mut()
+            .insert(callee_id, PublicKey::from_slice(&info.public_key).unwrap());
+
+        let (data, caller_key) = caller
+            .seal_call(caller_id, &addr, b"secret".to_vec())
+            .await
+            .unwrap();
+        assert!(!data.windows(6).any(|w| w == b"secret"));
+        let (opened, callee_key) = callee
+            .open_call(&caller_id.to_string(), &addr, data)
+            .unwrap();
+        assert_eq!(opened, b"secret".to_vec());
+
+        let reply = callee
+            .seal_reply(&callee_key, ResponseChunk::Full(b"reply".to_vec()))
+            .await
+            .unwrap();
+        match caller.open_reply_chunk(&caller_key, Ok(reply)).unwrap() {
+            ResponseChunk::Full(data) => assert_eq!(data, b"reply".to_vec()),
+            ResponseChunk::Part(_) => panic!("unexpected partial reply"),
+        }
+
+        let failure = Err(Error::GsbBadRequest("unknown session key".to_string()));
+        assert!(caller.open_reply(&caller_key, failure).is_err());
+        assert!(caller.inner.peer_keys.borrow().is_empty());
+    }
+
+    #[actix_rt::test]
+    async fn broadcast_is_signed_only() {
+        let (caller_id, caller_secret) = generate_identity();
+        let (_, receiver_secret) = generate_identity();
+        let caller = secure(EnvelopeMode::Encrypted, false, caller_secret);
+        let receiver = secure(EnvelopeMode::Plain, true, receiver_secret);
+
+        let data = caller
+            .seal_broadcast(caller_id, "market", vec![3])
+            .await
+            .unwrap();
+        let caller_str = caller_id.to_string();
+        assert!(receiver
+            .open_broadcast(&caller_str, "other", data.clone())
+            .is_err());
+        assert_eq!(
+            receiver
+                .open_broadcast(&caller_str, "market", data)
+                .unwrap(),
+            vec![3]
+        );
+        assert!(receiver
+            .open_broadcast(&caller_str, "market", vec![3])
+            .is_err());
+    }
+}
@@ -0,0 +1,230 @@
+//! Signed envelope wrapping payload of remote call or broadcast.
+use secp256k1::{PublicKey, Secp256k1, SecretKey};
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Sha3_256};
+
+use ya_agreement_utils::signature::verify_raw_signature;
+use ya_client_model::activity::encrypted::EncryptionCtx;
+use ya_core_model::NodeId;
+use ya_service_bus::serialization;
+
+/// Prefix distinguishing envelopes from plain payloads.
+const ENVELOPE_MAGIC: &[u8] = b"YAENV1";
+const SESSION_KEY_DOMAIN: &[u8] = b"yagna-net-session-key";
+
+#[derive(thiserror::Error, Debug)]
+pub enum EnvelopeError {
+    #[error("Message from [{0}] is not signed.")]
+    Missing(String),
+    #[error("Malformed envelope: {0}.")]
+    Malformed(String),
+    #[error("Envelope is signed by [{signer}], but hub reports caller [{caller}].")]
+    CallerMismatch { caller: String, signer: NodeId },
+    #[error("Reply from [{callee}] is signed by [{signer}].")]
+    CalleeMismatch { callee: NodeId, signer: NodeId },
+    #[error("Envelope is addressed to {signed}, but was delivered to {delivered}.")]
+    TargetMismatch { signed: String, delivered: String },
+    #[error("Envelope from [{0}] is expired or comes from the future.")]
+    Expired(NodeId),
+    #[error("Envelope from [{0}] was already received.")]
+    Replayed(NodeId),
+    #[error("Invalid signature of [{0}]: {1}.")]
+    InvalidSignature(NodeId, String),
+    #[error("Failed to sign as [{0}]: {1}.")]
+    Sign(NodeId, String),
+    #[error("Failed to get session key of [{0}]: {1}.")]
+    SessionKey(NodeId, String),
+    #[error("Encryption failed: {0}.")]
+    Encryption(String),
+    #[error("Decryption failed: {0}.")]
+    Decryption(String),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub enum Body {
+    Plain(Vec<u8>),
+    /// Payload encrypted with key shared between sender's ephemeral key
+    /// and recipient's session key.
+    Encrypted {
+        ephemeral_key: Vec<u8>,
+        data: Vec<u8>,
+    },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Envelope {
+    pub caller: NodeId,
+    /// Call address or broadcast topic, so envelope can't be redirected.
+    pub target: String,
+    /// Milliseconds since epoch, limits time window for replays.
+    pub timestamp: i64,
+    pub body: Body,
+    pub signature: Vec<u8>,
+}
+
+impl Envelope {
+    pub fn new(caller: NodeId, target: &str, timestamp: i64, body: Body) -> Self {
+        Envelope {
+            caller,
+            target: target.to_string(),
+            timestamp,
+            body,
+            signature: vec![],
+        }
+    }
+
+    /// Hash of all fields except signature.
+    pub fn digest(&self) -> Result<Vec<u8>, EnvelopeError> {
+        let fields = (&self.caller, &self.target, self.timestamp, &self.body);
+        let bytes =
+            serialization::to_vec(&fields).map_err(|e| EnvelopeError::Malformed(e.to_string()))?;
+        Ok(Sha3_256::digest(&bytes).to_vec())
+    }
+
+    pub fn verify_signature(&self) -> Result<(), EnvelopeError> {
+        verify_signature(&self.digest()?, &self.signature, self.caller)
+    }
+
+    pub fn encode(&self) -> Result<Vec<u8>, EnvelopeError> {
+        let mut bytes = ENVELOPE_MAGIC.to_vec();
+        bytes.extend(
+            serialization::to_vec(self).map_err(|e| EnvelopeError::Malformed(e.to_string()))?,
+        );
+        Ok(bytes)
+    }
+
+    /// Returns None for plain payload, which is not wrapped in envelope.
+    pub fn decode(data: &[u8]) -> Result<Option<Envelope>, EnvelopeError> {
+        if !data.starts_with(ENVELOPE_MAGIC) {
+            return Ok(None);
+        }
+        serialization::from_slice(&data[ENVELOPE_MAGIC.len()..])
+            .map(Some)
+            .map_err(|e| EnvelopeError::Malformed(e.to_string()))
+    }
+}
+
+/// Hash of session key, which identity signs to prove the key is its own.
+pub fn session_key_digest(node_id: NodeId, public_key: &[u8]) -> Vec<u8> {
+    let mut hasher = Sha3_256::new();
+    hasher.input(SESSION_KEY_DOMAIN);
+    hasher.input(&node_id.into_array());
+    hasher.input(public_key);
+    hasher.result().to_vec()
+}
+
+/// Checks, if `digest` was signed by `node_id` the way identity service signs.
+pub fn verify_signature(
+    digest: &[u8],
+    signature: &[u8],
+    node_id: NodeId,
+) -> Result<(), EnvelopeError> {
+    verify_raw_signature(digest, signature, &node_id)
+        .map_err(|e| EnvelopeError::InvalidSignature(node_id, e))
+}
+
+/// Signs digest the same way as identity service does.
+#[cfg(any(test, feature = "testing"))]
+pub fn sign(secret: &ethsign::SecretKey, digest: &[u8]) -> Vec<u8> {
+    let signature = secret.sign(digest).unwrap();
+    let mut bytes = Vec::with_capacity(65);
+    bytes.push(signature.v);
+    bytes.extend_from_slice(&signature.r[..]);
+    bytes.extend_from_slice(&signature.s[..]);
+    bytes
+}
+
+pub fn generate_keypair() -> (SecretKey, PublicKey) {
+    Secp256k1::new().generate_keypair(&mut rand::thread_rng())
+}
+
+/// Key shared by both sides of encrypted call. Caller uses it to encrypt
+/// request and decrypt reply, callee the other way round.
+#[derive(Clone)]
+pub struct ReplyKey {
+    pub node_id: NodeId,
+    their_key: PublicKey,
+    own_key: SecretKey,
+}
+
+impl ReplyKey {
+    pub fn new(node_id: NodeId, their_key: PublicKey, own_key: SecretKey) -> Self {
+        ReplyKey {
+            node_id,
+            their_key,
+            own_key,
+        }
+    }
+
+    pub fn encrypt(&self, data: &[u8]) -> Result<Vec<u8>, EnvelopeError> {
+        EncryptionCtx::new(&self.their_key, &self.own_key)
+            .encrypt(&data)
+            .map_err(|e| EnvelopeError::Encryption(format!("{:?}", e)))
+    }
+
+    pub fn decrypt(&self, data: &[u8]) -> Result<Vec<u8>, EnvelopeError> {
+        EncryptionCtx::new(&self.their_key, &self.own_key)
+            .decrypt(data)
+            .map_err(|e| EnvelopeError::Decryption(format!("{:?}", e)))
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use rand::{thread_rng, Rng};
+
+    pub fn generate_identity() -> (NodeId, ethsign::SecretKey) {
+        let secret = loop {
+            if let Ok(secret) = ethsign::SecretKey::from_raw(&thread_rng().gen::<[u8; 32]>()) {
+                break secret;
+            }
+        };
+        (NodeId::from(&secret.public().address()[..]), secret)
+    }
+
+    fn signed_envelope(target: &str) -> (Envelope, ethsign::SecretKey) {
+        let (node_id, secret) = generate_identity();
+        let mut envelope = Envelope::new(node_id, target, 1, Body::Plain(vec![1, 2, 3]));
+        envelope.signature = sign(&secret, &envelope.digest().unwrap());
+        (envelope, secret)
+    }
+
+    #[test]
+    fn envelope_roundtrip() {
+        let (envelope, _) = signed_envelope("/net/0x01/test");
+        let decoded = Envelope::decode(&envelope.encode().unwrap())
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(decoded.caller, envelope.caller);
+        assert_eq!(decoded.body, Body::Plain(vec![1, 2, 3]));
+        decoded.verify_signature().unwrap();
+        assert!(Envelope::decode(&[0x92, 1, 2]).unwrap().is_none());
+        assert!(Envelope::decode(b"YAENV1-garbage").is_err());
+    }
+
+    #[test]
+    fn tampered_envelope_is_rejected() {
+        let (mut envelope, _) = signed_envelope("/net/0x01/test");
+        envelope.target = "/net/0x01/other".to_string();
+        assert!(envelope.verify_signature().is_err());
+
+        let (mut envelope, _) = signed_envelope("/net/0x01/test");
+        envelope.caller = generate_identity().0;
+        assert!(envelope.verify_signature().is_err());
+    }
+
+    #[test]
+    fn reply_key_is_shared() {
+        let (caller_secret, caller_public) = generate_keypair();
+        let (callee_secret, callee_public) = generate_keypair();
+        let (node_id, _) = generate_identity();
+        let caller = ReplyKey::new(node_id, callee_public, caller_secret);
+        let callee = ReplyKey::new(node_id, caller_public, callee_secret);
+
+        let encrypted = caller.encrypt(&[4, 5, 6]).unwrap();
+        assert_ne!(encrypted, vec![4, 5, 6]);
+        assert_eq!(callee.decrypt(&encrypted).unwrap(), vec![4, 5, 6]);
+    }
+}
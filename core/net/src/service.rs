@@ -2,53 +2,205 @@ use actix_rt::Arbiter;
 use anyhow::anyhow;
 use futures::channel::oneshot;
 use futures::prelude::*;
+use serde::Deserialize;
 use std::net::{SocketAddr, ToSocketAddrs};
+use std::path::Path;
 use std::rc::Rc;
 
 use ya_core_model::identity::{self, IdentityInfo};
 use ya_core_model::net;
 use ya_core_model::net::local::{self as local_net, SendBroadcastMessage, SendBroadcastStub};
 use ya_core_model::NodeId;
+use ya_service_api::CliCtx;
+use ya_service_api_interfaces::{Provider, Service};
 use ya_service_bus::connection::ClientInfo;
 use ya_service_bus::{
-    connection, serialization, typed as bus, untyped as local_bus, Error, RpcEndpoint, RpcMessage,
+    connection, serialization, typed as bus, untyped as local_bus, Error, ResponseChunk,
+    RpcEndpoint, RpcMessage,
 };
 use ya_utils_networking::resolver;
 
 use crate::api::{net_service, parse_from_addr};
-use crate::handler::{auto_rebind, CentralBusHandler};
+use crate::bcast::{subnet_from_env, BCastService};
+use crate::handler::{auto_rebind, resubscribe, CentralBusHandler};
+use crate::health::Health;
+use crate::p2p::{bind_p2p, P2pConfig};
+use crate::secure::{EnvelopeConfig, Secure};
 
+/// Comma separated hub addresses, in order of preference.
 pub const CENTRAL_ADDR_ENV_VAR: &str = "CENTRAL_NET_HOST";
 
-async fn central_net_addr() -> std::io::Result<SocketAddr> {
-    Ok(match std::env::var(CENTRAL_ADDR_ENV_VAR) {
-        Ok(v) => v,
-        Err(_) => resolver::resolve_yagna_srv_record("_net._tcp").await?,
+/// Name of config file in yagna data directory. Net reads `[net]` section of it.
+pub const CONFIG_FILE_NAME: &str = "yagna.toml";
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct ConfigFile {
+    net: NetConfig,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct NetConfig {
+    /// Hub addresses, in order of preference.
+    hubs: Vec<String>,
+}
+
+/// Hubs listed in `[net]` section of config file in `data_dir`.
+/// Missing file means no hubs are configured.
+fn config_hubs(data_dir: &Path) -> anyhow::Result<Vec<String>> {
+    let path = data_dir.join(CONFIG_FILE_NAME);
+    match std::fs::read_to_string(&path) {
+        Ok(content) => parse_config_hubs(&content)
+            .map_err(|e| anyhow!("Failed to parse net config {}: {}", path.display(), e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(anyhow!(
+            "Failed to read net config {}: {}",
+            path.display(),
+            e
+        )),
+    }
+}
+
+fn parse_config_hubs(content: &str) -> Result<Vec<String>, toml::de::Error> {
+    Ok(toml::from_str::<ConfigFile>(content)?.net.hubs)
+}
+
+/// Hubs from `CENTRAL_NET_HOST` take precedence over hubs from config file.
+/// `None` means, that hubs should be resolved from SRV records.
+fn hub_hosts(env: Option<String>, config_hubs: &[String]) -> Option<Vec<String>> {
+    match env {
+        Some(env) => Some(parse_hosts(&env)),
+        None if !config_hubs.is_empty() => Some(config_hubs.to_vec()),
+        None => None,
+    }
+}
+
+/// Hubs from `CENTRAL_NET_HOST`, config file or, if neither of them lists hubs,
+/// from all `_net._tcp` SRV records.
+async fn central_net_addrs(config_hubs: &[String]) -> std::io::Result<Vec<SocketAddr>> {
+    let env = std::env::var(CENTRAL_ADDR_ENV_VAR).ok();
+    let hosts = match hub_hosts(env, config_hubs) {
+        Some(hosts) => hosts,
+        None => resolver::resolve_yagna_srv_records("_net._tcp").await?,
+    };
+
+    let mut addrs = Vec::new();
+    for host in hosts {
+        match host.to_socket_addrs() {
+            Ok(resolved) => {
+                for addr in resolved {
+                    if !addrs.contains(&addr) {
+                        addrs.push(addr);
+                    }
+                }
+            }
+            Err(e) => log::warn!("Failed to resolve hub address {}: {}", host, e),
+        }
+    }
+    if addrs.is_empty() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "central net hub addr needed",
+        ));
     }
-    .to_socket_addrs()?
-    .next()
-    .expect("central net hub addr needed"))
+    Ok(addrs)
+}
+
+fn parse_hosts(hosts: &str) -> Vec<String> {
+    hosts
+        .split(',')
+        .map(str::trim)
+        .filter(|host| !host.is_empty())
+        .map(str::to_string)
+        .collect()
 }
 
-/// Initialize net module on a hub.
+/// Initialize net module on a hub. Messages are sent without envelope.
 pub async fn bind_remote(
     client_info: ClientInfo,
     default_node_id: NodeId,
     nodes: Vec<NodeId>,
 ) -> std::io::Result<oneshot::Receiver<()>> {
-    let hub_addr = central_net_addr().await?;
+    let hubs = central_net_addrs(&[]).await?;
+    bind_remote_any(
+        &hubs,
+        client_info,
+        default_node_id,
+        nodes,
+        subnet_from_env(),
+        Health::default(),
+        Secure::new(EnvelopeConfig::default()),
+    )
+    .await
+}
+
+/// Initialize net module on the first of `hubs`, which accepts connection.
+async fn bind_remote_any(
+    hubs: &[SocketAddr],
+    client_info: ClientInfo,
+    default_node_id: NodeId,
+    nodes: Vec<NodeId>,
+    subnet: Option<String>,
+    health: Health,
+    secure: Secure,
+) -> std::io::Result<oneshot::Receiver<()>> {
+    let mut last_error = None;
+    for hub_addr in hubs {
+        let bind = bind_remote_at(
+            *hub_addr,
+            client_info.clone(),
+            default_node_id,
+            nodes.clone(),
+            subnet.clone(),
+            health.clone(),
+            secure.clone(),
+        );
+        match bind.await {
+            Ok(done_rx) => return Ok(done_rx),
+            Err(e) => {
+                log::warn!("Failed to bind on hub {}: {}", hub_addr, e);
+                last_error = Some(e);
+            }
+        }
+    }
+    Err(last_error
+        .unwrap_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, "no hub to bind on")))
+}
+
+/// Initialize net module on a hub listening at `hub_addr`.
+async fn bind_remote_at(
+    hub_addr: SocketAddr,
+    client_info: ClientInfo,
+    default_node_id: NodeId,
+    nodes: Vec<NodeId>,
+    subnet: Option<String>,
+    health: Health,
+    secure: Secure,
+) -> std::io::Result<oneshot::Receiver<()>> {
     let conn = connection::tcp(hub_addr).await?;
-    let bcast = super::bcast::BCastService::default();
+    let bcast = BCastService::new(subnet, nodes.clone());
     let bcast_service_id = <SendBroadcastMessage<()> as RpcMessage>::ID;
 
     // connect to hub with forwarding handler
     let own_net_nodes: Vec<_> = nodes.iter().map(|id| net_service(id)).collect();
 
+    let call_health = health.clone();
+    let call_secure = secure.clone();
     let forward_call = move |request_id: String, caller: String, addr: String, data: Vec<u8>| {
+        call_health.message_received(data.len());
         let prefix = own_net_nodes
             .iter()
             .find(|&own_net_node_id| addr.starts_with(own_net_node_id));
         if let Some(prefix) = prefix {
+            let (data, reply_seal) = match call_secure.open_call(&caller, &addr, data) {
+                Ok(opened) => opened,
+                Err(e) => {
+                    log::debug!("Rejected call from [{}] to {}: {}", caller, addr, e);
+                    return stream::once(future::err(Error::GsbBadRequest(e.to_string())))
+                        .left_stream();
+                }
+            };
             // replaces  /net/<dest_node_id>/test/1 --> /public/test/1
             let local_addr: String = addr.replacen(prefix, net::PUBLIC_PREFIX, 1);
             log::trace!(
@@ -59,7 +211,17 @@ pub async fn bind_remote(
                 request_id
             );
             // actual forwarding to my local bus
-            local_bus::call_stream(&local_addr, &caller, &data).right_stream()
+            let health = call_health.clone();
+            let secure = call_secure.clone();
+            local_bus::call_stream(&local_addr, &caller, &data)
+                .then(move |chunk| {
+                    let secure = secure.clone();
+                    let reply_seal = reply_seal.clone();
+                    async move { secure.seal_reply(&reply_seal, chunk?).await }
+                })
+                .inspect(move |chunk| count_reply(chunk, |len| health.reply_sent(len)))
+                .boxed_local()
+                .right_stream()
         } else {
             return stream::once(future::err(Error::GsbBadRequest(format!(
                 "wrong routing: {}; I'll accept only addrs starting with: {:?}",
@@ -71,9 +233,19 @@ pub async fn bind_remote(
 
     let broadcast_handler = {
         let bcast = bcast.clone();
+        let health = health.clone();
+        let secure = secure.clone();
 
         move |caller: String, topic: String, msg: Vec<u8>| {
-            let endpoints = bcast.resolve(&topic);
+            health.message_received(msg.len());
+            let msg = match secure.open_broadcast(&caller, &topic, msg) {
+                Ok(msg) => msg,
+                Err(e) => {
+                    log::debug!("Dropped broadcast to topic {}: {}", topic, e);
+                    return;
+                }
+            };
+            let endpoints = bcast.resolve_received(&topic, &msg);
             let msg: Rc<[u8]> = msg.into();
             Arbiter::spawn(async move {
                 log::trace!("Received broadcast to topic {} from [{}].", &topic, &caller);
@@ -97,6 +269,7 @@ pub async fn bind_remote(
             .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, format!("{}", e)))?;
         log::info!("network service bound at: {} under: {}", hub_addr, addr);
     }
+    health.connected(hub_addr);
 
     // bind /net on my local bus and forward all calls to remote bus under /net
     {
@@ -112,24 +285,62 @@ pub async fn bind_remote(
         // `caller` is usually "local", so we replace it with our default node id
         let central_bus_rpc = central_bus.clone();
         let default_caller_rpc = default_node_id.to_string();
+        let health_rpc = health.clone();
+        let secure_rpc = secure.clone();
         let rpc = move |_caller: &str, addr: &str, msg: &[u8]| {
             let caller = default_caller_rpc.clone();
             log_message("rpc", &caller, addr);
+            let central_bus = central_bus_rpc.clone();
+            let health = health_rpc.clone();
+            let secure = secure_rpc.clone();
             let addr = addr.to_string();
-            central_bus_rpc
-                .call(caller, addr.clone(), Vec::from(msg))
-                .map_err(|e| Error::RemoteError(addr, e.to_string()))
+            let msg = Vec::from(msg);
+            async move {
+                let (msg, reply_seal) = secure
+                    .seal_call(default_node_id, &addr, msg)
+                    .await
+                    .map_err(|e| Error::GsbFailure(e.to_string()))?;
+                health.message_sent(msg.len());
+                let reply = central_bus
+                    .call(caller, addr.clone(), msg)
+                    .await
+                    .map_err(|e| Error::RemoteError(addr, e.to_string()))
+                    .map(|reply| {
+                        health.reply_received(reply.len());
+                        reply
+                    });
+                secure.open_reply(&reply_seal, reply)
+            }
+            .boxed_local()
         };
 
         let central_bus_stream = central_bus.clone();
         let default_caller_stream = default_node_id.to_string();
+        let health_stream = health.clone();
+        let secure_stream = secure.clone();
         let stream = move |_caller: &str, addr: &str, msg: &[u8]| {
             let caller = default_caller_stream.clone();
             log_message("stream", &caller, addr);
+            let central_bus = central_bus_stream.clone();
+            let health = health_stream.clone();
+            let secure = secure_stream.clone();
             let addr = addr.to_string();
-            central_bus_stream
-                .call_streaming(caller, addr.clone(), Vec::from(msg))
-                .map_err(move |e| Error::RemoteError(addr.clone(), e.to_string()))
+            let msg = Vec::from(msg);
+            async move {
+                let (msg, reply_seal) = secure
+                    .seal_call(default_node_id, &addr, msg)
+                    .await
+                    .map_err(|e| Error::GsbFailure(e.to_string()))?;
+                health.message_sent(msg.len());
+                let replies = central_bus
+                    .call_streaming(caller, addr.clone(), msg)
+                    .map_err(move |e| Error::RemoteError(addr.clone(), e.to_string()))
+                    .inspect(move |chunk| count_reply(chunk, |len| health.reply_received(len)))
+                    .map(move |chunk| secure.open_reply_chunk(&reply_seal, chunk));
+                Ok::<_, Error>(replies)
+            }
+            .try_flatten_stream()
+            .boxed_local()
         };
 
         local_bus::subscribe(net::BUS_ID, rpc, stream);
@@ -139,6 +350,8 @@ pub async fn bind_remote(
     {
         let nodes_rpc = nodes.clone();
         let central_bus_rpc = central_bus.clone();
+        let health_rpc = health.clone();
+        let secure_rpc = secure.clone();
         let rpc = move |_caller: &str, addr: &str, msg: &[u8]| {
             let (from_node, to_addr) = match parse_from_addr(addr) {
                 Ok(v) => v,
@@ -153,14 +366,34 @@ pub async fn bind_remote(
                 .left_future();
             }
 
-            central_bus_rpc
-                .call(from_node.to_string(), to_addr.clone(), Vec::from(msg))
-                .map_err(|e| Error::RemoteError(to_addr, e.to_string()))
-                .right_future()
+            let central_bus = central_bus_rpc.clone();
+            let health = health_rpc.clone();
+            let secure = secure_rpc.clone();
+            let msg = Vec::from(msg);
+            async move {
+                let (msg, reply_seal) = secure
+                    .seal_call(from_node, &to_addr, msg)
+                    .await
+                    .map_err(|e| Error::GsbFailure(e.to_string()))?;
+                health.message_sent(msg.len());
+                let reply = central_bus
+                    .call(from_node.to_string(), to_addr.clone(), msg)
+                    .await
+                    .map_err(|e| Error::RemoteError(to_addr, e.to_string()))
+                    .map(|reply| {
+                        health.reply_received(reply.len());
+                        reply
+                    });
+                secure.open_reply(&reply_seal, reply)
+            }
+            .boxed_local()
+            .right_future()
         };
 
         let nodes_stream = nodes.clone();
         let central_bus_stream = central_bus.clone();
+        let health_stream = health.clone();
+        let secure_stream = secure.clone();
         let stream = move |_caller: &str, addr: &str, msg: &[u8]| {
             let (from_node, to_addr) = match parse_from_addr(addr) {
                 Ok(v) => v,
@@ -182,9 +415,25 @@ pub async fn bind_remote(
                     .left_stream();
             }
 
-            central_bus_stream
-                .call_streaming(from_node.to_string(), to_addr, Vec::from(msg))
-                .right_stream()
+            let central_bus = central_bus_stream.clone();
+            let health = health_stream.clone();
+            let secure = secure_stream.clone();
+            let msg = Vec::from(msg);
+            async move {
+                let (msg, reply_seal) = secure
+                    .seal_call(from_node, &to_addr, msg)
+                    .await
+                    .map_err(|e| Error::GsbFailure(e.to_string()))?;
+                health.message_sent(msg.len());
+                let replies = central_bus
+                    .call_streaming(from_node.to_string(), to_addr, msg)
+                    .inspect(move |chunk| count_reply(chunk, |len| health.reply_received(len)))
+                    .map(move |chunk| secure.open_reply_chunk(&reply_seal, chunk));
+                Ok::<_, Error>(replies)
+            }
+            .try_flatten_stream()
+            .boxed_local()
+            .right_stream()
         };
 
         local_bus::subscribe("/from", rpc, stream);
@@ -196,25 +445,56 @@ pub async fn bind_remote(
         let central_bus = central_bus.clone();
 
         let _ = bus::bind(local_net::BUS_ID, move |subscribe: local_net::Subscribe| {
-            let topic = subscribe.topic().to_owned();
+            let topics = bcast.subscribed_topics(subscribe.topic());
             let (is_new, id) = bcast.add(subscribe);
             let central_bus = central_bus.clone();
             async move {
-                log::debug!("Subscribe topic {} on central bus.", topic);
                 if is_new {
-                    if let Err(e) = central_bus.subscribe(topic.clone()).await {
-                        log::error!("fail to subscribe to: {}, {}", topic, e);
+                    for topic in topics {
+                        log::debug!("Subscribe topic {} on central bus.", topic);
+                        if let Err(e) = central_bus.subscribe(topic.clone()).await {
+                            log::error!("fail to subscribe to: {}, {}", topic, e);
+                        }
                     }
-                    log::debug!("Created new topic: {}", topic);
                 }
                 Ok(id)
             }
         });
     }
 
+    // Unsubscribe broadcast. Hub stops sending broadcasts of the topic,
+    // when its last endpoint is removed.
+    {
+        let bcast = bcast.clone();
+        let central_bus = central_bus.clone();
+        let _ = bus::bind(
+            local_net::BUS_ID,
+            move |unsubscribe: local_net::Unsubscribe| {
+                let removed = bcast.remove(&unsubscribe);
+                let topics = match removed && bcast.resolve(unsubscribe.topic()).is_empty() {
+                    true => bcast.subscribed_topics(unsubscribe.topic()),
+                    false => Vec::new(),
+                };
+                let central_bus = central_bus.clone();
+                async move {
+                    for topic in topics {
+                        log::debug!("Unsubscribe topic {} on central bus.", topic);
+                        if let Err(e) = central_bus.unsubscribe(topic.clone()).await {
+                            log::error!("fail to unsubscribe from: {}, {}", topic, e);
+                        }
+                    }
+                    Ok(removed)
+                }
+            },
+        );
+    }
+
     // Send broadcast to remote
     {
+        let bcast = bcast.clone();
         let central_bus = central_bus.clone();
+        let health = health.clone();
+        let secure = secure.clone();
         let addr = format!("{}/{}", local_net::BUS_ID, bcast_service_id);
         let resp: Rc<[u8]> = serialization::to_vec(&Ok::<(), ()>(())).unwrap().into();
         let _ = local_bus::subscribe(
@@ -231,21 +511,42 @@ pub async fn bind_remote(
                     }
                 };
 
+                let topics = bcast.network_topics(&stub.topic, &stub.scope);
                 log::trace!(
-                    "Broadcast msg related to topic {} from [{}].",
-                    stub.topic,
+                    "Broadcast msg related to topics {:?} from [{}].",
+                    topics,
                     &caller
                 );
 
-                let fut = central_bus.broadcast(caller.to_owned(), stub.topic, msg.into());
+                // Signed broadcast has to be sent as one of own identities.
+                let sender = match caller.parse::<NodeId>() {
+                    Ok(node_id) if nodes.contains(&node_id) => node_id,
+                    _ => default_node_id,
+                };
+                let caller = match secure.signs() {
+                    true => sender.to_string(),
+                    false => caller.to_owned(),
+                };
+                let central_bus = central_bus.clone();
+                let health = health.clone();
+                let secure = secure.clone();
+                let msg = Vec::from(msg);
                 let resp = resp.clone();
                 async move {
-                    if let Err(e) = fut.await {
-                        Err(Error::GsbFailure(format!("bcast send failure: {}", e)))
-                    } else {
-                        Ok(Vec::from(resp.as_ref()))
+                    // Envelope is bound to topic, so it is sealed for each of them.
+                    for topic in topics {
+                        let msg = secure
+                            .seal_broadcast(sender, &topic, msg.clone())
+                            .await
+                            .map_err(|e| Error::GsbFailure(format!("bcast send failure: {}", e)))?;
+                        health.message_sent(msg.len());
+                        if let Err(e) = central_bus.broadcast(caller.clone(), topic, msg).await {
+                            return Err(Error::GsbFailure(format!("bcast send failure: {}", e)));
+                        }
                     }
+                    Ok(Vec::from(resp.as_ref()))
                 }
+                .boxed_local()
                 .left_future()
             },
             (),
@@ -255,6 +556,70 @@ pub async fn bind_remote(
     Ok(done_rx)
 }
 
+fn count_reply(chunk: &Result<ResponseChunk, Error>, count: impl FnOnce(usize)) {
+    match chunk {
+        Ok(ResponseChunk::Part(data)) | Ok(ResponseChunk::Full(data)) => count(data.len()),
+        Err(_) => (),
+    }
+}
+
+/// Binds net module on a hub and binds it again, whenever connection breaks.
+/// Hubs are tried in order, failing over to the next one. All of them are
+/// resolved again on each attempt, unless `hubs` are given.
+/// Session key for encrypted envelopes is kept across reconnections.
+pub(crate) async fn auto_bind_remote(
+    hubs: Option<Vec<SocketAddr>>,
+    config_hubs: Vec<String>,
+    client_info: ClientInfo,
+    default_node_id: NodeId,
+    nodes: Vec<NodeId>,
+    subnet: Option<String>,
+    envelope: EnvelopeConfig,
+) {
+    let health = Health::default();
+    health.bind_gsb();
+    let secure = Secure::new(envelope);
+    secure.bind_gsb();
+
+    let nodes_clone = nodes.clone();
+    let unbind_health = health.clone();
+    auto_rebind(
+        move || {
+            let client_info = client_info.clone();
+            let nodes = nodes.clone();
+            let hubs = hubs.clone();
+            let config_hubs = config_hubs.clone();
+            let subnet = subnet.clone();
+            let health = health.clone();
+            let secure = secure.clone();
+            async move {
+                let hubs = match hubs {
+                    Some(hubs) => hubs,
+                    None => central_net_addrs(&config_hubs).await?,
+                };
+                health.set_hubs(&hubs);
+                let done_rx = bind_remote_any(
+                    &hubs,
+                    client_info,
+                    default_node_id,
+                    nodes,
+                    subnet,
+                    health,
+                    secure,
+                )
+                .await?;
+                resubscribe().await;
+                Ok::<_, std::io::Error>(done_rx)
+            }
+        },
+        move || {
+            unbind_health.disconnected();
+            unbind_remote(nodes_clone.clone())
+        },
+    )
+    .await
+}
+
 async fn unbind_remote(nodes: Vec<NodeId>) {
     let addrs = nodes
         .into_iter()
@@ -264,11 +629,18 @@ async fn unbind_remote(nodes: Vec<NodeId>) {
             local_net::BUS_ID,
             <SendBroadcastMessage<()> as RpcMessage>::ID
         )))
-        .chain(
-            [net::BUS_ID, local_net::BUS_ID, "/from"]
-                .iter()
-                .map(|s| s.to_string()),
-        )
+        // Status handler stays bound, while there is no connection.
+        .chain(std::iter::once(format!(
+            "{}/{}",
+            local_net::BUS_ID,
+            <local_net::Subscribe as RpcMessage>::ID
+        )))
+        .chain(std::iter::once(format!(
+            "{}/{}",
+            local_net::BUS_ID,
+            <local_net::Unsubscribe as RpcMessage>::ID
+        )))
+        .chain([net::BUS_ID, "/from"].iter().map(|s| s.to_string()))
         .collect::<Vec<_>>();
 
     log::debug!("Unbinding remote handlers");
@@ -281,8 +653,15 @@ async fn unbind_remote(nodes: Vec<NodeId>) {
 
 pub struct Net;
 
+impl Service for Net {
+    type Cli = crate::cli::NetCommand;
+}
+
 impl Net {
-    pub async fn gsb<Context>(_: Context) -> anyhow::Result<()> {
+    pub async fn gsb<Context>(ctx: &Context) -> anyhow::Result<()>
+    where
+        Context: Provider<Self, CliCtx>,
+    {
         let ids: Vec<IdentityInfo> = bus::service(identity::BUS_ID)
             .send(identity::List::default())
             .await
@@ -299,13 +678,74 @@ impl Net {
             .into_iter()
             .map(|id| id.node_id)
             .collect::<Vec<NodeId>>();
-        let ids_clone = ids.clone();
+        let subnet = subnet_from_env();
+        if let Some(subnet) = &subnet {
+            log::info!("receiving broadcasts of subnet: {}", subnet);
+        }
 
-        auto_rebind(
-            move || bind_remote(client_info.clone(), default_id.clone(), ids.clone()),
-            move || unbind_remote(ids_clone.clone()),
+        if let Some(config) = P2pConfig::from_env()? {
+            log::info!("using peer-to-peer network without hub: {:?}", config);
+            bind_p2p(config, default_id, ids, subnet).await?;
+            resubscribe().await;
+            return Ok(());
+        }
+
+        let envelope = EnvelopeConfig::from_env()?;
+        log::info!("using {:?} envelope for messages relayed by hub", envelope);
+        let cli_ctx: CliCtx = ctx.component();
+        let config_hubs = config_hubs(&cli_ctx.data_dir)?;
+        auto_bind_remote(
+            None,
+            config_hubs,
+            client_info,
+            default_id,
+            ids,
+            subnet,
+            envelope,
         )
         .await;
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_hub_list() {
+        assert_eq!(
+            parse_hosts(" hub-1:7464, 10.0.0.2:7464,,"),
+            vec!["hub-1:7464", "10.0.0.2:7464"]
+        );
+        assert!(parse_hosts("").is_empty());
+    }
+
+    #[test]
+    fn hubs_from_env_then_config_then_srv() {
+        let config = vec!["config-hub:7464".to_string()];
+        assert_eq!(
+            hub_hosts(Some("env-hub:7464".into()), &config),
+            Some(vec!["env-hub:7464".to_string()])
+        );
+        assert_eq!(hub_hosts(None, &config), Some(config.clone()));
+        assert_eq!(hub_hosts(None, &[]), None);
+    }
+
+    #[test]
+    fn parse_hubs_from_config_file() {
+        let content = r#"
+            [market]
+            subscription = { default-ttl = "1h" }
+
+            [net]
+            hubs = ["hub-1:7464", "10.0.0.2:7464"]
+        "#;
+        assert_eq!(
+            parse_config_hubs(content).unwrap(),
+            vec!["hub-1:7464", "10.0.0.2:7464"]
+        );
+        assert!(parse_config_hubs("[market]").unwrap().is_empty());
+        assert!(parse_config_hubs("[net]\nhub = \"typo:7464\"").is_err());
+    }
+}
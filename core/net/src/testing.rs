@@ -0,0 +1,64 @@
+//! In-process hub for integration tests of cross-node features.
+//! [`TestHub`] runs GSB router inside the test process, behind a proxy
+//! which can delay traffic and break connections. [`TestNode`] is a node
+//! connected to that hub, which keeps its services in memory instead of
+//! on the process-wide GSB, so many of them can live in one process.
+//! Net module of the test process itself joins the hub with [`bind_net`].
+use ya_core_model::NodeId;
+use ya_service_bus::connection::ClientInfo;
+
+use crate::secure::EnvelopeConfig;
+use crate::service::auto_bind_remote;
+
+mod hub;
+mod node;
+
+pub use crate::secure::{sign, signed_envelope};
+pub use hub::TestHub;
+pub use node::TestNode;
+
+/// Binds net module of this process on `hub` the way `Net::gsb` binds it
+/// on a real hub, so it is bound again after every disconnect.
+pub async fn bind_net(hub: &TestHub, default_node_id: NodeId, nodes: Vec<NodeId>) {
+    bind_net_with_failover(&[hub], default_node_id, nodes).await
+}
+
+/// Like [`bind_net`], but fails over to next of `hubs`, when one is not available.
+pub async fn bind_net_with_failover(
+    hubs: &[&TestHub],
+    default_node_id: NodeId,
+    nodes: Vec<NodeId>,
+) {
+    bind_remote(hubs, default_node_id, nodes, EnvelopeConfig::default()).await
+}
+
+/// Like [`bind_net`], but wraps messages in envelopes as configured by `envelope`.
+/// Signatures are made by identity service of this process.
+pub async fn bind_net_with_envelope(
+    hub: &TestHub,
+    default_node_id: NodeId,
+    nodes: Vec<NodeId>,
+    envelope: EnvelopeConfig,
+) {
+    bind_remote(&[hub], default_node_id, nodes, envelope).await
+}
+
+async fn bind_remote(
+    hubs: &[&TestHub],
+    default_node_id: NodeId,
+    nodes: Vec<NodeId>,
+    envelope: EnvelopeConfig,
+) {
+    let client_info = ClientInfo::new("sb-client-net");
+    let hubs = hubs.iter().map(|hub| hub.addr()).collect();
+    auto_bind_remote(
+        Some(hubs),
+        Vec::new(),
+        client_info,
+        default_node_id,
+        nodes,
+        None,
+        envelope,
+    )
+    .await
+}
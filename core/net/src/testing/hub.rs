@@ -0,0 +1,154 @@
+use actix_rt::Arbiter;
+use futures::channel::oneshot;
+use futures::prelude::*;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::rc::Rc;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+const PROXY_BUFFER_SIZE: usize = 8192;
+
+/// GSB router running in test process. Nodes connect to it through
+/// a proxy, which injects faults: latency, dropped connections and
+/// refused connection attempts.
+#[derive(Clone)]
+pub struct TestHub {
+    addr: SocketAddr,
+    router_addr: SocketAddr,
+    proxy: Rc<RefCell<ProxyState>>,
+}
+
+#[derive(Default)]
+struct ProxyState {
+    latency: Duration,
+    refuse: bool,
+    last_id: u64,
+    connections: HashMap<u64, oneshot::Sender<()>>,
+}
+
+impl TestHub {
+    pub async fn start() -> anyhow::Result<TestHub> {
+        let router_addr = std::net::TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?.local_addr()?;
+        ya_sb_router::bind_gsb_router(Some(format!("tcp://{}", router_addr).parse()?)).await?;
+
+        let mut listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await?;
+        let hub = TestHub {
+            addr: listener.local_addr()?,
+            router_addr,
+            proxy: Default::default(),
+        };
+        log::debug!(
+            "Test hub listening on {}, router on {}.",
+            hub.addr,
+            router_addr
+        );
+
+        let proxy = hub.proxy.clone();
+        Arbiter::spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok((client, addr)) => Arbiter::spawn(
+                        proxy_connection(proxy.clone(), client, router_addr).map(move |result| {
+                            if let Err(e) = result {
+                                log::debug!("Test hub connection with {} failed: {}", addr, e);
+                            }
+                        }),
+                    ),
+                    Err(e) => log::warn!("Test hub failed to accept connection: {}", e),
+                }
+            }
+        });
+        Ok(hub)
+    }
+
+    /// Address nodes should connect to.
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Address of the router itself, bypassing fault injection.
+    pub fn router_addr(&self) -> SocketAddr {
+        self.router_addr
+    }
+
+    /// Delays every chunk of data passing through the hub in any direction.
+    pub fn set_latency(&self, latency: Duration) {
+        self.proxy.borrow_mut().latency = latency;
+    }
+
+    /// Refused connections are closed right after they are accepted,
+    /// so nodes keep retrying until connections are accepted again.
+    pub fn refuse_connections(&self, refuse: bool) {
+        self.proxy.borrow_mut().refuse = refuse;
+    }
+
+    /// Breaks all current connections, as if the hub restarted.
+    pub fn disconnect_all(&self) {
+        let connections = std::mem::take(&mut self.proxy.borrow_mut().connections);
+        log::debug!("Test hub drops {} connections.", connections.len());
+        for (_, kill) in connections {
+            let _ = kill.send(());
+        }
+    }
+
+    /// Number of open connections.
+    pub fn connections(&self) -> usize {
+        self.proxy.borrow().connections.len()
+    }
+}
+
+async fn proxy_connection(
+    proxy: Rc<RefCell<ProxyState>>,
+    client: TcpStream,
+    router_addr: SocketAddr,
+) -> std::io::Result<()> {
+    if proxy.borrow().refuse {
+        return Ok(());
+    }
+    let router = TcpStream::connect(router_addr).await?;
+
+    let (kill_tx, kill_rx) = oneshot::channel();
+    let id = {
+        let mut state = proxy.borrow_mut();
+        state.last_id += 1;
+        let id = state.last_id;
+        state.connections.insert(id, kill_tx);
+        id
+    };
+
+    let (client_read, client_write) = tokio::io::split(client);
+    let (router_read, router_write) = tokio::io::split(router);
+    let upstream = pump(proxy.clone(), client_read, router_write).boxed_local();
+    let downstream = pump(proxy.clone(), router_read, client_write).boxed_local();
+
+    // Whichever side closes first, or kill request, closes both sides.
+    let result = match future::select(future::select(upstream, downstream), kill_rx).await {
+        future::Either::Left((future::Either::Left((result, _)), _)) => result,
+        future::Either::Left((future::Either::Right((result, _)), _)) => result,
+        future::Either::Right(_) => Ok(()),
+    };
+    proxy.borrow_mut().connections.remove(&id);
+    result
+}
+
+async fn pump<R, W>(proxy: Rc<RefCell<ProxyState>>, mut from: R, mut to: W) -> std::io::Result<()>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut buf = vec![0u8; PROXY_BUFFER_SIZE];
+    loop {
+        let len = from.read(&mut buf).await?;
+        if len == 0 {
+            return Ok(());
+        }
+        let latency = proxy.borrow().latency;
+        if latency > Duration::from_secs(0) {
+            tokio::time::delay_for(latency).await;
+        }
+        to.write_all(&buf[..len]).await?;
+    }
+}
@@ -0,0 +1,364 @@
+use actix_rt::Arbiter;
+use futures::channel::{mpsc, oneshot};
+use futures::prelude::*;
+use futures::stream::LocalBoxStream;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use ya_core_model::net::local::{BroadcastMessage, BroadcastScope, SendBroadcastMessage};
+use ya_core_model::net::{net_service, PUBLIC_PREFIX};
+use ya_core_model::NodeId;
+use ya_service_bus::connection::{self, ClientInfo};
+use ya_service_bus::{serialization, Error, ResponseChunk, RpcMessage};
+
+use crate::bcast::BCastService;
+use crate::handler::{auto_rebind, CentralBusHandler};
+use crate::testing::TestHub;
+
+const WAIT_INTERVAL: Duration = Duration::from_millis(50);
+
+type Handler =
+    Rc<dyn Fn(String, Vec<u8>) -> future::LocalBoxFuture<'static, Result<Vec<u8>, Error>>>;
+
+/// Node connected to [`TestHub`]. Its public services are bound on the node
+/// itself, not on the process-wide GSB. Like net module, the node binds itself
+/// on the hub again, whenever connection breaks.
+#[derive(Clone)]
+pub struct TestNode {
+    node_id: NodeId,
+    inner: Rc<RefCell<TestNodeInner>>,
+}
+
+#[derive(Default)]
+struct TestNodeInner {
+    services: HashMap<String, Handler>,
+    topics: HashMap<String, Vec<mpsc::UnboundedSender<(String, Vec<u8>)>>>,
+    connection: Option<mpsc::UnboundedSender<Command>>,
+    binds: usize,
+}
+
+enum Command {
+    Call {
+        caller: String,
+        addr: String,
+        data: Vec<u8>,
+        reply: oneshot::Sender<Result<Vec<u8>, Error>>,
+    },
+    Subscribe {
+        topic: String,
+        reply: oneshot::Sender<Result<(), Error>>,
+    },
+    Broadcast {
+        caller: String,
+        topic: String,
+        data: Vec<u8>,
+        reply: oneshot::Sender<Result<(), Error>>,
+    },
+}
+
+impl TestNode {
+    /// Returns after node is bound on the hub for the first time.
+    pub async fn connect(hub: &TestHub, node_id: NodeId) -> TestNode {
+        let node = TestNode {
+            node_id,
+            inner: Default::default(),
+        };
+        let hub_addr = hub.addr();
+        let bind_node = node.clone();
+        let unbind_node = node.clone();
+
+        auto_rebind(
+            move || bind(bind_node.clone(), hub_addr),
+            move || {
+                unbind_node.inner.borrow_mut().connection = None;
+                future::ready(())
+            },
+        )
+        .await;
+        node
+    }
+
+    pub fn node_id(&self) -> NodeId {
+        self.node_id
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.inner.borrow().connection.is_some()
+    }
+
+    /// Number of times node was bound on the hub, including the first one.
+    pub fn binds(&self) -> usize {
+        self.inner.borrow().binds
+    }
+
+    /// Waits until node was bound on the hub at least `binds` times.
+    pub async fn wait_for_binds(&self, binds: usize, timeout: Duration) -> anyhow::Result<()> {
+        let start = Instant::now();
+        while self.binds() < binds || !self.is_connected() {
+            if start.elapsed() > timeout {
+                anyhow::bail!(
+                    "Node {} bound {} times, expected {}.",
+                    self.node_id,
+                    self.binds(),
+                    binds
+                );
+            }
+            tokio::time::delay_for(WAIT_INTERVAL).await;
+        }
+        Ok(())
+    }
+
+    /// Binds handler of `T` under public `service` address, for example `/public/test`.
+    pub fn bind<T, F, Fut>(&self, service: &str, handler: F)
+    where
+        T: RpcMessage,
+        F: Fn(String, T) -> Fut + 'static,
+        Fut: Future<Output = Result<T::Item, T::Error>> + 'static,
+    {
+        let addr = format!("{}/{}", service_path(service), T::ID);
+        let handler: Handler = Rc::new(move |caller, data| {
+            let msg: T = match serialization::from_slice(&data) {
+                Ok(msg) => msg,
+                Err(e) => return future::err(Error::GsbBadRequest(e.to_string())).boxed_local(),
+            };
+            handler(caller, msg)
+                .map(|result| {
+                    serialization::to_vec(&result).map_err(|e| Error::GsbFailure(e.to_string()))
+                })
+                .boxed_local()
+        });
+        self.inner.borrow_mut().services.insert(addr, handler);
+    }
+
+    /// Calls public `service` of node `to` through the hub.
+    pub async fn call<T: RpcMessage>(
+        &self,
+        to: NodeId,
+        service: &str,
+        msg: T,
+    ) -> Result<Result<T::Item, T::Error>, Error> {
+        let addr = format!("{}{}/{}", net_service(to), service_path(service), T::ID);
+        let data = serialization::to_vec(&msg).map_err(|e| Error::GsbFailure(e.to_string()))?;
+
+        let reply = self.call_raw(addr, data).await?;
+        serialization::from_slice(&reply).map_err(|e| Error::GsbFailure(e.to_string()))
+    }
+
+    /// Sends `data` to `addr` through the hub as is, for example
+    /// to check how net module handles a forged message.
+    pub async fn call_raw(&self, addr: String, data: Vec<u8>) -> Result<Vec<u8>, Error> {
+        let caller = self.node_id.to_string();
+        self.request(|reply| Command::Call {
+            caller,
+            addr,
+            data,
+            reply,
+        })
+        .await
+    }
+
+    /// Stream of broadcasts of `M` sent by other nodes, paired with sender id.
+    pub async fn subscribe<M>(
+        &self,
+    ) -> Result<LocalBoxStream<'static, (String, SendBroadcastMessage<M>)>, Error>
+    where
+        M: BroadcastMessage + 'static,
+    {
+        let (tx, rx) = mpsc::unbounded();
+        let is_new = {
+            let mut inner = self.inner.borrow_mut();
+            let listeners = inner.topics.entry(M::TOPIC.to_string()).or_default();
+            listeners.push(tx);
+            listeners.len() == 1
+        };
+        // Not connected node subscribes all topics, when it is bound again.
+        if is_new && self.is_connected() {
+            let topic = M::TOPIC.to_string();
+            self.request(|reply| Command::Subscribe { topic, reply })
+                .await?;
+        }
+
+        Ok(rx
+            .filter_map(|(caller, data)| {
+                future::ready(match serialization::from_slice(&data) {
+                    Ok(msg) => Some((caller, msg)),
+                    Err(e) => {
+                        log::warn!("Test node received invalid broadcast: {}", e);
+                        None
+                    }
+                })
+            })
+            .boxed_local())
+    }
+
+    pub async fn broadcast<M: BroadcastMessage>(&self, msg: M) -> Result<(), Error> {
+        self.broadcast_to(msg, BroadcastScope::Network).await
+    }
+
+    /// Broadcasts within `scope`, as net module does. Test node has no subnet.
+    pub async fn broadcast_to<M: BroadcastMessage>(
+        &self,
+        msg: M,
+        scope: BroadcastScope,
+    ) -> Result<(), Error> {
+        let topics = BCastService::default().network_topics(M::TOPIC, &scope);
+        let data = serialization::to_vec(&SendBroadcastMessage::new(msg).with_scope(scope))
+            .map_err(|e| Error::GsbFailure(e.to_string()))?;
+
+        for topic in topics {
+            let caller = self.node_id.to_string();
+            let data = data.clone();
+            self.request(|reply| Command::Broadcast {
+                caller,
+                topic,
+                data,
+                reply,
+            })
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn request<R>(
+        &self,
+        command: impl FnOnce(oneshot::Sender<Result<R, Error>>) -> Command,
+    ) -> Result<R, Error> {
+        let not_connected = || Error::GsbFailure(format!("{} not connected to hub", self.node_id));
+        let connection = self
+            .inner
+            .borrow()
+            .connection
+            .clone()
+            .ok_or_else(not_connected)?;
+
+        let (tx, rx) = oneshot::channel();
+        connection
+            .unbounded_send(command(tx))
+            .map_err(|_| not_connected())?;
+        rx.await.map_err(|_| not_connected())?
+    }
+
+    fn handle_call(
+        &self,
+        caller: String,
+        addr: String,
+        data: Vec<u8>,
+    ) -> LocalBoxStream<'static, Result<ResponseChunk, Error>> {
+        let handler = addr
+            .strip_prefix(&net_service(self.node_id))
+            .and_then(|path| self.inner.borrow().services.get(path).cloned());
+        match handler {
+            Some(handler) => {
+                log::trace!(
+                    "Test node {} handles {} from {}.",
+                    self.node_id,
+                    addr,
+                    caller
+                );
+                handler(caller, data)
+                    .map_ok(ResponseChunk::Full)
+                    .into_stream()
+                    .boxed_local()
+            }
+            None => {
+                let err = Error::GsbBadRequest(format!("unknown service: {}", addr));
+                stream::once(future::err(err)).boxed_local()
+            }
+        }
+    }
+
+    fn handle_broadcast(&self, caller: String, topic: String, data: Vec<u8>) {
+        if let Some(listeners) = self.inner.borrow_mut().topics.get_mut(&topic) {
+            listeners.retain(|listener| {
+                listener
+                    .unbounded_send((caller.clone(), data.clone()))
+                    .is_ok()
+            });
+        }
+    }
+}
+
+/// Binds node on the hub and starts serving its commands.
+async fn bind(node: TestNode, hub_addr: SocketAddr) -> std::io::Result<oneshot::Receiver<()>> {
+    let io_error = |e: String| std::io::Error::new(std::io::ErrorKind::Other, e);
+    let conn = connection::tcp(hub_addr).await?;
+
+    let call_node = node.clone();
+    let forward_call = move |_request_id: String, caller: String, addr: String, data: Vec<u8>| {
+        call_node.handle_call(caller, addr, data)
+    };
+    let event_node = node.clone();
+    let broadcast_handler = move |caller: String, topic: String, data: Vec<u8>| {
+        event_node.handle_broadcast(caller, topic, data)
+    };
+
+    let (handler, done_rx) = CentralBusHandler::new(forward_call, broadcast_handler);
+    let central_bus =
+        connection::connect_with_handler(ClientInfo::new("sb-client-test-node"), conn, handler);
+
+    central_bus
+        .bind(net_service(node.node_id))
+        .await
+        .map_err(|e| io_error(e.to_string()))?;
+    let topics: Vec<String> = node.inner.borrow().topics.keys().cloned().collect();
+    for topic in topics {
+        central_bus
+            .subscribe(topic)
+            .await
+            .map_err(|e| io_error(e.to_string()))?;
+    }
+
+    let (tx, rx) = mpsc::unbounded();
+    Arbiter::spawn(rx.for_each(move |command| {
+        match command {
+            Command::Call {
+                caller,
+                addr,
+                data,
+                reply,
+            } => {
+                let fut = central_bus.call(caller, addr.clone(), data);
+                Arbiter::spawn(async move {
+                    let result = fut
+                        .await
+                        .map_err(|e| Error::RemoteError(addr, e.to_string()));
+                    let _ = reply.send(result);
+                })
+            }
+            Command::Subscribe { topic, reply } => {
+                let fut = central_bus.subscribe(topic);
+                Arbiter::spawn(async move {
+                    let result = fut.await.map_err(|e| Error::GsbFailure(e.to_string()));
+                    let _ = reply.send(result.map(|_| ()));
+                })
+            }
+            Command::Broadcast {
+                caller,
+                topic,
+                data,
+                reply,
+            } => {
+                let fut = central_bus.broadcast(caller, topic, data);
+                Arbiter::spawn(async move {
+                    let result = fut.await.map_err(|e| Error::GsbFailure(e.to_string()));
+                    let _ = reply.send(result.map(|_| ()));
+                })
+            }
+        }
+        future::ready(())
+    }));
+
+    let mut inner = node.inner.borrow_mut();
+    inner.connection = Some(tx);
+    inner.binds += 1;
+    log::debug!("Test node {} bound on hub {}.", node.node_id, hub_addr);
+    Ok(done_rx)
+}
+
+/// Path of public service relative to node, `/public/test` -> `/test`.
+fn service_path(service: &str) -> &str {
+    service.strip_prefix(PUBLIC_PREFIX).unwrap_or(service)
+}
@@ -0,0 +1,352 @@
+use futures::channel::mpsc;
+use futures::{future, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use ya_core_model::identity;
+use ya_core_model::net::local::{
+    self as local_net, BroadcastMessage, BroadcastScope, SendBroadcastMessage, ToEndpoint,
+};
+use ya_core_model::net::net_service;
+use ya_core_model::NodeId;
+use ya_net::testing::{
+    bind_net, bind_net_with_envelope, bind_net_with_failover, sign, signed_envelope, TestHub,
+    TestNode,
+};
+use ya_net::{EnvelopeConfig, EnvelopeMode, RemoteEndpoint};
+use ya_service_bus::{serialization, typed as bus, RpcEndpoint, RpcMessage};
+
+const TEST_SERVICE: &str = "/public/test";
+const PING_ENDPOINT: &str = "/local/test/ping";
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Echo(String);
+
+impl RpcMessage for Echo {
+    const ID: &'static str = "Echo";
+    type Item = String;
+    type Error = String;
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct Ping(u32);
+
+impl BroadcastMessage for Ping {
+    const TOPIC: &'static str = "test-ping";
+}
+
+fn node_id(n: u8) -> NodeId {
+    format!("0x{:040x}", n).parse::<NodeId>().unwrap()
+}
+
+fn secret_key(n: u8) -> ethsign::SecretKey {
+    ethsign::SecretKey::from_raw(&[n; 32]).unwrap()
+}
+
+fn key_node_id(secret: &ethsign::SecretKey) -> NodeId {
+    NodeId::from(&secret.public().address()[..])
+}
+
+async fn echo_node(hub: &TestHub, n: u8) -> TestNode {
+    let node = TestNode::connect(hub, node_id(n)).await;
+    node.bind(TEST_SERVICE, move |caller: String, msg: Echo| async move {
+        Ok::<_, String>(format!("{} from {} to {}", msg.0, caller, node_id(n)))
+    });
+    node
+}
+
+#[cfg_attr(not(feature = "test-suite"), ignore)]
+#[serial_test::serial]
+async fn test_nodes_call_and_broadcast_through_hub() {
+    let _ = env_logger::builder().try_init();
+    let hub = TestHub::start().await.unwrap();
+    let node1 = echo_node(&hub, 1).await;
+    let node2 = echo_node(&hub, 2).await;
+    let node3 = echo_node(&hub, 3).await;
+    assert_eq!(hub.connections(), 3);
+
+    let reply = node1
+        .call(node2.node_id(), TEST_SERVICE, Echo("hello".into()))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        reply,
+        format!("hello from {} to {}", node_id(1), node_id(2))
+    );
+    assert!(node1
+        .call(node2.node_id(), "/public/unknown", Echo("hello".into()))
+        .await
+        .is_err());
+
+    let mut pings2 = node2.subscribe::<Ping>().await.unwrap();
+    let mut pings3 = node3.subscribe::<Ping>().await.unwrap();
+    node1.broadcast(Ping(7)).await.unwrap();
+
+    let (caller, ping) = pings2.next().await.unwrap();
+    assert_eq!(caller, node_id(1).to_string());
+    assert_eq!(ping.body().0, 7);
+    assert_eq!(pings3.next().await.unwrap().1.body().0, 7);
+}
+
+#[cfg_attr(not(feature = "test-suite"), ignore)]
+#[serial_test::serial]
+async fn test_nodes_rebind_after_disconnect() {
+    let _ = env_logger::builder().try_init();
+    let hub = TestHub::start().await.unwrap();
+    let node1 = echo_node(&hub, 1).await;
+    let node2 = echo_node(&hub, 2).await;
+    let mut pings2 = node2.subscribe::<Ping>().await.unwrap();
+
+    hub.disconnect_all();
+    node1.wait_for_binds(2, TIMEOUT).await.unwrap();
+    node2.wait_for_binds(2, TIMEOUT).await.unwrap();
+
+    // Services and subscriptions survive reconnection.
+    let reply = node1.call(node2.node_id(), TEST_SERVICE, Echo("again".into()));
+    assert!(reply.await.unwrap().is_ok());
+    node1.broadcast(Ping(1)).await.unwrap();
+    assert_eq!(pings2.next().await.unwrap().1.body().0, 1);
+
+    // Node keeps retrying, while hub refuses connections.
+    hub.refuse_connections(true);
+    hub.disconnect_all();
+    tokio::time::delay_for(Duration::from_millis(500)).await;
+    assert!(!node1.is_connected());
+    assert!(node1
+        .call(node2.node_id(), TEST_SERVICE, Echo("lost".into()))
+        .await
+        .is_err());
+
+    hub.refuse_connections(false);
+    node1.wait_for_binds(3, TIMEOUT).await.unwrap();
+    node2.wait_for_binds(3, TIMEOUT).await.unwrap();
+    let reply = node2.call(node1.node_id(), TEST_SERVICE, Echo("back".into()));
+    assert!(reply.await.unwrap().is_ok());
+}
+
+#[cfg_attr(not(feature = "test-suite"), ignore)]
+#[serial_test::serial]
+async fn test_hub_latency() {
+    let _ = env_logger::builder().try_init();
+    let hub = TestHub::start().await.unwrap();
+    let node1 = echo_node(&hub, 1).await;
+    let node2 = echo_node(&hub, 2).await;
+
+    let latency = Duration::from_millis(200);
+    hub.set_latency(latency);
+
+    // Request and reply pass through connections of both nodes.
+    let start = Instant::now();
+    let reply = node1.call(node2.node_id(), TEST_SERVICE, Echo("slow".into()));
+    assert!(reply.await.unwrap().is_ok());
+    assert!(start.elapsed() >= latency * 4);
+}
+
+#[cfg_attr(not(feature = "test-suite"), ignore)]
+#[serial_test::serial]
+async fn test_net_service_rebinds_on_test_hub() {
+    let _ = env_logger::builder().try_init();
+    let hub = TestHub::start().await.unwrap();
+    let local_id = node_id(10);
+    bind_net(&hub, local_id, vec![local_id]).await;
+    let _ = bus::bind(TEST_SERVICE, |msg: Echo| async move {
+        Ok::<_, String>(format!("{} from local", msg.0))
+    });
+    let node = echo_node(&hub, 1).await;
+
+    let call_local = || node.call(local_id, TEST_SERVICE, Echo("hi".into()));
+    let call_remote = || {
+        ya_net::from(local_id)
+            .to(node.node_id())
+            .service(TEST_SERVICE)
+            .send(Echo("hi".into()))
+    };
+    assert_eq!(call_local().await.unwrap().unwrap(), "hi from local");
+    assert!(call_remote().await.unwrap().is_ok());
+
+    // Disconnect triggers `CentralBusHandler::on_disconnect`
+    // and net module of this process is bound again.
+    hub.disconnect_all();
+    node.wait_for_binds(2, TIMEOUT).await.unwrap();
+
+    let start = Instant::now();
+    while call_local().await.is_err() || call_remote().await.is_err() {
+        assert!(start.elapsed() < TIMEOUT, "net module not bound again");
+        tokio::time::delay_for(Duration::from_millis(100)).await;
+    }
+    assert!(hub.connections() >= 2);
+}
+
+#[cfg_attr(not(feature = "test-suite"), ignore)]
+#[serial_test::serial]
+async fn test_net_service_fails_over_to_next_hub() {
+    let _ = env_logger::builder().try_init();
+    let hub1 = TestHub::start().await.unwrap();
+    let hub2 = TestHub::start().await.unwrap();
+    let local_id = node_id(11);
+    bind_net_with_failover(&[&hub1, &hub2], local_id, vec![local_id]).await;
+    let _ = bus::bind(
+        TEST_SERVICE,
+        |msg: Echo| async move { Ok::<_, String>(msg.0) },
+    );
+
+    let status = bus::service(local_net::BUS_ID)
+        .send(local_net::Status {})
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(status.hub, Some(hub1.addr().to_string()));
+    assert_eq!(
+        status.hubs,
+        vec![hub1.addr().to_string(), hub2.addr().to_string()]
+    );
+    assert_eq!(status.reconnects, 0);
+    assert!(status.connected_since.is_some());
+
+    // Node on the second hub is reachable only after failover.
+    let node = echo_node(&hub2, 1).await;
+    hub1.refuse_connections(true);
+    hub1.disconnect_all();
+
+    let start = Instant::now();
+    while node
+        .call(local_id, TEST_SERVICE, Echo("hi".into()))
+        .await
+        .is_err()
+    {
+        assert!(
+            start.elapsed() < TIMEOUT,
+            "net module not bound on second hub"
+        );
+        tokio::time::delay_for(Duration::from_millis(100)).await;
+    }
+
+    let status = bus::service(local_net::BUS_ID)
+        .send(local_net::Status {})
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(status.hub, Some(hub2.addr().to_string()));
+    assert_eq!(status.reconnects, 1);
+    assert!(status.messages_received >= 1);
+    assert!(status.bytes_sent > 0);
+}
+
+#[cfg_attr(not(feature = "test-suite"), ignore)]
+#[serial_test::serial]
+async fn test_net_service_scoped_broadcasts_and_unsubscribe() {
+    let _ = env_logger::builder().try_init();
+    let hub = TestHub::start().await.unwrap();
+    let local_id = node_id(12);
+    bind_net(&hub, local_id, vec![local_id]).await;
+    let node = TestNode::connect(&hub, node_id(1)).await;
+    let bystander = TestNode::connect(&hub, node_id(3)).await;
+    let bystander_pings = bystander.subscribe::<Ping>().await.unwrap();
+
+    let (tx, mut pings) = mpsc::unbounded();
+    // Keeps stream open after handler is dropped with its binding.
+    let _tx = tx.clone();
+    ya_net::bind_broadcast_with_caller(
+        PING_ENDPOINT,
+        move |_caller, msg: SendBroadcastMessage<Ping>| {
+            let _ = tx.unbounded_send(msg.body().0);
+            future::ok(())
+        },
+    )
+    .await
+    .unwrap();
+
+    node.broadcast(Ping(1)).await.unwrap();
+    assert_eq!(pings.next().await, Some(1));
+
+    // Broadcasts are delivered in order, so skipped ones were dropped.
+    let other_node = BroadcastScope::Nodes(vec![node_id(2)]);
+    let other_subnet = BroadcastScope::Subnet("test".to_string());
+    let local_node = BroadcastScope::Nodes(vec![node_id(2), local_id]);
+    node.broadcast_to(Ping(2), other_node).await.unwrap();
+    node.broadcast_to(Ping(3), other_subnet).await.unwrap();
+    node.broadcast_to(Ping(4), local_node).await.unwrap();
+    assert_eq!(pings.next().await, Some(4));
+
+    ya_net::unbind_broadcast::<Ping>(PING_ENDPOINT)
+        .await
+        .unwrap();
+    let unsubscribed = bus::service(local_net::BUS_ID)
+        .send(Ping::into_unsubscribe_msg(PING_ENDPOINT))
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(!unsubscribed);
+
+    // Nothing is delivered to unbound endpoint anymore.
+    node.broadcast(Ping(5)).await.unwrap();
+    let delivered = tokio::time::timeout(Duration::from_millis(500), pings.next()).await;
+    assert!(
+        delivered.is_err(),
+        "delivered after unbind: {:?}",
+        delivered
+    );
+
+    // Hub doesn't send broadcasts to nodes outside of their scope.
+    let bystander_pings = bystander_pings.map(|(_, ping)| ping.body().0);
+    assert_eq!(
+        bystander_pings.take(2).collect::<Vec<_>>().await,
+        vec![1, 5]
+    );
+}
+
+#[cfg_attr(not(feature = "test-suite"), ignore)]
+#[serial_test::serial]
+async fn test_net_service_drops_tampered_and_replayed_envelopes() {
+    let _ = env_logger::builder().try_init();
+    let hub = TestHub::start().await.unwrap();
+    let local_id = key_node_id(&secret_key(13));
+    let envelope = EnvelopeConfig {
+        mode: EnvelopeMode::Signed,
+        required: true,
+    };
+    bind_net_with_envelope(&hub, local_id, vec![local_id], envelope).await;
+    // Replies are signed by identity service of this process.
+    let _ = bus::bind(identity::BUS_ID, |msg: identity::Sign| async move {
+        Ok::<_, identity::Error>(sign(&secret_key(13), &msg.payload))
+    });
+    let handled = Arc::new(AtomicUsize::new(0));
+    let counter = handled.clone();
+    let _ = bus::bind(TEST_SERVICE, move |msg: Echo| {
+        counter.fetch_add(1, Ordering::SeqCst);
+        async move { Ok::<_, String>(msg.0) }
+    });
+
+    let secret = secret_key(1);
+    let node = TestNode::connect(&hub, key_node_id(&secret)).await;
+    let addr = format!("{}/test/{}", net_service(local_id), Echo::ID);
+    let envelope = |text: &str| {
+        let data = serialization::to_vec(&Echo(text.to_string())).unwrap();
+        signed_envelope(&secret, &addr, data)
+    };
+
+    let valid = envelope("original");
+    assert!(node.call_raw(addr.clone(), valid.clone()).await.is_ok());
+    assert_eq!(handled.load(Ordering::SeqCst), 1);
+
+    // Hub resends the same envelope.
+    assert!(node.call_raw(addr.clone(), valid).await.is_err());
+
+    // Hub swaps payload of signed envelope for other one of the same length.
+    let mut tampered = envelope("original");
+    let at = tampered
+        .windows(8)
+        .position(|window| window == b"original")
+        .unwrap();
+    tampered[at..at + 8].copy_from_slice(b"tampered");
+    assert!(node.call_raw(addr.clone(), tampered).await.is_err());
+
+    // Unsigned message is rejected too, as envelope is required.
+    let plain = serialization::to_vec(&Echo("plain".to_string())).unwrap();
+    assert!(node.call_raw(addr, plain).await.is_err());
+    assert_eq!(handled.load(Ordering::SeqCst), 1);
+}
@@ -0,0 +1,105 @@
+use chrono::{DateTime, Duration, Utc};
+use futures::channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+/// Source of current time. System clock follows wall-clock time,
+/// manual clock stands still until it is advanced, which lets tests
+/// trigger expirations and deadlines without waiting.
+#[derive(Clone, Default)]
+pub struct Clock {
+    manual: Option<Arc<Mutex<ManualClock>>>,
+}
+
+struct ManualClock {
+    now: DateTime<Utc>,
+    listeners: Vec<UnboundedSender<DateTime<Utc>>>,
+}
+
+impl Clock {
+    pub fn system() -> Clock {
+        Clock { manual: None }
+    }
+
+    /// Clock starting at `start`, which changes only by `advance` and `set`.
+    pub fn manual(start: DateTime<Utc>) -> Clock {
+        Clock {
+            manual: Some(Arc::new(Mutex::new(ManualClock {
+                now: start,
+                listeners: vec![],
+            }))),
+        }
+    }
+
+    pub fn now(&self) -> DateTime<Utc> {
+        match &self.manual {
+            Some(clock) => clock.lock().unwrap().now,
+            None => Utc::now(),
+        }
+    }
+
+    pub fn is_manual(&self) -> bool {
+        self.manual.is_some()
+    }
+
+    /// Moves manual clock forward. Panics for system clock.
+    pub fn advance(&self, duration: Duration) {
+        let now = self.now() + duration;
+        self.set(now)
+    }
+
+    /// Sets manual clock to `now`. Clock never goes back, so earlier
+    /// timestamps are ignored. Panics for system clock.
+    pub fn set(&self, now: DateTime<Utc>) {
+        let clock = self.manual.as_ref().expect("Can't change system clock.");
+        let mut clock = clock.lock().unwrap();
+        if now <= clock.now {
+            return;
+        }
+
+        clock.now = now;
+        clock
+            .listeners
+            .retain(|listener| listener.unbounded_send(now).is_ok());
+    }
+
+    /// Stream of timestamps, that manual clock was set to.
+    /// System clock changes continuously, so it returns None.
+    pub fn changes(&self) -> Option<UnboundedReceiver<DateTime<Utc>>> {
+        let clock = self.manual.as_ref()?;
+        let (sender, receiver) = unbounded();
+        clock.lock().unwrap().listeners.push(sender);
+        Some(receiver)
+    }
+}
+
+impl fmt::Debug for Clock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.is_manual() {
+            true => write!(f, "Clock::Manual({})", self.now()),
+            false => write!(f, "Clock::System"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use futures::StreamExt;
+
+    #[actix_rt::test]
+    async fn test_manual_clock_goes_only_forward() {
+        let start = Utc::now();
+        let clock = Clock::manual(start);
+        let mut changes = clock.changes().unwrap();
+
+        clock.advance(Duration::seconds(10));
+        clock.set(start);
+        clock.set(start + Duration::minutes(1));
+
+        assert_eq!(clock.now(), start + Duration::minutes(1));
+        assert_eq!(changes.next().await.unwrap(), start + Duration::seconds(10));
+        assert_eq!(changes.next().await.unwrap(), start + Duration::minutes(1));
+        assert!(Clock::system().changes().is_none());
+    }
+}
@@ -6,6 +6,7 @@ use std::collections::HashMap;
 use crate::{
     actix_signal::{SignalSlot, Subscribe},
     actix_signal_handler,
+    clock::Clock,
 };
 use std::pin::Pin;
 
@@ -54,17 +55,25 @@ pub struct DeadlineChecker {
     nearest_deadline: DateTime<Utc>,
     handle: Option<SpawnHandle>,
     callback: SignalSlot<DeadlineElapsed>,
+    clock: Clock,
 }
 
 actix_signal_handler!(DeadlineChecker, DeadlineElapsed, callback);
 
 impl DeadlineChecker {
     pub fn new() -> DeadlineChecker {
+        DeadlineChecker::with_clock(Clock::system())
+    }
+
+    /// Deadlines are compared with `clock` time. Manual clock
+    /// triggers deadlines only when it is advanced.
+    pub fn with_clock(clock: Clock) -> DeadlineChecker {
         DeadlineChecker {
             deadlines: HashMap::new(),
-            nearest_deadline: Utc::now() + Duration::weeks(50),
+            nearest_deadline: clock.now() + Duration::weeks(50),
             callback: SignalSlot::<DeadlineElapsed>::new(),
             handle: None,
+            clock,
         }
     }
 
@@ -75,8 +84,16 @@ impl DeadlineChecker {
                 ctx.cancel_future(handle);
             }
 
+            self.nearest_deadline = top_deadline;
+
+            // Manual clock notifies us, when it is advanced.
+            let wait_duration = top_deadline - self.clock.now();
+            if self.clock.is_manual() && wait_duration > Duration::zero() {
+                return Ok(());
+            }
+
             let notify_timestamp = top_deadline.clone();
-            let wait_duration = (top_deadline - Utc::now())
+            let wait_duration = wait_duration
                 .max(Duration::milliseconds(1))
                 .to_std()
                 .map_err(|e| anyhow!("Failed to convert chrono to std Duration. {}", e))?;
@@ -84,16 +101,17 @@ impl DeadlineChecker {
             self.handle = Some(ctx.run_later(wait_duration, move |myself, ctx| {
                 myself.on_deadline_elapsed(ctx, notify_timestamp);
             }));
-
-            self.nearest_deadline = top_deadline;
         }
         Ok(())
     }
 
     fn on_deadline_elapsed(&mut self, ctx: &mut Context<Self>, deadline: DateTime<Utc>) {
-        let now = Utc::now();
+        let now = self.clock.now();
         assert!(now >= deadline);
+        self.notify_elapsed(ctx, now);
+    }
 
+    fn notify_elapsed(&mut self, ctx: &mut Context<Self>, now: DateTime<Utc>) {
         let elapsed = self.drain_elapsed(now);
 
         for event in elapsed.into_iter() {
@@ -155,7 +173,7 @@ impl DeadlineChecker {
 
         match nearest {
             Some(deadline) => deadline,
-            None => Utc::now() + Duration::weeks(50),
+            None => self.clock.now() + Duration::weeks(50),
         }
     }
 }
@@ -222,6 +240,20 @@ impl Handler<StopTrackingCategory> for DeadlineChecker {
 
 impl Actor for DeadlineChecker {
     type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Context<Self>) {
+        if let Some(changes) = self.clock.changes() {
+            ctx.add_stream(changes);
+        }
+    }
+}
+
+impl StreamHandler<DateTime<Utc>> for DeadlineChecker {
+    fn handle(&mut self, now: DateTime<Utc>, ctx: &mut Context<Self>) {
+        self.notify_elapsed(ctx, now);
+    }
+
+    fn finished(&mut self, _ctx: &mut Context<Self>) {}
 }
 
 struct DeadlineFun {
@@ -300,6 +332,53 @@ mod test {
         checker
     }
 
+    #[actix_rt::test]
+    async fn test_deadline_checker_manual_clock() {
+        let now = Utc::now();
+        let clock = Clock::manual(now);
+        let receiver = DeadlineReceiver::new();
+        let checker = DeadlineChecker::with_clock(clock.clone()).start();
+        checker
+            .send(Subscribe::<DeadlineElapsed>(receiver.clone().recipient()))
+            .await
+            .unwrap();
+
+        for i in 1..4 {
+            checker
+                .send(TrackDeadline {
+                    category: "agrrrrr-1".to_string(),
+                    deadline: now + Duration::hours(i),
+                    id: i.to_string(),
+                })
+                .await
+                .unwrap();
+        }
+
+        // Deadlines are far in the future in wall-clock time.
+        clock.advance(Duration::minutes(150));
+        tokio::time::delay_for(std::time::Duration::from_millis(50)).await;
+
+        let deadlined = receiver.send(Collect {}).await.unwrap();
+        assert_eq!(deadlined.len(), 2);
+        assert_eq!(deadlined[0].id, 1.to_string());
+        assert_eq!(deadlined[1].id, 2.to_string());
+
+        // Deadline, that already elapsed, is reported without advancing clock.
+        checker
+            .send(TrackDeadline {
+                category: "agrrrrr-2".to_string(),
+                deadline: now + Duration::minutes(1),
+                id: 4.to_string(),
+            })
+            .await
+            .unwrap();
+        tokio::time::delay_for(std::time::Duration::from_millis(50)).await;
+
+        let deadlined = receiver.send(Collect {}).await.unwrap();
+        assert_eq!(deadlined.len(), 1);
+        assert_eq!(deadlined[0].id, 4.to_string());
+    }
+
     #[cfg_attr(not(feature = "time-dependent-tests"), ignore)]
     #[actix_rt::test]
     async fn test_deadline_checker_single_agreement() {
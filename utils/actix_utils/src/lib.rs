@@ -1,3 +1,4 @@
 pub mod actix_handler;
 pub mod actix_signal;
+pub mod clock;
 pub mod deadline_checker;
@@ -1,7 +1,9 @@
 pub mod agreement;
 mod constraints;
+pub mod signature;
 mod typed_props;
 
 pub use agreement::{AgreementView, Error, OfferTemplate};
 pub use constraints::*;
+pub use signature::{verify_agreement, SignatureError};
 pub use typed_props::*;
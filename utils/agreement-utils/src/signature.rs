@@ -0,0 +1,137 @@
+use ethsign::Signature;
+use serde_json::{json, Value};
+use sha3::{Digest, Sha3_256};
+
+use ya_client_model::market::Agreement;
+use ya_client_model::NodeId;
+
+use crate::agreement::flatten;
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq)]
+pub enum SignatureError {
+    #[error("Agreement [{0}] has no {1} signature.")]
+    Missing(String, &'static str),
+    #[error("Invalid {1} signature of Agreement [{0}]: {2}")]
+    Invalid(String, &'static str, String),
+}
+
+/// Canonical serialization of Agreement terms, that both parties sign.
+/// Properties are flattened and object keys are sorted, so the result doesn't
+/// depend on the way Agreement was serialized or stored by either side.
+/// Only fields agreed on before signing are included; state, approval date,
+/// app session id and signatures themselves are left out.
+/// `version` of Agreement terms is zero for initial terms and is incremented with every
+/// approved amendment, so signatures of previous versions can't be replayed.
+pub fn canonical_agreement(agreement: &Agreement, version: u32) -> String {
+    let terms = json!({
+        "agreementId": agreement.agreement_id,
+        "version": version,
+        "demand": {
+            "demandId": agreement.demand.demand_id,
+            "requestorId": agreement.demand.requestor_id.to_string(),
+            "properties": flatten(agreement.demand.properties.clone()),
+            "constraints": agreement.demand.constraints,
+        },
+        "offer": {
+            "offerId": agreement.offer.offer_id,
+            "providerId": agreement.offer.provider_id.to_string(),
+            "properties": flatten(agreement.offer.properties.clone()),
+            "constraints": agreement.offer.constraints,
+        },
+        "validTo": agreement.valid_to.timestamp_millis(),
+        "timestamp": agreement.timestamp.timestamp_millis(),
+    });
+
+    let mut canonical = String::new();
+    write_canonical(&terms, &mut canonical);
+    canonical
+}
+
+/// Hash of canonical Agreement serialization. This is the payload signed by identities.
+pub fn agreement_hash(agreement: &Agreement, version: u32) -> Vec<u8> {
+    Sha3_256::digest(canonical_agreement(agreement, version).as_bytes()).to_vec()
+}
+
+/// Checks offline, that Agreement was confirmed by Requestor and approved by Provider,
+/// and that both signed the same terms of given `version`. Committed signature is checked
+/// only if present.
+pub fn verify_agreement(agreement: &Agreement, version: u32) -> Result<(), SignatureError> {
+    let hash = agreement_hash(agreement, version);
+    let requestor_id = &agreement.demand.requestor_id;
+    let provider_id = &agreement.offer.provider_id;
+
+    let check = |signature: &Option<String>, kind: &'static str, signer: &NodeId| {
+        let signature = signature
+            .as_ref()
+            .ok_or_else(|| SignatureError::Missing(agreement.agreement_id.clone(), kind))?;
+        verify_signature(&hash, signature, signer)
+            .map_err(|e| SignatureError::Invalid(agreement.agreement_id.clone(), kind, e))
+    };
+
+    check(&agreement.proposed_signature, "proposed", requestor_id)?;
+    check(&agreement.approved_signature, "approved", provider_id)?;
+    if agreement.committed_signature.is_some() {
+        check(&agreement.committed_signature, "committed", requestor_id)?;
+    }
+    Ok(())
+}
+
+/// Checks, that hex encoded `[v, r, s]` signature of `hash` was made by `signer`.
+pub fn verify_signature(hash: &[u8], signature: &str, signer: &NodeId) -> Result<(), String> {
+    let signature = hex::decode(signature).map_err(|e| e.to_string())?;
+    verify_raw_signature(hash, &signature, signer)
+}
+
+/// Checks, that `[v, r, s]` signature of `hash` was made by `signer`,
+/// the way identity service signs.
+pub fn verify_raw_signature(hash: &[u8], signature: &[u8], signer: &NodeId) -> Result<(), String> {
+    if signature.len() != 65 {
+        return Err("wrong signature length".to_string());
+    }
+
+    let mut r = [0u8; 32];
+    let mut s = [0u8; 32];
+    r.copy_from_slice(&signature[1..33]);
+    s.copy_from_slice(&signature[33..65]);
+    let signature = Signature {
+        v: signature[0],
+        r,
+        s,
+    };
+
+    let public_key = signature.recover(hash).map_err(|e| e.to_string())?;
+    if public_key.address() != &signer.into_array() {
+        return Err(format!("not signed by {}", signer));
+    }
+    Ok(())
+}
+
+fn write_canonical(value: &Value, out: &mut String) {
+    match value {
+        Value::Object(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            out.push('{');
+            for (idx, key) in keys.into_iter().enumerate() {
+                if idx > 0 {
+                    out.push(',');
+                }
+                out.push_str(&Value::String(key.clone()).to_string());
+                out.push(':');
+                write_canonical(&map[key], out);
+            }
+            out.push('}');
+        }
+        Value::Array(items) => {
+            out.push('[');
+            for (idx, item) in items.iter().enumerate() {
+                if idx > 0 {
+                    out.push(',');
+                }
+                write_canonical(item, out);
+            }
+            out.push(']');
+        }
+        other => out.push_str(&other.to_string()),
+    }
+}
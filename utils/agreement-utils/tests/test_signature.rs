@@ -0,0 +1,138 @@
+use ethsign::SecretKey;
+use serde_json::json;
+
+use ya_agreement_utils::signature::{agreement_hash, canonical_agreement};
+use ya_agreement_utils::{verify_agreement, SignatureError};
+use ya_client_model::market::Agreement;
+use ya_client_model::NodeId;
+
+fn node_id(secret: &SecretKey) -> NodeId {
+    NodeId::from(&secret.public().address()[..])
+}
+
+fn sign(secret: &SecretKey, agreement: &Agreement) -> String {
+    let signature = secret.sign(&agreement_hash(agreement, 0)).unwrap();
+    let mut bytes = vec![signature.v];
+    bytes.extend_from_slice(&signature.r[..]);
+    bytes.extend_from_slice(&signature.s[..]);
+    hex::encode(bytes)
+}
+
+fn sample_agreement(requestor: &SecretKey, provider: &SecretKey) -> Agreement {
+    serde_json::from_value(json!({
+        "agreementId": "0f5a1b3e2c4d",
+        "demand": {
+            "demandId": "98513eff-defe-4e52-adf4-32c3e9cba6c8",
+            "requestorId": node_id(requestor),
+            "properties": {"golem": {"node": {"debug": {"subnet": "demo-1"}}}},
+            "constraints": "(golem.inf.mem.gib>=0.5)",
+            "timestamp": "2021-01-20T10:00:00.123Z",
+        },
+        "offer": {
+            "offerId": "4724b50d-493e-4f0c-85ab-30098f56c624",
+            "providerId": node_id(provider),
+            "properties": {"golem.inf.mem.gib": 1.0, "golem.node.debug.subnet": "demo-1"},
+            "constraints": "(golem.node.debug.subnet=demo-1)",
+            "timestamp": "2021-01-20T10:00:00.123Z",
+        },
+        "validTo": "2021-01-20T12:00:00Z",
+        "state": "Approved",
+        "timestamp": "2021-01-20T10:00:00.123Z",
+    }))
+    .unwrap()
+}
+
+fn signed_agreement(requestor: &SecretKey, provider: &SecretKey) -> Agreement {
+    let mut agreement = sample_agreement(requestor, provider);
+    agreement.proposed_signature = Some(sign(requestor, &agreement));
+    agreement.approved_signature = Some(sign(provider, &agreement));
+    agreement
+}
+
+#[test]
+fn test_canonical_serialization_ignores_layout() {
+    let requestor = SecretKey::from_raw(&[1u8; 32]).unwrap();
+    let provider = SecretKey::from_raw(&[2u8; 32]).unwrap();
+    let agreement = sample_agreement(&requestor, &provider);
+
+    let mut relaid = agreement.clone();
+    relaid.offer.properties =
+        json!({"golem": {"node.debug.subnet": "demo-1", "inf": {"mem.gib": 1.0}}});
+    relaid.approved_signature = Some("ff".to_string());
+    relaid.app_session_id = Some("session".to_string());
+
+    assert_eq!(
+        canonical_agreement(&agreement, 0),
+        canonical_agreement(&relaid, 0)
+    );
+    assert!(canonical_agreement(&agreement, 0).contains(r#""golem.inf.mem.gib":1.0"#));
+}
+
+#[test]
+fn test_verify_signed_agreement() {
+    let requestor = SecretKey::from_raw(&[1u8; 32]).unwrap();
+    let provider = SecretKey::from_raw(&[2u8; 32]).unwrap();
+    let mut agreement = signed_agreement(&requestor, &provider);
+    verify_agreement(&agreement, 0).unwrap();
+
+    agreement.committed_signature = Some(sign(&requestor, &agreement));
+    verify_agreement(&agreement, 0).unwrap();
+}
+
+#[test]
+fn test_verify_rejects_changed_terms() {
+    let requestor = SecretKey::from_raw(&[1u8; 32]).unwrap();
+    let provider = SecretKey::from_raw(&[2u8; 32]).unwrap();
+    let mut agreement = signed_agreement(&requestor, &provider);
+    agreement.offer.constraints = "()".to_string();
+
+    match verify_agreement(&agreement, 0) {
+        Err(SignatureError::Invalid(_, "proposed", _)) => (),
+        result => panic!("Expected invalid proposed signature, got: {:?}", result),
+    }
+}
+
+#[test]
+fn test_verify_rejects_wrong_signer() {
+    let requestor = SecretKey::from_raw(&[1u8; 32]).unwrap();
+    let provider = SecretKey::from_raw(&[2u8; 32]).unwrap();
+    let mut agreement = signed_agreement(&requestor, &provider);
+    agreement.approved_signature = Some(sign(&requestor, &agreement));
+
+    match verify_agreement(&agreement, 0) {
+        Err(SignatureError::Invalid(_, "approved", _)) => (),
+        result => panic!("Expected invalid approved signature, got: {:?}", result),
+    }
+}
+
+#[test]
+fn test_verify_rejects_missing_signature() {
+    let requestor = SecretKey::from_raw(&[1u8; 32]).unwrap();
+    let provider = SecretKey::from_raw(&[2u8; 32]).unwrap();
+    let mut agreement = signed_agreement(&requestor, &provider);
+    agreement.approved_signature = None;
+
+    assert_eq!(
+        verify_agreement(&agreement, 0),
+        Err(SignatureError::Missing(
+            "0f5a1b3e2c4d".to_string(),
+            "approved"
+        ))
+    );
+}
+
+#[test]
+fn test_verify_rejects_other_version() {
+    let requestor = SecretKey::from_raw(&[1u8; 32]).unwrap();
+    let provider = SecretKey::from_raw(&[2u8; 32]).unwrap();
+    let agreement = signed_agreement(&requestor, &provider);
+
+    assert_ne!(
+        canonical_agreement(&agreement, 0),
+        canonical_agreement(&agreement, 1)
+    );
+    match verify_agreement(&agreement, 1) {
+        Err(SignatureError::Invalid(_, "proposed", _)) => (),
+        result => panic!("Expected invalid proposed signature, got: {:?}", result),
+    }
+}
@@ -7,12 +7,16 @@ const DEFAULT_LOOKUP_DOMAIN: &'static str = "dev.golem.network";
 
 /// Resolves prefixes in the `DEFAULT_LOOKUP_DOMAIN`, see also `resolve_record`
 pub async fn resolve_yagna_srv_record(prefix: &str) -> std::io::Result<String> {
-    resolve_srv_record(&format!(
-        "{}.{}",
-        prefix.trim_end_matches('.'),
-        DEFAULT_LOOKUP_DOMAIN
-    ))
-    .await
+    resolve_srv_record(&yagna_record(prefix)).await
+}
+
+/// Resolves all records of prefix in the `DEFAULT_LOOKUP_DOMAIN`, see also `resolve_srv_records`
+pub async fn resolve_yagna_srv_records(prefix: &str) -> std::io::Result<Vec<String>> {
+    resolve_srv_records(&yagna_record(prefix)).await
+}
+
+fn yagna_record(prefix: &str) -> String {
+    format!("{}.{}", prefix.trim_end_matches('.'), DEFAULT_LOOKUP_DOMAIN)
 }
 
 /// Performs lookup of the Service Record (SRV) in the Domain Name System
@@ -35,6 +39,33 @@ pub async fn resolve_srv_record(record: &str) -> std::io::Result<String> {
     Ok(addr)
 }
 
+/// Performs lookup of all Service Records (SRV) in the Domain Name System.
+/// Responds with `hostname:port` addresses ordered by priority, then by weight
+pub async fn resolve_srv_records(record: &str) -> std::io::Result<Vec<String>> {
+    let resolver: TokioAsyncResolver =
+        TokioAsyncResolver::tokio(ResolverConfig::google(), ResolverOpts::default()).await?;
+    let lookup = resolver.srv_lookup(record).await?;
+    let mut srvs = lookup.iter().collect::<Vec<_>>();
+    if srvs.is_empty() {
+        return Err(IoError::from(IoErrorKind::NotFound));
+    }
+    srvs.sort_by_key(|srv| (srv.priority(), std::cmp::Reverse(srv.weight())));
+
+    let addrs = srvs
+        .into_iter()
+        .map(|srv| {
+            format!(
+                "{}:{}",
+                srv.target().to_string().trim_end_matches('.'),
+                srv.port()
+            )
+        })
+        .collect::<Vec<_>>();
+
+    log::debug!("Resolved addresses: {:?}", addrs);
+    Ok(addrs)
+}
+
 /// Replace domain name in URL with resolved IP address
 /// Hack required on windows to bypass failing resolution on Windows 10
 /// Not needed when https://github.com/actix/actix-web/issues/1047 is resolved